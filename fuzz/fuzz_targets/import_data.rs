@@ -0,0 +1,35 @@
+//! Import arbitrary bytes as CSV, NDJSON or a JSON array
+//!
+//! The first byte picks the format; the rest is the input.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use oxi_db::{Column, ColumnType, CsvOptions, Database, JsonFormat};
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&format, input)) = data.split_first() else {
+        return;
+    };
+    let mut db = Database::in_memory();
+    match format % 4 {
+        0 => {
+            let _ = db.import_csv("fuzz", input, &CsvOptions::default());
+        }
+        1 => {
+            let _ = db.import_json(input, JsonFormat::Ndjson);
+        }
+        2 => {
+            let _ = db.import_json(input, JsonFormat::Array);
+        }
+        _ => {
+            let columns = vec![
+                Column::new("id", ColumnType::Integer).auto_increment(),
+                Column::new("tags", ColumnType::Array(Box::new(ColumnType::Text))),
+                Column::new("doc", ColumnType::Json),
+            ];
+            db.create_table("fuzz", columns, Some("id".to_string())).unwrap();
+            let _ = db.import_table_json("fuzz", input, JsonFormat::Ndjson);
+        }
+    }
+});
@@ -0,0 +1,30 @@
+//! Open arbitrary bytes as a database file, then read everything back
+//!
+//! Loading must fail with an error, never panic or exhaust memory. The
+//! same bytes also go through the integrity check, which reads damaged
+//! files leniently.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use oxi_db::{Database, MemoryStorage, Storage};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+fuzz_target!(|data: &[u8]| {
+    let storage = MemoryStorage::new();
+    storage.write(Path::new("fuzz.db"), 0, data).unwrap();
+    if let Ok(db) = Database::open_in(Arc::new(storage), "fuzz.db") {
+        for name in db.list_tables() {
+            let table = db.get_table(&name).unwrap();
+            let _ = table.get_all();
+            let _ = table.checksum();
+        }
+        let _ = db.dump(io::sink());
+    }
+
+    let path = std::env::temp_dir().join(format!("oxi-db-fuzz-{}.db", std::process::id()));
+    std::fs::write(&path, data).unwrap();
+    let _ = Database::integrity_check(&path);
+});
@@ -0,0 +1,21 @@
+//! Parse arbitrary text as filters, expressions, literals, keys and types,
+//! and its bytes as an encoded key
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use oxi_db::{ColumnType, Expr, Filter, JsonPath, Key, Value};
+
+fuzz_target!(|text: &str| {
+    let _ = Filter::parse(text);
+    let _ = Expr::parse(text);
+    let _ = JsonPath::parse(text);
+    let _ = Key::parse(text);
+    let _ = Key::decode(text.as_bytes());
+    if let Ok(column_type) = ColumnType::parse(text) {
+        let _ = Value::parse_as(text, &column_type);
+    }
+    if let Ok(value) = Value::parse_literal(text) {
+        let _ = Value::parse_literal(&value.to_string());
+    }
+});
@@ -0,0 +1,14 @@
+//! Replay arbitrary text as a SQL dump into an empty database
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use oxi_db::Database;
+use std::io;
+
+fuzz_target!(|data: &[u8]| {
+    let mut db = Database::in_memory();
+    if db.restore(data).is_ok() {
+        db.dump(io::sink()).unwrap();
+    }
+});
@@ -0,0 +1,76 @@
+use crate::error::Result;
+use crate::storage::Storage;
+use std::path::{Path, PathBuf};
+
+/// Number of bytes written between two progress reports
+pub(crate) const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Progress of a backup, reported after each chunk is written
+///
+/// Only writing the copy is reported on, not encoding it beforehand.
+///
+/// # Examples
+///
+/// ```
+/// use oxi_db::BackupProgress;
+///
+/// let progress = BackupProgress { bytes_written: 512, total_bytes: 2048 };
+/// assert_eq!(progress.fraction(), 0.25);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackupProgress {
+    /// Bytes written to the backup so far
+    pub bytes_written: u64,
+    /// Size of the complete backup in bytes
+    pub total_bytes: u64,
+}
+
+impl BackupProgress {
+    /// Get the share of the backup written so far, from 0.0 to 1.0
+    pub fn fraction(&self) -> f64 {
+        if self.total_bytes == 0 {
+            1.0
+        } else {
+            self.bytes_written as f64 / self.total_bytes as f64
+        }
+    }
+}
+
+/// Write `data` to `path` so that readers see either the old or the new file
+///
+/// The data is written and synced to a temporary file next to `path` in
+/// chunks of `CHUNK_SIZE`, calling `progress` after each, and the temporary
+/// file then replaces `path`, a rename that is synced too. The temporary
+/// file is removed if anything fails. The caller holds the lock of `path`,
+/// so two writers never share the temporary file.
+pub(crate) fn write_atomically(
+    storage: &dyn Storage,
+    path: &Path,
+    data: &[u8],
+    mut progress: impl FnMut(BackupProgress),
+) -> Result<()> {
+    let mut temp_path = path.to_path_buf().into_os_string();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+
+    let written = (|| {
+        storage.truncate(&temp_path, 0)?;
+        let mut bytes_written = 0;
+        for chunk in data.chunks(CHUNK_SIZE) {
+            storage.write(&temp_path, bytes_written, chunk)?;
+            bytes_written += chunk.len() as u64;
+            progress(BackupProgress {
+                bytes_written,
+                total_bytes: data.len() as u64,
+            });
+        }
+        storage.sync(&temp_path)?;
+        storage.rename(&temp_path, path)?;
+        storage.sync_directory(path)
+    })();
+    if written.is_err() {
+        // Gone already if only syncing the directory failed
+        let _ = storage.remove(&temp_path);
+    }
+    Ok(written?)
+}
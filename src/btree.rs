@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap as StdBTreeMap;
 use std::fmt::Debug;
+use std::ops::RangeBounds;
+
+/// Most keys in a node of the standard library's B-tree
+const NODE_KEYS: usize = 11;
 
 /// A wrapper around the standard library's BTreeMap for the database
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,6 +58,29 @@ where
         }
     }
 
+    /// Iterate in order over the key-value pairs whose keys lie in `range`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is after its end, or if both are the
+    /// same excluded bound.
+    pub fn range<R>(&self, range: R) -> impl Iterator<Item = (&K, &V)>
+    where
+        R: RangeBounds<K>,
+    {
+        self.data.range(range)
+    }
+
+    /// Get the pair with the smallest key
+    pub fn first(&self) -> Option<(&K, &V)> {
+        self.data.first_key_value()
+    }
+
+    /// Get the pair with the largest key
+    pub fn last(&self) -> Option<(&K, &V)> {
+        self.data.last_key_value()
+    }
+
     /// Get all key-value pairs as a vector
     pub fn to_vec(&self) -> Vec<(K, V)> {
         self.data.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
@@ -69,8 +96,33 @@ where
         self.data.len()
     }
 
+    /// Estimate the number of levels of the B-tree, 0 when it is empty
+    ///
+    /// The standard library does not expose the shape of its tree, so this is
+    /// the height of the smallest tree that holds `len` entries, with up to
+    /// 11 keys per node. A lookup visits about this many nodes.
+    pub fn estimated_depth(&self) -> usize {
+        let mut depth = 0;
+        let mut capacity = 0usize;
+        while capacity < self.len() {
+            depth += 1;
+            capacity = capacity.saturating_mul(NODE_KEYS + 1).saturating_add(NODE_KEYS);
+        }
+        depth
+    }
+
     /// Clear the B-tree
     pub fn clear(&mut self) {
         self.data.clear();
     }
 }
+
+impl<K, V> Default for BTree<K, V>
+where
+    K: Ord + Clone + Debug,
+    V: Clone + Debug,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
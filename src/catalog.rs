@@ -0,0 +1,129 @@
+use crate::sequence::Sequence;
+use crate::table::Table;
+use crate::types::{Column, ColumnType, Key, Row, Value};
+use std::collections::BTreeMap;
+
+/// Names of the read-only tables that describe the database
+///
+/// `oxi_tables` has a row per table, `oxi_columns` a row per column keyed by
+/// table name and position, `oxi_indexes` a row per index keyed by table and
+/// index name, and `oxi_sequences` a row per named sequence. They describe
+/// the tables of the database, not themselves.
+pub const CATALOG_TABLES: [&str; 4] = ["oxi_tables", "oxi_columns", "oxi_indexes", "oxi_sequences"];
+
+/// Check if a name belongs to a catalog table
+pub(crate) fn is_catalog_table(name: &str) -> bool {
+    CATALOG_TABLES.contains(&name)
+}
+
+/// Build the catalog tables from the tables and sequences of a database
+pub(crate) fn build(
+    tables: &BTreeMap<String, Table>,
+    sequences: &BTreeMap<String, Sequence>,
+) -> BTreeMap<String, Table> {
+    let mut oxi_tables = Table::new(
+        "oxi_tables",
+        vec![
+            Column::new("name", ColumnType::Text),
+            Column::new("columns", ColumnType::Integer),
+            Column::new("rows", ColumnType::Integer),
+            Column::new("primary_key", ColumnType::Text),
+            Column::new("indexes", ColumnType::Integer),
+            Column::new("compression", ColumnType::Text),
+        ],
+        Some("name".to_string()),
+    );
+    let mut oxi_columns = Table::new(
+        "oxi_columns",
+        vec![
+            Column::new("table", ColumnType::Text),
+            Column::new("position", ColumnType::Integer),
+            Column::new("name", ColumnType::Text),
+            Column::new("type", ColumnType::Text),
+            Column::new("primary_key", ColumnType::Boolean),
+            Column::new("auto_increment", ColumnType::Boolean),
+        ],
+        None,
+    );
+    let mut oxi_indexes = Table::new(
+        "oxi_indexes",
+        vec![
+            Column::new("table", ColumnType::Text),
+            Column::new("name", ColumnType::Text),
+            Column::new("expression", ColumnType::Text),
+            Column::new("values", ColumnType::Integer),
+            Column::new("rows", ColumnType::Integer),
+        ],
+        None,
+    );
+    let mut oxi_sequences = Table::new(
+        "oxi_sequences",
+        vec![
+            Column::new("name", ColumnType::Text),
+            Column::new("next", ColumnType::Integer),
+            Column::new("increment", ColumnType::Integer),
+            Column::new("current", ColumnType::Integer),
+        ],
+        Some("name".to_string()),
+    );
+
+    for (name, table) in tables {
+        oxi_tables.load_row(
+            Key::Text(name.clone()),
+            Row::new(vec![
+                Value::from(name.as_str()),
+                Value::Integer(table.columns.len() as i64),
+                Value::Integer(table.len() as i64),
+                table.primary_key.as_deref().map_or(Value::Null, Value::from),
+                Value::Integer(table.indexes().len() as i64),
+                Value::from(table.compression().to_string()),
+            ]),
+        );
+
+        for (position, column) in table.columns.iter().enumerate() {
+            let key = Key::Composite(vec![Key::Text(name.clone()), Key::Integer(position as i64)]);
+            oxi_columns.load_row(
+                key,
+                Row::new(vec![
+                    Value::from(name.as_str()),
+                    Value::Integer(position as i64),
+                    Value::from(column.name.as_str()),
+                    Value::from(column.column_type.to_string()),
+                    Value::Boolean(table.primary_key.as_ref() == Some(&column.name)),
+                    Value::Boolean(column.auto_increment),
+                ]),
+            );
+        }
+
+        for index in table.indexes() {
+            let key = Key::Composite(vec![Key::Text(name.clone()), Key::Text(index.name.clone())]);
+            oxi_indexes.load_row(
+                key,
+                Row::new(vec![
+                    Value::from(name.as_str()),
+                    Value::from(index.name.as_str()),
+                    Value::from(index.expr.to_string()),
+                    Value::Integer(index.len() as i64),
+                    Value::Integer(index.key_count() as i64),
+                ]),
+            );
+        }
+    }
+
+    for (name, sequence) in sequences {
+        oxi_sequences.load_row(
+            Key::Text(name.clone()),
+            Row::new(vec![
+                Value::from(name.as_str()),
+                Value::Integer(sequence.peek()),
+                Value::Integer(sequence.increment),
+                sequence.current_value().map_or(Value::Null, Value::Integer),
+            ]),
+        );
+    }
+
+    [oxi_tables, oxi_columns, oxi_indexes, oxi_sequences]
+        .into_iter()
+        .map(|table| (table.name.clone(), table))
+        .collect()
+}
@@ -0,0 +1,588 @@
+use crate::cli::format::{
+    format_rows, format_schema, format_stats, format_table_stats, format_tables, plan_to_json, rows_to_json,
+    stats_to_json, table_stats_to_json, tables_to_json,
+};
+use crate::cli::shell::take_key;
+use crate::csv::CsvOptions;
+use crate::database::Database;
+use crate::encryption::EncryptionKey;
+use crate::error::{DbError, Result};
+use crate::file_format::IntegrityReport;
+use crate::json::JsonFormat;
+use crate::parser::Parser;
+use crate::planner::Query;
+use crate::sql::quote_ident;
+use crate::types::Key;
+use serde_json::json;
+use std::io::{Read, Write};
+use std::path::Path;
+use zeroize::Zeroizing;
+
+/// Usage text of the `oxi-db` binary
+pub const USAGE: &str = "\
+Usage: oxi-db <database-file>                      Start the interactive shell
+       oxi-db <database-file> <command> [args...]  Run one command and exit
+
+Commands:
+  create-table <table> [<column definitions>]     e.g. \"id INTEGER PRIMARY KEY, name TEXT\"
+  drop-table <table>
+  tables
+  schema <table>
+  get <table> [<key>]
+  put <table> [[<key>] <value>...]                Insert or replace a row
+  delete <table> [<key>...]
+  count <table> [<clauses>]
+  query <table> [<clauses>]                       Clauses: join <table> on <a> = <b>,
+                                                  where <filter>, order by <expr>
+                                                  [desc], ..., limit <n>
+  explain <table> [<clauses>]                     Run a query and show its plan
+                                                  with estimated and actual rows
+  import-csv <table>                              Read CSV from stdin, creating
+                                                  the table if needed
+  export-csv <table>                              Write the table as CSV
+  import-json [<table>]                           Read NDJSON or a JSON array from
+                                                  stdin into a table, or a whole
+                                                  database export into the file
+  export-json [<table>]                           Write a table, or the whole
+                                                  database, as NDJSON
+  dump                                            Write the database as SQL
+  restore                                         Replay a SQL dump from stdin
+  backup <file>                                   Copy the database to a file
+                                                  and verify the copy
+  encrypt <passphrase-file>                       Encrypt the file, or change
+                                                  its passphrase
+  decrypt                                         Store the file unencrypted
+  compress <table> [none|lz4]                     Set the compression of a table
+                                                  and show its space on disk
+  stats [<table>]                                 Show the size of the file and
+                                                  its tables, or of one table
+                                                  with its columns and indexes
+  check                                           Check the file for damage
+  repair <file>                                   Recover readable tables and
+                                                  rows into a new file
+
+Options:
+  --format <table|json>, --json                   Output format (default: table)
+  --delimiter <char>, --quote <char>              CSV field separator and quote
+  --quote-all                                     Quote every exported CSV field
+  --no-header                                     CSV has no header row
+  --null <text>                                   CSV text for NULL (default: \\N)
+  --primary-key <column>                          Key of a table created by import-csv
+  --array                                         Export a JSON array instead of NDJSON
+  --passphrase-file <file>                        Passphrase of an encrypted file
+
+Operands that are left out are read from stdin; `put` reads one row per
+line. Pass `-` as the clauses of `count`, `query` or `explain` to read them
+from stdin. `explain` gathers column statistics before planning.
+The read-only tables oxi_tables, oxi_columns, oxi_indexes and oxi_sequences
+describe the database and work with schema, get, count, query and exports.
+";
+
+/// How a command writes its result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OutputFormat {
+    /// Aligned text tables and short messages
+    Table,
+    /// A single JSON document
+    Json,
+}
+
+/// Map an error to the process exit code of a failed command
+///
+/// | Code | Errors |
+/// |------|--------|
+/// | 1 | any other error |
+/// | 2 | `ParseError`, `CsvError`: malformed command line or input |
+/// | 3 | `TableNotFound`, `KeyNotFound`, `ColumnNotFound`, `IndexNotFound`, `SequenceNotFound` |
+/// | 4 | `TableExists`, `KeyExists`, `IndexExists`, `SequenceExists` |
+/// | 5 | `TypeConversionError`, `NumericOverflow`, `DivisionByZero` |
+/// | 6 | `CompareFailed` |
+/// | 7 | `IoError` |
+/// | 8 | `SerializationError`, `BincodeError`, `Corruption`: unreadable database file |
+/// | 9 | `WrongKey`, `KeyRequired`: wrong or missing encryption key |
+pub fn exit_code(error: &DbError) -> i32 {
+    match error {
+        DbError::ParseError(_) | DbError::CsvError(_) => 2,
+        DbError::TableNotFound
+        | DbError::KeyNotFound
+        | DbError::ColumnNotFound(_)
+        | DbError::IndexNotFound(_)
+        | DbError::SequenceNotFound(_) => 3,
+        DbError::TableExists
+        | DbError::KeyExists
+        | DbError::IndexExists(_)
+        | DbError::SequenceExists(_) => 4,
+        DbError::TypeConversionError | DbError::NumericOverflow | DbError::DivisionByZero => 5,
+        DbError::CompareFailed => 6,
+        DbError::IoError(_) => 7,
+        DbError::SerializationError(_) | DbError::BincodeError(_) | DbError::Corruption { .. } => 8,
+        DbError::WrongKey | DbError::KeyRequired => 9,
+        DbError::ReadOnlyTable(_) | DbError::Other(_) => 1,
+    }
+}
+
+/// Run one command and write its result
+///
+/// `args` are the command-line arguments after the program name: the
+/// database file, the command and its operands. `--format` and `--json` may
+/// appear anywhere. Operands that are left out are read from `input`.
+///
+/// # Examples
+///
+/// ```
+/// use oxi_db::cli::run;
+///
+/// let args = |line: &str| line.split(' ').map(String::from).collect::<Vec<_>>();
+/// let mut output = Vec::new();
+///
+/// run(&args("commands_example.db create-table users id INTEGER PRIMARY KEY, name TEXT"),
+///     &mut std::io::empty(), &mut output).unwrap();
+/// run(&args("commands_example.db put users 1 'Alice'"), &mut std::io::empty(), &mut output).unwrap();
+///
+/// output.clear();
+/// run(&args("commands_example.db count users --json"), &mut std::io::empty(), &mut output).unwrap();
+/// assert_eq!(String::from_utf8(output).unwrap(), "{\n  \"count\": 1\n}\n");
+/// # std::fs::remove_file("commands_example.db").unwrap_or(());
+/// ```
+///
+/// # Errors
+///
+/// Returns `DbError::ParseError` for malformed arguments or input, and any
+/// error raised by the database operation. `exit_code` maps them to exit
+/// codes.
+pub fn run(args: &[String], input: &mut dyn Read, output: &mut dyn Write) -> Result<()> {
+    let mut format = OutputFormat::Table;
+    let mut csv = CsvOptions::new();
+    let mut layout = JsonFormat::Ndjson;
+    let mut passphrase = None;
+    let mut positional = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .map(String::as_str)
+                .ok_or_else(|| DbError::ParseError(format!("{} needs a value", arg)))
+        };
+        match arg.as_str() {
+            "--json" => format = OutputFormat::Json,
+            "--format" => format = parse_format(value()?)?,
+            other if other.starts_with("--format=") => format = parse_format(&other[9..])?,
+            "--delimiter" => csv.delimiter = parse_char(value()?)?,
+            "--quote" => csv.quote = parse_char(value()?)?,
+            "--quote-all" => csv.quote_all = true,
+            "--no-header" => csv.has_header = false,
+            "--null" => csv.null = value()?.to_string(),
+            "--primary-key" => csv.primary_key = Some(value()?.to_string()),
+            "--array" => layout = JsonFormat::Array,
+            "--passphrase-file" => passphrase = Some(read_passphrase(value()?)?),
+            other if other.starts_with("--") => {
+                return Err(DbError::ParseError(format!("Unknown option '{}'", other)));
+            }
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    let (path, command, operands) = match positional.as_slice() {
+        [path, command, operands @ ..] => (Path::new(path), command.as_str(), operands),
+        _ => return Err(DbError::ParseError(USAGE.to_string())),
+    };
+    let table = operands.first().map(String::as_str);
+    let rest = operands.get(1..).unwrap_or(&[]);
+    let require_table =
+        || table.ok_or_else(|| DbError::ParseError(format!("{} needs a table name", command)));
+
+    let (text, json) = match command {
+        "create-table" => {
+            let table = require_table()?;
+            let mut parser = Parser::new(&operand(rest, input)?)?;
+            let (columns, primary_key) = parser.parse_column_defs()?;
+            parser.expect_end()?;
+
+            let mut db = open_or_create(path, passphrase.as_ref())?;
+            db.create_table(table, columns, primary_key)?;
+            (
+                format!("Created table {}\n", table),
+                json!({"created": table}),
+            )
+        }
+        "drop-table" => {
+            let table = require_table()?;
+            expect_no_operands(rest)?;
+            open_database(path, passphrase.as_ref())?.drop_table(table)?;
+            (
+                format!("Dropped table {}\n", table),
+                json!({"dropped": table}),
+            )
+        }
+        "tables" => {
+            expect_no_operands(operands)?;
+            let db = open_database(path, passphrase.as_ref())?;
+            (format_tables(&db)?, tables_to_json(&db)?)
+        }
+        "schema" => {
+            let table = require_table()?;
+            expect_no_operands(rest)?;
+            let db = open_database(path, passphrase.as_ref())?;
+            let table = db.get_table(table)?;
+            (format_schema(table), table.schema_to_json())
+        }
+        "get" => {
+            let table = require_table()?;
+            let key = Key::parse(&operand(rest, input)?)?;
+            let db = open_database(path, passphrase.as_ref())?;
+            let row = db.get(table, &key)?.clone();
+            let table = db.get_table(table)?;
+            (
+                format_rows(&table.columns, &[(key.clone(), row.clone())]),
+                table.row_to_json(&key, &row),
+            )
+        }
+        "put" => {
+            let table = require_table()?;
+            let lines = if rest.is_empty() {
+                operand(rest, input)?.lines().map(String::from).collect()
+            } else {
+                vec![rest.join(" ")]
+            };
+
+            // The rows are applied in memory and saved once, all or nothing
+            let mut db = open_database(path, passphrase.as_ref())?;
+            let rows = db.get_table_mut(table)?;
+            let (mut text, mut inserted, mut updated) = (String::new(), Vec::new(), Vec::new());
+            for line in lines.iter().filter(|line| !line.trim().is_empty()) {
+                let mut parser = Parser::new(line)?;
+                let mut values = Vec::new();
+                while !parser.is_at_end() {
+                    values.push(parser.parse_literal()?);
+                }
+
+                match take_key(rows, &mut values)? {
+                    Some(key) => {
+                        if rows.upsert(key.clone(), values)? {
+                            text.push_str(&format!("Inserted row {}\n", key));
+                            inserted.push(key.to_json());
+                        } else {
+                            text.push_str(&format!("Updated row {}\n", key));
+                            updated.push(key.to_json());
+                        }
+                    }
+                    None => {
+                        let key = rows.insert_auto(values)?;
+                        text.push_str(&format!("Inserted row {}\n", key));
+                        inserted.push(key.to_json());
+                    }
+                }
+            }
+            db.save()?;
+            (text, json!({"inserted": inserted, "updated": updated}))
+        }
+        "delete" => {
+            let table = require_table()?;
+            let mut parser = Parser::new(&operand(rest, input)?)?;
+            let mut keys = Vec::new();
+            while !parser.is_at_end() {
+                keys.push(parser.parse_key()?);
+                parser.eat_symbol(",");
+            }
+
+            let mut db = open_database(path, passphrase.as_ref())?;
+            let rows = db.get_table_mut(table)?;
+            let mut text = String::new();
+            for key in &keys {
+                rows.delete(key)?;
+                text.push_str(&format!("Deleted row {}\n", key));
+            }
+            db.save()?;
+            let deleted: Vec<serde_json::Value> = keys.iter().map(Key::to_json).collect();
+            (text, json!({"deleted": deleted}))
+        }
+        "count" | "query" | "explain" => {
+            let table = require_table()?;
+            let clauses = if rest.is_empty() { String::new() } else { operand(rest, input)? };
+            let query = Query::parse(&format!("{} {}", quote_ident(table), clauses))?;
+            let mut db = open_database(path, passphrase.as_ref())?;
+
+            if command == "explain" {
+                db.analyze()?;
+                let plan = db.explain(&query)?;
+                (plan.to_string(), plan_to_json(&plan))
+            } else {
+                let result = db.query(&query)?;
+                if command == "count" {
+                    (format!("{}\n", result.rows.len()), json!({"count": result.rows.len()}))
+                } else {
+                    let mut text = format_rows(&result.columns, &result.rows);
+                    text.push_str(&format!("({} rows)\n", result.rows.len()));
+                    (text, rows_to_json(&result.columns, &result.rows))
+                }
+            }
+        }
+        "import-csv" => {
+            let table = require_table()?;
+            expect_no_operands(rest)?;
+            let mut db = open_or_create(path, passphrase.as_ref())?;
+            let report = db.import_csv(table, input, &csv)?;
+
+            let mut text = format!("Imported {} rows\n", report.imported);
+            for error in &report.errors {
+                text.push_str(&format!("{}\n", error));
+            }
+            let errors: Vec<serde_json::Value> = report
+                .errors
+                .iter()
+                .map(|e| json!({"line": e.line, "error": e.error.to_string()}))
+                .collect();
+            (text, json!({"imported": report.imported, "errors": errors}))
+        }
+        "export-csv" => {
+            let table = require_table()?;
+            expect_no_operands(rest)?;
+            // CSV is the output format, so --format does not apply
+            return open_database(path, passphrase.as_ref())?.export_csv(table, output, &csv);
+        }
+        "import-json" => {
+            expect_no_operands(rest)?;
+            let mut data = Vec::new();
+            input.read_to_end(&mut data)?;
+            // A JSON array starts with '['; anything else is read as NDJSON
+            let layout = match data.iter().find(|b| !b.is_ascii_whitespace()) {
+                Some(b'[') => JsonFormat::Array,
+                _ => JsonFormat::Ndjson,
+            };
+
+            let mut db = open_or_create(path, passphrase.as_ref())?;
+            let count = match table {
+                Some(table) => db.import_table_json(table, data.as_slice(), layout)?,
+                None => db.import_json(data.as_slice(), layout)?,
+            };
+            (format!("Imported {} rows\n", count), json!({"imported": count}))
+        }
+        "export-json" => {
+            expect_no_operands(rest)?;
+            let db = open_database(path, passphrase.as_ref())?;
+            // The export is JSON already, so --format does not apply
+            return match table {
+                Some(table) => db.export_table_json(table, output, layout),
+                None => db.export_json(output, layout),
+            };
+        }
+        "dump" => {
+            expect_no_operands(operands)?;
+            return open_database(path, passphrase.as_ref())?.dump(output);
+        }
+        "backup" => {
+            let destination = table
+                .ok_or_else(|| DbError::ParseError("backup needs a destination file".to_string()))?;
+            expect_no_operands(rest)?;
+            let db = open_database(path, passphrase.as_ref())?;
+            let mut bytes = 0;
+            db.backup_with_progress(destination, |progress| bytes = progress.total_bytes)?;
+            db.verify_backup(destination)?;
+            (
+                format!("Backed up {} bytes to {}\n", bytes, destination),
+                json!({"backup": destination, "bytes": bytes}),
+            )
+        }
+        "encrypt" => {
+            let file = table
+                .ok_or_else(|| DbError::ParseError("encrypt needs a passphrase file".to_string()))?;
+            expect_no_operands(rest)?;
+            let new_key = read_passphrase(file)?;
+            open_database(path, passphrase.as_ref())?.set_encryption(Some(&new_key))?;
+            ("Encrypted the database\n".to_string(), json!({"encrypted": true}))
+        }
+        "decrypt" => {
+            expect_no_operands(operands)?;
+            open_database(path, passphrase.as_ref())?.set_encryption(None)?;
+            ("Decrypted the database\n".to_string(), json!({"encrypted": false}))
+        }
+        "compress" => {
+            let table = require_table()?;
+            let mut db = open_database(path, passphrase.as_ref())?;
+            if let Some((compression, rest)) = rest.split_first() {
+                expect_no_operands(rest)?;
+                db.set_compression(table, compression.parse()?)?;
+            }
+            let compression = db.get_table(table)?.compression();
+            let stats = db.compression_stats(table)?;
+            (
+                format!(
+                    "{}: {}, {} pages ({} compressed, {} overflow), {} bytes stored of {}\n",
+                    table,
+                    compression,
+                    stats.pages,
+                    stats.compressed_pages,
+                    stats.overflow_pages,
+                    stats.stored_bytes,
+                    stats.raw_bytes
+                ),
+                json!({
+                    "table": table,
+                    "compression": compression.to_string(),
+                    "pages": stats.pages,
+                    "compressed_pages": stats.compressed_pages,
+                    "overflow_pages": stats.overflow_pages,
+                    "raw_bytes": stats.raw_bytes,
+                    "stored_bytes": stats.stored_bytes,
+                }),
+            )
+        }
+        "stats" => {
+            expect_no_operands(rest)?;
+            let db = open_database(path, passphrase.as_ref())?;
+            match table {
+                Some(table) => {
+                    let stats = db.get_table(table)?.stats()?;
+                    (format_table_stats(&stats), table_stats_to_json(&stats))
+                }
+                None => {
+                    let stats = db.stats()?;
+                    (format_stats(&stats), stats_to_json(&stats))
+                }
+            }
+        }
+        "check" => {
+            expect_no_operands(operands)?;
+            let report = match &passphrase {
+                Some(key) => Database::integrity_check_encrypted(path, key)?,
+                None => Database::integrity_check(path)?,
+            };
+            let summary = format!(
+                "Checked {} tables and {} rows: {} problems\n",
+                report.tables,
+                report.rows,
+                report.problems.len()
+            );
+            let (text, json) = report_output(&report, summary);
+            write_result(output, format, &text, &json)?;
+            if !report.is_ok() {
+                return Err(DbError::Other(format!(
+                    "Integrity check found {} problems",
+                    report.problems.len()
+                )));
+            }
+            return Ok(());
+        }
+        "repair" => {
+            let destination = table
+                .ok_or_else(|| DbError::ParseError("repair needs a destination file".to_string()))?;
+            expect_no_operands(rest)?;
+            let report = match &passphrase {
+                Some(key) => Database::repair_encrypted(path, destination, key)?,
+                None => Database::repair(path, destination)?,
+            };
+            let summary = format!(
+                "Recovered {} tables and {} rows into {}\n",
+                report.tables, report.rows, destination
+            );
+            report_output(&report, summary)
+        }
+        "restore" => {
+            expect_no_operands(operands)?;
+            let mut db = open_or_create(path, passphrase.as_ref())?;
+            let count = db.restore(input)?;
+            (format!("Restored {} rows\n", count), json!({"restored": count}))
+        }
+        other => {
+            return Err(DbError::ParseError(format!(
+                "Unknown command '{}'\n\n{}",
+                other, USAGE
+            )))
+        }
+    };
+
+    write_result(output, format, &text, &json)
+}
+
+/// Open the database file, with the key given by `--passphrase-file`
+fn open_database(path: &Path, key: Option<&EncryptionKey>) -> Result<Database> {
+    match key {
+        Some(key) => Database::open_encrypted(path, key),
+        None => Database::open(path),
+    }
+}
+
+/// Open the database file, or start a new one if it does not exist
+fn open_or_create(path: &Path, key: Option<&EncryptionKey>) -> Result<Database> {
+    match key {
+        _ if path.exists() => open_database(path, key),
+        Some(key) => Database::new_encrypted(path, key),
+        None => Ok(Database::new(path)),
+    }
+}
+
+/// Read a passphrase from the first line of a file
+fn read_passphrase(file: &str) -> Result<EncryptionKey> {
+    let text = Zeroizing::new(std::fs::read_to_string(file)?);
+    let passphrase = text.lines().next().unwrap_or_default();
+    if passphrase.is_empty() {
+        return Err(DbError::ParseError(format!("No passphrase in {}", file)));
+    }
+    Ok(EncryptionKey::passphrase(passphrase))
+}
+
+fn write_result(output: &mut dyn Write, format: OutputFormat, text: &str, json: &serde_json::Value) -> Result<()> {
+    match format {
+        OutputFormat::Table => output.write_all(text.as_bytes())?,
+        OutputFormat::Json => writeln!(output, "{}", serde_json::to_string_pretty(json)?)?,
+    }
+    Ok(())
+}
+
+/// Describe an integrity report as text and JSON
+fn report_output(report: &IntegrityReport, summary: String) -> (String, serde_json::Value) {
+    let problems: Vec<String> = report.problems.iter().map(ToString::to_string).collect();
+    let mut text: String = problems.iter().map(|problem| format!("{}\n", problem)).collect();
+    text.push_str(&summary);
+    let json = json!({
+        "tables": report.tables,
+        "rows": report.rows,
+        "problems": problems,
+    });
+    (text, json)
+}
+
+fn parse_format(name: &str) -> Result<OutputFormat> {
+    match name {
+        "table" => Ok(OutputFormat::Table),
+        "json" => Ok(OutputFormat::Json),
+        other => Err(DbError::ParseError(format!(
+            "Unknown output format '{}'; expected 'table' or 'json'",
+            other
+        ))),
+    }
+}
+
+/// Parse a single-character option value; `\t` stands for a tab
+fn parse_char(text: &str) -> Result<u8> {
+    match text.as_bytes() {
+        [c] => Ok(*c),
+        b"\\t" => Ok(b'\t'),
+        _ => Err(DbError::ParseError(format!(
+            "Expected a single character, found '{}'",
+            text
+        ))),
+    }
+}
+
+/// Join operands given as arguments, or read them from `input` when there
+/// are none or the only one is `-`
+fn operand(words: &[String], input: &mut dyn Read) -> Result<String> {
+    if words.is_empty() || words == ["-"] {
+        let mut text = String::new();
+        input.read_to_string(&mut text)?;
+        Ok(text)
+    } else {
+        Ok(words.join(" "))
+    }
+}
+
+fn expect_no_operands(words: &[String]) -> Result<()> {
+    match words.first() {
+        Some(word) => Err(DbError::ParseError(format!(
+            "Unexpected argument '{}'",
+            word
+        ))),
+        None => Ok(()),
+    }
+}
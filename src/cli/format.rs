@@ -0,0 +1,303 @@
+use crate::database::Database;
+use crate::error::Result;
+use crate::planner::Plan;
+use crate::stats::{DatabaseStats, TableStats};
+use crate::table::Table;
+use crate::types::{Column, Key, Row, Value};
+use serde_json::json;
+
+/// Format a value for display in a table cell
+///
+/// Text is shown without quotes; every other value uses its `Display` form.
+pub(crate) fn format_value(value: &Value) -> String {
+    match value {
+        Value::Text(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Lay out rows of cells as an aligned text table with a header
+///
+/// Columns are as wide as their widest cell and framed with `+`, `-` and
+/// `|`, e.g. `| id | name  |` between two `+----+-------+` rules.
+pub(crate) fn format_table(headers: &[String], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            if i < widths.len() {
+                widths[i] = widths[i].max(cell.chars().count());
+            }
+        }
+    }
+
+    let separator = format!(
+        "+{}+\n",
+        widths
+            .iter()
+            .map(|w| "-".repeat(w + 2))
+            .collect::<Vec<_>>()
+            .join("+")
+    );
+    let format_line = |cells: &[String]| {
+        let cells: Vec<String> = widths
+            .iter()
+            .enumerate()
+            .map(|(i, w)| {
+                let cell = cells.get(i).map(String::as_str).unwrap_or("");
+                format!(" {}{} ", cell, " ".repeat(w - cell.chars().count()))
+            })
+            .collect();
+        format!("|{}|\n", cells.join("|"))
+    };
+
+    let mut out = separator.clone();
+    out.push_str(&format_line(headers));
+    out.push_str(&separator);
+    for row in rows {
+        out.push_str(&format_line(row));
+    }
+    if !rows.is_empty() {
+        out.push_str(&separator);
+    }
+    out
+}
+
+/// Format rows with the given columns, with the row key as the first column
+pub(crate) fn format_rows(columns: &[Column], rows: &[(Key, Row)]) -> String {
+    let mut headers = vec!["key".to_string()];
+    headers.extend(columns.iter().map(|c| c.name.clone()));
+
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|(key, row)| {
+            let mut cells = vec![key.to_string()];
+            cells.extend(row.values.iter().map(format_value));
+            cells
+        })
+        .collect();
+
+    format_table(&headers, &cells)
+}
+
+/// Format the tables of a database with their column and row counts
+pub(crate) fn format_tables(db: &Database) -> Result<String> {
+    let rows: Vec<Vec<String>> = db
+        .list_tables()
+        .into_iter()
+        .map(|name| {
+            let table = db.get_table(&name)?;
+            Ok(vec![name, table.columns.len().to_string(), table.len().to_string()])
+        })
+        .collect::<Result<_>>()?;
+    let headers = ["table", "columns", "rows"].map(String::from);
+    Ok(format_table(&headers, &rows))
+}
+
+/// Format the columns of a table with their types and constraints
+pub(crate) fn format_schema(table: &Table) -> String {
+    let headers = vec!["column".to_string(), "type".to_string(), "constraints".to_string()];
+
+    let rows: Vec<Vec<String>> = table
+        .columns
+        .iter()
+        .map(|column| {
+            let mut constraints = Vec::new();
+            if table.primary_key.as_ref() == Some(&column.name) {
+                constraints.push("PRIMARY KEY");
+            }
+            if column.auto_increment {
+                constraints.push("AUTO_INCREMENT");
+            }
+            vec![
+                column.name.clone(),
+                column.column_type.to_string(),
+                constraints.join(" "),
+            ]
+        })
+        .collect();
+
+    let mut out = format_table(&headers, &rows);
+    for index in table.indexes() {
+        out.push_str(&format!("index {} on {}\n", index.name, index.expr));
+    }
+    out
+}
+
+/// Describe the tables of a database as a JSON array
+pub(crate) fn tables_to_json(db: &Database) -> Result<serde_json::Value> {
+    db.list_tables()
+        .into_iter()
+        .map(|name| {
+            let table = db.get_table(&name)?;
+            Ok(json!({"name": name, "columns": table.columns.len(), "rows": table.len()}))
+        })
+        .collect()
+}
+
+/// Convert rows with the given columns into a JSON array of objects
+///
+/// Objects have the same shape as those of `Table::row_to_json`.
+pub(crate) fn rows_to_json(columns: &[Column], rows: &[(Key, Row)]) -> serde_json::Value {
+    rows.iter()
+        .map(|(key, row)| {
+            let mut object = serde_json::Map::new();
+            object.insert("_key".to_string(), key.to_json());
+            for (column, value) in columns.iter().zip(&row.values) {
+                object.insert(column.name.clone(), value.to_json(&column.column_type));
+            }
+            serde_json::Value::Object(object)
+        })
+        .collect()
+}
+
+/// Describe a query plan as a tree of JSON objects
+pub(crate) fn plan_to_json(plan: &Plan) -> serde_json::Value {
+    json!({
+        "operator": plan.description(),
+        "estimated_rows": plan.estimated_rows,
+        "actual_rows": plan.actual_rows,
+        "cost": plan.cost,
+        "inputs": plan.inputs.iter().map(plan_to_json).collect::<Vec<_>>(),
+    })
+}
+
+/// Format the size of a database and of each of its tables
+pub(crate) fn format_stats(stats: &DatabaseStats) -> String {
+    let rows: Vec<Vec<String>> = stats
+        .tables
+        .iter()
+        .map(|table| {
+            vec![
+                table.name.clone(),
+                table.rows.to_string(),
+                table.estimated_depth.to_string(),
+                table.bytes.to_string(),
+                table.index_bytes.to_string(),
+                table.compression.to_string(),
+            ]
+        })
+        .collect();
+    let headers = ["table", "rows", "est. depth", "bytes", "index bytes", "compression"].map(String::from);
+
+    let mut out = format!(
+        "{} bytes in file, {} used, {} free; {} tables, {} rows, {} sequences\n",
+        stats.file_bytes,
+        stats.used_bytes,
+        stats.free_bytes,
+        stats.tables.len(),
+        stats.rows(),
+        stats.sequences
+    );
+    out.push_str(&format_table(&headers, &rows));
+    out
+}
+
+/// Format the size of a table, the distribution of each column and its indexes
+pub(crate) fn format_table_stats(stats: &TableStats) -> String {
+    let optional = |value: &Option<Value>| value.as_ref().map(format_value).unwrap_or_default();
+    let rows: Vec<Vec<String>> = stats
+        .columns
+        .iter()
+        .map(|column| {
+            vec![
+                column.name.clone(),
+                column.column_type.to_string(),
+                column.nulls.to_string(),
+                column.distinct.to_string(),
+                optional(&column.min),
+                optional(&column.max),
+            ]
+        })
+        .collect();
+    let headers = ["column", "type", "nulls", "distinct", "min", "max"].map(String::from);
+
+    let mut out = format!(
+        "{}: {} rows, estimated depth {}, {} bytes ({} in indexes, {} overflow pages), compression {}\n",
+        stats.name,
+        stats.rows,
+        stats.estimated_depth,
+        stats.bytes,
+        stats.index_bytes,
+        stats.overflow_pages,
+        stats.compression
+    );
+    out.push_str(&format_table(&headers, &rows));
+    if !stats.indexes.is_empty() {
+        let rows: Vec<Vec<String>> = stats
+            .indexes
+            .iter()
+            .map(|index| {
+                vec![
+                    index.name.clone(),
+                    index.expr.to_string(),
+                    index.values.to_string(),
+                    index.entries.to_string(),
+                    index.estimated_depth.to_string(),
+                    index.bytes.to_string(),
+                ]
+            })
+            .collect();
+        let headers = ["index", "expression", "values", "rows", "est. depth", "bytes"].map(String::from);
+        out.push_str(&format_table(&headers, &rows));
+    }
+    out
+}
+
+/// Describe the size of a database and its tables as JSON
+pub(crate) fn stats_to_json(stats: &DatabaseStats) -> serde_json::Value {
+    json!({
+        "file_bytes": stats.file_bytes,
+        "used_bytes": stats.used_bytes,
+        "free_bytes": stats.free_bytes,
+        "sequences": stats.sequences,
+        "tables": stats.tables.iter().map(table_stats_to_json).collect::<Vec<_>>(),
+    })
+}
+
+/// Describe the size of a table, its columns and its indexes as JSON
+pub(crate) fn table_stats_to_json(stats: &TableStats) -> serde_json::Value {
+    let columns: Vec<serde_json::Value> = stats
+        .columns
+        .iter()
+        .map(|column| {
+            let value = |value: &Option<Value>| {
+                value
+                    .as_ref()
+                    .map_or(serde_json::Value::Null, |value| value.to_json(&column.column_type))
+            };
+            json!({
+                "name": column.name,
+                "type": column.column_type.to_string(),
+                "nulls": column.nulls,
+                "distinct": column.distinct,
+                "min": value(&column.min),
+                "max": value(&column.max),
+            })
+        })
+        .collect();
+    let indexes: Vec<serde_json::Value> = stats
+        .indexes
+        .iter()
+        .map(|index| {
+            json!({
+                "name": index.name,
+                "expression": index.expr.to_string(),
+                "values": index.values,
+                "rows": index.entries,
+                "estimated_depth": index.estimated_depth,
+                "bytes": index.bytes,
+            })
+        })
+        .collect();
+    json!({
+        "name": stats.name,
+        "rows": stats.rows,
+        "estimated_depth": stats.estimated_depth,
+        "bytes": stats.bytes,
+        "index_bytes": stats.index_bytes,
+        "overflow_pages": stats.overflow_pages,
+        "compression": stats.compression.to_string(),
+        "columns": columns,
+        "indexes": indexes,
+    })
+}
@@ -0,0 +1,13 @@
+//! Command-line front end for the `oxi-db` binary
+//!
+//! Enabled by the `cli` feature. Only what the binary needs is public:
+//! [`Shell`] and [`run_shell`] for the interactive shell, and [`run`] and
+//! [`exit_code`] for the one-shot subcommands used from scripts. Rows and
+//! schemas are rendered as text tables or JSON by a private module.
+
+mod commands;
+mod format;
+mod shell;
+
+pub use commands::{exit_code, run, USAGE};
+pub use shell::{run as run_shell, Shell};
@@ -0,0 +1,296 @@
+use crate::cli::format::{format_rows, format_schema, format_stats, format_table_stats, format_tables};
+use crate::database::Database;
+use crate::error::{DbError, Result};
+use crate::parser::{coerce_literals, Parser};
+use crate::table::Table;
+use crate::types::{Key, Value};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::path::{Path, PathBuf};
+
+const HELP: &str = "\
+Commands:
+  tables                                  List all tables
+  schema <table>                          Describe the columns of a table
+  stats [<table>]                         Show the size of the database, or
+                                          of a table and its columns
+  insert <table> [key] <value>...         Insert a row; the key defaults to the
+                                          auto-increment or primary key column
+  get <table> <key>                       Show a row
+  update <table> <key> <col> = <value>, ...
+                                          Change some columns of a row
+  delete <table> <key>                    Delete a row
+  query <table> [join <table> on <a> = <b>] [where <filter>]
+        [order by <expr> [desc], ...] [limit <n>]
+                                          Show the rows matching a query
+  explain <query>                         Run a query and show its plan with
+                                          estimated and actual row counts
+  analyze                                 Gather the statistics the planner
+                                          estimates costs with
+  save '<file>'                           Write the database to a file and
+                                          keep saving it there
+  help                                    Show this message
+  exit                                    Leave the shell
+
+Keys written as integers are integer keys; quote them ('42') for text keys.
+Read-only tables oxi_tables, oxi_columns, oxi_indexes and oxi_sequences
+describe the database, e.g. query oxi_columns where table = 'users'
+Values: NULL, TRUE, FALSE, 42, 19.99, 1e-3, 'text', x'00ff', [1, 2, 3]
+Filters: age >= 18 AND (name = 'Bob' OR profile$.city = 'Paris'),
+         tags CONTAINS 'rust', ANY(scores) > 90, email IS NOT NULL
+";
+
+/// Path that opens an in-memory database in the shell
+const MEMORY_PATH: &str = ":memory:";
+
+/// An interactive session over an open database
+///
+/// The shell executes one command per line and returns its output as text,
+/// which `run` prints. Keeping execution separate from the terminal makes
+/// every command usable from tests and scripts.
+///
+/// # Examples
+///
+/// ```
+/// use oxi_db::cli::Shell;
+/// use oxi_db::{Column, ColumnType, Database};
+///
+/// let mut db = Database::new("shell_example.db");
+/// db.create_table("users", vec![Column::new("name", ColumnType::Text)], None).unwrap();
+///
+/// let mut shell = Shell::new(db);
+/// shell.execute("insert users 1 'Alice'").unwrap();
+/// let output = shell.execute("query users where name = 'Alice'").unwrap();
+/// assert!(output.contains("Alice"));
+/// # std::fs::remove_file("shell_example.db").unwrap_or(());
+/// ```
+pub struct Shell {
+    db: Database,
+}
+
+impl Shell {
+    /// Create a shell over a database
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Get the database the shell operates on
+    pub fn database(&self) -> &Database {
+        &self.db
+    }
+
+    /// Execute one command line and return its output
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ParseError` for malformed commands, and any error
+    /// raised by the database operation itself.
+    pub fn execute(&mut self, line: &str) -> Result<String> {
+        let line = line.trim().trim_end_matches(';');
+        let (command, rest) = match line.split_once(char::is_whitespace) {
+            Some((command, rest)) => (command, rest),
+            None => (line, ""),
+        };
+        let mut parser = Parser::new(rest)?;
+
+        match command.trim_start_matches('.').to_lowercase().as_str() {
+            "" => Ok(String::new()),
+            "help" => Ok(HELP.to_string()),
+            "tables" => {
+                parser.expect_end()?;
+                format_tables(&self.db)
+            }
+            "schema" | "describe" => {
+                let table = parser.parse_ident()?;
+                parser.expect_end()?;
+                Ok(format_schema(self.db.get_table(&table)?))
+            }
+            "stats" => {
+                if parser.is_at_end() {
+                    return Ok(format_stats(&self.db.stats()?));
+                }
+                let table = parser.parse_ident()?;
+                parser.expect_end()?;
+                Ok(format_table_stats(&self.db.get_table(&table)?.stats()?))
+            }
+            "insert" => self.insert(&mut parser),
+            "get" => {
+                let table = parser.parse_ident()?;
+                let key = parser.parse_key()?;
+                parser.expect_end()?;
+                let row = self.db.get(&table, &key)?.clone();
+                Ok(format_rows(&self.db.get_table(&table)?.columns, &[(key, row)]))
+            }
+            "update" => self.update(&mut parser),
+            "delete" => {
+                let table = parser.parse_ident()?;
+                let key = parser.parse_key()?;
+                parser.expect_end()?;
+                self.db.delete(&table, &key)?;
+                Ok(format!("Deleted row {}\n", key))
+            }
+            "query" | "select" => {
+                let query = parser.parse_query()?;
+                parser.expect_end()?;
+                let result = self.db.query(&query)?;
+                let mut out = format_rows(&result.columns, &result.rows);
+                out.push_str(&format!("({} rows)\n", result.rows.len()));
+                Ok(out)
+            }
+            "explain" => {
+                let query = parser.parse_query()?;
+                parser.expect_end()?;
+                Ok(self.db.explain(&query)?.to_string())
+            }
+            "analyze" => {
+                parser.expect_end()?;
+                self.db.analyze()?;
+                Ok("Analyzed all tables\n".to_string())
+            }
+            "save" => {
+                let file = match parser.parse_literal()? {
+                    Value::Text(file) => file,
+                    other => return Err(DbError::ParseError(format!("Expected a file name, got {}", other))),
+                };
+                parser.expect_end()?;
+                self.db.persist_to(&file)?;
+                Ok(format!("Saved to {}\n", file))
+            }
+            other => Err(DbError::ParseError(format!(
+                "Unknown command '{}'; type 'help' for a list of commands",
+                other
+            ))),
+        }
+    }
+
+    fn insert(&mut self, parser: &mut Parser) -> Result<String> {
+        let table_name = parser.parse_ident()?;
+        let mut values = Vec::new();
+        while !parser.is_at_end() {
+            values.push(parser.parse_literal()?);
+        }
+
+        let key = match take_key(self.db.get_table(&table_name)?, &mut values)? {
+            Some(key) => {
+                self.db.insert(&table_name, key.clone(), values)?;
+                key
+            }
+            None => self.db.insert_auto(&table_name, values)?,
+        };
+
+        Ok(format!("Inserted row {}\n", key))
+    }
+
+    fn update(&mut self, parser: &mut Parser) -> Result<String> {
+        let table = parser.parse_ident()?;
+        let key = parser.parse_key()?;
+        parser.eat_keyword("set");
+
+        let mut updates: Vec<(String, Value)> = Vec::new();
+        loop {
+            let column = parser.parse_ident()?;
+            parser.expect_symbol("=")?;
+            updates.push((column, parser.parse_literal()?));
+            if !parser.eat_symbol(",") {
+                break;
+            }
+        }
+        parser.expect_end()?;
+
+        let columns = &self.db.get_table(&table)?.columns;
+        let updates: Vec<(&str, Value)> = updates
+            .iter()
+            .map(|(column, value)| {
+                let value = match columns.iter().find(|c| &c.name == column) {
+                    Some(c) => value.clone().coerce(&c.column_type)?,
+                    None => value.clone(),
+                };
+                Ok((column.as_str(), value))
+            })
+            .collect::<Result<_>>()?;
+        self.db.update_columns(&table, &key, &updates)?;
+        Ok(format!("Updated row {}\n", key))
+    }
+}
+
+/// Work out the key of a row being inserted
+///
+/// With one value more than the table has columns, the first value is the
+/// key and is removed from `values`. The other values are converted to the
+/// types of their columns, and unless a key was given it comes from the
+/// primary key column; `None` is returned when that value is NULL and the
+/// table's auto-increment counter should supply it.
+pub(crate) fn take_key(table: &Table, values: &mut Vec<Value>) -> Result<Option<Key>> {
+    let key = (values.len() == table.columns.len() + 1).then(|| values.remove(0));
+    *values = coerce_literals(std::mem::take(values), &table.columns)?;
+    if let Some(key) = key {
+        return Key::from_value(&key)
+            .map(Some)
+            .ok_or_else(|| DbError::ParseError(format!("Invalid key: {}", key)));
+    }
+
+    match table.key_from_values(values) {
+        Some(key) => Ok(Some(key)),
+        None if table.next_auto_increment().is_some() => Ok(None),
+        None => Err(DbError::ParseError("A key is required for this table".to_string())),
+    }
+}
+
+/// Open a database file and run the interactive shell until `exit` or EOF
+///
+/// The file is created if it does not exist; the path `:memory:` opens an
+/// in-memory database instead. Command history is kept in
+/// `~/.oxi_db_history`.
+pub fn run(path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    let db = if path == Path::new(MEMORY_PATH) {
+        Database::in_memory()
+    } else if path.exists() {
+        Database::open(path)?
+    } else {
+        Database::new(path)
+    };
+    let mut shell = Shell::new(db);
+
+    let mut editor = DefaultEditor::new().map_err(|e| DbError::Other(e.to_string()))?;
+    let history = history_path();
+    if let Some(history) = &history {
+        // A missing history file is expected on first use
+        let _ = editor.load_history(history);
+    }
+
+    println!("oxi-db {} - {}", crate::VERSION, path.display());
+    println!("Type 'help' for a list of commands.");
+
+    loop {
+        match editor.readline("oxi-db> ") {
+            Ok(line) => {
+                let command = line.trim();
+                if command.is_empty() {
+                    continue;
+                }
+                let _ = editor.add_history_entry(command);
+                if matches!(command, "exit" | "quit" | ".exit" | ".quit") {
+                    break;
+                }
+                match shell.execute(command) {
+                    Ok(output) => print!("{}", output),
+                    Err(e) => eprintln!("Error: {}", e),
+                }
+            }
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(DbError::Other(e.to_string())),
+        }
+    }
+
+    if let Some(history) = &history {
+        let _ = editor.save_history(history);
+    }
+    Ok(())
+}
+
+/// Location of the command history file, if a home directory is known
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".oxi_db_history"))
+}
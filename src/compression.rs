@@ -0,0 +1,125 @@
+use crate::error::{DbError, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Values whose encoding is larger than this many bytes are stored out of
+/// line in overflow pages
+pub(crate) const OVERFLOW_THRESHOLD: usize = 4096;
+
+/// Largest payload of a single overflow page
+pub(crate) const OVERFLOW_PAGE_SIZE: usize = 4096;
+
+/// Largest page that is compressed; larger pages are stored as they are, so
+/// a compressed page claiming more is damaged
+pub(crate) const MAX_COMPRESSED_PAGE_SIZE: usize = 64 << 20;
+
+/// How the pages of a table are compressed in the database file
+///
+/// Compression applies to the row, overflow and index pages of a table, each
+/// page on its own. A page that does not get smaller is stored as is.
+///
+/// # Examples
+///
+/// ```
+/// use oxi_db::Compression;
+///
+/// let compression: Compression = "lz4".parse().unwrap();
+/// assert_eq!(compression, Compression::Lz4);
+/// assert_eq!(compression.to_string(), "lz4");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Compression {
+    /// Pages are stored as they are
+    #[default]
+    None,
+    /// Pages are compressed with LZ4
+    Lz4,
+}
+
+impl Compression {
+    /// Compress a page payload, or return `None` if that does not save space
+    pub(crate) fn compress(self, payload: &[u8]) -> Option<Vec<u8>> {
+        match self {
+            _ if payload.len() > MAX_COMPRESSED_PAGE_SIZE => None,
+            Compression::None => None,
+            Compression::Lz4 => Some(lz4_flex::compress_prepend_size(payload)),
+        }
+        .filter(|compressed| compressed.len() < payload.len())
+    }
+
+    /// Decompress a page payload compressed with `compress`
+    pub(crate) fn decompress(self, data: &[u8]) -> std::result::Result<Vec<u8>, String> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Lz4 => {
+                // The size comes first; a size no page is compressed at is
+                // damage, not a reason to allocate it
+                let size = data.get(..4).map(|size| u32::from_le_bytes(size.try_into().unwrap()));
+                if size.is_some_and(|size| size as usize > MAX_COMPRESSED_PAGE_SIZE) {
+                    return Err("compressed page claims an impossible size".to_string());
+                }
+                lz4_flex::decompress_size_prepended(data).map_err(|e| format!("undecompressable page: {}", e))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Compression::None => write!(f, "none"),
+            Compression::Lz4 => write!(f, "lz4"),
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = DbError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Compression::None),
+            "lz4" => Ok(Compression::Lz4),
+            _ => Err(DbError::ParseError(format!("Unknown compression: {}", s))),
+        }
+    }
+}
+
+/// Space taken by the pages of a table in the database file
+///
+/// Byte counts cover page payloads, without the eight-byte frame of each
+/// page.
+///
+/// # Examples
+///
+/// ```
+/// use oxi_db::CompressionStats;
+///
+/// let stats = CompressionStats { raw_bytes: 1000, stored_bytes: 250, ..Default::default() };
+/// assert_eq!(stats.ratio(), 0.25);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompressionStats {
+    /// Number of pages written
+    pub pages: u64,
+    /// Number of pages stored compressed
+    pub compressed_pages: u64,
+    /// Number of overflow pages holding large values
+    pub overflow_pages: u64,
+    /// Size of the pages before compression
+    pub raw_bytes: u64,
+    /// Size of the pages as stored
+    pub stored_bytes: u64,
+}
+
+impl CompressionStats {
+    /// Get the stored size as a share of the uncompressed size
+    pub fn ratio(&self) -> f64 {
+        if self.raw_bytes == 0 {
+            1.0
+        } else {
+            self.stored_bytes as f64 / self.raw_bytes as f64
+        }
+    }
+}
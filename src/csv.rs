@@ -0,0 +1,366 @@
+use crate::decimal::{Decimal, MAX_PRECISION};
+use crate::error::{DbError, Result};
+use crate::table::Table;
+use crate::types::{encode_hex, Column, ColumnType, Key, Value};
+use std::fmt;
+use std::io::{Read, Write};
+
+/// Options for reading and writing CSV
+///
+/// The defaults follow RFC 4180: comma-separated fields, `"` for quoting
+/// and a header row with the column names. NULL is written as `\N`, so that
+/// empty text survives a round trip; on import, an empty field in a column
+/// that has no empty value (a number, say) is read as NULL as well.
+///
+/// # Examples
+///
+/// ```
+/// use oxi_db::CsvOptions;
+///
+/// let options = CsvOptions::new().delimiter(b';').null("NA");
+/// assert_eq!(options.delimiter, b';');
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+    /// Field separator
+    pub delimiter: u8,
+    /// Character used to quote fields
+    pub quote: u8,
+    /// Quote every field on export instead of only those that need it
+    pub quote_all: bool,
+    /// Whether the first record holds the column names
+    pub has_header: bool,
+    /// Field text that stands for NULL, `\N` by default
+    pub null: String,
+    /// Primary key column of a table created by an import; defaults to the
+    /// first column
+    pub primary_key: Option<String>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            quote: b'"',
+            quote_all: false,
+            has_header: true,
+            null: "\\N".to_string(),
+            primary_key: None,
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Create options with the defaults
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the field separator
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Set the quote character
+    pub fn quote(mut self, quote: u8) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    /// Quote every field on export
+    pub fn quote_all(mut self, quote_all: bool) -> Self {
+        self.quote_all = quote_all;
+        self
+    }
+
+    /// Set whether the first record holds the column names
+    pub fn has_header(mut self, has_header: bool) -> Self {
+        self.has_header = has_header;
+        self
+    }
+
+    /// Set the field text that stands for NULL
+    pub fn null(mut self, null: impl Into<String>) -> Self {
+        self.null = null.into();
+        self
+    }
+
+    /// Set the primary key column of a table created by an import
+    pub fn primary_key(mut self, column: impl Into<String>) -> Self {
+        self.primary_key = Some(column.into());
+        self
+    }
+
+    fn reader<R: Read>(&self, reader: R) -> ::csv::Reader<R> {
+        ::csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .quote(self.quote)
+            .has_headers(self.has_header)
+            .flexible(true)
+            .from_reader(reader)
+    }
+}
+
+/// The outcome of a CSV import
+///
+/// Rows that fail to parse or insert are skipped and reported; all other
+/// rows are imported.
+#[derive(Debug, Default)]
+pub struct CsvImportReport {
+    /// Number of rows inserted
+    pub imported: usize,
+    /// Rows that could not be imported
+    pub errors: Vec<CsvRowError>,
+}
+
+/// A CSV record that could not be imported
+#[derive(Debug)]
+pub struct CsvRowError {
+    /// Line of the record in the input, starting at 1
+    pub line: u64,
+    /// Why the record was rejected
+    pub error: DbError,
+}
+
+impl fmt::Display for CsvRowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+/// Write the rows of a table as CSV, in key order
+pub(crate) fn export(table: &Table, writer: impl Write, options: &CsvOptions) -> Result<()> {
+    let mut writer = ::csv::WriterBuilder::new()
+        .delimiter(options.delimiter)
+        .quote(options.quote)
+        .quote_style(if options.quote_all {
+            ::csv::QuoteStyle::Always
+        } else {
+            ::csv::QuoteStyle::Necessary
+        })
+        .from_writer(writer);
+
+    if options.has_header {
+        writer.write_record(table.columns.iter().map(|c| c.name.as_str()))?;
+    }
+    for (_, row) in table.get_all() {
+        let fields = table
+            .columns
+            .iter()
+            .zip(&row.values)
+            .map(|(column, value)| format_field(value, &column.column_type, &options.null));
+        writer.write_record(fields)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Format a value as the text of a CSV field
+fn format_field(value: &Value, column_type: &ColumnType, null: &str) -> String {
+    match value {
+        Value::Null => null.to_string(),
+        Value::Text(s) => s.clone(),
+        Value::Blob(bytes) => encode_hex(bytes),
+        Value::Array(_) | Value::Struct(_) => value.to_json(column_type).to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Insert CSV records into an existing table
+///
+/// With a header, fields are matched to columns by name and columns missing
+/// from the input are NULL; without one, fields are taken in column order.
+pub(crate) fn import(table: &mut Table, reader: impl Read, options: &CsvOptions) -> Result<CsvImportReport> {
+    if table.primary_key.is_none() {
+        return Err(DbError::Other(format!(
+            "Table '{}' needs a primary key to import CSV",
+            table.name
+        )));
+    }
+
+    let mut reader = options.reader(reader);
+    let headers = read_headers(&mut reader, options)?;
+    import_records(table, headers.as_ref(), reader.records(), options)
+}
+
+/// Create a table from CSV data, inferring its columns, and import the rows
+///
+/// The input is read once: the well-formed records decide the column types
+/// and malformed ones end up in the report, as for `import`.
+pub(crate) fn import_new(name: &str, reader: impl Read, options: &CsvOptions) -> Result<(Table, CsvImportReport)> {
+    let mut reader = options.reader(reader);
+    let headers = read_headers(&mut reader, options)?;
+    let records: Vec<::csv::Result<::csv::StringRecord>> = reader.records().collect();
+    let (columns, primary_key) = infer_schema(headers.as_ref(), &records, options)?;
+
+    let mut table = Table::new(name, columns, primary_key);
+    table.validate_schema()?;
+    let report = import_records(&mut table, headers.as_ref(), records, options)?;
+    Ok((table, report))
+}
+
+fn read_headers<R: Read>(reader: &mut ::csv::Reader<R>, options: &CsvOptions) -> Result<Option<::csv::StringRecord>> {
+    if !options.has_header {
+        return Ok(None);
+    }
+    Ok(Some(reader.headers()?.clone()))
+}
+
+fn import_records(
+    table: &mut Table,
+    headers: Option<&::csv::StringRecord>,
+    records: impl IntoIterator<Item = ::csv::Result<::csv::StringRecord>>,
+    options: &CsvOptions,
+) -> Result<CsvImportReport> {
+    let mapping: Vec<usize> = match headers {
+        Some(headers) => headers
+            .iter()
+            .map(|name| table.column_index(name.trim()))
+            .collect::<Result<_>>()?,
+        None => (0..table.columns.len()).collect(),
+    };
+
+    let mut report = CsvImportReport::default();
+    for record in records {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) if e.is_io_error() => return Err(e.into()),
+            Err(e) => {
+                let line = e.position().map_or(0, |p| p.line());
+                report.errors.push(CsvRowError { line, error: e.into() });
+                continue;
+            }
+        };
+
+        let line = record.position().map_or(0, |p| p.line());
+        match import_record(table, &record, &mapping, options) {
+            Ok(()) => report.imported += 1,
+            Err(error) => report.errors.push(CsvRowError { line, error }),
+        }
+    }
+
+    Ok(report)
+}
+
+fn import_record(
+    table: &mut Table,
+    record: &::csv::StringRecord,
+    mapping: &[usize],
+    options: &CsvOptions,
+) -> Result<()> {
+    if record.len() != mapping.len() {
+        return Err(DbError::ParseError(format!(
+            "Expected {} fields, found {}",
+            mapping.len(),
+            record.len()
+        )));
+    }
+
+    let mut values = vec![Value::Null; table.columns.len()];
+    for (field, &idx) in record.iter().zip(mapping) {
+        if field != options.null {
+            values[idx] = match Value::parse_as(field, &table.columns[idx].column_type) {
+                Err(_) if field.is_empty() => Value::Null,
+                parsed => parsed?,
+            };
+        }
+    }
+
+    let key = table
+        .primary_key
+        .as_ref()
+        .and_then(|pk| table.column_index(pk).ok())
+        .and_then(|idx| Key::from_value(&values[idx]));
+    match key {
+        Some(key) => table.insert(key, values),
+        None if table.next_auto_increment().is_some() => table.insert_auto(values).map(|_| ()),
+        None => Err(DbError::ParseError("Missing primary key value".to_string())),
+    }
+}
+
+/// Infer the columns of a new table from CSV data
+///
+/// Each column gets the narrowest type that accepts all of its non-NULL
+/// fields: `INTEGER`, `BOOLEAN`, `DECIMAL` (sized to fit), `FLOAT`, `UUID`,
+/// or `TEXT` otherwise, ignoring empty fields. Without a
+/// header the columns are named `column1`, `column2` and so on. Malformed
+/// records are left out here; the import reports them.
+fn infer_schema(
+    headers: Option<&::csv::StringRecord>,
+    records: &[::csv::Result<::csv::StringRecord>],
+    options: &CsvOptions,
+) -> Result<(Vec<Column>, Option<String>)> {
+    let records: Vec<&::csv::StringRecord> = records.iter().filter_map(|r| r.as_ref().ok()).collect();
+
+    let names: Vec<String> = match headers {
+        Some(headers) => headers.iter().map(|name| name.trim().to_string()).collect(),
+        None => {
+            let width = records.iter().map(|r| r.len()).max().unwrap_or(0);
+            (1..=width).map(|i| format!("column{}", i)).collect()
+        }
+    };
+
+    let columns: Vec<Column> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let fields: Vec<&str> = records
+                .iter()
+                .filter_map(|r| r.get(i))
+                .filter(|field| !field.is_empty() && *field != options.null)
+                .collect();
+            Column::new(name.clone(), infer_type(&fields))
+        })
+        .collect();
+
+    let primary_key = match &options.primary_key {
+        Some(pk) if !names.contains(pk) => return Err(DbError::ColumnNotFound(pk.clone())),
+        Some(pk) => Some(pk.clone()),
+        None => names.first().cloned(),
+    };
+
+    Ok((columns, primary_key))
+}
+
+fn infer_type(fields: &[&str]) -> ColumnType {
+    let fields: Vec<&str> = fields.iter().map(|f| f.trim()).collect();
+    if fields.is_empty() {
+        return ColumnType::Text;
+    }
+
+    if fields.iter().all(|f| f.parse::<i64>().is_ok()) {
+        return ColumnType::Integer;
+    }
+    if fields
+        .iter()
+        .all(|f| f.eq_ignore_ascii_case("true") || f.eq_ignore_ascii_case("false"))
+    {
+        return ColumnType::Boolean;
+    }
+
+    let decimals: Option<Vec<Decimal>> = fields
+        .iter()
+        .map(|f| if f.contains(['e', 'E']) { None } else { f.parse().ok() })
+        .collect();
+    if let Some(decimals) = decimals {
+        let scale = decimals.iter().map(Decimal::scale).max().unwrap_or(0);
+        let digits = decimals
+            .iter()
+            .map(|d| d.precision().saturating_sub(d.scale()))
+            .max()
+            .unwrap_or(0);
+        if digits + scale <= MAX_PRECISION {
+            return ColumnType::Decimal((digits + scale).max(1), scale);
+        }
+    }
+
+    if fields.iter().all(|f| f.parse::<f64>().is_ok()) {
+        return ColumnType::Float;
+    }
+    if fields.iter().all(|f| f.parse::<uuid::Uuid>().is_ok()) {
+        return ColumnType::Uuid;
+    }
+    ColumnType::Text
+}
@@ -1,10 +1,25 @@
+use crate::backup::{write_atomically, BackupProgress};
+use crate::catalog;
+use crate::compression::{Compression, CompressionStats};
+use crate::csv::{CsvImportReport, CsvOptions};
+use crate::encryption::{self, Cipher, EncryptionKey, Unlock, SEAL_OVERHEAD};
 use crate::error::{DbError, Result};
-use crate::table::Table;
+use crate::file_format::{self, IntegrityProblem, IntegrityReport};
+use crate::json::{read_objects, JsonFormat, ObjectWriter};
+use crate::planner::{self, Plan, Query, QueryResult};
+use crate::table::{RowSnapshot, Table};
+use crate::parser::coerce_literals;
+use crate::query::{Expr, Filter};
+use crate::sequence::Sequence;
+use crate::sql::{parse_script, write_dump, Statement};
+use crate::stats::DatabaseStats;
+use crate::storage::{FileStorage, PathLock, Storage};
 use crate::types::{Column, Key, Value};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
-use std::fs;
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 
 /// Database structure that manages tables and provides persistence
 ///
@@ -12,6 +27,11 @@ use std::path::{Path, PathBuf};
 /// It manages a collection of tables and handles serialization/deserialization
 /// to and from disk.
 ///
+/// Every save takes the lock of the file while it writes, so saves of the
+/// same file by several databases, in this process or another, do not mix
+/// their writes. Opening a file does not lock it, and a database opened
+/// twice saves whichever copy was saved last.
+///
 /// # Examples
 ///
 /// Creating a new database:
@@ -24,17 +44,90 @@ use std::path::{Path, PathBuf};
 ///
 /// Opening an existing database:
 ///
-/// ```
+/// ```no_run
 /// use oxi_db::Database;
 ///
 /// let db = Database::open("my_database.db").expect("Failed to open database");
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Database {
-    /// Path where the database file is stored
-    path: PathBuf,
+    /// Path where the database file is stored, or `None` for an in-memory
+    /// database
+    path: Option<PathBuf>,
     /// Collection of tables in the database
     tables: BTreeMap<String, Table>,
+    /// Named sequences stored in the database
+    sequences: BTreeMap<String, Sequence>,
+    /// Key the file is encrypted with, if any
+    #[serde(skip)]
+    cipher: Option<Cipher>,
+    /// Where the file is read from and written to
+    #[serde(skip, default = "default_storage")]
+    storage: Arc<dyn Storage>,
+    /// The catalog tables, built on first use after a change
+    #[serde(skip)]
+    catalog: OnceLock<BTreeMap<String, Table>>,
+}
+
+fn default_storage() -> Arc<dyn Storage> {
+    Arc::new(FileStorage::new())
+}
+
+/// A part of the database that a change may alter
+///
+/// `commit` keeps the parts a change names as they were, to put them back
+/// if the change fails, rather than a copy of the whole database.
+enum Scope<'a> {
+    /// Rows of a table, by key, and the table's auto-increment counter
+    Rows(&'a str, Vec<Key>),
+    /// A whole table, which need not exist yet
+    Table(&'a str),
+    /// A named sequence, which need not exist yet
+    Sequence(&'a str),
+    /// The key the file is encrypted with
+    Cipher,
+}
+
+/// A part of the database as it was before a change
+enum Undo {
+    Rows(String, RowSnapshot),
+    Table(String, Option<Box<Table>>),
+    Sequence(String, Option<Sequence>),
+    Cipher(Option<Cipher>),
+}
+
+/// Decrypt a file image if it is encrypted, returning the cipher it used
+///
+/// A key must be given exactly when the file is encrypted.
+fn decrypt(data: Vec<u8>, unlock: Option<Unlock>) -> Result<(Vec<u8>, Option<Cipher>)> {
+    match (encryption::is_encrypted(&data), unlock) {
+        (false, None) => Ok((data, None)),
+        (true, Some(unlock)) => {
+            let (plaintext, cipher) = encryption::open(&data, unlock)?;
+            Ok((plaintext, Some(cipher)))
+        }
+        (true, None) => Err(DbError::KeyRequired),
+        (false, Some(_)) => Err(DbError::Other("Database file is not encrypted".to_string())),
+    }
+}
+
+/// Check a file for damage, decrypting it with `key` first
+///
+/// A file that fails to decrypt is reported as one problem.
+fn check(storage: &dyn Storage, path: &Path, key: Option<&EncryptionKey>) -> Result<IntegrityReport> {
+    match decrypt(storage.read(path)?, key.map(Unlock::Key)) {
+        Ok((data, _)) => Ok(file_format::salvage(&data).1),
+        Err(DbError::Corruption { table, offset, message }) => Ok(IntegrityReport {
+            tables: 0,
+            rows: 0,
+            problems: vec![IntegrityProblem {
+                table,
+                offset: Some(offset),
+                message,
+            }],
+        }),
+        Err(e) => Err(e),
+    }
 }
 
 impl Database {
@@ -56,11 +149,71 @@ impl Database {
     /// ```
     pub fn new(path: impl Into<PathBuf>) -> Self {
         Self {
-            path: path.into(),
+            path: Some(path.into()),
             tables: BTreeMap::new(),
+            sequences: BTreeMap::new(),
+            cipher: None,
+            storage: default_storage(),
+            catalog: OnceLock::new(),
+        }
+    }
+
+    /// Create a new database that lives in memory only
+    ///
+    /// It behaves like a database created with `new`, except that `save`
+    /// writes nothing, so nothing is left on disk. `persist_to` gives it a
+    /// file later on.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::{Column, ColumnType, Database, Value};
+    ///
+    /// let mut db = Database::in_memory();
+    /// db.create_table("tags", vec![Column::new("name", ColumnType::Text)], Some("name".to_string())).unwrap();
+    /// db.insert("tags", "rust", vec![Value::from("rust")]).unwrap();
+    /// assert!(db.is_in_memory());
+    /// assert_eq!(db.path(), None);
+    /// ```
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            ..Self::new(PathBuf::new())
         }
     }
 
+    /// Create a new database whose file is encrypted with `key`
+    ///
+    /// The file, the temporary file written by each save and every backup
+    /// are encrypted with XChaCha20-Poly1305, which also detects tampering.
+    /// A passphrase is stretched with Argon2id once, here; saves reuse the
+    /// derived key. Exports (CSV, JSON and SQL dumps) are not encrypted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key cannot be derived from a passphrase.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::{Database, DbError, EncryptionKey};
+    ///
+    /// let key = EncryptionKey::Raw([42; 32]);
+    /// let db = Database::new_encrypted("encrypted_example.db", &key).unwrap();
+    /// db.save().unwrap();
+    /// drop(db);
+    ///
+    /// assert!(matches!(Database::open("encrypted_example.db"), Err(DbError::KeyRequired)));
+    /// let db = Database::open_encrypted("encrypted_example.db", &key).unwrap();
+    /// assert!(db.is_encrypted());
+    /// # std::fs::remove_file("encrypted_example.db").unwrap_or(());
+    /// ```
+    pub fn new_encrypted(path: impl Into<PathBuf>, key: &EncryptionKey) -> Result<Self> {
+        let mut db = Self::new(path);
+        db.cipher = Some(Cipher::new(key)?);
+        Ok(db)
+    }
+
     /// Open an existing database from the specified path
     ///
     /// This loads a database from disk. The file must exist and be a valid
@@ -83,25 +236,162 @@ impl Database {
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// use oxi_db::Database;
     ///
     /// let db = Database::open("my_database.db").expect("Failed to open database");
     /// ```
     pub fn open(path: impl AsRef<Path>) -> Result<Self> {
-        let path = path.as_ref();
+        Self::read(default_storage(), path.as_ref(), None)
+    }
+
+    /// Open a database file kept in `storage`
+    ///
+    /// The database goes on saving to `storage`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `Database::open`.
+    pub fn open_in(storage: Arc<dyn Storage>, path: impl AsRef<Path>) -> Result<Self> {
+        Self::read(storage, path.as_ref(), None)
+    }
+
+    /// Open a database file kept in `storage`, encrypted with `key`
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `Database::open_encrypted`.
+    pub fn open_encrypted_in(
+        storage: Arc<dyn Storage>,
+        path: impl AsRef<Path>,
+        key: &EncryptionKey,
+    ) -> Result<Self> {
+        Self::read(storage, path.as_ref(), Some(Unlock::Key(key)))
+    }
+
+    /// Keep the database file in `storage` instead of the local file system
+    ///
+    /// Every later save and backup goes to `storage`.
+    pub fn with_storage(mut self, storage: Arc<dyn Storage>) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// Open a database file encrypted with `key`
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::WrongKey` if `key` is not the key of the file,
+    /// `DbError::Corruption` if the file was damaged or tampered with, and
+    /// the errors of `Database::open`.
+    pub fn open_encrypted(path: impl AsRef<Path>, key: &EncryptionKey) -> Result<Self> {
+        Self::read(default_storage(), path.as_ref(), Some(Unlock::Key(key)))
+    }
+
+    /// Read a database file
+    fn read(storage: Arc<dyn Storage>, path: &Path, unlock: Option<Unlock>) -> Result<Self> {
+        let not_found = |e: io::Error| match e.kind() {
+            io::ErrorKind::NotFound => io::Error::new(io::ErrorKind::NotFound, "Database file not found"),
+            _ => e,
+        };
+        let data = storage.read(path).map_err(not_found)?;
+        let (data, cipher) = decrypt(data, unlock)?;
+        let (tables, sequences) = file_format::decode(&data)?;
+
+        // The file may have been copied or backed up from another path
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            tables,
+            sequences,
+            cipher,
+            storage,
+            catalog: OnceLock::new(),
+        })
+    }
 
-        if !path.exists() {
-            return Err(DbError::IoError(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "Database file not found",
-            )));
+    /// Load a database file into memory
+    ///
+    /// The returned database is in memory: changes to it are not written
+    /// back to the file. Use `open_encrypted`
+    /// and `into_memory` for an encrypted file.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `Database::open`.
+    pub fn load_into_memory(path: impl AsRef<Path>) -> Result<Self> {
+        Self::read(default_storage(), path.as_ref(), None).map(Self::into_memory)
+    }
+
+    /// Detach the database from its file, keeping its contents in memory
+    ///
+    /// An encrypted database keeps its key, which `persist_to` uses.
+    pub fn into_memory(mut self) -> Self {
+        self.path = None;
+        self
+    }
+
+    /// Write the database to `path` and save it there from now on
+    ///
+    /// This gives an in-memory database a file, or moves a database to a
+    /// new file, leaving the old one as it was.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::Database;
+    ///
+    /// let mut db = Database::in_memory();
+    /// db.create_sequence("tickets", 1, 1).unwrap();
+    /// db.persist_to("persist_example.db").unwrap();
+    /// assert!(!db.is_in_memory());
+    /// assert!(Database::load_into_memory("persist_example.db").is_ok());
+    /// # std::fs::remove_file("persist_example.db").unwrap_or(());
+    /// ```
+    pub fn persist_to(&mut self, path: impl Into<PathBuf>) -> Result<()> {
+        let path = path.into();
+        if path.as_os_str().is_empty() {
+            return Err(DbError::Other("Cannot persist a database to an empty path".to_string()));
         }
+        let _lock = PathLock::acquire(self.storage.clone(), &path)?;
+        write_atomically(self.storage.as_ref(), &path, &self.encode()?, |_| {})?;
+        self.path = Some(path);
+        Ok(())
+    }
 
-        let data = fs::read(path)?;
-        let db: Database = bincode::deserialize(&data)?;
+    /// Check whether the database lives in memory only
+    pub fn is_in_memory(&self) -> bool {
+        self.path.is_none()
+    }
 
-        Ok(db)
+    /// Get the path of the database file, or `None` for an in-memory database
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Check whether the database file is encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    /// Encrypt the file with a new key, or decrypt it with `None`
+    ///
+    /// The database is saved right away, so this also rotates the key of an
+    /// encrypted database. Backups taken before keep their old key.
+    pub fn set_encryption(&mut self, key: Option<&EncryptionKey>) -> Result<()> {
+        let cipher = key.map(Cipher::new).transpose()?;
+        self.commit(vec![Scope::Cipher], |db| {
+            db.cipher = cipher;
+            Ok(())
+        })
+    }
+
+    /// Encode the database as the bytes of its file
+    fn encode(&self) -> Result<Vec<u8>> {
+        let data = file_format::encode(&self.tables, &self.sequences)?;
+        match &self.cipher {
+            Some(cipher) => cipher.seal(&data),
+            None => Ok(data),
+        }
     }
 
     /// Save the database to disk
@@ -110,6 +400,13 @@ impl Database {
     /// when the database was created or opened. If the parent directory does not
     /// exist, it will be created.
     ///
+    /// The data is first written and synced to a temporary file next to the
+    /// database, which then replaces the database file, and the directory is
+    /// synced, so a crash during or after a save leaves either the old or
+    /// the new contents on disk.
+    ///
+    /// An in-memory database is not written anywhere.
+    ///
     /// # Returns
     ///
     /// A `Result` indicating success or failure
@@ -118,6 +415,7 @@ impl Database {
     ///
     /// Returns an error if:
     /// - The database cannot be serialized
+    /// - The file is being saved by another database
     /// - The file cannot be written
     /// - The parent directory cannot be created
     ///
@@ -130,17 +428,300 @@ impl Database {
     /// db.save().expect("Failed to save database");
     /// ```
     pub fn save(&self) -> Result<()> {
-        // Create parent directory if it doesn't exist
-        if let Some(parent) = self.path.parent() {
-            fs::create_dir_all(parent)?;
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let _lock = PathLock::acquire(self.storage.clone(), path)?;
+        let serialized = self.encode()?;
+        write_atomically(self.storage.as_ref(), path, &serialized, |_| {})
+    }
+
+    /// Apply a change to the parts in `scope` and save it, undoing the change if either fails
+    ///
+    /// A write that returns an error is undone in memory, so a later save
+    /// does not write it. A save that fails only at syncing the directory
+    /// has already moved the new file into place, though, so after a crash
+    /// or when the file is opened again it may hold the write after all.
+    fn commit<T>(&mut self, scope: Vec<Scope>, change: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let undo: Vec<Undo> = scope
+            .into_iter()
+            .filter_map(|part| match part {
+                Scope::Rows(name, keys) => {
+                    let rows = self.tables.get(name)?.snapshot_rows(keys);
+                    Some(Undo::Rows(name.to_string(), rows))
+                }
+                Scope::Table(name) => {
+                    let table = self.tables.get(name).cloned().map(Box::new);
+                    Some(Undo::Table(name.to_string(), table))
+                }
+                Scope::Sequence(name) => {
+                    let sequence = self.sequences.get(name).cloned();
+                    Some(Undo::Sequence(name.to_string(), sequence))
+                }
+                Scope::Cipher => Some(Undo::Cipher(self.cipher.clone())),
+            })
+            .collect();
+
+        let result = change(self).and_then(|value| self.save().map(|()| value));
+        if result.is_err() {
+            for part in undo {
+                match part {
+                    Undo::Rows(name, rows) => {
+                        if let Some(table) = self.tables.get_mut(&name) {
+                            table.restore_rows(rows);
+                        }
+                    }
+                    Undo::Table(name, Some(table)) => {
+                        self.tables.insert(name, *table);
+                    }
+                    Undo::Table(name, None) => {
+                        self.tables.remove(&name);
+                    }
+                    Undo::Sequence(name, Some(sequence)) => {
+                        self.sequences.insert(name, sequence);
+                    }
+                    Undo::Sequence(name, None) => {
+                        self.sequences.remove(&name);
+                    }
+                    Undo::Cipher(cipher) => self.cipher = cipher,
+                }
+            }
+            self.catalog.take();
         }
+        result
+    }
+
+    /// Write a consistent copy of the database to `path`
+    ///
+    /// The copy is taken from the open database rather than from its file,
+    /// so it never captures a half-written save, and like `save` it replaces
+    /// `path` only once it is complete, holding its lock meanwhile. The copy
+    /// opens with `Database::open` like any database file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::{Column, ColumnType, Database, Value};
+    ///
+    /// let mut db = Database::new("backup_example.db");
+    /// db.create_table("tags", vec![Column::new("name", ColumnType::Text)], Some("name".to_string())).unwrap();
+    /// db.insert("tags", "rust", vec![Value::from("rust")]).unwrap();
+    ///
+    /// db.backup_to("backup_example_copy.db").unwrap();
+    /// db.verify_backup("backup_example_copy.db").unwrap();
+    /// # std::fs::remove_file("backup_example.db").unwrap_or(());
+    /// # std::fs::remove_file("backup_example_copy.db").unwrap_or(());
+    /// ```
+    pub fn backup_to(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.backup_with_progress(path, |_| {})
+    }
+
+    /// Write a consistent copy of the database to `path`, reporting progress
+    ///
+    /// `progress` is called after every chunk of the copy is written, with
+    /// the number of bytes written so far and the total. It covers only the
+    /// write: the copy is encoded (and encrypted) in memory first, without
+    /// reports, since the total is only known once that is done.
+    pub fn backup_with_progress(
+        &self,
+        path: impl AsRef<Path>,
+        progress: impl FnMut(BackupProgress),
+    ) -> Result<()> {
+        let _lock = PathLock::acquire(self.storage.clone(), path.as_ref())?;
+        let serialized = self.encode()?;
+        write_atomically(self.storage.as_ref(), path.as_ref(), &serialized, progress)
+    }
+
+    /// Check that the backup at `path` holds the same data as this database
+    ///
+    /// The backup is opened and must have the same tables, each with the
+    /// same schema, row count and `Table::checksum`, and the same sequences.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of opening the backup, or `DbError::Other`
+    /// describing the first difference found.
+    pub fn verify_backup(&self, path: impl AsRef<Path>) -> Result<()> {
+        let unlock = self.cipher.as_ref().map(Unlock::Cipher);
+        let backup = Self::read(self.storage.clone(), path.as_ref(), unlock)?;
+        let mismatch = |what: String| Err(DbError::Other(format!("Backup does not match: {}", what)));
 
-        let serialized = bincode::serialize(self)?;
-        fs::write(&self.path, serialized)?;
+        if backup.list_tables() != self.list_tables() {
+            return mismatch(format!(
+                "tables are {:?}, expected {:?}",
+                backup.list_tables(),
+                self.list_tables()
+            ));
+        }
+        for (name, table) in &self.tables {
+            let copy = &backup.tables[name];
+            if copy.columns != table.columns || copy.primary_key != table.primary_key {
+                return mismatch(format!("table {} has a different schema", name));
+            }
+            if copy.len() != table.len() {
+                return mismatch(format!("table {} has {} rows, expected {}", name, copy.len(), table.len()));
+            }
+            if copy.checksum()? != table.checksum()? {
+                return mismatch(format!("table {} has different rows", name));
+            }
+        }
+        if backup.sequences != self.sequences {
+            return mismatch("sequences differ".to_string());
+        }
 
         Ok(())
     }
 
+    /// Check a database file for damage without opening it
+    ///
+    /// Every page's checksum is verified, every row is checked against its
+    /// table's columns, and every index is compared with the rows. Files in
+    /// the layout written before pages were introduced are checked as far
+    /// as they can be decoded.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::KeyRequired` if the file is encrypted (see
+    /// `integrity_check_encrypted`), and an error if the file cannot be
+    /// read; damage is listed in the report.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::Database;
+    ///
+    /// let db = Database::new("integrity_example.db");
+    /// db.save().unwrap();
+    ///
+    /// let report = Database::integrity_check("integrity_example.db").unwrap();
+    /// assert!(report.is_ok());
+    /// # std::fs::remove_file("integrity_example.db").unwrap_or(());
+    /// ```
+    pub fn integrity_check(path: impl AsRef<Path>) -> Result<IntegrityReport> {
+        Self::integrity_check_in(default_storage(), path)
+    }
+
+    /// Check a database file kept in `storage` for damage
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `Database::integrity_check`.
+    pub fn integrity_check_in(storage: Arc<dyn Storage>, path: impl AsRef<Path>) -> Result<IntegrityReport> {
+        check(storage.as_ref(), path.as_ref(), None)
+    }
+
+    /// Check a database file encrypted with `key` for damage
+    ///
+    /// The file is decrypted before its pages are checked. Encryption
+    /// authenticates the file as a whole, so damage to an encrypted file is
+    /// reported as a single problem, with no tables or rows.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::WrongKey` if `key` is not the key of the file, and
+    /// an error if the file is not encrypted or cannot be read.
+    pub fn integrity_check_encrypted(path: impl AsRef<Path>, key: &EncryptionKey) -> Result<IntegrityReport> {
+        Self::integrity_check_encrypted_in(default_storage(), path, key)
+    }
+
+    /// Check a database file kept in `storage`, encrypted with `key`, for
+    /// damage
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `Database::integrity_check_encrypted`.
+    pub fn integrity_check_encrypted_in(
+        storage: Arc<dyn Storage>,
+        path: impl AsRef<Path>,
+        key: &EncryptionKey,
+    ) -> Result<IntegrityReport> {
+        check(storage.as_ref(), path.as_ref(), Some(key))
+    }
+
+    /// Recover everything readable from a damaged file into a new file
+    ///
+    /// Damaged pages, rows that do not match their table and rows of tables
+    /// whose schema is lost are left out; indexes are rebuilt from the rows
+    /// that remain. The recovered database is written to `destination`, and
+    /// the report lists what was left out.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::KeyRequired` if `source` is encrypted (see
+    /// `repair_encrypted`), and an error if `source` cannot be read or
+    /// `destination` cannot be written.
+    pub fn repair(source: impl AsRef<Path>, destination: impl AsRef<Path>) -> Result<IntegrityReport> {
+        Self::repair_in(default_storage(), source, destination)
+    }
+
+    /// Recover everything readable from a damaged file kept in `storage`
+    /// into a new file there
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `Database::repair`.
+    pub fn repair_in(
+        storage: Arc<dyn Storage>,
+        source: impl AsRef<Path>,
+        destination: impl AsRef<Path>,
+    ) -> Result<IntegrityReport> {
+        Self::repair_with(storage, source.as_ref(), destination.as_ref(), None)
+    }
+
+    /// Recover everything readable from a damaged file encrypted with `key`
+    /// into a new file, encrypted with the same key
+    ///
+    /// Only damage found once the file is decrypted can be repaired: a file
+    /// that fails to decrypt has nothing to recover.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::WrongKey` if `key` is not the key of `source`,
+    /// `DbError::Corruption` if `source` cannot be decrypted, and the errors
+    /// of `Database::repair`.
+    pub fn repair_encrypted(
+        source: impl AsRef<Path>,
+        destination: impl AsRef<Path>,
+        key: &EncryptionKey,
+    ) -> Result<IntegrityReport> {
+        Self::repair_encrypted_in(default_storage(), source, destination, key)
+    }
+
+    /// Recover everything readable from a damaged file kept in `storage`,
+    /// encrypted with `key`, into a new file there
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `Database::repair_encrypted`.
+    pub fn repair_encrypted_in(
+        storage: Arc<dyn Storage>,
+        source: impl AsRef<Path>,
+        destination: impl AsRef<Path>,
+        key: &EncryptionKey,
+    ) -> Result<IntegrityReport> {
+        Self::repair_with(storage, source.as_ref(), destination.as_ref(), Some(key))
+    }
+
+    fn repair_with(
+        storage: Arc<dyn Storage>,
+        source: &Path,
+        destination: &Path,
+        key: Option<&EncryptionKey>,
+    ) -> Result<IntegrityReport> {
+        let (data, cipher) = decrypt(storage.read(source)?, key.map(Unlock::Key))?;
+        let ((tables, sequences), report) = file_format::salvage(&data);
+        let repaired = Self {
+            path: Some(destination.to_path_buf()),
+            tables,
+            sequences,
+            cipher,
+            storage,
+            catalog: OnceLock::new(),
+        };
+        repaired.save()?;
+        Ok(report)
+    }
+
     /// Create a new table in the database
     pub fn create_table(
         &mut self,
@@ -150,50 +731,102 @@ impl Database {
     ) -> Result<()> {
         let name = name.into();
 
-        if self.tables.contains_key(&name) {
+        if self.has_table(&name) {
             return Err(DbError::TableExists);
         }
 
         let table = Table::new(name.clone(), columns, primary_key);
-        self.tables.insert(name, table);
-
-        self.save()
+        table.validate_schema()?;
+        self.commit(vec![Scope::Table(&name)], |db| {
+            db.catalog.take();
+            db.tables.insert(name.clone(), table);
+            Ok(())
+        })
     }
 
     /// Drop a table from the database
     pub fn drop_table(&mut self, name: &str) -> Result<()> {
-        if !self.tables.contains_key(name) {
-            return Err(DbError::TableNotFound);
-        }
-
-        self.tables.remove(name);
-        self.save()
+        self.get_table_mut(name)?;
+        self.commit(vec![Scope::Table(name)], |db| {
+            db.tables.remove(name);
+            Ok(())
+        })
     }
 
     /// Get a reference to a table
+    ///
+    /// The catalog tables named in `CATALOG_TABLES` describe the tables,
+    /// columns, indexes and sequences of the database and can be read and
+    /// queried like any other table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::{Column, ColumnType, Database, Filter};
+    ///
+    /// let mut db = Database::in_memory();
+    /// db.create_table("users", vec![Column::new("name", ColumnType::Text)], None).unwrap();
+    ///
+    /// let columns = db.find_where("oxi_columns", &Filter::parse("table = 'users'").unwrap()).unwrap();
+    /// assert_eq!(columns.len(), 1);
+    /// assert_eq!(db.get_table("oxi_tables").unwrap().len(), 1);
+    /// ```
     pub fn get_table(&self, name: &str) -> Result<&Table> {
-        self.tables
-            .get(name)
-            .ok_or(DbError::TableNotFound)
+        match self.tables.get(name) {
+            Some(table) => Ok(table),
+            None if catalog::is_catalog_table(name) => self
+                .catalog
+                .get_or_init(|| catalog::build(&self.tables, &self.sequences))
+                .get(name)
+                .ok_or(DbError::TableNotFound),
+            None => Err(DbError::TableNotFound),
+        }
     }
 
     /// Get a mutable reference to a table
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ReadOnlyTable` for a catalog table.
     pub fn get_table_mut(&mut self, name: &str) -> Result<&mut Table> {
+        if catalog::is_catalog_table(name) && !self.tables.contains_key(name) {
+            return Err(DbError::ReadOnlyTable(name.to_string()));
+        }
+        self.catalog.take();
         self.tables
             .get_mut(name)
             .ok_or(DbError::TableNotFound)
     }
 
-    /// List all tables in the database
+    /// List the tables in the database, without the catalog tables
     pub fn list_tables(&self) -> Vec<String> {
         self.tables.keys().cloned().collect()
     }
 
+    /// Check if a table of the database or of the catalog has this name
+    fn has_table(&self, name: &str) -> bool {
+        self.tables.contains_key(name) || catalog::is_catalog_table(name)
+    }
+
     /// Insert a row into a table
     pub fn insert(&mut self, table_name: &str, key: impl Into<Key>, values: Vec<Value>) -> Result<()> {
-        let table = self.get_table_mut(table_name)?;
-        table.insert(key, values)?;
-        self.save()
+        let key = key.into();
+        self.commit(vec![Scope::Rows(table_name, vec![key.clone()])], |db| {
+            db.get_table_mut(table_name)?.insert(key, values)
+        })
+    }
+
+    /// Insert a row into a table, assigning the next auto-increment key
+    ///
+    /// The assigned key is returned once the row has been saved.
+    pub fn insert_auto(&mut self, table_name: &str, values: Vec<Value>) -> Result<Key> {
+        // The key the row gets, given or the next value of the counter
+        let key = self.tables.get(table_name).and_then(|table| {
+            let next = table.next_auto_increment().map(Key::Integer);
+            table.key_from_values(&values).or(next)
+        });
+        let scope = key.map(|key| Scope::Rows(table_name, vec![key])).into_iter().collect();
+        self.commit(scope, |db| db.get_table_mut(table_name)?.insert_auto(values))
     }
 
     /// Get a row from a table
@@ -204,16 +837,534 @@ impl Database {
 
     /// Update a row in a table
     pub fn update(&mut self, table_name: &str, key: &Key, values: Vec<Value>) -> Result<()> {
-        let table = self.get_table_mut(table_name)?;
-        table.update(key, values)?;
-        self.save()
+        self.commit(vec![Scope::Rows(table_name, vec![key.clone()])], |db| {
+            db.get_table_mut(table_name)?.update(key, values)
+        })
+    }
+
+    /// Insert a row into a table, or replace it if the key already exists
+    ///
+    /// Returns `true` if a new row was inserted.
+    pub fn upsert(&mut self, table_name: &str, key: impl Into<Key>, values: Vec<Value>) -> Result<bool> {
+        let key = key.into();
+        self.commit(vec![Scope::Rows(table_name, vec![key.clone()])], |db| {
+            db.get_table_mut(table_name)?.upsert(key, values)
+        })
+    }
+
+    /// Update some columns of a row by name, keeping the other values
+    pub fn update_columns(&mut self, table_name: &str, key: &Key, updates: &[(&str, Value)]) -> Result<()> {
+        self.commit(vec![Scope::Rows(table_name, vec![key.clone()])], |db| {
+            db.get_table_mut(table_name)?.update_columns(key, updates)
+        })
+    }
+
+    /// Replace a row only if it currently equals `expected`
+    pub fn compare_and_swap(
+        &mut self,
+        table_name: &str,
+        key: &Key,
+        expected: &Row,
+        values: Vec<Value>,
+    ) -> Result<()> {
+        self.commit(vec![Scope::Rows(table_name, vec![key.clone()])], |db| {
+            db.get_table_mut(table_name)?.compare_and_swap(key, expected, values)
+        })
     }
 
     /// Delete a row from a table
     pub fn delete(&mut self, table_name: &str, key: &Key) -> Result<()> {
-        let table = self.get_table_mut(table_name)?;
-        table.delete(key)?;
-        self.save()
+        self.commit(vec![Scope::Rows(table_name, vec![key.clone()])], |db| {
+            db.get_table_mut(table_name)?.delete(key)
+        })
+    }
+
+    /// Find rows in a table that match a filter
+    pub fn find_where(&self, table_name: &str, filter: &Filter) -> Result<Vec<(Key, Row)>> {
+        let table = self.get_table(table_name)?;
+        table.find_where(filter)
+    }
+
+    /// Write a table as CSV
+    ///
+    /// See `Table::export_csv`.
+    pub fn export_csv(&self, table_name: &str, writer: impl Write, options: &CsvOptions) -> Result<()> {
+        self.get_table(table_name)?.export_csv(writer, options)
+    }
+
+    /// Import CSV into a table, creating the table if it does not exist
+    ///
+    /// A new table gets one column per CSV field, typed by inspecting the
+    /// data (integers, booleans, decimals, floats, UUIDs, otherwise text),
+    /// and `options.primary_key` or the first column as its primary key.
+    /// Rows are then inserted as by `Table::import_csv`, and the database is
+    /// saved once at the end.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::{ColumnType, CsvOptions, Database};
+    ///
+    /// let mut db = Database::new("csv_example.db");
+    /// let csv = "sku;price;in_stock\nA-1;9.99;true\nB-2;12.50;false\nC-3;oops;true\n";
+    ///
+    /// let report = db.import_csv("products", csv.as_bytes(), &CsvOptions::new().delimiter(b';')).unwrap();
+    /// assert_eq!(report.imported, 3);
+    ///
+    /// let table = db.get_table("products").unwrap();
+    /// assert_eq!(table.columns[1].column_type, ColumnType::Text);
+    /// assert_eq!(table.columns[2].column_type, ColumnType::Boolean);
+    /// # std::fs::remove_file("csv_example.db").unwrap_or(());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `Table::import_csv`, and `DbError::IoError` if
+    /// the database cannot be saved. Errors in individual rows are reported
+    /// in the returned `CsvImportReport` instead.
+    pub fn import_csv(
+        &mut self,
+        table_name: &str,
+        reader: impl Read,
+        options: &CsvOptions,
+    ) -> Result<CsvImportReport> {
+        if self.has_table(table_name) {
+            return self.commit(vec![Scope::Table(table_name)], |db| {
+                db.get_table_mut(table_name)?.import_csv(reader, options)
+            });
+        }
+
+        let (table, report) = crate::csv::import_new(table_name, reader, options)?;
+        self.commit(vec![Scope::Table(table_name)], |db| {
+            db.catalog.take();
+            db.tables.insert(table_name.to_string(), table);
+            Ok(report)
+        })
+    }
+
+    /// Write the rows of a table as JSON
+    ///
+    /// See `Table::export_json`.
+    pub fn export_table_json(&self, table_name: &str, writer: impl Write, format: JsonFormat) -> Result<()> {
+        self.get_table(table_name)?.export_json(writer, format)
+    }
+
+    /// Insert rows read as JSON into an existing table
+    ///
+    /// Rows are inserted as by `Table::import_json`, but the import is all
+    /// or nothing: if any row fails, the table is left unchanged. Returns
+    /// the number of rows inserted.
+    pub fn import_table_json(&mut self, table_name: &str, reader: impl Read, format: JsonFormat) -> Result<usize> {
+        self.commit(vec![Scope::Table(table_name)], |db| {
+            db.get_table_mut(table_name)?.import_json(reader, format)
+        })
+    }
+
+    /// Write the whole database as a stream of JSON objects
+    ///
+    /// Each sequence is written as `{"_sequence": {...}}`, and each table as
+    /// a `{"_table": {...}}` object holding its schema (see
+    /// `Table::schema_to_json`) followed by one object per row.
+    /// `Database::import_json` reads the stream back into another database.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::{Column, ColumnType, Database, JsonFormat, Value};
+    ///
+    /// let mut db = Database::new("json_export_example.db");
+    /// db.create_table("tags", vec![Column::new("name", ColumnType::Text)], Some("name".to_string())).unwrap();
+    /// db.insert("tags", "rust", vec![Value::from("rust")]).unwrap();
+    ///
+    /// let mut dump = Vec::new();
+    /// db.export_json(&mut dump, JsonFormat::Ndjson).unwrap();
+    ///
+    /// let mut copy = Database::new("json_import_example.db");
+    /// assert_eq!(copy.import_json(dump.as_slice(), JsonFormat::Ndjson).unwrap(), 1);
+    /// assert_eq!(copy.get_table("tags").unwrap().len(), 1);
+    /// # std::fs::remove_file("json_export_example.db").unwrap_or(());
+    /// # std::fs::remove_file("json_import_example.db").unwrap_or(());
+    /// ```
+    pub fn export_json(&self, writer: impl Write, format: JsonFormat) -> Result<()> {
+        let mut writer = ObjectWriter::new(writer, format);
+        for sequence in self.sequences.values() {
+            writer.write(&serde_json::json!({ "_sequence": sequence }))?;
+        }
+        for table in self.tables.values() {
+            writer.write(&serde_json::json!({ "_table": table.schema_to_json() }))?;
+            table.write_json_rows(&mut writer)?;
+        }
+        writer.finish()
+    }
+
+    /// Read a stream written by `Database::export_json` into this database
+    ///
+    /// Tables and sequences in the stream are created, and rows are
+    /// inserted into the table described before them. Nothing is changed
+    /// unless the whole stream is imported. Returns the number of rows
+    /// inserted.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::TableExists` or `DbError::SequenceExists` if the
+    /// stream defines a table or sequence that already exists, and the
+    /// first error of any row.
+    pub fn import_json(&mut self, reader: impl Read, format: JsonFormat) -> Result<usize> {
+        let mut tables: Vec<Table> = Vec::new();
+        let mut sequences: Vec<Sequence> = Vec::new();
+        let mut count = 0;
+
+        read_objects(reader, format, |object| {
+            if let Some(schema) = object.get("_table") {
+                let table = Table::from_schema_json(schema)?;
+                if self.has_table(&table.name) || tables.iter().any(|t| t.name == table.name) {
+                    return Err(DbError::TableExists);
+                }
+                tables.push(table);
+            } else if let Some(sequence) = object.get("_sequence") {
+                let sequence: Sequence = serde_json::from_value(sequence.clone())?;
+                if self.sequences.contains_key(&sequence.name) || sequences.iter().any(|s| s.name == sequence.name) {
+                    return Err(DbError::SequenceExists(sequence.name));
+                }
+                sequences.push(sequence);
+            } else {
+                let table = tables
+                    .last_mut()
+                    .ok_or_else(|| DbError::ParseError("Row before any table".to_string()))?;
+                table.insert_json(object)?;
+                count += 1;
+            }
+            Ok(())
+        })?;
+
+        self.add_all(tables, sequences)?;
+        Ok(count)
+    }
+
+    /// Write the whole database as a script of SQL statements
+    ///
+    /// The script creates every sequence and table, inserts the rows of each
+    /// table, sets its auto-increment counter and then creates its indexes.
+    /// `Database::restore` replays it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::{Column, ColumnType, Database, Value};
+    ///
+    /// let mut db = Database::new("sql_dump_example.db");
+    /// db.create_table("tags", vec![Column::new("name", ColumnType::Text)], Some("name".to_string())).unwrap();
+    /// db.insert("tags", "rust", vec![Value::from("rust")]).unwrap();
+    ///
+    /// let mut dump = Vec::new();
+    /// db.dump(&mut dump).unwrap();
+    /// assert!(String::from_utf8_lossy(&dump).contains("INSERT INTO tags VALUES ('rust');"));
+    ///
+    /// let mut copy = Database::new("sql_restore_example.db");
+    /// assert_eq!(copy.restore(dump.as_slice()).unwrap(), 1);
+    /// # std::fs::remove_file("sql_dump_example.db").unwrap_or(());
+    /// # std::fs::remove_file("sql_restore_example.db").unwrap_or(());
+    /// ```
+    pub fn dump(&self, writer: impl Write) -> Result<()> {
+        write_dump(self, writer)
+    }
+
+    /// Replay a script written by `Database::dump` into this database
+    ///
+    /// Rows may only be inserted into tables the script creates, and their
+    /// literals are converted to the column types. Nothing is changed unless
+    /// the whole script succeeds. Returns the number of rows
+    /// inserted.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ParseError` for a malformed statement,
+    /// `DbError::TableExists` or `DbError::SequenceExists` if the script
+    /// creates a table or sequence that already exists, and the first error
+    /// of any statement.
+    pub fn restore(&mut self, mut reader: impl Read) -> Result<usize> {
+        let mut script = String::new();
+        reader.read_to_string(&mut script)?;
+
+        let mut tables: Vec<Table> = Vec::new();
+        let mut sequences: Vec<Sequence> = Vec::new();
+        let mut count = 0;
+
+        for statement in parse_script(&script)? {
+            match statement {
+                Statement::CreateSequence { name, start, increment } => {
+                    if self.sequences.contains_key(&name) || sequences.iter().any(|s| s.name == name) {
+                        return Err(DbError::SequenceExists(name));
+                    }
+                    sequences.push(Sequence::new(name, start, increment)?);
+                }
+                Statement::CreateTable { name, columns, primary_key } => {
+                    if self.has_table(&name) || tables.iter().any(|t| t.name == name) {
+                        return Err(DbError::TableExists);
+                    }
+                    let table = Table::new(name, columns, primary_key);
+                    table.validate_schema()?;
+                    tables.push(table);
+                }
+                Statement::CreateIndex { name, table, expr } => {
+                    let table = tables
+                        .iter_mut()
+                        .find(|t| t.name == table)
+                        .ok_or(DbError::TableNotFound)?;
+                    table.create_index(name, expr)?;
+                }
+                Statement::Insert { table, key, values } => {
+                    let table = tables
+                        .iter_mut()
+                        .find(|t| t.name == table)
+                        .ok_or(DbError::TableNotFound)?;
+                    let values = coerce_literals(values, &table.columns)?;
+                    match key.or_else(|| table.key_from_values(&values)) {
+                        Some(key) => table.insert(key, values)?,
+                        None if table.next_auto_increment().is_some() => {
+                            table.insert_auto(values)?;
+                        }
+                        None => return Err(DbError::ParseError("INSERT without a key".to_string())),
+                    }
+                    count += 1;
+                }
+                Statement::SetAutoIncrement { table, next } => {
+                    tables
+                        .iter_mut()
+                        .find(|t| t.name == table)
+                        .ok_or(DbError::TableNotFound)?
+                        .advance_auto_increment(next)?;
+                }
+            }
+        }
+
+        self.add_all(tables, sequences)?;
+        Ok(count)
+    }
+
+    /// Add new tables and sequences and save them, all or nothing
+    fn add_all(&mut self, tables: Vec<Table>, sequences: Vec<Sequence>) -> Result<()> {
+        let table_names: Vec<String> = tables.iter().map(|table| table.name.clone()).collect();
+        let sequence_names: Vec<String> = sequences.iter().map(|sequence| sequence.name.clone()).collect();
+        let scope = table_names
+            .iter()
+            .map(|name| Scope::Table(name))
+            .chain(sequence_names.iter().map(|name| Scope::Sequence(name)))
+            .collect();
+        self.commit(scope, |db| {
+            db.catalog.take();
+            for table in tables {
+                db.tables.insert(table.name.clone(), table);
+            }
+            for sequence in sequences {
+                db.sequences.insert(sequence.name.clone(), sequence);
+            }
+            Ok(())
+        })
+    }
+
+    /// Create a secondary index on a table
+    pub fn create_index(&mut self, table_name: &str, index_name: impl Into<String>, expr: Expr) -> Result<()> {
+        self.commit(vec![Scope::Table(table_name)], |db| {
+            db.get_table_mut(table_name)?.create_index(index_name, expr)
+        })
+    }
+
+    /// Set how the pages of a table are compressed in the database file
+    ///
+    /// The database is saved right away, so the file shrinks (or grows back)
+    /// immediately.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::{Column, ColumnType, Compression, Database, Value};
+    ///
+    /// let mut db = Database::new("compression_example.db");
+    /// db.create_table("notes", vec![Column::new("text", ColumnType::Text)], None).unwrap();
+    /// db.insert("notes", 1, vec![Value::from("la ".repeat(1000))]).unwrap();
+    ///
+    /// db.set_compression("notes", Compression::Lz4).unwrap();
+    /// let stats = db.compression_stats("notes").unwrap();
+    /// assert!(stats.stored_bytes < stats.raw_bytes);
+    /// # std::fs::remove_file("compression_example.db").unwrap_or(());
+    /// ```
+    pub fn set_compression(&mut self, table_name: &str, compression: Compression) -> Result<()> {
+        self.commit(vec![Scope::Table(table_name)], |db| {
+            db.get_table_mut(table_name)?.set_compression(compression);
+            Ok(())
+        })
+    }
+
+    /// Measure the space the pages of a table take in the database file
+    pub fn compression_stats(&self, table_name: &str) -> Result<CompressionStats> {
+        file_format::table_stats(self.get_table(table_name)?)
+    }
+
+    /// Gather the size of the database file and the statistics of every table
+    ///
+    /// See `Table::stats` for a single table. The size of the file is asked
+    /// from the storage without reading it, and the size the next save
+    /// writes is added up from the sizes of the pages, without encoding or
+    /// encrypting the database.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::{Column, ColumnType, Database, Value};
+    ///
+    /// let mut db = Database::in_memory();
+    /// db.create_table("notes", vec![Column::new("text", ColumnType::Text)], None).unwrap();
+    /// db.insert("notes", 1, vec![Value::from("hello")]).unwrap();
+    ///
+    /// let stats = db.stats().unwrap();
+    /// assert_eq!(stats.rows(), 1);
+    /// assert_eq!(stats.table("notes").unwrap().estimated_depth, 1);
+    /// ```
+    pub fn stats(&self) -> Result<DatabaseStats> {
+        let tables = self.tables.values().map(Table::stats).collect::<Result<Vec<_>>>()?;
+        let table_bytes = tables.iter().map(|table| table.bytes).sum();
+        let mut used_bytes = file_format::encoded_size(&self.sequences, table_bytes)?;
+        if self.cipher.is_some() {
+            used_bytes += SEAL_OVERHEAD;
+        }
+        let file_bytes = match self.path.as_deref().map(|path| self.storage.size(path)) {
+            None => 0,
+            Some(Ok(size)) => size,
+            Some(Err(e)) if e.kind() == io::ErrorKind::NotFound => 0,
+            Some(Err(e)) => return Err(e.into()),
+        };
+
+        Ok(DatabaseStats {
+            tables,
+            sequences: self.sequences.len() as u64,
+            file_bytes,
+            used_bytes,
+            free_bytes: file_bytes.saturating_sub(used_bytes),
+        })
+    }
+
+    /// Gather the statistics the planner estimates query costs with
+    ///
+    /// See `Table::analyze`. The statistics are kept in memory only, so a
+    /// database opened from a file starts without them.
+    pub fn analyze(&mut self) -> Result<()> {
+        self.tables.values_mut().try_for_each(Table::analyze)
+    }
+
+    /// Choose a plan for a query without running it
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::TableNotFound` or `DbError::ColumnNotFound` if the
+    /// query names a table or column that does not exist, and
+    /// `DbError::ParseError` for a column name that fits both tables of a
+    /// join or a join condition that does not compare the two tables.
+    pub fn plan(&self, query: &Query) -> Result<Plan> {
+        planner::plan_query(query, &|name| self.get_table(name))
+    }
+
+    /// Run a query with the plan `plan` chooses for it
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::{Column, ColumnType, Database, Query, Value};
+    ///
+    /// let mut db = Database::in_memory();
+    /// let users = vec![Column::new("id", ColumnType::Integer), Column::new("name", ColumnType::Text)];
+    /// let orders = vec![Column::new("user", ColumnType::Integer), Column::new("total", ColumnType::Integer)];
+    /// db.create_table("users", users, Some("id".to_string())).unwrap();
+    /// db.create_table("orders", orders, None).unwrap();
+    /// db.insert("users", 1, vec![Value::Integer(1), Value::from("Alice")]).unwrap();
+    /// db.insert("orders", 1, vec![Value::Integer(1), Value::Integer(30)]).unwrap();
+    /// db.insert("orders", 2, vec![Value::Integer(1), Value::Integer(70)]).unwrap();
+    ///
+    /// let query = Query::parse("orders JOIN users ON user = users.id WHERE total > 50").unwrap();
+    /// let result = db.query(&query).unwrap();
+    /// assert_eq!(result.rows.len(), 1);
+    /// assert_eq!(result.columns[3].name, "users.name");
+    /// assert_eq!(result.rows[0].1.values[3], Value::from("Alice"));
+    /// ```
+    pub fn query(&self, query: &Query) -> Result<QueryResult> {
+        let tables = |name: &str| self.get_table(name);
+        let mut plan = planner::plan_query(query, &tables)?;
+        let rows = plan.run(&tables)?;
+        Ok(QueryResult {
+            columns: plan.columns(&tables)?,
+            rows,
+        })
+    }
+
+    /// Run a query and return its plan with the rows every step produced
+    ///
+    /// The plan's `Display` shows the estimated and the actual row counts
+    /// side by side.
+    pub fn explain(&self, query: &Query) -> Result<Plan> {
+        let tables = |name: &str| self.get_table(name);
+        let mut plan = planner::plan_query(query, &tables)?;
+        plan.run(&tables)?;
+        Ok(plan)
+    }
+
+    /// Create a named sequence starting at `start` and advancing by `increment`
+    pub fn create_sequence(&mut self, name: impl Into<String>, start: i64, increment: i64) -> Result<()> {
+        let name = name.into();
+
+        if self.sequences.contains_key(&name) {
+            return Err(DbError::SequenceExists(name));
+        }
+
+        let sequence = Sequence::new(name.clone(), start, increment)?;
+        self.commit(vec![Scope::Sequence(&name)], |db| {
+            db.catalog.take();
+            db.sequences.insert(name.clone(), sequence);
+            Ok(())
+        })
+    }
+
+    /// Drop a named sequence
+    pub fn drop_sequence(&mut self, name: &str) -> Result<()> {
+        self.commit(vec![Scope::Sequence(name)], |db| {
+            db.catalog.take();
+            match db.sequences.remove(name) {
+                Some(_) => Ok(()),
+                None => Err(DbError::SequenceNotFound(name.to_string())),
+            }
+        })
+    }
+
+    /// Get a named sequence
+    pub fn get_sequence(&self, name: &str) -> Result<&Sequence> {
+        self.sequences
+            .get(name)
+            .ok_or_else(|| DbError::SequenceNotFound(name.to_string()))
+    }
+
+    /// List all sequences in the database
+    pub fn list_sequences(&self) -> Vec<String> {
+        self.sequences.keys().cloned().collect()
+    }
+
+    /// Take the next value from a named sequence
+    ///
+    /// The advanced sequence is saved before the value is returned, so the
+    /// value is never handed out again, even if the process crashes.
+    pub fn next_value(&mut self, name: &str) -> Result<i64> {
+        self.commit(vec![Scope::Sequence(name)], |db| {
+            db.catalog.take();
+            db.sequences
+                .get_mut(name)
+                .ok_or_else(|| DbError::SequenceNotFound(name.to_string()))?
+                .next_value()
+        })
+    }
+
+    /// Drop a secondary index from a table
+    pub fn drop_index(&mut self, table_name: &str, index_name: &str) -> Result<()> {
+        self.commit(vec![Scope::Table(table_name)], |db| {
+            db.get_table_mut(table_name)?.drop_index(index_name)
+        })
     }
 }
 
@@ -0,0 +1,442 @@
+use crate::error::{DbError, Result};
+use serde::de::{self, Deserializer, Visitor};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Neg;
+use std::str::FromStr;
+
+/// Maximum number of significant digits a `Decimal` can hold
+pub const MAX_PRECISION: u32 = 38;
+
+/// Rounding strategy used when a decimal has to lose digits
+///
+/// Rounding happens when a value is rescaled to fewer fractional digits or
+/// when the result of a division cannot be represented exactly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoundingMode {
+    /// Round towards zero (truncate)
+    Down,
+    /// Round away from zero
+    Up,
+    /// Round towards negative infinity
+    Floor,
+    /// Round towards positive infinity
+    Ceiling,
+    /// Round to nearest, ties away from zero
+    HalfUp,
+    /// Round to nearest, ties towards zero
+    HalfDown,
+    /// Round to nearest, ties to the even neighbour (banker's rounding)
+    HalfEven,
+}
+
+/// An exact fixed-point decimal number
+///
+/// A `Decimal` is stored as an integer mantissa and a scale, so that the
+/// represented number is `mantissa / 10^scale`. Unlike `f64`, every value
+/// written in base 10 with up to 38 significant digits is represented
+/// exactly, which makes it suitable for money and other billing data.
+///
+/// # Examples
+///
+/// ```
+/// use oxi_db::{Decimal, RoundingMode};
+///
+/// let price: Decimal = "19.99".parse().unwrap();
+/// let quantity = Decimal::from(3);
+///
+/// let total = price.checked_mul(&quantity).unwrap();
+/// assert_eq!(total.to_string(), "59.97");
+///
+/// let share = total
+///     .checked_div(&Decimal::from(7), 2, RoundingMode::HalfEven)
+///     .unwrap();
+/// assert_eq!(share.to_string(), "8.57");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    /// Create a decimal from a mantissa and a scale
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::NumericOverflow` if the scale or the number of digits
+    /// exceeds `MAX_PRECISION`.
+    pub fn new(mantissa: i128, scale: u32) -> Result<Self> {
+        let decimal = Self { mantissa, scale };
+        if scale > MAX_PRECISION || decimal.precision() > MAX_PRECISION {
+            return Err(DbError::NumericOverflow);
+        }
+        Ok(decimal)
+    }
+
+    /// The decimal zero with a scale of 0
+    pub fn zero() -> Self {
+        Self {
+            mantissa: 0,
+            scale: 0,
+        }
+    }
+
+    /// The unscaled integer value
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    /// The number of digits after the decimal point
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// The number of significant digits in the mantissa
+    pub fn precision(&self) -> u32 {
+        let mut digits = 1;
+        let mut rest = self.mantissa.unsigned_abs() / 10;
+        while rest > 0 {
+            digits += 1;
+            rest /= 10;
+        }
+        digits
+    }
+
+    /// Check if the value is zero
+    pub fn is_zero(&self) -> bool {
+        self.mantissa == 0
+    }
+
+    /// Check if the value is strictly negative
+    pub fn is_negative(&self) -> bool {
+        self.mantissa < 0
+    }
+
+    /// Check if the value can be stored in a `DECIMAL(precision, scale)` column
+    ///
+    /// A value fits when it has at most `scale` fractional digits and at most
+    /// `precision - scale` integer digits.
+    pub fn fits(&self, precision: u32, scale: u32) -> bool {
+        if self.scale > scale || scale > precision {
+            return false;
+        }
+        match self.rescale(scale, RoundingMode::Down) {
+            Ok(rescaled) => rescaled.precision() <= precision,
+            Err(_) => false,
+        }
+    }
+
+    /// Change the number of fractional digits, rounding if digits are lost
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::NumericOverflow` if the result does not fit in
+    /// `MAX_PRECISION` digits.
+    pub fn rescale(&self, scale: u32, mode: RoundingMode) -> Result<Self> {
+        match scale.cmp(&self.scale) {
+            Ordering::Equal => Ok(*self),
+            Ordering::Greater => {
+                let factor = pow10(scale - self.scale)?;
+                let mantissa = self
+                    .mantissa
+                    .checked_mul(factor)
+                    .ok_or(DbError::NumericOverflow)?;
+                Self::new(mantissa, scale)
+            }
+            Ordering::Less => {
+                let factor = pow10(self.scale - scale)?;
+                Self::new(div_round(self.mantissa, factor, mode), scale)
+            }
+        }
+    }
+
+    /// Round to `scale` fractional digits using the given rounding mode
+    ///
+    /// Unlike `rescale`, this never adds trailing zeros.
+    pub fn round(&self, scale: u32, mode: RoundingMode) -> Result<Self> {
+        if scale >= self.scale {
+            return Ok(*self);
+        }
+        self.rescale(scale, mode)
+    }
+
+    /// Remove trailing fractional zeros without changing the value
+    pub fn normalize(&self) -> Self {
+        let mut result = *self;
+        while result.scale > 0 && result.mantissa % 10 == 0 {
+            result.mantissa /= 10;
+            result.scale -= 1;
+        }
+        result
+    }
+
+    /// Add two decimals exactly
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::NumericOverflow` if the sum does not fit.
+    pub fn checked_add(&self, other: &Self) -> Result<Self> {
+        let (a, b, scale) = align(self, other)?;
+        let mantissa = a.checked_add(b).ok_or(DbError::NumericOverflow)?;
+        Self::new(mantissa, scale)
+    }
+
+    /// Subtract `other` from `self` exactly
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::NumericOverflow` if the difference does not fit.
+    pub fn checked_sub(&self, other: &Self) -> Result<Self> {
+        let (a, b, scale) = align(self, other)?;
+        let mantissa = a.checked_sub(b).ok_or(DbError::NumericOverflow)?;
+        Self::new(mantissa, scale)
+    }
+
+    /// Multiply two decimals exactly
+    ///
+    /// The scale of the result is the sum of both scales.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::NumericOverflow` if the product does not fit.
+    pub fn checked_mul(&self, other: &Self) -> Result<Self> {
+        let mantissa = self
+            .mantissa
+            .checked_mul(other.mantissa)
+            .ok_or(DbError::NumericOverflow)?;
+        Self::new(mantissa, self.scale + other.scale)
+    }
+
+    /// Divide `self` by `other`, producing `scale` fractional digits
+    ///
+    /// Division is generally not exact, so the caller chooses the scale of the
+    /// result and how the last digit is rounded.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::DivisionByZero` if `other` is zero, or
+    /// `DbError::NumericOverflow` if the quotient does not fit.
+    pub fn checked_div(&self, other: &Self, scale: u32, mode: RoundingMode) -> Result<Self> {
+        if other.is_zero() {
+            return Err(DbError::DivisionByZero);
+        }
+        if scale > MAX_PRECISION {
+            return Err(DbError::NumericOverflow);
+        }
+
+        // self / other * 10^scale == self.mantissa * 10^exp / other.mantissa
+        let exp = scale as i64 + other.scale as i64 - self.scale as i64;
+        let (numerator, denominator) = if exp >= 0 {
+            let numerator = self
+                .mantissa
+                .checked_mul(pow10(exp as u32)?)
+                .ok_or(DbError::NumericOverflow)?;
+            (numerator, other.mantissa)
+        } else {
+            let denominator = other
+                .mantissa
+                .checked_mul(pow10((-exp) as u32)?)
+                .ok_or(DbError::NumericOverflow)?;
+            (self.mantissa, denominator)
+        };
+
+        Self::new(div_round(numerator, denominator, mode), scale)
+    }
+
+    /// Convert to the nearest `f64`; this may lose precision
+    pub fn to_f64(&self) -> f64 {
+        self.mantissa as f64 / 10f64.powi(self.scale as i32)
+    }
+}
+
+/// Compute `10^exp` as an `i128`
+fn pow10(exp: u32) -> Result<i128> {
+    10i128.checked_pow(exp).ok_or(DbError::NumericOverflow)
+}
+
+/// Bring two decimals to a common scale, returning both mantissas and the scale
+fn align(a: &Decimal, b: &Decimal) -> Result<(i128, i128, u32)> {
+    let scale = a.scale.max(b.scale);
+    let a = a.rescale(scale, RoundingMode::Down)?;
+    let b = b.rescale(scale, RoundingMode::Down)?;
+    Ok((a.mantissa, b.mantissa, scale))
+}
+
+/// Integer division that rounds the quotient according to `mode`
+fn div_round(numerator: i128, denominator: i128, mode: RoundingMode) -> i128 {
+    let quotient = numerator / denominator;
+    let remainder = numerator % denominator;
+    if remainder == 0 {
+        return quotient;
+    }
+
+    let negative = (numerator < 0) != (denominator < 0);
+    let remainder = remainder.unsigned_abs();
+    let rest = denominator.unsigned_abs() - remainder;
+
+    let away_from_zero = match mode {
+        RoundingMode::Down => false,
+        RoundingMode::Up => true,
+        RoundingMode::Floor => negative,
+        RoundingMode::Ceiling => !negative,
+        RoundingMode::HalfUp => remainder >= rest,
+        RoundingMode::HalfDown => remainder > rest,
+        RoundingMode::HalfEven => remainder > rest || (remainder == rest && quotient % 2 != 0),
+    };
+
+    match (away_from_zero, negative) {
+        (false, _) => quotient,
+        (true, false) => quotient + 1,
+        (true, true) => quotient - 1,
+    }
+}
+
+impl From<i64> for Decimal {
+    /// Create a decimal with a scale of 0 from an integer
+    fn from(value: i64) -> Self {
+        Self {
+            mantissa: value as i128,
+            scale: 0,
+        }
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Self;
+
+    /// Negate the value, keeping its scale
+    fn neg(self) -> Self {
+        Self {
+            mantissa: -self.mantissa,
+            scale: self.scale,
+        }
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    /// Compare by numeric value, so `1.0` and `1.00` are equal
+    fn cmp(&self, other: &Self) -> Ordering {
+        match align(self, other) {
+            Ok((a, b, _)) => a.cmp(&b),
+            // Rescaling only overflows for the side with the larger magnitude,
+            // so comparing the normalized values is enough to decide.
+            Err(_) => {
+                let (a, b) = (self.normalize(), other.normalize());
+                match align(&a, &b) {
+                    Ok((a, b, _)) => a.cmp(&b),
+                    Err(_) if a.scale < b.scale => a.mantissa.signum().cmp(&0),
+                    Err(_) => 0.cmp(&b.mantissa.signum()),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let scale = self.scale as usize;
+
+        if scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, integer, fraction)
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = DbError;
+
+    /// Parse a decimal such as `-12.345`, keeping every written digit
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || DbError::ParseError(format!("Invalid decimal: {}", s));
+
+        let s = s.trim();
+        let (negative, unsigned) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (integer, fraction) = match unsigned.split_once('.') {
+            Some((integer, fraction)) => (integer, fraction),
+            None => (unsigned, ""),
+        };
+
+        if integer.is_empty() && fraction.is_empty() {
+            return Err(invalid());
+        }
+        if !integer.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+
+        let mut mantissa: i128 = 0;
+        for c in integer.chars().chain(fraction.chars()) {
+            mantissa = mantissa
+                .checked_mul(10)
+                .and_then(|m| m.checked_add(c.to_digit(10).unwrap_or(0) as i128))
+                .ok_or(DbError::NumericOverflow)?;
+        }
+        if negative {
+            mantissa = -mantissa;
+        }
+
+        Self::new(mantissa, fraction.len() as u32)
+    }
+}
+
+impl Serialize for Decimal {
+    /// Serialize as a string in human-readable formats such as JSON, and as a
+    /// `(mantissa, scale)` pair in binary formats; both are lossless
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            (self.mantissa, self.scale).serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            struct DecimalVisitor;
+
+            impl Visitor<'_> for DecimalVisitor {
+                type Value = Decimal;
+
+                fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    write!(f, "a decimal number as a string")
+                }
+
+                fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<Decimal, E> {
+                    v.parse().map_err(E::custom)
+                }
+            }
+
+            deserializer.deserialize_str(DecimalVisitor)
+        } else {
+            let (mantissa, scale) = <(i128, u32)>::deserialize(deserializer)?;
+            Decimal::new(mantissa, scale).map_err(de::Error::custom)
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use crate::error::{DbError, Result};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use std::fmt;
+use zeroize::Zeroizing;
+
+/// Bytes every encrypted database file starts with
+pub(crate) const ENCRYPTED_MAGIC: &[u8; 6] = b"OXIDBE";
+
+/// Version of the encrypted file header
+const ENCRYPTION_VERSION: u8 = 1;
+
+/// Key derivation function identifiers stored in the header
+const KDF_NONE: u8 = 0;
+const KDF_ARGON2ID: u8 = 1;
+
+/// Largest Argon2 costs accepted from a file header: 1 GiB of memory, 16
+/// passes and 16 lanes. Higher costs are treated as damage rather than run.
+const MAX_M_COST: u32 = 1 << 20;
+const MAX_T_COST: u32 = 16;
+const MAX_P_COST: u32 = 16;
+
+const SALT_SIZE: usize = 16;
+const CHECK_SIZE: usize = 16;
+const NONCE_SIZE: usize = 24;
+
+/// Size of the header: magic, version, KDF, three Argon2 costs, salt, key
+/// check and nonce
+const HEADER_SIZE: usize = 6 + 1 + 1 + 12 + SALT_SIZE + CHECK_SIZE + NONCE_SIZE;
+
+/// Size of the authentication tag after the ciphertext
+const TAG_SIZE: usize = 16;
+
+/// Bytes an encrypted file adds to the file image it protects
+pub(crate) const SEAL_OVERHEAD: u64 = (HEADER_SIZE + TAG_SIZE) as u64;
+
+/// Associated data of the key check, which tells a wrong key from damage
+const KEY_CHECK_DATA: &[u8] = b"oxi-db key check";
+
+/// Key protecting an encrypted database file
+///
+/// The debug output never shows the key or passphrase.
+///
+/// # Examples
+///
+/// ```
+/// use oxi_db::EncryptionKey;
+///
+/// let key = EncryptionKey::Raw([7; 32]);
+/// let passphrase = EncryptionKey::passphrase("correct horse battery staple");
+/// assert_eq!(format!("{:?}", passphrase), "EncryptionKey::Passphrase(..)");
+/// ```
+#[derive(Clone)]
+pub enum EncryptionKey {
+    /// A 256-bit key, used as is
+    Raw([u8; 32]),
+    /// A passphrase, stretched into a key with Argon2id and a random salt
+    /// that is stored in the file, and wiped from memory when dropped
+    Passphrase(Zeroizing<String>),
+}
+
+impl EncryptionKey {
+    /// Create a key from a passphrase
+    pub fn passphrase(passphrase: impl Into<String>) -> Self {
+        EncryptionKey::Passphrase(Zeroizing::new(passphrase.into()))
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EncryptionKey::Raw(_) => write!(f, "EncryptionKey::Raw(..)"),
+            EncryptionKey::Passphrase(_) => write!(f, "EncryptionKey::Passphrase(..)"),
+        }
+    }
+}
+
+/// How the key of a file was derived, as stored in its header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kdf {
+    None,
+    Argon2id {
+        m_cost: u32,
+        t_cost: u32,
+        p_cost: u32,
+        salt: [u8; SALT_SIZE],
+    },
+}
+
+/// A derived key, ready to encrypt and decrypt database files
+///
+/// The same salt and key are reused by every save, so a passphrase is
+/// only stretched when the database is opened or created.
+#[derive(Clone)]
+pub(crate) struct Cipher {
+    key: Zeroizing<[u8; 32]>,
+    kdf: Kdf,
+}
+
+impl fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Cipher").field("kdf", &self.kdf).finish_non_exhaustive()
+    }
+}
+
+impl Cipher {
+    /// Derive the cipher for a new file, with a fresh salt for a passphrase
+    pub(crate) fn new(key: &EncryptionKey) -> Result<Self> {
+        let kdf = match key {
+            EncryptionKey::Raw(_) => Kdf::None,
+            EncryptionKey::Passphrase(_) => {
+                let mut salt = [0; SALT_SIZE];
+                OsRng.fill_bytes(&mut salt);
+                Kdf::Argon2id {
+                    m_cost: argon2::Params::DEFAULT_M_COST,
+                    t_cost: argon2::Params::DEFAULT_T_COST,
+                    p_cost: argon2::Params::DEFAULT_P_COST,
+                    salt,
+                }
+            }
+        };
+        Self::derive(key, kdf)
+    }
+
+    fn derive(key: &EncryptionKey, kdf: Kdf) -> Result<Self> {
+        let derived = match (key, kdf) {
+            (EncryptionKey::Raw(bytes), Kdf::None) => Zeroizing::new(*bytes),
+            (
+                EncryptionKey::Passphrase(passphrase),
+                Kdf::Argon2id {
+                    m_cost,
+                    t_cost,
+                    p_cost,
+                    salt,
+                },
+            ) => {
+                let kdf_error = |e: argon2::Error| DbError::Other(format!("Key derivation failed: {}", e));
+                let params = argon2::Params::new(m_cost, t_cost, p_cost, Some(32)).map_err(kdf_error)?;
+                let argon2 = argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+                let mut derived = Zeroizing::new([0; 32]);
+                argon2
+                    .hash_password_into(passphrase.as_bytes(), &salt, derived.as_mut())
+                    .map_err(kdf_error)?;
+                derived
+            }
+            // A raw key for a passphrase file, or the other way round
+            _ => return Err(DbError::WrongKey),
+        };
+        Ok(Self { key: derived, kdf })
+    }
+
+    fn aead(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(self.key.as_ref().into())
+    }
+
+    /// Tag proving knowledge of the key without decrypting the file
+    fn key_check(&self) -> Result<[u8; CHECK_SIZE]> {
+        let payload = Payload {
+            msg: &[],
+            aad: KEY_CHECK_DATA,
+        };
+        let tag = self
+            .aead()
+            .encrypt(&XNonce::default(), payload)
+            .map_err(|_| DbError::Other("Encryption failed".to_string()))?;
+        Ok(tag.try_into().expect("an empty message encrypts to its tag"))
+    }
+
+    /// Encrypt a database file image
+    pub(crate) fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+        let mut header = Vec::with_capacity(HEADER_SIZE + plaintext.len() + TAG_SIZE);
+        header.extend_from_slice(ENCRYPTED_MAGIC);
+        header.push(ENCRYPTION_VERSION);
+        match self.kdf {
+            Kdf::None => {
+                header.push(KDF_NONE);
+                header.extend_from_slice(&[0; 12 + SALT_SIZE]);
+            }
+            Kdf::Argon2id {
+                m_cost,
+                t_cost,
+                p_cost,
+                salt,
+            } => {
+                header.push(KDF_ARGON2ID);
+                for cost in [m_cost, t_cost, p_cost] {
+                    header.extend_from_slice(&cost.to_le_bytes());
+                }
+                header.extend_from_slice(&salt);
+            }
+        }
+        header.extend_from_slice(&self.key_check()?);
+        header.extend_from_slice(&nonce);
+
+        // The header is authenticated along with the data
+        let payload = Payload {
+            msg: plaintext,
+            aad: &header,
+        };
+        let ciphertext = self
+            .aead()
+            .encrypt(&nonce, payload)
+            .map_err(|_| DbError::Other("Encryption failed".to_string()))?;
+        let mut sealed = header;
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+}
+
+/// Check whether a file image is encrypted
+pub(crate) fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(ENCRYPTED_MAGIC)
+}
+
+/// The key to decrypt a file with
+pub(crate) enum Unlock<'a> {
+    /// A key supplied by the user, derived with the file's salt
+    Key(&'a EncryptionKey),
+    /// The cipher of an open database, for its own backups
+    Cipher(&'a Cipher),
+}
+
+/// Decrypt an encrypted file image, returning the plaintext and the cipher
+///
+/// # Errors
+///
+/// Returns `DbError::WrongKey` if the key does not match the file, and
+/// `DbError::Corruption` if the file was damaged.
+pub(crate) fn open(data: &[u8], unlock: Unlock) -> Result<(Vec<u8>, Cipher)> {
+    let corrupt = |offset: usize, message: &str| DbError::Corruption {
+        table: None,
+        offset: offset as u64,
+        message: message.to_string(),
+    };
+    if data.len() < HEADER_SIZE {
+        return Err(corrupt(0, "truncated encryption header"));
+    }
+    if data[6] != ENCRYPTION_VERSION {
+        return Err(DbError::Other(format!("Unsupported encryption version {}", data[6])));
+    }
+
+    let u32_at = |at: usize| u32::from_le_bytes(data[at..at + 4].try_into().unwrap());
+    let kdf = match data[7] {
+        KDF_NONE => Kdf::None,
+        KDF_ARGON2ID => Kdf::Argon2id {
+            m_cost: u32_at(8),
+            t_cost: u32_at(12),
+            p_cost: u32_at(16),
+            salt: data[20..20 + SALT_SIZE].try_into().unwrap(),
+        },
+        other => return Err(corrupt(7, &format!("unknown key derivation {}", other))),
+    };
+    if let Kdf::Argon2id {
+        m_cost,
+        t_cost,
+        p_cost,
+        ..
+    } = kdf
+    {
+        if m_cost > MAX_M_COST || t_cost > MAX_T_COST || p_cost > MAX_P_COST {
+            return Err(corrupt(8, "key derivation costs out of range"));
+        }
+    }
+    let check_at = 20 + SALT_SIZE;
+    let nonce_at = check_at + CHECK_SIZE;
+
+    let cipher = match unlock {
+        Unlock::Key(key) => Cipher::derive(key, kdf)?,
+        Unlock::Cipher(cipher) if cipher.kdf == kdf => cipher.clone(),
+        Unlock::Cipher(_) => return Err(DbError::WrongKey),
+    };
+    if cipher.key_check()? != data[check_at..nonce_at] {
+        return Err(DbError::WrongKey);
+    }
+
+    let payload = Payload {
+        msg: &data[HEADER_SIZE..],
+        aad: &data[..HEADER_SIZE],
+    };
+    let plaintext = cipher
+        .aead()
+        .decrypt(XNonce::from_slice(&data[nonce_at..HEADER_SIZE]), payload)
+        .map_err(|_| corrupt(HEADER_SIZE, "encrypted data failed authentication"))?;
+    Ok((plaintext, cipher))
+}
@@ -24,11 +24,70 @@ pub enum DbError {
     #[error("Bincode error: {0}")]
     BincodeError(#[from] bincode::Error),
     
+    #[error("CSV error: {0}")]
+    CsvError(csv::Error),
+    
+    #[error("Compare-and-swap failed: row has changed")]
+    CompareFailed,
+    
+    #[error("Column not found: {0}")]
+    ColumnNotFound(String),
+    
+    #[error("Table is read-only: {0}")]
+    ReadOnlyTable(String),
+    
+    #[error("Index already exists: {0}")]
+    IndexExists(String),
+    
+    #[error("Index not found: {0}")]
+    IndexNotFound(String),
+    
+    #[error("Sequence already exists: {0}")]
+    SequenceExists(String),
+    
+    #[error("Sequence not found: {0}")]
+    SequenceNotFound(String),
+    
     #[error("Type conversion error")]
     TypeConversionError,
     
+    #[error("Numeric overflow")]
+    NumericOverflow,
+    
+    #[error("Division by zero")]
+    DivisionByZero,
+    
+    #[error(
+        "Corrupt database file at offset {offset}{}: {message}",
+        .table.as_ref().map(|table| format!(" in table {}", table)).unwrap_or_default()
+    )]
+    Corruption {
+        table: Option<String>,
+        offset: u64,
+        message: String,
+    },
+    
+    #[error("Wrong encryption key")]
+    WrongKey,
+    
+    #[error("Database file is encrypted: a key is required to open it")]
+    KeyRequired,
+    
+    #[error("Parse error: {0}")]
+    ParseError(String),
+    
     #[error("Database error: {0}")]
     Other(String),
 }
 
+impl From<csv::Error> for DbError {
+    /// Failed reads and writes stay I/O errors; the rest is malformed CSV
+    fn from(error: csv::Error) -> Self {
+        match error.kind() {
+            csv::ErrorKind::Io(e) => DbError::IoError(io::Error::new(e.kind(), error)),
+            _ => DbError::CsvError(error),
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, DbError>;
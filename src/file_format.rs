@@ -0,0 +1,803 @@
+use crate::compression::{Compression, CompressionStats, OVERFLOW_PAGE_SIZE, OVERFLOW_THRESHOLD};
+use crate::error::{DbError, Result};
+use crate::index::Index;
+use crate::sequence::Sequence;
+use crate::table::Table;
+use crate::types::{Column, ColumnType, Key, Row, Value};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Bytes every database file starts with, followed by the format version
+pub(crate) const MAGIC: &[u8; 6] = b"OXIDB\0";
+
+/// Version of the page layout written by `encode`, the only one read
+pub(crate) const FORMAT_VERSION: u16 = 1;
+
+/// Size of the frame in front of every page: its length and CRC-32
+pub(crate) const PAGE_HEADER_SIZE: usize = 8;
+
+/// The tables and sequences stored in a database file
+pub(crate) type Contents = (BTreeMap<String, Table>, BTreeMap<String, Sequence>);
+
+/// A page of a database file, as read back
+///
+/// A file holds the sequences, then for each table its `Table` page, one
+/// `Row` page per row and one `Index` page per index, in that order. The
+/// `Table` page counts the pages after it that belong to the table, so
+/// those of a table whose page is damaged are not taken for another's. The
+/// `End` page closes the file with the number of pages before it, so a file
+/// cut short between two pages is detected too.
+///
+/// A `Compression` page after the `Table` page selects the compression of
+/// the table, and any page may be wrapped in a `Compressed` page. A row
+/// with values larger than `OVERFLOW_THRESHOLD` is written as `Overflow`
+/// pages holding those values, followed by an `OverflowRow` page with the
+/// rest of the row and the column and encoded length of each value.
+#[derive(Debug, Deserialize)]
+pub(crate) enum Page {
+    Sequence(Sequence),
+    Table {
+        name: String,
+        columns: Vec<Column>,
+        primary_key: Option<String>,
+        auto_increment: Sequence,
+        pages: u64,
+    },
+    Row(Key, Row),
+    Index(Index),
+    End { pages: u64 },
+    Compression(Compression),
+    Compressed(Compression, Vec<u8>),
+    Overflow(Vec<u8>),
+    OverflowRow {
+        key: Key,
+        row: Row,
+        values: Vec<(u32, u64)>,
+    },
+}
+
+/// A page to be written, borrowing its contents
+///
+/// The variants mirror `Page`, so both encode the same way.
+#[derive(Debug, Serialize)]
+pub(crate) enum PageRef<'a> {
+    Sequence(&'a Sequence),
+    Table {
+        name: &'a str,
+        columns: &'a [Column],
+        primary_key: &'a Option<String>,
+        auto_increment: &'a Sequence,
+        pages: u64,
+    },
+    Row(&'a Key, &'a Row),
+    Index(&'a Index),
+    End { pages: u64 },
+    Compression(Compression),
+    Compressed(Compression, &'a [u8]),
+    Overflow(&'a [u8]),
+    OverflowRow {
+        key: &'a Key,
+        row: &'a Row,
+        values: &'a [(u32, u64)],
+    },
+}
+
+impl Page {
+    /// Whether the page belongs to the table before it
+    fn belongs_to_table(&self) -> bool {
+        !matches!(self, Page::Sequence(_) | Page::Table { .. } | Page::End { .. })
+    }
+}
+
+/// Writes the pages of a database file into a buffer
+pub(crate) struct PageWriter {
+    buffer: Vec<u8>,
+    pages: u64,
+    stats: CompressionStats,
+    /// Whether the pages are kept, rather than only counted
+    keep: bool,
+}
+
+impl PageWriter {
+    fn new() -> Self {
+        let mut buffer = MAGIC.to_vec();
+        buffer.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        Self {
+            buffer,
+            pages: 0,
+            stats: CompressionStats::default(),
+            keep: true,
+        }
+    }
+
+    /// Create a writer that counts pages without keeping their bytes
+    fn measuring() -> Self {
+        Self {
+            keep: false,
+            ..Self::new()
+        }
+    }
+
+    /// Close the file with the `End` page and return its bytes
+    fn finish(mut self) -> Result<Vec<u8>> {
+        self.write(&PageRef::End { pages: self.pages })?;
+        Ok(self.buffer)
+    }
+
+    /// Append a page, framed by its length and CRC-32
+    pub(crate) fn write(&mut self, page: &PageRef) -> Result<()> {
+        let payload = bincode::serialize(page)?;
+        self.stats.raw_bytes += payload.len() as u64;
+        self.frame(&payload)
+    }
+
+    /// Append a page, compressed if that makes it smaller
+    pub(crate) fn write_compressed(&mut self, page: &PageRef, compression: Compression) -> Result<()> {
+        let payload = bincode::serialize(page)?;
+        self.stats.raw_bytes += payload.len() as u64;
+        match compression.compress(&payload) {
+            Some(compressed) => {
+                self.stats.compressed_pages += 1;
+                self.frame(&bincode::serialize(&PageRef::Compressed(compression, &compressed))?)
+            }
+            None => self.frame(&payload),
+        }
+    }
+
+    /// Number of pages `write_row` writes for a row
+    pub(crate) fn row_pages(row: &Row) -> Result<u64> {
+        let mut overflow = 0;
+        for value in &row.values {
+            let size = bincode::serialized_size(value)?;
+            if size as usize > OVERFLOW_THRESHOLD {
+                overflow += size;
+            }
+        }
+        Ok(1 + overflow.div_ceil(OVERFLOW_PAGE_SIZE as u64))
+    }
+
+    /// Append a row, moving values larger than `OVERFLOW_THRESHOLD` out of
+    /// line into overflow pages
+    pub(crate) fn write_row(&mut self, key: &Key, row: &Row, compression: Compression) -> Result<()> {
+        let mut overflow = Vec::new();
+        let mut values = Vec::new();
+        for (column, value) in row.values.iter().enumerate() {
+            if bincode::serialized_size(value)? as usize > OVERFLOW_THRESHOLD {
+                let encoded = bincode::serialize(value)?;
+                values.push((column as u32, encoded.len() as u64));
+                overflow.extend_from_slice(&encoded);
+            }
+        }
+        if values.is_empty() {
+            return self.write_compressed(&PageRef::Row(key, row), compression);
+        }
+
+        for chunk in overflow.chunks(OVERFLOW_PAGE_SIZE) {
+            self.write_compressed(&PageRef::Overflow(chunk), compression)?;
+            self.stats.overflow_pages += 1;
+        }
+        let mut inline = row.clone();
+        for &(column, _) in &values {
+            inline.values[column as usize] = Value::Null;
+        }
+        let page = PageRef::OverflowRow {
+            key,
+            row: &inline,
+            values: &values,
+        };
+        self.write_compressed(&page, compression)
+    }
+
+    fn frame(&mut self, payload: &[u8]) -> Result<()> {
+        let length = u32::try_from(payload.len())
+            .map_err(|_| DbError::Other("Page larger than 4 GiB".to_string()))?;
+        if self.keep {
+            self.buffer.extend_from_slice(&length.to_le_bytes());
+            self.buffer.extend_from_slice(&crc32fast::hash(payload).to_le_bytes());
+            self.buffer.extend_from_slice(payload);
+        }
+        self.pages += 1;
+        self.stats.pages += 1;
+        self.stats.stored_bytes += payload.len() as u64;
+        Ok(())
+    }
+}
+
+/// Measure the pages of a table as `encode` would write them
+pub(crate) fn table_stats(table: &Table) -> Result<CompressionStats> {
+    let mut writer = PageWriter::measuring();
+    table.write_pages(&mut writer)?;
+    Ok(writer.stats)
+}
+
+/// Measure the page of an index as `encode` would write it
+pub(crate) fn index_stats(index: &Index, compression: Compression) -> Result<CompressionStats> {
+    let mut writer = PageWriter::measuring();
+    writer.write_compressed(&PageRef::Index(index), compression)?;
+    Ok(writer.stats)
+}
+
+/// Measure the file `encode` writes, given the size of the tables' pages
+///
+/// Adds the header, the sequences and the `End` page, so the tables are
+/// not encoded a second time.
+pub(crate) fn encoded_size(sequences: &BTreeMap<String, Sequence>, table_bytes: u64) -> Result<u64> {
+    let mut writer = PageWriter::measuring();
+    for sequence in sequences.values() {
+        writer.write(&PageRef::Sequence(sequence))?;
+    }
+    writer.write(&PageRef::End { pages: 0 })?;
+    Ok(writer.buffer.len() as u64 + crate::stats::file_bytes(&writer.stats) + table_bytes)
+}
+
+/// Put the values of an `OverflowRow` page back into its row
+///
+/// `data` holds the contents of the overflow pages before the row.
+fn assemble_row(mut row: Row, values: &[(u32, u64)], data: &[u8]) -> std::result::Result<Row, String> {
+    let mut rest = data;
+    for &(column, length) in values {
+        let length = length as usize;
+        if rest.len() < length {
+            return Err("overflow pages are missing".to_string());
+        }
+        let (encoded, tail) = rest.split_at(length);
+        let value = row
+            .values
+            .get_mut(column as usize)
+            .ok_or_else(|| format!("overflow value for missing column {}", column))?;
+        *value = decode_bytes(encoded).map_err(|e| format!("undecodable overflow value: {}", e))?;
+        rest = tail;
+    }
+    if !rest.is_empty() {
+        return Err(format!("{} bytes of overflow pages belong to no value", rest.len()));
+    }
+    Ok(row)
+}
+
+/// Reads the pages of a database file, with the offset of each
+///
+/// A page whose checksum does not match is reported and skipped; a page
+/// that runs past the end of the file ends the iteration and sets
+/// `truncated`.
+struct PageReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    truncated: bool,
+}
+
+impl<'a> PageReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            offset: MAGIC.len() + 2,
+            truncated: false,
+        }
+    }
+}
+
+impl Iterator for PageReader<'_> {
+    type Item = (u64, std::result::Result<Page, String>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.offset;
+        let remaining = self.data.get(offset..).filter(|rest| !rest.is_empty())?;
+
+        if remaining.len() < PAGE_HEADER_SIZE {
+            self.offset = self.data.len();
+            self.truncated = true;
+            return Some((offset as u64, Err("truncated page header".to_string())));
+        }
+        let length = u32::from_le_bytes(remaining[0..4].try_into().unwrap()) as usize;
+        let checksum = u32::from_le_bytes(remaining[4..8].try_into().unwrap());
+        let Some(payload) = remaining[PAGE_HEADER_SIZE..].get(..length) else {
+            self.offset = self.data.len();
+            self.truncated = true;
+            return Some((
+                offset as u64,
+                Err(format!(
+                    "truncated page: expected {} bytes, found {}",
+                    length,
+                    remaining.len() - PAGE_HEADER_SIZE
+                )),
+            ));
+        };
+        self.offset += PAGE_HEADER_SIZE + length;
+
+        if crc32fast::hash(payload) != checksum {
+            return Some((offset as u64, Err("checksum mismatch".to_string())));
+        }
+        let page = match decode_bytes(payload) {
+            Ok(Page::Compressed(compression, data)) => compression.decompress(&data).and_then(|payload| {
+                match decode_bytes(&payload) {
+                    Ok(Page::Compressed(..)) => Err("compressed page inside a compressed page".to_string()),
+                    page => page.map_err(|e| format!("undecodable page: {}", e)),
+                }
+            }),
+            page => page.map_err(|e| format!("undecodable page: {}", e)),
+        };
+        Some((offset as u64, page))
+    }
+}
+
+/// Decode bincode that must fit in `bytes`
+///
+/// The limit keeps a corrupt length prefix from allocating more than the
+/// input holds.
+fn decode_bytes<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> bincode::Result<T> {
+    bincode::options()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(bytes.len() as u64)
+        .deserialize(bytes)
+}
+
+/// Layout of files written before the paged format: one bincode `Database`
+/// whose tables map text keys to rows
+///
+/// The values and column types of that time are the first variants of
+/// `Value` and `ColumnType`, which encode the same way today.
+#[derive(Deserialize)]
+struct BaselineDatabase {
+    #[allow(dead_code)]
+    path: PathBuf,
+    tables: BTreeMap<String, BaselineTable>,
+}
+
+#[derive(Deserialize)]
+struct BaselineTable {
+    name: String,
+    columns: Vec<BaselineColumn>,
+    primary_key: Option<String>,
+    data: BTreeMap<String, Row>,
+}
+
+#[derive(Deserialize)]
+struct BaselineColumn {
+    name: String,
+    column_type: ColumnType,
+}
+
+impl BaselineTable {
+    /// Split into an empty table with the same schema and its rows
+    fn into_parts(self) -> (Table, Vec<(Key, Row)>) {
+        let columns = self
+            .columns
+            .into_iter()
+            .map(|column| Column::new(column.name, column.column_type))
+            .collect();
+        let rows = self.data.into_iter().map(|(key, row)| (Key::Text(key), row)).collect();
+        (Table::new(self.name, columns, self.primary_key), rows)
+    }
+}
+
+/// Encode tables and sequences as a database file
+pub(crate) fn encode(
+    tables: &BTreeMap<String, Table>,
+    sequences: &BTreeMap<String, Sequence>,
+) -> Result<Vec<u8>> {
+    let mut writer = PageWriter::new();
+    for sequence in sequences.values() {
+        writer.write(&PageRef::Sequence(sequence))?;
+    }
+    for table in tables.values() {
+        table.write_pages(&mut writer)?;
+    }
+    writer.finish()
+}
+
+/// Decode a database file, failing on the first damaged page
+///
+/// Files without the magic bytes are read in the layout from before the
+/// paged format.
+///
+/// # Errors
+///
+/// Returns `DbError::Corruption` for a page that fails its checksum or
+/// cannot be decoded, for an invalid schema, for a row that does not fit
+/// its table, for pages out of place and for a truncated file.
+pub(crate) fn decode(data: &[u8]) -> Result<Contents> {
+    if !data.starts_with(MAGIC) {
+        return decode_baseline(data);
+    }
+    check_version(data)?;
+
+    let corrupt = |table: Option<&Table>, offset: u64, message: String| DbError::Corruption {
+        table: table.map(|table| table.name.clone()),
+        offset,
+        message,
+    };
+    let mut tables = BTreeMap::new();
+    let mut sequences = BTreeMap::new();
+    let mut current: Option<Table> = None;
+    let mut owned = 0;
+    let mut overflow = Vec::new();
+    let mut ended = false;
+
+    for (pages, (offset, page)) in (0..).zip(PageReader::new(data)) {
+        if ended {
+            return Err(corrupt(None, offset, "page after the end page".to_string()));
+        }
+        let page = page.map_err(|message| corrupt(current.as_ref(), offset, message))?;
+        if page.belongs_to_table() {
+            if owned == 0 {
+                return Err(corrupt(None, offset, "page does not belong to a table".to_string()));
+            }
+            owned -= 1;
+        }
+        if !overflow.is_empty() && !matches!(page, Page::Overflow(_) | Page::OverflowRow { .. }) {
+            return Err(corrupt(current.as_ref(), offset, "overflow pages without a row".to_string()));
+        }
+        match page {
+            Page::Sequence(sequence) => {
+                sequences.insert(sequence.name.clone(), sequence);
+            }
+            Page::Table {
+                name,
+                columns,
+                primary_key,
+                auto_increment,
+                pages,
+            } => {
+                owned = pages;
+                let table = Table::new(name, columns, primary_key).with_auto_increment(auto_increment);
+                if let Err(e) = table.validate_schema() {
+                    return Err(corrupt(Some(&table), offset, format!("invalid schema: {}", e)));
+                }
+                if let Some(done) = current.replace(table) {
+                    tables.insert(done.name.clone(), done);
+                }
+            }
+            Page::Row(key, row) => {
+                let table = current
+                    .as_mut()
+                    .ok_or_else(|| corrupt(None, offset, format!("row {} before any table", key)))?;
+                if let Err(e) = table.check_row(&row) {
+                    return Err(corrupt(Some(table), offset, format!("row {}: {}", key, e)));
+                }
+                table.load_row(key, row);
+            }
+            Page::Index(index) => current
+                .as_mut()
+                .ok_or_else(|| corrupt(None, offset, format!("index {} before any table", index.name)))?
+                .load_index(index),
+            Page::Compression(compression) => current
+                .as_mut()
+                .ok_or_else(|| corrupt(None, offset, "compression before any table".to_string()))?
+                .set_compression(compression),
+            Page::Overflow(chunk) => overflow.extend_from_slice(&chunk),
+            Page::OverflowRow { key, row, values } => {
+                let table = current
+                    .as_mut()
+                    .ok_or_else(|| corrupt(None, offset, format!("row {} before any table", key)))?;
+                let row = assemble_row(row, &values, &overflow)
+                    .map_err(|message| corrupt(Some(table), offset, format!("row {}: {}", key, message)))?;
+                if let Err(e) = table.check_row(&row) {
+                    return Err(corrupt(Some(table), offset, format!("row {}: {}", key, e)));
+                }
+                table.load_row(key, row);
+                overflow.clear();
+            }
+            Page::Compressed(..) => {
+                return Err(corrupt(current.as_ref(), offset, "compressed page was not unpacked".to_string()));
+            }
+            Page::End { pages: expected } => {
+                if expected != pages {
+                    let message = format!("end page counts {} pages, found {}", expected, pages);
+                    return Err(corrupt(None, offset, message));
+                }
+                ended = true;
+            }
+        }
+    }
+    if !ended {
+        let message = "file is truncated: the end page is missing".to_string();
+        return Err(corrupt(current.as_ref(), data.len() as u64, message));
+    }
+    if let Some(done) = current {
+        tables.insert(done.name.clone(), done);
+    }
+
+    Ok((tables, sequences))
+}
+
+/// Decode a file in the layout from before the paged format
+///
+/// Keys were text then. Every table and row is checked as for a paged file.
+fn decode_baseline(data: &[u8]) -> Result<Contents> {
+    let baseline: BaselineDatabase = decode_bytes(data)?;
+    let mut tables = BTreeMap::new();
+    for table in baseline.tables.into_values() {
+        let (mut table, rows) = table.into_parts();
+        let corrupt = |table: &Table, message: String| DbError::Corruption {
+            table: Some(table.name.clone()),
+            offset: 0,
+            message,
+        };
+        if let Err(e) = table.validate_schema() {
+            return Err(corrupt(&table, format!("invalid schema: {}", e)));
+        }
+        for (key, row) in rows {
+            if let Err(e) = table.check_row(&row) {
+                return Err(corrupt(&table, format!("row {}: {}", key, e)));
+            }
+            table.load_row(key, row);
+        }
+        tables.insert(table.name.clone(), table);
+    }
+    Ok((tables, BTreeMap::new()))
+}
+
+/// Check the format version in the file header
+fn check_version(data: &[u8]) -> Result<()> {
+    let version = data
+        .get(MAGIC.len()..MAGIC.len() + 2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| DbError::Corruption {
+            table: None,
+            offset: 0,
+            message: "truncated file header".to_string(),
+        })?;
+    if version != FORMAT_VERSION {
+        return Err(DbError::Other(format!("Unsupported database file format version {}", version)));
+    }
+    Ok(())
+}
+
+/// A problem found by `Database::integrity_check` or `Database::repair`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityProblem {
+    /// The table the problem was found in, if any
+    pub table: Option<String>,
+    /// Byte offset of the damaged page, if the problem is tied to one
+    pub offset: Option<u64>,
+    /// What is wrong
+    pub message: String,
+}
+
+impl fmt::Display for IntegrityProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(table) = &self.table {
+            write!(f, "table {}: ", table)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, "offset {}: ", offset)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// The outcome of `Database::integrity_check` or `Database::repair`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntegrityReport {
+    /// Number of tables that could be read
+    pub tables: usize,
+    /// Number of rows that could be read and passed every check
+    pub rows: usize,
+    /// Problems found, in file order; empty when the file is sound
+    pub problems: Vec<IntegrityProblem>,
+}
+
+impl IntegrityReport {
+    /// Check whether no problems were found
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    fn problem(&mut self, table: Option<&str>, offset: Option<u64>, message: impl Into<String>) {
+        self.problems.push(IntegrityProblem {
+            table: table.map(String::from),
+            offset,
+            message: message.into(),
+        });
+    }
+}
+
+/// A table as found in the file, before its rows and indexes are checked
+struct FoundTable {
+    schema: Table,
+    offset: Option<u64>,
+    rows: Vec<(Option<u64>, Key, Row)>,
+    indexes: Vec<Index>,
+    /// Whether a damaged page was found among the table's pages
+    damaged: bool,
+}
+
+/// Read everything that can be read from a database file
+///
+/// Damaged pages are skipped, as are rows and indexes that no table page
+/// counts as its own. Each table is rebuilt from its schema by inserting
+/// its rows one by one, so rows with the wrong number of values, values of
+/// the wrong type or duplicate keys are left out, and indexes are
+/// recomputed and compared with the stored ones. Every problem is recorded
+/// in the report.
+pub(crate) fn salvage(data: &[u8]) -> (Contents, IntegrityReport) {
+    let mut report = IntegrityReport::default();
+    let mut found: Vec<FoundTable> = Vec::new();
+    let mut sequences = BTreeMap::new();
+
+    if !data.starts_with(MAGIC) {
+        match decode_bytes::<BaselineDatabase>(data) {
+            Ok(baseline) => {
+                for table in baseline.tables.into_values() {
+                    let (schema, rows) = table.into_parts();
+                    found.push(FoundTable {
+                        schema,
+                        offset: None,
+                        rows: rows.into_iter().map(|(key, row)| (None, key, row)).collect(),
+                        indexes: Vec::new(),
+                        damaged: false,
+                    });
+                }
+            }
+            Err(e) => report.problem(None, None, format!("unreadable database file: {}", e)),
+        }
+    } else {
+        if let Err(e) = check_version(data) {
+            report.problem(None, None, e.to_string());
+            return ((BTreeMap::new(), sequences), report);
+        }
+        let mut reader = PageReader::new(data);
+        let mut owned = 0;
+        let mut overflow = Vec::new();
+        let mut ended = false;
+        for (pages, (offset, page)) in (0..).zip(reader.by_ref()) {
+            if ended {
+                report.problem(None, Some(offset), "page after the end page");
+            }
+            // A page belongs to the last table only if its table page counts
+            // it, so after a damaged table page nothing is taken for the
+            // table before
+            let in_table = owned > 0 && page.as_ref().map_or(true, Page::belongs_to_table);
+            if in_table {
+                owned -= 1;
+            }
+            // Overflow pages only carry over into the row page right after them
+            if !overflow.is_empty() && !matches!(page, Ok(Page::Overflow(_) | Page::OverflowRow { .. })) {
+                let table = found.last().map(|table| table.schema.name.as_str());
+                if page.is_ok() {
+                    report.problem(table, Some(offset), "overflow pages without a row");
+                }
+                overflow.clear();
+            }
+            match page {
+                Err(message) => {
+                    let table = found.last_mut().filter(|_| in_table).map(|table| {
+                        table.damaged = true;
+                        table.schema.name.as_str()
+                    });
+                    report.problem(table, Some(offset), message);
+                }
+                Ok(Page::Sequence(sequence)) => {
+                    sequences.insert(sequence.name.clone(), sequence);
+                }
+                Ok(Page::Table {
+                    name,
+                    columns,
+                    primary_key,
+                    auto_increment,
+                    pages,
+                }) => {
+                    owned = pages;
+                    found.push(FoundTable {
+                        schema: Table::new(name, columns, primary_key).with_auto_increment(auto_increment),
+                        offset: Some(offset),
+                        rows: Vec::new(),
+                        indexes: Vec::new(),
+                        damaged: false,
+                    });
+                }
+                Ok(Page::Row(key, row)) => match found.last_mut().filter(|_| in_table) {
+                    Some(table) => table.rows.push((Some(offset), key, row)),
+                    None => report.problem(None, Some(offset), format!("row {} does not belong to a table", key)),
+                },
+                Ok(Page::Compression(compression)) => match found.last_mut().filter(|_| in_table) {
+                    Some(table) => table.schema.set_compression(compression),
+                    None => report.problem(None, Some(offset), "compression does not belong to a table"),
+                },
+                // Overflow pages are only checked with the row they belong to
+                Ok(Page::Overflow(chunk)) => overflow.extend_from_slice(&chunk),
+                Ok(Page::OverflowRow { key, row, values }) => {
+                    let data = std::mem::take(&mut overflow);
+                    match (found.last_mut().filter(|_| in_table), assemble_row(row, &values, &data)) {
+                        (Some(table), Ok(row)) => table.rows.push((Some(offset), key, row)),
+                        (Some(table), Err(message)) => {
+                            table.damaged = true;
+                            let message = format!("row {}: {}", key, message);
+                            report.problem(Some(&table.schema.name), Some(offset), message);
+                        }
+                        (None, _) => {
+                            report.problem(None, Some(offset), format!("row {} does not belong to a table", key))
+                        }
+                    }
+                }
+                Ok(Page::Compressed(..)) => {
+                    let table = found.last_mut().filter(|_| in_table).map(|table| {
+                        table.damaged = true;
+                        table.schema.name.as_str()
+                    });
+                    report.problem(table, Some(offset), "compressed page was not unpacked");
+                }
+                Ok(Page::Index(index)) => match found.last_mut().filter(|_| in_table) {
+                    Some(table) => table.indexes.push(index),
+                    None => report.problem(
+                        None,
+                        Some(offset),
+                        format!("index {} does not belong to a table", index.name),
+                    ),
+                },
+                Ok(Page::End { pages: expected }) => {
+                    if expected != pages {
+                        let message = format!("end page counts {} pages, found {}", expected, pages);
+                        report.problem(None, Some(offset), message);
+                    }
+                    ended = true;
+                }
+            }
+        }
+        if !ended && !reader.truncated {
+            let table = found.last().map(|table| table.schema.name.as_str());
+            let message = "file is truncated: the end page is missing";
+            report.problem(table, Some(data.len() as u64), message);
+        }
+    }
+
+    let mut tables = BTreeMap::new();
+    for table in found {
+        let name = table.schema.name.clone();
+        if tables.contains_key(&name) {
+            report.problem(Some(&name), table.offset, "duplicate table");
+            continue;
+        }
+        if let Some(table) = rebuild_table(table, &mut report) {
+            report.tables += 1;
+            tables.insert(name, table);
+        }
+    }
+
+    ((tables, sequences), report)
+}
+
+/// Rebuild a table from its schema, checking each row and index
+fn rebuild_table(found: FoundTable, report: &mut IntegrityReport) -> Option<Table> {
+    let mut table = found.schema;
+    let name = table.name.clone();
+    if let Err(e) = table.validate_schema() {
+        report.problem(Some(&name), found.offset, format!("invalid schema: {}", e));
+        return None;
+    }
+
+    for index in &found.indexes {
+        if let Err(e) = table.create_index(index.name.clone(), index.expr.clone()) {
+            report.problem(Some(&name), None, format!("index {}: {}", index.name, e));
+        }
+    }
+
+    let problems = report.problems.len();
+    for (offset, key, row) in found.rows {
+        let inserted = table
+            .check_row(&row)
+            .and_then(|_| table.insert(key.clone(), row.values));
+        match inserted {
+            Ok(()) => report.rows += 1,
+            Err(e) => report.problem(Some(&name), offset, format!("row {}: {}", key, e)),
+        }
+    }
+
+    // Indexes are only comparable when every row was kept
+    let rows_lost = found.damaged || report.problems.len() > problems;
+    for stored in found.indexes.iter().filter(|_| !rows_lost) {
+        let rebuilt = table.indexes().iter().find(|index| index.name == stored.name);
+        if rebuilt.is_some_and(|rebuilt| !rebuilt.same_entries(stored)) {
+            report.problem(
+                Some(&name),
+                None,
+                format!("index {} does not match the rows", stored.name),
+            );
+        }
+    }
+
+    Some(table)
+}
@@ -0,0 +1,185 @@
+use crate::btree::BTree;
+use crate::error::Result;
+use crate::query::Expr;
+use crate::types::{Column, Key, Row, Value};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::ops::Bound;
+
+/// A value used as a key in a secondary index
+///
+/// Values are ordered by `Value::total_cmp`: by type first (NULL, booleans,
+/// numbers, text, blobs, JSON documents, arrays, structs, UUIDs), and by
+/// `Value::compare` within a type, which gives a total order suitable for a
+/// B-tree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexValue(pub Value);
+
+impl PartialEq for IndexValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for IndexValue {}
+
+impl PartialOrd for IndexValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for IndexValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// A secondary index mapping the value of an expression to row keys
+///
+/// Indexes are maintained by `Table` on every insert, update and delete.
+/// Rows where the expression evaluates to NULL are not indexed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Index {
+    /// The name of the index
+    pub name: String,
+    /// The expression whose value is indexed
+    pub expr: Expr,
+    /// Index entries, from indexed value to the keys of matching rows
+    entries: BTree<IndexValue, Vec<Key>>,
+}
+
+impl Index {
+    /// Create an empty index over an expression
+    pub fn new(name: impl Into<String>, expr: Expr) -> Self {
+        Self {
+            name: name.into(),
+            expr,
+            entries: BTree::new(),
+        }
+    }
+
+    /// Add a row to the index
+    pub fn insert(&mut self, columns: &[Column], key: &Key, row: &Row) -> Result<()> {
+        let value = self.expr.evaluate(columns, row)?;
+        self.insert_value(key, value);
+        Ok(())
+    }
+
+    /// Add a row whose indexed value is already evaluated
+    pub(crate) fn insert_value(&mut self, key: &Key, value: Value) {
+        if value.is_null() {
+            return;
+        }
+
+        let value = IndexValue(value);
+        match self.entries.get_mut(&value) {
+            Some(keys) => keys.push(key.clone()),
+            None => self.entries.insert(value, vec![key.clone()]),
+        }
+    }
+
+    /// Remove a row from the index
+    pub fn remove(&mut self, columns: &[Column], key: &Key, row: &Row) -> Result<()> {
+        let value = self.expr.evaluate(columns, row)?;
+        self.remove_value(key, value);
+        Ok(())
+    }
+
+    /// Remove a row whose indexed value is already evaluated
+    pub(crate) fn remove_value(&mut self, key: &Key, value: Value) {
+        let value = IndexValue(value);
+
+        let now_empty = match self.entries.get_mut(&value) {
+            Some(keys) => {
+                keys.retain(|k| k != key);
+                keys.is_empty()
+            }
+            None => false,
+        };
+        if now_empty {
+            self.entries.remove(&value);
+        }
+    }
+
+    /// Check that two indexes map the same values to the same keys
+    pub(crate) fn same_entries(&self, other: &Index) -> bool {
+        let entries = |index: &Index| {
+            let mut entries = index.entries.to_vec();
+            for (_, keys) in &mut entries {
+                keys.sort();
+            }
+            entries
+        };
+        entries(self) == entries(other)
+    }
+
+    /// Find the keys of rows whose indexed value equals `value`
+    pub fn lookup(&self, value: &Value) -> Vec<Key> {
+        self.entries
+            .search(&IndexValue(value.clone()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Count the rows whose indexed value equals `value`, without copying their keys
+    pub fn count(&self, value: &Value) -> usize {
+        self.entries
+            .search(&IndexValue(value.clone()))
+            .map_or(0, Vec::len)
+    }
+
+    /// Find the keys of rows whose indexed value lies between two bounds
+    ///
+    /// Keys come in the order of their indexed values. Bounds that leave no
+    /// room between them give no keys.
+    pub fn range(&self, lower: Bound<&Value>, upper: Bound<&Value>) -> Vec<Key> {
+        let lower = lower.map(|value| IndexValue(value.clone()));
+        let upper = upper.map(|value| IndexValue(value.clone()));
+        fn inclusive(bound: &Bound<IndexValue>) -> Option<(&IndexValue, bool)> {
+            match bound {
+                Bound::Included(value) => Some((value, true)),
+                Bound::Excluded(value) => Some((value, false)),
+                Bound::Unbounded => None,
+            }
+        }
+        if let (Some((l, l_included)), Some((u, u_included))) = (inclusive(&lower), inclusive(&upper)) {
+            if l > u || (l == u && !(l_included && u_included)) {
+                return Vec::new();
+            }
+        }
+        self.entries
+            .range((lower, upper))
+            .flat_map(|(_, keys)| keys.iter().cloned())
+            .collect()
+    }
+
+    /// Get the smallest and the largest indexed value
+    pub fn bounds(&self) -> Option<(&Value, &Value)> {
+        Some((&self.entries.first()?.0 .0, &self.entries.last()?.0 .0))
+    }
+
+    /// Get the number of distinct indexed values
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the index has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the number of rows in the index
+    pub fn key_count(&self) -> usize {
+        let mut count = 0;
+        self.entries.traverse(|_, keys| count += keys.len());
+        count
+    }
+
+    /// Estimate the number of levels of the index's B-tree
+    ///
+    /// See `BTree::estimated_depth`.
+    pub fn estimated_depth(&self) -> usize {
+        self.entries.estimated_depth()
+    }
+}
@@ -0,0 +1,93 @@
+use crate::error::{DbError, Result};
+use std::io::{BufRead, BufReader, Read, Write};
+
+/// Layout of JSON exports and imports
+///
+/// Both layouts hold the same sequence of JSON objects, one per row.
+/// Newline-delimited JSON is written and read one line at a time, so it
+/// suits large tables and line-based tools such as `diff` and `grep`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonFormat {
+    /// One compact JSON object per line (NDJSON)
+    Ndjson,
+    /// A single JSON array with one object per line
+    Array,
+}
+
+/// Writes a stream of JSON objects in one of the layouts
+pub(crate) struct ObjectWriter<W: Write> {
+    writer: W,
+    format: JsonFormat,
+    count: usize,
+}
+
+impl<W: Write> ObjectWriter<W> {
+    pub(crate) fn new(writer: W, format: JsonFormat) -> Self {
+        Self {
+            writer,
+            format,
+            count: 0,
+        }
+    }
+
+    pub(crate) fn write(&mut self, object: &serde_json::Value) -> Result<()> {
+        if self.format == JsonFormat::Array {
+            self.writer
+                .write_all(if self.count == 0 { b"[\n  " } else { b",\n  " })?;
+        }
+        serde_json::to_writer(&mut self.writer, object)?;
+        if self.format == JsonFormat::Ndjson {
+            self.writer.write_all(b"\n")?;
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self) -> Result<()> {
+        if self.format == JsonFormat::Array {
+            self.writer
+                .write_all(if self.count == 0 { b"[]\n" } else { b"\n]\n" })?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Read a stream of JSON objects and pass each one to `f`
+///
+/// Parse errors, and parse errors returned by `f`, are prefixed with the
+/// line (NDJSON) or item number (array) so that bad input can be found.
+pub(crate) fn read_objects(
+    reader: impl Read,
+    format: JsonFormat,
+    mut f: impl FnMut(&serde_json::Value) -> Result<()>,
+) -> Result<()> {
+    let locate = |unit: &str, n: usize, error: DbError| match error {
+        DbError::ParseError(message) => DbError::ParseError(format!("{} {}: {}", unit, n, message)),
+        DbError::SerializationError(e) => DbError::ParseError(format!("{} {}: {}", unit, n, e)),
+        other => other,
+    };
+
+    match format {
+        JsonFormat::Ndjson => {
+            for (i, line) in BufReader::new(reader).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                serde_json::from_str(&line)
+                    .map_err(DbError::from)
+                    .and_then(|object| f(&object))
+                    .map_err(|e| locate("line", i + 1, e))?;
+            }
+        }
+        JsonFormat::Array => {
+            let items: Vec<serde_json::Value> = serde_json::from_reader(reader)
+                .map_err(|e| DbError::ParseError(format!("Invalid JSON array: {}", e)))?;
+            for (i, item) in items.iter().enumerate() {
+                f(item).map_err(|e| locate("item", i + 1, e))?;
+            }
+        }
+    }
+    Ok(())
+}
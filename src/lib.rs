@@ -5,8 +5,9 @@ Oxi-DB is a simple embedded database for Rust applications. It provides:
 
 - Key-value storage with B-tree indexing
 - Table-based data organization
-- Support for multiple data types
-- Persistence to disk
+- Support for multiple data types, including JSON documents
+- Filters with JSON path expressions and secondary indexes
+- Persistence to disk, with optional LZ4 compression of table pages
 - Simple and intuitive API
 
 ## Example
@@ -17,43 +18,80 @@ use oxi_db::{Column, ColumnType, Database, Value};
 // Create a new database
 let mut db = Database::new("example.db");
 
-// Create a table with columns
+// Create a table with an auto-increment primary key
 let columns = vec![
-    Column::new("id", ColumnType::Integer),
+    Column::new("id", ColumnType::Integer).auto_increment(),
     Column::new("name", ColumnType::Text),
     Column::new("active", ColumnType::Boolean),
 ];
 
 db.create_table("users", columns, Some("id".to_string())).unwrap();
 
-// Insert data
-db.insert(
+// Insert data, letting the database assign the key
+let key = db.insert_auto(
     "users",
-    "1",
     vec![
-        Value::Integer(1),
+        Value::Null,
         Value::Text("Alice".to_string()),
         Value::Boolean(true),
     ],
 ).unwrap();
+println!("Inserted user {}", key);
 
 // Save the database
 db.save().unwrap();
 ```
 */
 
+mod backup;
 mod btree;
+mod catalog;
+#[cfg(feature = "cli")]
+pub mod cli;
+mod compression;
+mod csv;
 mod database;
+mod decimal;
+mod encryption;
 mod error;
+mod file_format;
+mod index;
+mod json;
+mod parser;
+mod planner;
+mod query;
+mod sequence;
+#[cfg(feature = "sim")]
+mod sim;
+mod sql;
+mod stats;
+mod storage;
 mod table;
 mod types;
 
 // Re-export public items
+pub use backup::BackupProgress;
 pub use btree::BTree;
+pub use catalog::CATALOG_TABLES;
+pub use compression::{Compression, CompressionStats};
+pub use csv::{CsvImportReport, CsvOptions, CsvRowError};
 pub use database::Database;
+pub use decimal::{Decimal, RoundingMode, MAX_PRECISION};
+pub use encryption::EncryptionKey;
 pub use error::{DbError, Result};
+pub use file_format::{IntegrityProblem, IntegrityReport};
+pub use index::{Index, IndexValue};
+pub use json::JsonFormat;
+pub use planner::{Join, Operator, Plan, Query, QueryResult, SortKey};
+pub use query::{CompareOp, Expr, Filter, JsonPath, PathSegment};
+pub use sequence::Sequence;
+#[cfg(feature = "sim")]
+pub use sim::SimStorage;
+pub use stats::{ColumnStats, DatabaseStats, IndexStats, TableStats};
+pub use storage::{new_lock_token, FileStorage, MemoryStorage, Storage};
 pub use table::Table;
 pub use types::{Column, ColumnType, Key, Row, Value};
+pub use uuid::Uuid;
 
 /// Current version of the Oxi-DB crate
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -62,8 +100,8 @@ pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 ///
 /// This function demonstrates:
 /// - Creating a database
-/// - Creating a table with columns
-/// - Inserting data
+/// - Creating a table with an auto-increment primary key
+/// - Inserting data with generated keys
 /// - Saving the database
 ///
 /// # Returns
@@ -77,31 +115,29 @@ pub fn example() -> Result<()> {
     // Create a new database
     let mut db = Database::new("example.db");
 
-    // Create a table with columns
+    // Create a table with an auto-increment primary key
     let columns = vec![
-        Column::new("id", ColumnType::Integer),
+        Column::new("id", ColumnType::Integer).auto_increment(),
         Column::new("name", ColumnType::Text),
         Column::new("active", ColumnType::Boolean),
     ];
 
     db.create_table("users", columns, Some("id".to_string()))?;
 
-    // Insert data
-    db.insert(
+    // Insert data, letting the database assign the keys
+    db.insert_auto(
         "users",
-        "1",
         vec![
-            Value::Integer(1),
+            Value::Null,
             Value::Text("Alice".to_string()),
             Value::Boolean(true),
         ],
     )?;
 
-    db.insert(
+    db.insert_auto(
         "users",
-        "2",
         vec![
-            Value::Integer(2),
+            Value::Null,
             Value::Text("Bob".to_string()),
             Value::Boolean(false),
         ],
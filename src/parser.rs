@@ -0,0 +1,648 @@
+use crate::decimal::{Decimal, MAX_PRECISION};
+use crate::error::{DbError, Result};
+use crate::planner::Query;
+use crate::query::{CompareOp, Expr, Filter, JsonPath};
+use crate::types::{Column, ColumnType, Key, Value, MAX_NESTING};
+
+/// A lexical token of the query language
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token {
+    /// A bare word: a column or table name, or a keyword
+    Ident(String),
+    /// A quoted string literal
+    Str(String),
+    /// A numeric literal, kept as written so decimals stay exact
+    Number(String),
+    /// A blob literal written as `x'0aff'`
+    Blob(Vec<u8>),
+    /// A JSON path starting with `$`
+    Path(String),
+    /// An operator or punctuation symbol
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 16] = [
+    "<=", ">=", "!=", "<>", "=", "<", ">", "(", ")", "[", "]", "{", "}", ",", ";", ".",
+];
+
+/// Split input into tokens
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '-' && chars.get(i + 1) == Some(&'-') {
+            // A comment runs to the end of the line
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '\'' || c == '"' {
+            let (text, next) = read_quoted(&chars, i)?;
+            tokens.push(Token::Str(text));
+            i = next;
+        } else if (c == 'x' || c == 'X') && chars.get(i + 1) == Some(&'\'') {
+            let (hex, next) = read_quoted(&chars, i + 1)?;
+            tokens.push(Token::Blob(decode_hex(&hex)?));
+            i = next;
+        } else if c == '$' {
+            let start = i;
+            i += 1;
+            while i < chars.len() {
+                match chars[i] {
+                    '[' => {
+                        let (_, next) = read_bracket(&chars, i)?;
+                        i = next;
+                    }
+                    c if c == '.' || c == '_' || c.is_alphanumeric() => i += 1,
+                    _ => break,
+                }
+            }
+            tokens.push(Token::Path(chars[start..i].iter().collect()));
+        } else if c.is_ascii_digit()
+            || ((c == '-' || c == '+' || c == '.')
+                && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit() || *n == '.'))
+        {
+            let start = i;
+            i += 1;
+            while i < chars.len() {
+                let c = chars[i];
+                let exponent_sign = (c == '-' || c == '+') && matches!(chars[i - 1], 'e' | 'E');
+                if c.is_ascii_alphanumeric() || c == '.' || exponent_sign {
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token::Number(chars[start..i].iter().collect()));
+        } else if c.is_alphanumeric() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let symbol = SYMBOLS
+                .iter()
+                .find(|s| rest.starts_with(**s))
+                .ok_or_else(|| DbError::ParseError(format!("Unexpected character '{}'", c)))?;
+            tokens.push(Token::Symbol(symbol));
+            i += symbol.len();
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Read a string quoted with `'` or `"` starting at `start`
+///
+/// A doubled quote inside the string stands for a single quote character.
+pub(crate) fn read_quoted(chars: &[char], start: usize) -> Result<(String, usize)> {
+    let quote = chars[start];
+    let mut text = String::new();
+    let mut i = start + 1;
+
+    while i < chars.len() {
+        if chars[i] == quote {
+            if chars.get(i + 1) == Some(&quote) {
+                text.push(quote);
+                i += 2;
+                continue;
+            }
+            return Ok((text, i + 1));
+        }
+        text.push(chars[i]);
+        i += 1;
+    }
+
+    Err(DbError::ParseError("Unterminated string".to_string()))
+}
+
+/// Skip a `[...]` group of a JSON path, respecting quoted member names
+fn read_bracket(chars: &[char], start: usize) -> Result<(String, usize)> {
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            ']' => return Ok((chars[start..=i].iter().collect(), i + 1)),
+            '\'' | '"' => i = read_quoted(chars, i)?.1,
+            _ => i += 1,
+        }
+    }
+    Err(DbError::ParseError("Unclosed '[' in JSON path".to_string()))
+}
+
+/// Decode a hexadecimal string into bytes
+pub(crate) fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return Err(DbError::ParseError(format!("Invalid hex literal: {}", hex)));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|_| DbError::ParseError(format!("Invalid hex literal: {}", hex)))
+        })
+        .collect()
+}
+
+/// A recursive-descent parser over the tokens of the query language
+///
+/// The language has literals (`NULL`, `TRUE`, `42`, `19.99`, `'text'`,
+/// `x'00ff'`, arrays `[1, 2]` and structs `{'Paris', 75001}`), column expressions (`name`, `profile$.address.city`)
+/// and filters combining comparisons with `AND`, `OR` and `NOT`.
+pub(crate) struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Whether column names may be qualified with a table name, as in queries
+    qualified_names: bool,
+    /// Current nesting of parentheses, arrays, structs and composite keys
+    depth: usize,
+}
+
+impl Parser {
+    /// Tokenize the input and create a parser over it
+    pub(crate) fn new(input: &str) -> Result<Self> {
+        Ok(Self {
+            tokens: tokenize(input)?,
+            pos: 0,
+            qualified_names: false,
+            depth: 0,
+        })
+    }
+
+    /// Run a parse step one level deeper
+    ///
+    /// Input nested more than `MAX_NESTING` levels fails to parse instead
+    /// of overflowing the stack.
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= MAX_NESTING {
+            return Err(DbError::ParseError(format!("Nesting deeper than {} levels", MAX_NESTING)));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Look at the next token without consuming it
+    pub(crate) fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        if token.is_some() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn error<T>(&self, expected: &str) -> Result<T> {
+        match self.peek() {
+            Some(token) => Err(DbError::ParseError(format!("Expected {}, found {:?}", expected, token))),
+            None => Err(DbError::ParseError(format!("Expected {}, found end of input", expected))),
+        }
+    }
+
+    /// Check if all tokens have been consumed
+    pub(crate) fn is_at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    /// Fail unless all tokens have been consumed
+    pub(crate) fn expect_end(&self) -> Result<()> {
+        if self.is_at_end() {
+            Ok(())
+        } else {
+            self.error("end of input")
+        }
+    }
+
+    /// Check if the next token is the given keyword, ignoring case
+    pub(crate) fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    /// Consume the next token if it is the given keyword
+    pub(crate) fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    /// Consume the given keyword or fail
+    pub(crate) fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            self.error(keyword)
+        }
+    }
+
+    /// Consume the next token if it is the given symbol
+    pub(crate) fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    /// Consume the given symbol or fail
+    pub(crate) fn expect_symbol(&mut self, symbol: &str) -> Result<()> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            self.error(&format!("'{}'", symbol))
+        }
+    }
+
+    /// Parse a name such as a table or column name
+    ///
+    /// Quoted strings are accepted so that names can contain any character.
+    pub(crate) fn parse_ident(&mut self) -> Result<String> {
+        match self.peek() {
+            Some(Token::Ident(name)) | Some(Token::Str(name)) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => self.error("a name"),
+        }
+    }
+
+    /// Parse a literal value
+    ///
+    /// Integers without a decimal point become `Value::Integer`, numbers with
+    /// a decimal point become exact `Value::Decimal`s, and numbers with an
+    /// exponent become `Value::Float`s.
+    pub(crate) fn parse_literal(&mut self) -> Result<Value> {
+        match self.next() {
+            Some(Token::Number(text)) => parse_number(&text),
+            Some(Token::Str(text)) => Ok(Value::Text(text)),
+            Some(Token::Blob(bytes)) => Ok(Value::Blob(bytes)),
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("null") => Ok(Value::Null),
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("true") => Ok(Value::Boolean(true)),
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("false") => Ok(Value::Boolean(false)),
+            Some(Token::Symbol("[")) => {
+                let mut values = Vec::new();
+                if !self.eat_symbol("]") {
+                    loop {
+                        values.push(self.nested(Self::parse_literal)?);
+                        if self.eat_symbol("]") {
+                            break;
+                        }
+                        self.expect_symbol(",")?;
+                    }
+                }
+                Ok(Value::Array(values))
+            }
+            Some(Token::Symbol("{")) => {
+                let mut values = Vec::new();
+                if !self.eat_symbol("}") {
+                    loop {
+                        values.push(self.nested(Self::parse_literal)?);
+                        if self.eat_symbol("}") {
+                            break;
+                        }
+                        self.expect_symbol(",")?;
+                    }
+                }
+                Ok(Value::Struct(values))
+            }
+            _ => {
+                self.pos = self.pos.saturating_sub(1);
+                self.error("a literal value")
+            }
+        }
+    }
+
+    /// Parse a key: integers become integer keys, anything else text keys
+    ///
+    /// Quote a number (`'42'`) to use it as a text key. A string after the
+    /// word `UUID` is a UUID key, as in `UUID '6f9619ff-...'`.
+    pub(crate) fn parse_key(&mut self) -> Result<Key> {
+        match self.next() {
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("uuid") => match self.peek() {
+                Some(Token::Str(text)) => {
+                    let key = text
+                        .parse()
+                        .map(Key::Uuid)
+                        .map_err(|_| DbError::ParseError(format!("Invalid UUID: {}", text)));
+                    self.pos += 1;
+                    key
+                }
+                _ => Ok(Key::Text(word)),
+            },
+            Some(Token::Number(text)) => text
+                .parse::<i64>()
+                .map(Key::Integer)
+                .map_err(|_| DbError::ParseError(format!("Invalid integer key: {}", text))),
+            Some(Token::Str(text)) | Some(Token::Ident(text)) => Ok(Key::Text(text)),
+            Some(Token::Blob(bytes)) => Ok(Key::Bytes(bytes)),
+            Some(Token::Symbol("(")) => {
+                let mut parts = Vec::new();
+                if !self.eat_symbol(")") {
+                    loop {
+                        parts.push(self.nested(Self::parse_key)?);
+                        if self.eat_symbol(")") {
+                            break;
+                        }
+                        self.expect_symbol(",")?;
+                    }
+                }
+                Ok(Key::Composite(parts))
+            }
+            _ => {
+                self.pos = self.pos.saturating_sub(1);
+                self.error("a key")
+            }
+        }
+    }
+
+    /// Parse a column expression, optionally followed by a JSON path
+    pub(crate) fn parse_expr(&mut self) -> Result<Expr> {
+        let mut column = self.parse_ident()?;
+        if self.qualified_names && self.eat_symbol(".") {
+            column = format!("{}.{}", column, self.parse_ident()?);
+        }
+        if let Some(Token::Path(path)) = self.peek().cloned() {
+            self.pos += 1;
+            return Ok(Expr::JsonPath {
+                column,
+                path: JsonPath::parse(&path)?,
+            });
+        }
+        Ok(Expr::Column(column))
+    }
+
+    fn parse_compare_op(&mut self) -> Result<CompareOp> {
+        let op = match self.peek() {
+            Some(Token::Symbol("=")) => CompareOp::Eq,
+            Some(Token::Symbol("!=")) | Some(Token::Symbol("<>")) => CompareOp::Ne,
+            Some(Token::Symbol("<")) => CompareOp::Lt,
+            Some(Token::Symbol("<=")) => CompareOp::Le,
+            Some(Token::Symbol(">")) => CompareOp::Gt,
+            Some(Token::Symbol(">=")) => CompareOp::Ge,
+            _ => return self.error("a comparison operator"),
+        };
+        self.pos += 1;
+        Ok(op)
+    }
+
+    /// Parse a filter made of comparisons joined by `AND`, `OR` and `NOT`
+    pub(crate) fn parse_filter(&mut self) -> Result<Filter> {
+        let mut filter = self.parse_and()?;
+        while self.eat_keyword("or") {
+            filter = filter.or(self.parse_and()?);
+        }
+        Ok(filter)
+    }
+
+    fn parse_and(&mut self) -> Result<Filter> {
+        let mut filter = self.parse_unary()?;
+        while self.eat_keyword("and") {
+            filter = filter.and(self.parse_unary()?);
+        }
+        Ok(filter)
+    }
+
+    fn parse_unary(&mut self) -> Result<Filter> {
+        if self.eat_keyword("not") {
+            return Ok(self.nested(Self::parse_unary)?.not());
+        }
+        if self.eat_symbol("(") {
+            let filter = self.nested(Self::parse_filter)?;
+            self.expect_symbol(")")?;
+            return Ok(filter);
+        }
+        if self.eat_keyword("any") {
+            self.expect_symbol("(")?;
+            let expr = self.parse_expr()?;
+            self.expect_symbol(")")?;
+            let op = self.parse_compare_op()?;
+            return Ok(Filter::Any(expr, op, self.parse_literal()?));
+        }
+
+        let expr = self.parse_expr()?;
+        if self.eat_keyword("is") {
+            let negated = self.eat_keyword("not");
+            self.expect_keyword("null")?;
+            let filter = Filter::IsNull(expr);
+            return Ok(if negated { filter.not() } else { filter });
+        }
+        if self.eat_keyword("contains") {
+            return Ok(Filter::Contains(expr, self.parse_literal()?));
+        }
+
+        let op = self.parse_compare_op()?;
+        Ok(Filter::Compare(expr, op, self.parse_literal()?))
+    }
+
+    /// Parse a column type as written by `ColumnType`'s `Display`
+    ///
+    /// Common SQL spellings such as `INT`, `VARCHAR` and `BOOL` are accepted
+    /// as well. A `DECIMAL` without arguments gets the maximum precision and
+    /// a scale of zero.
+    pub(crate) fn parse_column_type(&mut self) -> Result<ColumnType> {
+        let name = match self.peek() {
+            Some(Token::Ident(name)) => name.to_uppercase(),
+            _ => return self.error("a column type"),
+        };
+        self.pos += 1;
+
+        Ok(match name.as_str() {
+            "INTEGER" | "INT" | "BIGINT" => ColumnType::Integer,
+            "FLOAT" | "REAL" | "DOUBLE" => ColumnType::Float,
+            "DECIMAL" | "NUMERIC" => {
+                if self.eat_symbol("(") {
+                    let precision = self.parse_u32()?;
+                    let scale = if self.eat_symbol(",") { self.parse_u32()? } else { 0 };
+                    self.expect_symbol(")")?;
+                    let column_type = ColumnType::Decimal(precision, scale);
+                    column_type.validate().map_err(DbError::ParseError)?;
+                    column_type
+                } else {
+                    ColumnType::Decimal(MAX_PRECISION, 0)
+                }
+            }
+            "TEXT" | "VARCHAR" | "STRING" => ColumnType::Text,
+            "BOOLEAN" | "BOOL" => ColumnType::Boolean,
+            "BLOB" | "BYTES" => ColumnType::Blob,
+            "UUID" => ColumnType::Uuid,
+            "JSON" => ColumnType::Json,
+            "ENUM" => {
+                self.expect_symbol("(")?;
+                let mut labels = Vec::new();
+                loop {
+                    match self.peek() {
+                        Some(Token::Str(label)) => labels.push(label.clone()),
+                        _ => return self.error("a quoted enum label"),
+                    }
+                    self.pos += 1;
+                    if self.eat_symbol(")") {
+                        break;
+                    }
+                    self.expect_symbol(",")?;
+                }
+                ColumnType::Enum(labels)
+            }
+            "ARRAY" => {
+                self.expect_symbol("(")?;
+                let element = self.nested(Self::parse_column_type)?;
+                self.expect_symbol(")")?;
+                ColumnType::Array(Box::new(element))
+            }
+            "STRUCT" => {
+                self.expect_symbol("(")?;
+                let mut fields = Vec::new();
+                loop {
+                    let name = self.parse_ident()?;
+                    fields.push(Column::new(name, self.nested(Self::parse_column_type)?));
+                    if self.eat_symbol(")") {
+                        break;
+                    }
+                    self.expect_symbol(",")?;
+                }
+                ColumnType::Struct(fields)
+            }
+            _ => {
+                self.pos -= 1;
+                return self.error("a column type");
+            }
+        })
+    }
+
+    /// Parse comma-separated column definitions such as
+    /// `id INTEGER PRIMARY KEY AUTO_INCREMENT, name TEXT`
+    ///
+    /// Returns the columns and the name of the primary key column, if one
+    /// was declared.
+    pub(crate) fn parse_column_defs(&mut self) -> Result<(Vec<Column>, Option<String>)> {
+        let mut columns = Vec::new();
+        let mut primary_key = None;
+
+        loop {
+            let name = self.parse_ident()?;
+            let mut column = Column::new(name, self.parse_column_type()?);
+            loop {
+                if self.eat_keyword("primary") {
+                    self.expect_keyword("key")?;
+                    if primary_key.replace(column.name.clone()).is_some() {
+                        return Err(DbError::ParseError(
+                            "Only one column can be the primary key".to_string(),
+                        ));
+                    }
+                } else if self.eat_keyword("auto_increment") || self.eat_keyword("autoincrement") {
+                    column = column.auto_increment();
+                } else {
+                    break;
+                }
+            }
+            columns.push(column);
+
+            if !self.eat_symbol(",") {
+                break;
+            }
+        }
+
+        Ok((columns, primary_key))
+    }
+
+    /// Parse a query: a table with optional `JOIN`, `WHERE`, `ORDER BY`
+    /// and `LIMIT` clauses
+    ///
+    /// A filter directly after the table name, without `WHERE`, is accepted
+    /// too. Column names in the query may be qualified, as in `users.id`.
+    pub(crate) fn parse_query(&mut self) -> Result<Query> {
+        self.qualified_names = true;
+        let mut query = Query::new(self.parse_ident()?);
+
+        if self.eat_keyword("join") {
+            let table = self.parse_ident()?;
+            self.expect_keyword("on")?;
+            let left = self.parse_expr()?;
+            self.expect_symbol("=")?;
+            let right = self.parse_expr()?;
+            query = query.join(table, left, right);
+        }
+
+        let clause_ends = self.is_at_end() || self.peek_keyword("order") || self.peek_keyword("limit");
+        if self.eat_keyword("where") || !clause_ends {
+            query = query.filter(self.parse_filter()?);
+        }
+
+        if self.eat_keyword("order") {
+            self.expect_keyword("by")?;
+            loop {
+                let expr = self.parse_expr()?;
+                let descending = self.eat_keyword("desc");
+                if !descending {
+                    self.eat_keyword("asc");
+                }
+                query = query.order_by(expr, descending);
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        if self.eat_keyword("limit") {
+            query = query.limit(self.parse_u32()? as usize);
+        }
+        Ok(query)
+    }
+
+    fn parse_u32(&mut self) -> Result<u32> {
+        match self.next() {
+            Some(Token::Number(text)) => text
+                .parse()
+                .map_err(|_| DbError::ParseError(format!("Invalid number: {}", text))),
+            _ => {
+                self.pos = self.pos.saturating_sub(1);
+                self.error("a number")
+            }
+        }
+    }
+}
+
+/// Convert literals to the types of the columns they are written for
+///
+/// A literal has the type it is written with: `20` is an integer and
+/// `19.99` a decimal whatever the column, and UUIDs, JSON documents and
+/// non-finite floats can only be written as strings. Each literal is
+/// converted with `Value::coerce`; literals beyond the last column are left
+/// as they are for the table to reject.
+pub(crate) fn coerce_literals(values: Vec<Value>, columns: &[Column]) -> Result<Vec<Value>> {
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| match columns.get(i) {
+            Some(column) => value.coerce(&column.column_type),
+            None => Ok(value),
+        })
+        .collect()
+}
+
+/// Parse a numeric literal as written
+fn parse_number(text: &str) -> Result<Value> {
+    if text.contains(['e', 'E']) {
+        return text
+            .parse::<f64>()
+            .map(Value::Float)
+            .map_err(|_| DbError::ParseError(format!("Invalid number: {}", text)));
+    }
+    if text.contains('.') {
+        return text.parse::<Decimal>().map(Value::Decimal);
+    }
+    text.parse::<i64>()
+        .map(Value::Integer)
+        .map_err(|_| DbError::ParseError(format!("Invalid number: {}", text)))
+}
@@ -0,0 +1,1108 @@
+use crate::error::{DbError, Result};
+use crate::index::{Index, IndexValue};
+use crate::parser::Parser;
+use crate::query::{CompareOp, Expr, Filter};
+use crate::table::Table;
+use crate::types::{Column, Key, Row, Value};
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Bound;
+
+/// Cost of reading one row in a scan, the unit of all costs
+const SCAN_COST: f64 = 1.0;
+
+/// Cost of fetching one row found through an index
+const FETCH_COST: f64 = 2.0;
+
+/// Share of rows assumed to match a condition nothing is known about
+const DEFAULT_SELECTIVITY: f64 = 0.1;
+
+/// Share of rows assumed to match one side of a range nothing is known about
+const RANGE_SELECTIVITY: f64 = 1.0 / 3.0;
+
+/// Looks up the tables a plan reads by name
+pub(crate) type Tables<'a> = dyn Fn(&str) -> Result<&'a Table> + 'a;
+
+/// A key to sort the results of a query by
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKey {
+    /// The expression whose values are compared
+    pub expr: Expr,
+    /// Whether larger values come first
+    pub descending: bool,
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.expr, if self.descending { " DESC" } else { "" })
+    }
+}
+
+/// An inner join with a second table on equal values
+#[derive(Debug, Clone, PartialEq)]
+pub struct Join {
+    /// The table joined with the table of the query
+    pub table: String,
+    /// The expression of one side of the join condition
+    pub left: Expr,
+    /// The expression of the other side of the join condition
+    pub right: Expr,
+}
+
+/// A query over a table, optionally joined with a second one
+///
+/// Without a join, expressions name the columns of the table. With a join,
+/// they may be qualified with a table name (`orders.total`), which is
+/// required when both tables have a column of that name, and the result
+/// has the columns of both tables, qualified. `Database::query` runs a
+/// query with the plan `Database::plan` chooses for it.
+///
+/// # Examples
+///
+/// ```
+/// use oxi_db::{Expr, Query};
+///
+/// let query = Query::new("users")
+///     .join("orders", Expr::column("users.id"), Expr::column("orders.user_id"))
+///     .filter(Expr::column("orders.total").gt(100))
+///     .order_by(Expr::column("orders.total"), true)
+///     .limit(10);
+/// let parsed = Query::parse(
+///     "users JOIN orders ON users.id = orders.user_id WHERE orders.total > 100 \
+///      ORDER BY orders.total DESC LIMIT 10",
+/// )
+/// .unwrap();
+/// assert_eq!(parsed, query);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    /// The table the query reads
+    pub table: String,
+    /// A second table joined with the first
+    pub join: Option<Join>,
+    /// The condition rows of the result must satisfy
+    pub filter: Option<Filter>,
+    /// The keys the result is sorted by; key order when empty
+    pub order_by: Vec<SortKey>,
+    /// The most rows the result may have
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    /// Create a query returning every row of a table
+    pub fn new(table: impl Into<String>) -> Self {
+        Self {
+            table: table.into(),
+            join: None,
+            filter: None,
+            order_by: Vec::new(),
+            limit: None,
+        }
+    }
+
+    /// Join with the rows of another table where `left` equals `right`
+    pub fn join(mut self, table: impl Into<String>, left: Expr, right: Expr) -> Self {
+        self.join = Some(Join {
+            table: table.into(),
+            left,
+            right,
+        });
+        self
+    }
+
+    /// Keep only rows matching a filter, in addition to earlier filters
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(match self.filter.take() {
+            Some(existing) => existing.and(filter),
+            None => filter,
+        });
+        self
+    }
+
+    /// Sort the result by an expression, after earlier sort keys
+    pub fn order_by(mut self, expr: Expr, descending: bool) -> Self {
+        self.order_by.push(SortKey { expr, descending });
+        self
+    }
+
+    /// Return at most `count` rows
+    pub fn limit(mut self, count: usize) -> Self {
+        self.limit = Some(count);
+        self
+    }
+
+    /// Parse a query written in the query language
+    ///
+    /// The syntax is `<table> [JOIN <table> ON <expr> = <expr>]
+    /// [WHERE <filter>] [ORDER BY <expr> [ASC|DESC], ...] [LIMIT <count>]`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ParseError` if the input is not a valid query.
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut parser = Parser::new(input)?;
+        let query = parser.parse_query()?;
+        parser.expect_end()?;
+        Ok(query)
+    }
+}
+
+/// The rows a query returned, with the columns they have
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryResult {
+    /// The columns of the rows; qualified with their table after a join
+    pub columns: Vec<Column>,
+    /// The rows with their keys; after a join, the key is the composite of
+    /// the keys of the joined rows
+    pub rows: Vec<(Key, Row)>,
+}
+
+/// A step of a query plan
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operator {
+    /// Read every row of a table, in key order
+    Scan {
+        /// The table to read
+        table: String,
+        /// The condition rows must satisfy
+        filter: Option<Filter>,
+    },
+    /// Read the rows an index maps one value to
+    IndexSeek {
+        /// The table to read
+        table: String,
+        /// The index to look the value up in
+        index: String,
+        /// The indexed expression
+        expr: Expr,
+        /// The value to look up
+        value: Value,
+        /// The condition rows must satisfy besides the indexed value
+        filter: Option<Filter>,
+    },
+    /// Read the rows whose indexed value lies between two bounds, in index
+    /// order
+    RangeScan {
+        /// The table to read
+        table: String,
+        /// The index to read a range of
+        index: String,
+        /// The indexed expression
+        expr: Expr,
+        /// The lower bound of the indexed value
+        lower: Bound<Value>,
+        /// The upper bound of the indexed value
+        upper: Bound<Value>,
+        /// The condition rows must satisfy besides the range
+        filter: Option<Filter>,
+    },
+    /// Compare every row of the first input with every row of the second
+    NestedLoopJoin {
+        /// The join expression on the rows of the first input
+        left: Expr,
+        /// The join expression on the rows of the second input
+        right: Expr,
+    },
+    /// Look up the rows matching each row of the input in an index
+    IndexJoin {
+        /// The join expression on the rows of the input
+        left: Expr,
+        /// The table joined with the input
+        table: String,
+        /// The index of `table` on `right`
+        index: String,
+        /// The join expression on the rows of `table`
+        right: Expr,
+        /// The condition the rows of `table` must satisfy
+        filter: Option<Filter>,
+    },
+    /// Sort both inputs by their join expression and merge them
+    MergeJoin {
+        /// The join expression on the rows of the first input
+        left: Expr,
+        /// The join expression on the rows of the second input
+        right: Expr,
+    },
+    /// Keep the rows of the input that match a condition
+    Filter {
+        /// The condition rows must satisfy
+        filter: Filter,
+    },
+    /// Sort the rows of the input
+    Sort {
+        /// The sort keys, most significant first
+        keys: Vec<SortKey>,
+    },
+    /// Keep the first rows of the input
+    Limit {
+        /// The most rows to keep
+        count: usize,
+    },
+}
+
+/// A physical plan for a query: a tree of operators with estimates
+///
+/// Estimates come from the row counts of the tables, the contents of their
+/// indexes and, after `Database::analyze`, the statistics of their
+/// columns. Costs are in rows read by a scan. `Database::explain` runs the
+/// plan and records the rows each operator produced, and `Display` shows
+/// the tree:
+///
+/// ```text
+/// Limit 10 (estimated 10 rows, actual 10, cost 239.8)
+///   Sort by orders.total DESC (estimated 20 rows, actual 20, cost 239.8)
+///     IndexJoin orders using orders_user on users.id = orders.user_id (estimated 20 rows, actual 20, cost 152.0)
+///       Scan users where name = "user7" (estimated 1 rows, actual 1, cost 100.0)
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plan {
+    /// What this step does
+    pub operator: Operator,
+    /// The steps whose rows this step reads
+    pub inputs: Vec<Plan>,
+    /// Estimated number of rows produced
+    pub estimated_rows: f64,
+    /// Estimated cost of this step and its inputs
+    pub cost: f64,
+    /// Number of rows produced when the plan ran
+    pub actual_rows: Option<u64>,
+}
+
+impl Plan {
+    fn new(operator: Operator, inputs: Vec<Plan>, estimated_rows: f64, cost: f64) -> Self {
+        Self {
+            operator,
+            inputs,
+            estimated_rows,
+            cost,
+            actual_rows: None,
+        }
+    }
+
+    /// Describe this step on one line, without its inputs
+    pub fn description(&self) -> String {
+        let filter = |filter: &Option<Filter>| {
+            filter
+                .as_ref()
+                .map(|f| format!(" where {}", f))
+                .unwrap_or_default()
+        };
+        let side = |i: usize, expr: &Expr| match self.inputs.get(i).and_then(Plan::table) {
+            Some(table) => format!("{}.{}", table, expr),
+            None => expr.to_string(),
+        };
+        match &self.operator {
+            Operator::Scan { table, filter: f } => format!("Scan {}{}", table, filter(f)),
+            Operator::IndexSeek {
+                table,
+                index,
+                expr,
+                value,
+                filter: f,
+            } => format!(
+                "IndexSeek {} using {} ({} = {}){}",
+                table,
+                index,
+                expr,
+                value,
+                filter(f)
+            ),
+            Operator::RangeScan {
+                table,
+                index,
+                expr,
+                lower,
+                upper,
+                filter: f,
+            } => {
+                let range = bound_filters(expr, lower, upper)
+                    .iter()
+                    .map(Filter::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" AND ");
+                format!("RangeScan {} using {} ({}){}", table, index, range, filter(f))
+            }
+            Operator::NestedLoopJoin { left, right } => {
+                format!("NestedLoopJoin on {} = {}", side(0, left), side(1, right))
+            }
+            Operator::IndexJoin {
+                left,
+                table,
+                index,
+                right,
+                filter: f,
+            } => format!(
+                "IndexJoin {} using {} on {} = {}.{}{}",
+                table,
+                index,
+                side(0, left),
+                table,
+                right,
+                filter(f)
+            ),
+            Operator::MergeJoin { left, right } => {
+                format!("MergeJoin on {} = {}", side(0, left), side(1, right))
+            }
+            Operator::Filter { filter } => format!("Filter {}", filter),
+            Operator::Sort { keys } => format!(
+                "Sort by {}",
+                keys.iter().map(SortKey::to_string).collect::<Vec<_>>().join(", ")
+            ),
+            Operator::Limit { count } => format!("Limit {}", count),
+        }
+    }
+
+    /// The table an access step reads
+    fn table(&self) -> Option<&str> {
+        match &self.operator {
+            Operator::Scan { table, .. }
+            | Operator::IndexSeek { table, .. }
+            | Operator::RangeScan { table, .. } => Some(table),
+            _ => None,
+        }
+    }
+
+    /// The expression the rows of this step are in ascending order of
+    fn ordered_by(&self) -> Option<&Expr> {
+        match &self.operator {
+            Operator::IndexSeek { expr, .. } | Operator::RangeScan { expr, .. } => Some(expr),
+            _ => None,
+        }
+    }
+
+    /// Get the columns of the rows this step produces
+    pub(crate) fn columns<'a>(&self, tables: &Tables<'a>) -> Result<Vec<Column>> {
+        let qualified = |plan: &Plan, columns: Vec<Column>| match plan.table() {
+            Some(table) => qualify_columns(table, columns),
+            None => columns,
+        };
+        match &self.operator {
+            Operator::Scan { table, .. }
+            | Operator::IndexSeek { table, .. }
+            | Operator::RangeScan { table, .. } => Ok(tables(table)?.columns.clone()),
+            Operator::NestedLoopJoin { .. } | Operator::MergeJoin { .. } => {
+                let mut columns = qualified(&self.inputs[0], self.inputs[0].columns(tables)?);
+                columns.extend(qualified(&self.inputs[1], self.inputs[1].columns(tables)?));
+                Ok(columns)
+            }
+            Operator::IndexJoin { table, .. } => {
+                let mut columns = qualified(&self.inputs[0], self.inputs[0].columns(tables)?);
+                columns.extend(qualify_columns(table, tables(table)?.columns.clone()));
+                Ok(columns)
+            }
+            Operator::Filter { .. } | Operator::Sort { .. } | Operator::Limit { .. } => {
+                self.inputs[0].columns(tables)
+            }
+        }
+    }
+
+    /// Run the plan, recording the rows each step produces
+    pub(crate) fn run<'a>(&mut self, tables: &Tables<'a>) -> Result<Vec<(Key, Row)>> {
+        let rows = match &self.operator {
+            Operator::Scan { table, filter } => tables(table)?.scan(filter.as_ref())?,
+            Operator::IndexSeek {
+                table,
+                index,
+                expr,
+                value,
+                filter,
+            } => {
+                let table = tables(table)?;
+                let mut keys = find_index(table, index)?.lookup(value);
+                keys.sort();
+                let condition = and_all(
+                    std::iter::once(Filter::Compare(expr.clone(), CompareOp::Eq, value.clone()))
+                        .chain(filter.clone())
+                        .collect(),
+                );
+                table.fetch(keys, condition.as_ref())?
+            }
+            Operator::RangeScan {
+                table,
+                index,
+                expr,
+                lower,
+                upper,
+                filter,
+            } => {
+                let table = tables(table)?;
+                let keys = find_index(table, index)?.range(lower.as_ref(), upper.as_ref());
+                let mut conditions = bound_filters(expr, lower, upper);
+                conditions.extend(filter.clone());
+                table.fetch(keys, and_all(conditions).as_ref())?
+            }
+            Operator::NestedLoopJoin { left, right } => {
+                let (outer, inner) = (self.inputs[0].run(tables)?, self.inputs[1].run(tables)?);
+                let left_columns = self.inputs[0].columns(tables)?;
+                let right_columns = self.inputs[1].columns(tables)?;
+                let inner = join_values(&inner, right, &right_columns)?;
+                let mut rows = Vec::new();
+                for (value, (key, row)) in join_values(&outer, left, &left_columns)? {
+                    for (inner_value, (inner_key, inner_row)) in &inner {
+                        if value.0.compare(&inner_value.0) == Some(Ordering::Equal) {
+                            rows.push(join_row(key, row, inner_key, inner_row));
+                        }
+                    }
+                }
+                rows
+            }
+            Operator::IndexJoin {
+                left,
+                table,
+                index,
+                right,
+                filter,
+            } => {
+                let outer = self.inputs[0].run(tables)?;
+                let left_columns = self.inputs[0].columns(tables)?;
+                let table = tables(table)?;
+                let index = find_index(table, index)?;
+                let mut rows = Vec::new();
+                for (value, (key, row)) in join_values(&outer, left, &left_columns)? {
+                    let mut keys = index.lookup(&value.0);
+                    keys.sort();
+                    let condition = and_all(
+                        std::iter::once(Filter::Compare(right.clone(), CompareOp::Eq, value.0))
+                            .chain(filter.clone())
+                            .collect(),
+                    );
+                    for (inner_key, inner_row) in table.fetch(keys, condition.as_ref())? {
+                        rows.push(join_row(key, row, &inner_key, &inner_row));
+                    }
+                }
+                rows
+            }
+            Operator::MergeJoin { left, right } => {
+                let (outer, inner) = (self.inputs[0].run(tables)?, self.inputs[1].run(tables)?);
+                let mut outer = join_values(&outer, left, &self.inputs[0].columns(tables)?)?;
+                let mut inner = join_values(&inner, right, &self.inputs[1].columns(tables)?)?;
+                outer.sort_by(|a, b| a.0.cmp(&b.0));
+                inner.sort_by(|a, b| a.0.cmp(&b.0));
+
+                let mut rows = Vec::new();
+                let (mut i, mut j) = (0, 0);
+                while i < outer.len() && j < inner.len() {
+                    match outer[i].0.cmp(&inner[j].0) {
+                        Ordering::Less => i += 1,
+                        Ordering::Greater => j += 1,
+                        Ordering::Equal => {
+                            let group = inner[j..]
+                                .iter()
+                                .take_while(|(value, _)| *value == outer[i].0)
+                                .count();
+                            while i < outer.len() && outer[i].0 == inner[j].0 {
+                                let (value, (key, row)) = &outer[i];
+                                for (inner_value, (inner_key, inner_row)) in &inner[j..j + group] {
+                                    if value.0.compare(&inner_value.0) == Some(Ordering::Equal) {
+                                        rows.push(join_row(key, row, inner_key, inner_row));
+                                    }
+                                }
+                                i += 1;
+                            }
+                            j += group;
+                        }
+                    }
+                }
+                rows
+            }
+            Operator::Filter { filter } => {
+                let columns = self.inputs[0].columns(tables)?;
+                let mut rows = Vec::new();
+                for (key, row) in self.inputs[0].run(tables)? {
+                    if filter.matches(&columns, &row)? {
+                        rows.push((key, row));
+                    }
+                }
+                rows
+            }
+            Operator::Sort { keys } => {
+                let columns = self.inputs[0].columns(tables)?;
+                let mut sorted = Vec::new();
+                for (key, row) in self.inputs[0].run(tables)? {
+                    let values = keys
+                        .iter()
+                        .map(|sort| Ok(IndexValue(sort.expr.evaluate(&columns, &row)?)))
+                        .collect::<Result<Vec<_>>>()?;
+                    sorted.push((values, (key, row)));
+                }
+                sorted.sort_by(|(a, _), (b, _)| {
+                    keys.iter()
+                        .zip(a.iter().zip(b))
+                        .map(|(sort, (a, b))| if sort.descending { b.cmp(a) } else { a.cmp(b) })
+                        .find(|ordering| *ordering != Ordering::Equal)
+                        .unwrap_or(Ordering::Equal)
+                });
+                sorted.into_iter().map(|(_, row)| row).collect()
+            }
+            Operator::Limit { count } => {
+                let mut rows = self.inputs[0].run(tables)?;
+                rows.truncate(*count);
+                rows
+            }
+        };
+        self.actual_rows = Some(rows.len() as u64);
+        Ok(rows)
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let actual = self
+            .actual_rows
+            .map(|rows| format!(", actual {}", rows))
+            .unwrap_or_default();
+        writeln!(
+            f,
+            "{}{} (estimated {:.0} rows{}, cost {:.1})",
+            "  ".repeat(depth),
+            self.description(),
+            self.estimated_rows,
+            actual,
+            self.cost
+        )?;
+        for input in &self.inputs {
+            input.write(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+/// Choose how to read the rows of a table that match a filter
+///
+/// Every index on an expression the filter compares with a value offers an
+/// `IndexSeek` (for `=`) or a `RangeScan` (for `<`, `<=`, `>` and `>=`);
+/// the cheapest of those and a full `Scan` wins. Indexes order values by
+/// `Value::total_cmp`, which agrees with the comparisons of filters for
+/// every type, so an index finds every row a scan would.
+pub(crate) fn plan_access(table: &Table, filter: Option<&Filter>) -> Plan {
+    let rows = table.len() as f64;
+    let estimated = rows * filter.map_or(1.0, |filter| selectivity(table, filter));
+    let scan = Operator::Scan {
+        table: table.name.clone(),
+        filter: filter.cloned(),
+    };
+    let mut best = Plan::new(scan, Vec::new(), estimated, rows * SCAN_COST);
+
+    let conjuncts = filter.map(conjuncts).unwrap_or_default();
+    for index in table.indexes() {
+        for plan in index_paths(table, index, &conjuncts) {
+            if plan.cost < best.cost {
+                best = plan;
+            }
+        }
+    }
+    best
+}
+
+/// The ways an index can narrow down the rows matching a conjunction
+fn index_paths(table: &Table, index: &Index, conjuncts: &[&Filter]) -> Vec<Plan> {
+    let rows = table.len() as f64;
+    let residual = |used: &[usize]| {
+        and_all(
+            conjuncts
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !used.contains(i))
+                .map(|(_, filter)| (*filter).clone())
+                .collect(),
+        )
+    };
+    let estimate = |matches: f64, filter: &Option<Filter>| {
+        matches * filter.as_ref().map_or(1.0, |filter| selectivity(table, filter))
+    };
+
+    let mut plans = Vec::new();
+    let (mut lower, mut upper) = (None, None);
+    for (i, conjunct) in conjuncts.iter().enumerate() {
+        let Filter::Compare(expr, op, value) = conjunct else {
+            continue;
+        };
+        if *expr != index.expr || value.is_null() {
+            continue;
+        }
+        match op {
+            CompareOp::Eq => {
+                let matches = index.count(value) as f64;
+                let filter = residual(&[i]);
+                let operator = Operator::IndexSeek {
+                    table: table.name.clone(),
+                    index: index.name.clone(),
+                    expr: expr.clone(),
+                    value: value.clone(),
+                    filter: filter.clone(),
+                };
+                let cost = seek_cost(rows) + matches * FETCH_COST;
+                plans.push(Plan::new(operator, Vec::new(), estimate(matches, &filter), cost));
+            }
+            CompareOp::Gt if lower.is_none() => lower = Some((i, Bound::Excluded(value.clone()))),
+            CompareOp::Ge if lower.is_none() => lower = Some((i, Bound::Included(value.clone()))),
+            CompareOp::Lt if upper.is_none() => upper = Some((i, Bound::Excluded(value.clone()))),
+            CompareOp::Le if upper.is_none() => upper = Some((i, Bound::Included(value.clone()))),
+            _ => {}
+        }
+    }
+
+    if lower.is_some() || upper.is_some() {
+        let used: Vec<usize> = lower.iter().chain(&upper).map(|(i, _)| *i).collect();
+        let lower = lower.map_or(Bound::Unbounded, |(_, bound)| bound);
+        let upper = upper.map_or(Bound::Unbounded, |(_, bound)| bound);
+        let matches = rows * range_fraction(table, &index.expr, &lower, &upper);
+        let filter = residual(&used);
+        let operator = Operator::RangeScan {
+            table: table.name.clone(),
+            index: index.name.clone(),
+            expr: index.expr.clone(),
+            lower,
+            upper,
+            filter: filter.clone(),
+        };
+        let cost = seek_cost(rows) + matches * FETCH_COST;
+        plans.push(Plan::new(operator, Vec::new(), estimate(matches, &filter), cost));
+    }
+    plans
+}
+
+/// Choose a plan for a query
+///
+/// Filters on one table of a join are applied while reading that table.
+/// The join itself is a `NestedLoopJoin`, a `MergeJoin` or, when the
+/// second table has an index on its join expression, an `IndexJoin`,
+/// whichever is estimated to be cheapest. A `Sort` is left out when the
+/// rows already come in the requested order.
+pub(crate) fn plan_query<'a>(query: &Query, tables: &Tables<'a>) -> Result<Plan> {
+    let table = tables(&query.table)?;
+    let mut plan = match &query.join {
+        None => {
+            let filter = query
+                .filter
+                .as_ref()
+                .map(|filter| bare_filter(table, filter))
+                .transpose()?;
+            plan_access(table, filter.as_ref())
+        }
+        Some(join) => plan_join(query, join, table, tables(&join.table)?)?,
+    };
+
+    if !query.order_by.is_empty() {
+        let keys = query
+            .order_by
+            .iter()
+            .map(|key| {
+                let expr = match &query.join {
+                    None => bare_expr(table, &key.expr)?,
+                    Some(join) => qualified_expr(table, tables(&join.table)?, &key.expr)?,
+                };
+                Ok(SortKey {
+                    expr,
+                    descending: key.descending,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let ordered =
+            matches!(keys.as_slice(), [key] if !key.descending && plan.ordered_by() == Some(&key.expr));
+        if !ordered {
+            let (rows, cost) = (plan.estimated_rows, plan.cost + sort_cost(plan.estimated_rows));
+            plan = Plan::new(Operator::Sort { keys }, vec![plan], rows, cost);
+        }
+    }
+
+    if let Some(count) = query.limit {
+        let (rows, cost) = (plan.estimated_rows.min(count as f64), plan.cost);
+        plan = Plan::new(Operator::Limit { count }, vec![plan], rows, cost);
+    }
+    Ok(plan)
+}
+
+/// Which table of a join an expression reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Left,
+    Right,
+}
+
+fn plan_join(query: &Query, join: &Join, left: &Table, right: &Table) -> Result<Plan> {
+    if left.name == right.name {
+        return Err(DbError::ParseError(format!(
+            "Cannot join table {} with itself",
+            left.name
+        )));
+    }
+
+    let (left_expr, right_expr) = match (
+        resolve(left, right, &join.left)?,
+        resolve(left, right, &join.right)?,
+    ) {
+        ((Side::Left, left_expr), (Side::Right, right_expr)) => (left_expr, right_expr),
+        ((Side::Right, right_expr), (Side::Left, left_expr)) => (left_expr, right_expr),
+        _ => {
+            return Err(DbError::ParseError(
+                "A join condition compares a column of each table".to_string(),
+            ))
+        }
+    };
+
+    // Conditions on one table are applied while reading it
+    let (mut left_filters, mut right_filters, mut joined_filters) = (Vec::new(), Vec::new(), Vec::new());
+    for conjunct in query.filter.as_ref().map(conjuncts).unwrap_or_default() {
+        let mut sides = Vec::new();
+        let bare = map_exprs(conjunct, &mut |expr| {
+            let (side, expr) = resolve(left, right, expr)?;
+            sides.push(side);
+            Ok(expr)
+        })?;
+        if sides.iter().all(|side| *side == Side::Left) {
+            left_filters.push(bare);
+        } else if sides.iter().all(|side| *side == Side::Right) {
+            right_filters.push(bare);
+        } else {
+            joined_filters.push(map_exprs(conjunct, &mut |expr| {
+                qualified_expr(left, right, expr)
+            })?);
+        }
+    }
+    let right_filter = and_all(right_filters);
+    let outer = plan_access(left, and_all(left_filters).as_ref());
+    let inner = plan_access(right, right_filter.as_ref());
+
+    let (n, m) = (outer.estimated_rows, inner.estimated_rows);
+    let distinct = distinct_values(left, &left_expr)
+        .max(distinct_values(right, &right_expr))
+        .max(1.0);
+    let rows = n * m / distinct;
+
+    let nested_cost = outer.cost + inner.cost + n * m * SCAN_COST;
+    let merge_cost = outer.cost + inner.cost + sort_cost(n) + sort_cost(m) + (n + m) * SCAN_COST;
+    let mut best = Plan::new(
+        Operator::NestedLoopJoin {
+            left: left_expr.clone(),
+            right: right_expr.clone(),
+        },
+        vec![outer.clone(), inner.clone()],
+        rows,
+        nested_cost,
+    );
+    if merge_cost < best.cost {
+        let operator = Operator::MergeJoin {
+            left: left_expr.clone(),
+            right: right_expr.clone(),
+        };
+        best = Plan::new(operator, vec![outer.clone(), inner], rows, merge_cost);
+    }
+    if let Some(index) = right.indexes().iter().find(|index| index.expr == right_expr) {
+        let table_rows = right.len() as f64;
+        let matches = table_rows / (index.len() as f64).max(1.0);
+        let cost = outer.cost + n * (seek_cost(table_rows) + matches * FETCH_COST);
+        if cost < best.cost {
+            let operator = Operator::IndexJoin {
+                left: left_expr,
+                table: right.name.clone(),
+                index: index.name.clone(),
+                right: right_expr,
+                filter: right_filter,
+            };
+            best = Plan::new(operator, vec![outer], rows, cost);
+        }
+    }
+
+    // Nothing is known about conditions spanning both tables
+    let selectivity = DEFAULT_SELECTIVITY.powi(joined_filters.len() as i32);
+    match and_all(joined_filters) {
+        Some(filter) => {
+            let (rows, cost) = (
+                best.estimated_rows * selectivity,
+                best.cost + best.estimated_rows,
+            );
+            Ok(Plan::new(Operator::Filter { filter }, vec![best], rows, cost))
+        }
+        None => Ok(best),
+    }
+}
+
+/// Find which table of a join a column belongs to, and its unqualified name
+fn resolve(left: &Table, right: &Table, expr: &Expr) -> Result<(Side, Expr)> {
+    let name = expr.column_name();
+    let has = |table: &Table, column: &str| table.columns.iter().any(|c| c.name == column);
+
+    let mut found = Vec::new();
+    for (side, table) in [(Side::Left, left), (Side::Right, right)] {
+        if has(table, name) {
+            found.push((side, name.to_string()));
+        } else if let Some(column) = name.strip_prefix(&format!("{}.", table.name)) {
+            if has(table, column) {
+                found.push((side, column.to_string()));
+            }
+        }
+    }
+    match found.as_slice() {
+        [(side, column)] => Ok((*side, with_column(expr, column.clone()))),
+        [] => Err(DbError::ColumnNotFound(name.to_string())),
+        _ => Err(DbError::ParseError(format!("Ambiguous column: {}", name))),
+    }
+}
+
+/// Name a column of a join by its table and column, as in its result
+fn qualified_expr(left: &Table, right: &Table, expr: &Expr) -> Result<Expr> {
+    let (side, bare) = resolve(left, right, expr)?;
+    let table = if side == Side::Left { left } else { right };
+    Ok(with_column(
+        &bare,
+        format!("{}.{}", table.name, bare.column_name()),
+    ))
+}
+
+/// Name a column of a table without its table name
+fn bare_expr(table: &Table, expr: &Expr) -> Result<Expr> {
+    let name = expr.column_name();
+    if table.columns.iter().any(|c| c.name == name) {
+        return Ok(expr.clone());
+    }
+    match name.strip_prefix(&format!("{}.", table.name)) {
+        Some(column) if table.columns.iter().any(|c| c.name == column) => {
+            Ok(with_column(expr, column.to_string()))
+        }
+        _ => Err(DbError::ColumnNotFound(name.to_string())),
+    }
+}
+
+fn bare_filter(table: &Table, filter: &Filter) -> Result<Filter> {
+    map_exprs(filter, &mut |expr| bare_expr(table, expr))
+}
+
+/// Replace the column an expression reads
+fn with_column(expr: &Expr, column: String) -> Expr {
+    match expr {
+        Expr::Column(_) => Expr::Column(column),
+        Expr::JsonPath { path, .. } => Expr::JsonPath {
+            column,
+            path: path.clone(),
+        },
+    }
+}
+
+/// Rebuild a filter with every expression mapped
+fn map_exprs(filter: &Filter, map: &mut dyn FnMut(&Expr) -> Result<Expr>) -> Result<Filter> {
+    Ok(match filter {
+        Filter::Compare(expr, op, value) => Filter::Compare(map(expr)?, *op, value.clone()),
+        Filter::IsNull(expr) => Filter::IsNull(map(expr)?),
+        Filter::Contains(expr, value) => Filter::Contains(map(expr)?, value.clone()),
+        Filter::Any(expr, op, value) => Filter::Any(map(expr)?, *op, value.clone()),
+        Filter::And(a, b) => map_exprs(a, map)?.and(map_exprs(b, map)?),
+        Filter::Or(a, b) => map_exprs(a, map)?.or(map_exprs(b, map)?),
+        Filter::Not(inner) => map_exprs(inner, map)?.not(),
+    })
+}
+
+/// Split a filter into the conditions joined by its top-level `AND`s
+fn conjuncts(filter: &Filter) -> Vec<&Filter> {
+    match filter {
+        Filter::And(a, b) => {
+            let mut parts = conjuncts(a);
+            parts.extend(conjuncts(b));
+            parts
+        }
+        other => vec![other],
+    }
+}
+
+/// Join conditions with `AND`, or `None` if there are none
+fn and_all(filters: Vec<Filter>) -> Option<Filter> {
+    filters.into_iter().reduce(Filter::and)
+}
+
+/// The comparisons equivalent to a range of an expression
+fn bound_filters(expr: &Expr, lower: &Bound<Value>, upper: &Bound<Value>) -> Vec<Filter> {
+    let mut filters = Vec::new();
+    match lower {
+        Bound::Included(value) => filters.push(Filter::Compare(expr.clone(), CompareOp::Ge, value.clone())),
+        Bound::Excluded(value) => filters.push(Filter::Compare(expr.clone(), CompareOp::Gt, value.clone())),
+        Bound::Unbounded => {}
+    }
+    match upper {
+        Bound::Included(value) => filters.push(Filter::Compare(expr.clone(), CompareOp::Le, value.clone())),
+        Bound::Excluded(value) => filters.push(Filter::Compare(expr.clone(), CompareOp::Lt, value.clone())),
+        Bound::Unbounded => {}
+    }
+    filters
+}
+
+fn find_index<'a>(table: &'a Table, name: &str) -> Result<&'a Index> {
+    table
+        .indexes()
+        .iter()
+        .find(|index| index.name == name)
+        .ok_or_else(|| DbError::IndexNotFound(name.to_string()))
+}
+
+fn qualify_columns(table: &str, columns: Vec<Column>) -> Vec<Column> {
+    columns
+        .into_iter()
+        .map(|mut column| {
+            column.name = format!("{}.{}", table, column.name);
+            column
+        })
+        .collect()
+}
+
+/// Pair rows with their value of a join expression, leaving out NULLs
+fn join_values<'r>(
+    rows: &'r [(Key, Row)],
+    expr: &Expr,
+    columns: &[Column],
+) -> Result<Vec<(IndexValue, &'r (Key, Row))>> {
+    let mut values = Vec::new();
+    for entry in rows {
+        let value = expr.evaluate(columns, &entry.1)?;
+        if !value.is_null() {
+            values.push((IndexValue(value), entry));
+        }
+    }
+    Ok(values)
+}
+
+fn join_row(key: &Key, row: &Row, inner_key: &Key, inner_row: &Row) -> (Key, Row) {
+    let mut values = row.values.clone();
+    values.extend(inner_row.values.iter().cloned());
+    (
+        Key::Composite(vec![key.clone(), inner_key.clone()]),
+        Row::new(values),
+    )
+}
+
+/// Cost of finding the first entry in a B-tree of `rows` entries
+fn seek_cost(rows: f64) -> f64 {
+    (rows + 1.0).log2() + 1.0
+}
+
+fn sort_cost(rows: f64) -> f64 {
+    rows * (rows + 1.0).log2() * SCAN_COST
+}
+
+/// Estimate the share of the rows of a table matching a filter
+fn selectivity(table: &Table, filter: &Filter) -> f64 {
+    let estimate = match filter {
+        Filter::Compare(expr, CompareOp::Eq, value) => equal_fraction(table, expr, value),
+        Filter::Compare(expr, CompareOp::Ne, value) => {
+            1.0 - equal_fraction(table, expr, value) - null_fraction(table, expr).unwrap_or(0.0)
+        }
+        Filter::Compare(expr, op, value) => {
+            let (lower, upper) = match op {
+                CompareOp::Gt => (Bound::Excluded(value.clone()), Bound::Unbounded),
+                CompareOp::Ge => (Bound::Included(value.clone()), Bound::Unbounded),
+                CompareOp::Lt => (Bound::Unbounded, Bound::Excluded(value.clone())),
+                _ => (Bound::Unbounded, Bound::Included(value.clone())),
+            };
+            range_fraction(table, expr, &lower, &upper)
+        }
+        Filter::IsNull(expr) => null_fraction(table, expr).unwrap_or(DEFAULT_SELECTIVITY),
+        Filter::Contains(..) | Filter::Any(..) => DEFAULT_SELECTIVITY,
+        Filter::And(a, b) => selectivity(table, a) * selectivity(table, b),
+        Filter::Or(a, b) => {
+            let (a, b) = (selectivity(table, a), selectivity(table, b));
+            a + b - a * b
+        }
+        Filter::Not(inner) => 1.0 - selectivity(table, inner),
+    };
+    estimate.clamp(0.0, 1.0)
+}
+
+/// Find the index on an expression, if there is one
+fn index_on<'a>(table: &'a Table, expr: &Expr) -> Option<&'a Index> {
+    table.indexes().iter().find(|index| index.expr == *expr)
+}
+
+/// Estimate the share of rows where an expression equals a value
+///
+/// An index counts the matching rows exactly; otherwise the values are
+/// assumed to be spread evenly over the distinct values of the column.
+fn equal_fraction(table: &Table, expr: &Expr, value: &Value) -> f64 {
+    if table.is_empty() || value.is_null() {
+        return 0.0;
+    }
+    if let Some(index) = index_on(table, expr) {
+        return index.count(value) as f64 / table.len() as f64;
+    }
+    match column_stats(table, expr) {
+        Some((stats, rows)) if stats.distinct > 0 => {
+            (1.0 - stats.nulls as f64 / rows) / stats.distinct as f64
+        }
+        Some(_) => 0.0,
+        None => DEFAULT_SELECTIVITY,
+    }
+}
+
+/// Get the share of NULLs in a column, if it has been analyzed
+fn null_fraction(table: &Table, expr: &Expr) -> Option<f64> {
+    column_stats(table, expr).map(|(stats, rows)| stats.nulls as f64 / rows)
+}
+
+/// Estimate the share of rows where an expression lies in a range
+///
+/// With the smallest and largest value, known from an index or the column
+/// statistics, numeric ranges are interpolated between them.
+fn range_fraction(table: &Table, expr: &Expr, lower: &Bound<Value>, upper: &Bound<Value>) -> f64 {
+    let known = match index_on(table, expr) {
+        Some(index) => index.bounds().map(|(min, max)| (min.clone(), max.clone())),
+        None => {
+            column_stats(table, expr).and_then(|(stats, _)| Some((stats.min.clone()?, stats.max.clone()?)))
+        }
+    };
+    let bound = |bound: &Bound<Value>| match bound {
+        Bound::Included(value) | Bound::Excluded(value) => Some(numeric(value)),
+        Bound::Unbounded => None,
+    };
+
+    let interpolated = known.and_then(|(min, max)| {
+        let (min, max) = (numeric(&min)?, numeric(&max)?);
+        let low = bound(lower).map_or(Some(min), |value| value)?.max(min);
+        let high = bound(upper).map_or(Some(max), |value| value)?.min(max);
+        if max > min {
+            Some(((high - low) / (max - min)).clamp(0.0, 1.0))
+        } else {
+            Some(if low <= min && min <= high { 1.0 } else { 0.0 })
+        }
+    });
+    interpolated.unwrap_or_else(|| {
+        let sides = [lower, upper]
+            .iter()
+            .filter(|bound| !matches!(bound, Bound::Unbounded))
+            .count();
+        RANGE_SELECTIVITY.powi(sides as i32)
+    })
+}
+
+/// Estimate the number of distinct values of an expression
+fn distinct_values(table: &Table, expr: &Expr) -> f64 {
+    if let Some(index) = index_on(table, expr) {
+        return index.len() as f64;
+    }
+    match column_stats(table, expr) {
+        Some((stats, _)) => stats.distinct as f64,
+        None => table.len() as f64,
+    }
+}
+
+/// Get the analyzed statistics of a column with the row count they were
+/// taken at
+fn column_stats<'a>(table: &'a Table, expr: &Expr) -> Option<(&'a crate::stats::ColumnStats, f64)> {
+    let Expr::Column(name) = expr else {
+        return None;
+    };
+    let stats = table.statistics()?;
+    if stats.rows == 0 {
+        return None;
+    }
+    Some((stats.column(name)?, stats.rows as f64))
+}
+
+fn numeric(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(i) => Some(*i as f64),
+        Value::Float(f) => Some(*f),
+        Value::Decimal(d) => Some(d.to_f64()),
+        _ => None,
+    }
+}
@@ -0,0 +1,496 @@
+use crate::error::{DbError, Result};
+use crate::parser::{read_quoted, Parser};
+use crate::types::{Column, Row, Value};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A single step in a JSON path
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PathSegment {
+    /// Access a member of an object by name
+    Key(String),
+    /// Access an element of an array by position
+    Index(usize),
+}
+
+/// A path expression selecting a value inside a JSON document
+///
+/// Paths start at the document root `$` and are followed by `.member`,
+/// `["member"]` or `[index]` steps.
+///
+/// # Examples
+///
+/// ```
+/// use oxi_db::JsonPath;
+/// use serde_json::json;
+///
+/// let path = JsonPath::parse("$.address.city").unwrap();
+/// let document = json!({"address": {"city": "Paris"}});
+///
+/// assert_eq!(path.evaluate(&document), Some(&json!("Paris")));
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct JsonPath {
+    /// The steps of the path, applied from the document root
+    pub segments: Vec<PathSegment>,
+}
+
+impl JsonPath {
+    /// Parse a path expression such as `$.tags[0]` or `$["first name"]`
+    ///
+    /// A quote inside a quoted member name is written twice, as in
+    /// `$["say ""hi"""]`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ParseError` if the expression is malformed.
+    pub fn parse(path: &str) -> Result<Self> {
+        let invalid = |reason: &str| DbError::ParseError(format!("Invalid JSON path '{}': {}", path, reason));
+
+        let rest = path
+            .trim()
+            .strip_prefix('$')
+            .ok_or_else(|| invalid("must start with '$'"))?;
+        let chars: Vec<char> = rest.chars().collect();
+        let mut segments = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            match chars[i] {
+                '.' => {
+                    let start = i + 1;
+                    i = start;
+                    while i < chars.len() && chars[i] != '.' && chars[i] != '[' {
+                        i += 1;
+                    }
+                    if i == start {
+                        return Err(invalid("empty member name"));
+                    }
+                    segments.push(PathSegment::Key(chars[start..i].iter().collect()));
+                }
+                '[' => {
+                    let skip_spaces = |mut at: usize| {
+                        while chars.get(at).is_some_and(|c| c.is_whitespace()) {
+                            at += 1;
+                        }
+                        at
+                    };
+                    let start = skip_spaces(i + 1);
+                    let end = if matches!(chars.get(start), Some('"' | '\'')) {
+                        let (name, next) =
+                            read_quoted(&chars, start).map_err(|_| invalid("unterminated member name"))?;
+                        segments.push(PathSegment::Key(name));
+                        skip_spaces(next)
+                    } else {
+                        let end = chars[start..]
+                            .iter()
+                            .position(|&c| c == ']')
+                            .map_or(chars.len(), |offset| start + offset);
+                        let inner: String = chars[start..end].iter().collect();
+                        let index = inner.trim().parse().map_err(|_| invalid("invalid array index"))?;
+                        segments.push(PathSegment::Index(index));
+                        end
+                    };
+                    if chars.get(end) != Some(&']') {
+                        return Err(invalid("unclosed '['"));
+                    }
+                    i = end + 1;
+                }
+                _ => return Err(invalid("expected '.' or '['")),
+            }
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Find the value the path points to inside a document
+    pub fn evaluate<'a>(&self, document: &'a serde_json::Value) -> Option<&'a serde_json::Value> {
+        self.segments
+            .iter()
+            .try_fold(document, |current, segment| match segment {
+                PathSegment::Key(name) => current.get(name.as_str()),
+                PathSegment::Index(index) => current.get(*index),
+            })
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        for segment in &self.segments {
+            match segment {
+                PathSegment::Key(name)
+                    if !name.is_empty()
+                        && name.chars().all(|c| c.is_alphanumeric() || c == '_') =>
+                {
+                    write!(f, ".{}", name)?
+                }
+                PathSegment::Key(name) => write!(f, "[\"{}\"]", name.replace('"', "\"\""))?,
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An expression that extracts a value from a row
+///
+/// Expressions are used on the left-hand side of filters and to define
+/// indexes.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Expr {
+    /// The value of a column
+    Column(String),
+    /// A value inside a JSON column, selected by a path
+    JsonPath {
+        /// The JSON column to look into
+        column: String,
+        /// The path inside the document
+        path: JsonPath,
+    },
+}
+
+impl Expr {
+    /// An expression selecting a column
+    pub fn column(name: impl Into<String>) -> Self {
+        Expr::Column(name.into())
+    }
+
+    /// An expression selecting a path inside a JSON column
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ParseError` if the path is malformed.
+    pub fn json_path(column: impl Into<String>, path: &str) -> Result<Self> {
+        Ok(Expr::JsonPath {
+            column: column.into(),
+            path: JsonPath::parse(path)?,
+        })
+    }
+
+    /// Parse an expression such as `name` or `profile$.address.city`
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ParseError` if the input is not a valid expression.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::Expr;
+    ///
+    /// let expr = Expr::parse("profile$.address.city").unwrap();
+    /// assert_eq!(expr, Expr::json_path("profile", "$.address.city").unwrap());
+    /// assert_eq!(Expr::parse(&expr.to_string()).unwrap(), expr);
+    /// ```
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut parser = Parser::new(input)?;
+        let expr = parser.parse_expr()?;
+        parser.expect_end()?;
+        Ok(expr)
+    }
+
+    /// The column the expression reads from
+    pub fn column_name(&self) -> &str {
+        match self {
+            Expr::Column(name) => name,
+            Expr::JsonPath { column, .. } => column,
+        }
+    }
+
+    /// Evaluate the expression against a row
+    ///
+    /// JSON paths that do not resolve evaluate to NULL.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ColumnNotFound` if the column is not in the schema.
+    pub fn evaluate(&self, columns: &[Column], row: &Row) -> Result<Value> {
+        let idx = columns
+            .iter()
+            .position(|c| c.name == self.column_name())
+            .ok_or_else(|| DbError::ColumnNotFound(self.column_name().to_string()))?;
+        let value = row.values.get(idx).cloned().unwrap_or(Value::Null);
+
+        match self {
+            Expr::Column(_) => Ok(value),
+            Expr::JsonPath { path, .. } => Ok(match &value {
+                Value::Json(document) => path
+                    .evaluate(document)
+                    .map(Value::from_json)
+                    .unwrap_or(Value::Null),
+                _ => Value::Null,
+            }),
+        }
+    }
+
+    /// Build a filter matching rows where the expression equals `value`
+    pub fn eq(self, value: impl Into<Value>) -> Filter {
+        Filter::Compare(self, CompareOp::Eq, value.into())
+    }
+
+    /// Build a filter matching rows where the expression differs from `value`
+    pub fn ne(self, value: impl Into<Value>) -> Filter {
+        Filter::Compare(self, CompareOp::Ne, value.into())
+    }
+
+    /// Build a filter matching rows where the expression is less than `value`
+    pub fn lt(self, value: impl Into<Value>) -> Filter {
+        Filter::Compare(self, CompareOp::Lt, value.into())
+    }
+
+    /// Build a filter matching rows where the expression is at most `value`
+    pub fn le(self, value: impl Into<Value>) -> Filter {
+        Filter::Compare(self, CompareOp::Le, value.into())
+    }
+
+    /// Build a filter matching rows where the expression is greater than `value`
+    pub fn gt(self, value: impl Into<Value>) -> Filter {
+        Filter::Compare(self, CompareOp::Gt, value.into())
+    }
+
+    /// Build a filter matching rows where the expression is at least `value`
+    pub fn ge(self, value: impl Into<Value>) -> Filter {
+        Filter::Compare(self, CompareOp::Ge, value.into())
+    }
+
+    /// Build a filter matching rows where the expression is NULL
+    pub fn is_null(self) -> Filter {
+        Filter::IsNull(self)
+    }
+
+    /// Build a filter matching rows where the array contains `value`
+    pub fn contains(self, value: impl Into<Value>) -> Filter {
+        Filter::Contains(self, value.into())
+    }
+
+    /// Build a filter matching rows where any array element compares with
+    /// `value` according to `op`
+    pub fn any(self, op: CompareOp, value: impl Into<Value>) -> Filter {
+        Filter::Any(self, op, value.into())
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Column(name) => write!(f, "{}", name),
+            Expr::JsonPath { column, path } => write!(f, "{}{}", column, path),
+        }
+    }
+}
+
+/// Comparison operators usable in filters
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum CompareOp {
+    /// Equal (`=`)
+    Eq,
+    /// Not equal (`!=`)
+    Ne,
+    /// Less than (`<`)
+    Lt,
+    /// Less than or equal (`<=`)
+    Le,
+    /// Greater than (`>`)
+    Gt,
+    /// Greater than or equal (`>=`)
+    Ge,
+}
+
+impl CompareOp {
+    /// Check if an ordering between two values satisfies the operator
+    pub fn matches(&self, ordering: Ordering) -> bool {
+        match self {
+            CompareOp::Eq => ordering == Ordering::Equal,
+            CompareOp::Ne => ordering != Ordering::Equal,
+            CompareOp::Lt => ordering == Ordering::Less,
+            CompareOp::Le => ordering != Ordering::Greater,
+            CompareOp::Gt => ordering == Ordering::Greater,
+            CompareOp::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+impl fmt::Display for CompareOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            CompareOp::Eq => "=",
+            CompareOp::Ne => "!=",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+/// A condition that rows must satisfy to be returned by a query
+///
+/// # Examples
+///
+/// ```
+/// use oxi_db::{Column, ColumnType, Expr, Table, Value};
+/// use serde_json::json;
+///
+/// let mut table = Table::new(
+///     "users",
+///     vec![
+///         Column::new("name", ColumnType::Text),
+///         Column::new("profile", ColumnType::Json),
+///     ],
+///     None,
+/// );
+/// table.insert("1", vec![
+///     Value::from("Alice"),
+///     Value::Json(json!({"address": {"city": "Paris"}})),
+/// ]).unwrap();
+///
+/// let filter = Expr::json_path("profile", "$.address.city").unwrap().eq("Paris");
+/// let parisians = table.find_where(&filter).unwrap();
+/// assert_eq!(parisians.len(), 1);
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Filter {
+    /// Compare an expression with a constant value
+    Compare(Expr, CompareOp, Value),
+    /// Match rows where the expression is NULL
+    IsNull(Expr),
+    /// Match rows where the array produced by the expression contains a value
+    Contains(Expr, Value),
+    /// Match rows where at least one element of the array produced by the
+    /// expression satisfies the comparison
+    Any(Expr, CompareOp, Value),
+    /// Match rows satisfying both filters
+    And(Box<Filter>, Box<Filter>),
+    /// Match rows satisfying either filter
+    Or(Box<Filter>, Box<Filter>),
+    /// Match rows not satisfying the filter
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    /// Parse a filter written in the query language
+    ///
+    /// Filters compare columns, or paths inside JSON columns, with literals,
+    /// and combine them with `AND`, `OR`, `NOT` and parentheses. Strings are
+    /// quoted with `'` or `"`, and numbers with a decimal point are exact
+    /// decimals.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ParseError` if the input is not a valid filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::{Expr, Filter};
+    ///
+    /// let filter = Filter::parse("age >= 18 AND profile$.address.city = 'Paris'").unwrap();
+    /// let expected = Expr::column("age")
+    ///     .ge(18)
+    ///     .and(Expr::json_path("profile", "$.address.city").unwrap().eq("Paris"));
+    /// assert_eq!(filter, expected);
+    ///
+    /// let filter = Filter::parse("tags CONTAINS 'rust' OR ANY(scores) > 90").unwrap();
+    /// let filter = Filter::parse("NOT (email IS NULL)").unwrap();
+    /// ```
+    pub fn parse(input: &str) -> Result<Filter> {
+        let mut parser = Parser::new(input)?;
+        let filter = parser.parse_filter()?;
+        parser.expect_end()?;
+        Ok(filter)
+    }
+
+    /// Combine with another filter that must also match
+    pub fn and(self, other: Filter) -> Filter {
+        Filter::And(Box::new(self), Box::new(other))
+    }
+
+    /// Combine with another filter that may match instead
+    pub fn or(self, other: Filter) -> Filter {
+        Filter::Or(Box::new(self), Box::new(other))
+    }
+
+    /// Negate the filter
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Filter {
+        Filter::Not(Box::new(self))
+    }
+
+    /// Check if a row satisfies the filter
+    ///
+    /// Comparisons involving NULL or values of incompatible types are
+    /// unknown rather than false, and `AND`, `OR` and `NOT` follow SQL's
+    /// three-valued logic, so `NOT (x = 1)` does not match rows where `x` is
+    /// NULL. A row only matches when the filter is true.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ColumnNotFound` if the filter references a column
+    /// that is not in the schema.
+    pub fn matches(&self, columns: &[Column], row: &Row) -> Result<bool> {
+        Ok(self.truth(columns, row)? == Some(true))
+    }
+
+    /// Evaluate the filter on a row, with `None` standing for unknown
+    fn truth(&self, columns: &[Column], row: &Row) -> Result<Option<bool>> {
+        // True if any element matches, unknown if none does but one is unknown
+        fn any_element(value: Value, test: impl Fn(&Value) -> Option<bool>) -> Option<bool> {
+            let elements = value.elements()?;
+            let mut truth = Some(false);
+            for element in elements {
+                match test(&element) {
+                    Some(true) => return Some(true),
+                    Some(false) => {}
+                    None => truth = None,
+                }
+            }
+            truth
+        }
+
+        match self {
+            Filter::Compare(expr, op, value) => {
+                let actual = expr.evaluate(columns, row)?;
+                Ok(actual.compare(value).map(|ordering| op.matches(ordering)))
+            }
+            Filter::IsNull(expr) => Ok(Some(expr.evaluate(columns, row)?.is_null())),
+            Filter::Contains(expr, value) => Ok(any_element(expr.evaluate(columns, row)?, |e| {
+                e.compare(value).map(|o| o == Ordering::Equal)
+            })),
+            Filter::Any(expr, op, value) => Ok(any_element(expr.evaluate(columns, row)?, |e| {
+                e.compare(value).map(|o| op.matches(o))
+            })),
+            Filter::And(a, b) => match a.truth(columns, row)? {
+                Some(false) => Ok(Some(false)),
+                left => Ok(match b.truth(columns, row)? {
+                    Some(false) => Some(false),
+                    right => left.and(right),
+                }),
+            },
+            Filter::Or(a, b) => match a.truth(columns, row)? {
+                Some(true) => Ok(Some(true)),
+                left => Ok(match b.truth(columns, row)? {
+                    Some(true) => Some(true),
+                    right => left.and(right),
+                }),
+            },
+            Filter::Not(inner) => Ok(inner.truth(columns, row)?.map(|truth| !truth)),
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::Compare(expr, op, value) => write!(f, "{} {} {}", expr, op, value),
+            Filter::IsNull(expr) => write!(f, "{} IS NULL", expr),
+            Filter::Contains(expr, value) => write!(f, "{} CONTAINS {}", expr, value),
+            Filter::Any(expr, op, value) => write!(f, "ANY({}) {} {}", expr, op, value),
+            Filter::And(a, b) => write!(f, "({} AND {})", a, b),
+            Filter::Or(a, b) => write!(f, "({} OR {})", a, b),
+            Filter::Not(inner) => write!(f, "NOT {}", inner),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use crate::error::{DbError, Result};
+use serde::{Deserialize, Serialize};
+
+/// A named generator of increasing integer values
+///
+/// Sequences are stored in the `Database` and persisted with it. A value
+/// handed out by `Database::next_value` is saved before it is returned, so it
+/// is never handed out again, even after a crash.
+///
+/// # Examples
+///
+/// ```
+/// use oxi_db::Sequence;
+///
+/// let mut seq = Sequence::new("invoice_numbers", 1000, 10).unwrap();
+/// assert_eq!(seq.next_value().unwrap(), 1000);
+/// assert_eq!(seq.next_value().unwrap(), 1010);
+/// assert_eq!(seq.current_value(), Some(1010));
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Sequence {
+    /// The name of the sequence
+    pub name: String,
+    /// The amount added to the value on each call
+    pub increment: i64,
+    /// The value the next call will return
+    next: i64,
+    /// The last value returned, if any
+    current: Option<i64>,
+}
+
+impl Sequence {
+    /// Create a sequence that starts at `start` and advances by `increment`
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::Other` if `increment` is zero.
+    pub fn new(name: impl Into<String>, start: i64, increment: i64) -> Result<Self> {
+        if increment == 0 {
+            return Err(DbError::Other("Sequence increment cannot be zero".to_string()));
+        }
+        Ok(Self {
+            name: name.into(),
+            increment,
+            next: start,
+            current: None,
+        })
+    }
+
+    /// Return the next value and advance the sequence
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::NumericOverflow` once the sequence is exhausted.
+    pub fn next_value(&mut self) -> Result<i64> {
+        if self.current == Some(self.next) {
+            return Err(DbError::NumericOverflow);
+        }
+
+        let value = self.next;
+        // Stay on the last value when the range is exhausted, so the next call
+        // reports the overflow instead of repeating a value.
+        self.next = value.checked_add(self.increment).unwrap_or(value);
+        self.current = Some(value);
+        Ok(value)
+    }
+
+    /// Get the last value returned by `next_value`, if any
+    pub fn current_value(&self) -> Option<i64> {
+        self.current
+    }
+
+    /// Get the value the next call to `next_value` will return
+    pub fn peek(&self) -> i64 {
+        self.next
+    }
+
+    /// Make sure the sequence will never return `value` or anything before it
+    pub(crate) fn advance_past(&mut self, value: i64) {
+        if self.increment > 0 && value >= self.next {
+            self.next = value.checked_add(self.increment).unwrap_or(value);
+            if self.next == value {
+                self.current = Some(value);
+            }
+        }
+    }
+}
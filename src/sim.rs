@@ -0,0 +1,327 @@
+use crate::storage::{new_lock_token, Storage};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// A change to a simulated file that has not been synced yet
+#[derive(Debug, Clone)]
+enum SimWrite {
+    Data { offset: u64, data: Vec<u8> },
+    Truncate(u64),
+}
+
+impl SimWrite {
+    fn apply(&self, contents: &mut Vec<u8>) {
+        match self {
+            SimWrite::Data { offset, data } => {
+                let start = *offset as usize;
+                if contents.len() < start + data.len() {
+                    contents.resize(start + data.len(), 0);
+                }
+                contents[start..start + data.len()].copy_from_slice(data);
+            }
+            SimWrite::Truncate(len) => contents.resize(*len as usize, 0),
+        }
+    }
+}
+
+/// A simulated file: what survives a crash, and what was written since
+#[derive(Debug, Clone, Default)]
+struct SimFile {
+    durable: Vec<u8>,
+    unsynced: Vec<SimWrite>,
+}
+
+impl SimFile {
+    fn contents(&self) -> Vec<u8> {
+        let mut contents = self.durable.clone();
+        for write in &self.unsynced {
+            write.apply(&mut contents);
+        }
+        contents
+    }
+}
+
+#[derive(Debug)]
+struct SimState {
+    /// Files by number, whether or not a name still leads to them
+    files: BTreeMap<u64, SimFile>,
+    /// The number of the file at each path
+    names: BTreeMap<PathBuf, u64>,
+    /// The names as of the last `sync_directory` of their directories
+    durable_names: BTreeMap<PathBuf, u64>,
+    next_file: u64,
+    /// Tokens of the locked paths
+    locks: BTreeMap<PathBuf, u64>,
+    /// State of the SplitMix64 generator deciding what a crash keeps
+    rng: u64,
+    /// Operations left until the simulated crash, if one is planned
+    crash_after: Option<u64>,
+    crashed: bool,
+    failing_syncs: usize,
+}
+
+impl SimState {
+    fn next_random(&mut self) -> u64 {
+        self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A random number below `bound`, which must not be zero
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next_random() % bound as u64) as usize
+    }
+
+    /// Count an operation, failing it if the storage has crashed
+    fn step(&mut self) -> io::Result<()> {
+        match self.crash_after {
+            _ if self.crashed => {}
+            Some(0) => {
+                self.crash_after = None;
+                self.crashed = true;
+            }
+            Some(ref mut left) => *left -= 1,
+            None => {}
+        }
+        if self.crashed {
+            return Err(io::Error::other("simulated crash"));
+        }
+        Ok(())
+    }
+
+    /// Fail a sync if failed syncs are planned
+    fn fail_sync(&mut self) -> io::Result<()> {
+        if self.failing_syncs > 0 {
+            self.failing_syncs -= 1;
+            return Err(io::Error::other("simulated sync failure"));
+        }
+        Ok(())
+    }
+
+    fn file(&self, path: &Path) -> io::Result<&SimFile> {
+        self.names
+            .get(path)
+            .and_then(|number| self.files.get(number))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File not found"))
+    }
+
+    /// The file at `path`, created if there is none
+    fn file_mut(&mut self, path: &Path) -> &mut SimFile {
+        let number = match self.names.get(path) {
+            Some(number) => *number,
+            None => {
+                self.next_file += 1;
+                self.names.insert(path.to_path_buf(), self.next_file);
+                self.next_file
+            }
+        };
+        self.files.entry(number).or_default()
+    }
+}
+
+/// A simulated disk that loses, tears and refuses writes, for crash tests
+///
+/// Writes stay volatile until the file is synced, and creating, renaming or
+/// removing a file stays volatile until its directory is synced.
+/// `crash_after` makes the storage crash once a number of further
+/// operations have succeeded: the operation that hits the crash and every
+/// one after it fail, as if the process had died. `restart` then brings the
+/// disk back the way a power loss would: the changes to the directories
+/// since they were last synced are kept or lost all together, and every
+/// file keeps what was synced plus a random number of its later writes, in
+/// order, of which the last may be torn at a random byte. `fail_syncs` makes
+/// syncs fail without crashing.
+///
+/// What a restart keeps depends only on the seed and the operations, so a
+/// failing run can be replayed. Clones share the same disk. Only built with
+/// the `sim` feature.
+///
+/// # Examples
+///
+/// ```
+/// use oxi_db::{Database, SimStorage};
+/// use std::sync::Arc;
+///
+/// let storage = SimStorage::new(7);
+/// let db = Database::new("sim.db").with_storage(Arc::new(storage.clone()));
+/// db.save().unwrap();
+///
+/// storage.crash_after(2);
+/// assert!(db.save().is_err());
+/// storage.restart();
+/// assert!(Database::open_in(Arc::new(storage), "sim.db").is_ok());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SimStorage {
+    state: Arc<Mutex<SimState>>,
+}
+
+impl SimStorage {
+    /// Create an empty disk whose crashes are decided by `seed`
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(SimState {
+                files: BTreeMap::new(),
+                names: BTreeMap::new(),
+                durable_names: BTreeMap::new(),
+                next_file: 0,
+                locks: BTreeMap::new(),
+                rng: seed,
+                crash_after: None,
+                crashed: false,
+                failing_syncs: 0,
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, SimState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Crash after `operations` more operations have succeeded
+    pub fn crash_after(&self, operations: u64) {
+        self.state().crash_after = Some(operations);
+    }
+
+    /// Check whether the storage has crashed and awaits `restart`
+    pub fn has_crashed(&self) -> bool {
+        self.state().crashed
+    }
+
+    /// Make the next `count` syncs of files or directories fail, leaving
+    /// their changes unsynced
+    pub fn fail_syncs(&self, count: usize) {
+        self.state().failing_syncs = count;
+    }
+
+    /// Recover from a crash, or cause one now and recover from it
+    ///
+    /// Unsynced changes are partly kept as described on `SimStorage`, and
+    /// every lock is released. Files whose directory was never synced may
+    /// be gone, and files that were never synced may come back empty.
+    pub fn restart(&self) {
+        let mut state = self.state();
+        if state.names != state.durable_names && state.below(2) == 0 {
+            state.names = state.durable_names.clone();
+        }
+        state.durable_names = state.names.clone();
+
+        let mut files = std::mem::take(&mut state.files);
+        files.retain(|number, _| state.names.values().any(|n| n == number));
+        for file in files.values_mut() {
+            let unsynced = std::mem::take(&mut file.unsynced);
+            let kept = state.below(unsynced.len() + 1);
+            for write in &unsynced[..kept] {
+                write.apply(&mut file.durable);
+            }
+            if let Some(SimWrite::Data { offset, data }) = unsynced.get(kept) {
+                let torn = SimWrite::Data {
+                    offset: *offset,
+                    data: data[..state.below(data.len() + 1)].to_vec(),
+                };
+                torn.apply(&mut file.durable);
+            }
+        }
+        state.files = files;
+        state.locks.clear();
+        state.crash_after = None;
+        state.crashed = false;
+        state.failing_syncs = 0;
+    }
+}
+
+impl Storage for SimStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let mut state = self.state();
+        state.step()?;
+        Ok(state.file(path)?.contents())
+    }
+
+    fn write(&self, path: &Path, offset: u64, data: &[u8]) -> io::Result<()> {
+        let mut state = self.state();
+        state.step()?;
+        let write = SimWrite::Data {
+            offset,
+            data: data.to_vec(),
+        };
+        state.file_mut(path).unsynced.push(write);
+        Ok(())
+    }
+
+    fn truncate(&self, path: &Path, len: u64) -> io::Result<()> {
+        let mut state = self.state();
+        state.step()?;
+        state.file_mut(path).unsynced.push(SimWrite::Truncate(len));
+        Ok(())
+    }
+
+    fn sync(&self, path: &Path) -> io::Result<()> {
+        let mut state = self.state();
+        state.step()?;
+        state.file(path)?;
+        state.fail_sync()?;
+        let file = state.file_mut(path);
+        file.durable = file.contents();
+        file.unsynced.clear();
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut state = self.state();
+        state.step()?;
+        let number = state
+            .names
+            .remove(from)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File not found"))?;
+        state.names.insert(to.to_path_buf(), number);
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        let mut state = self.state();
+        state.step()?;
+        state
+            .names
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File not found"))
+    }
+
+    fn sync_directory(&self, path: &Path) -> io::Result<()> {
+        let mut state = self.state();
+        state.step()?;
+        state.fail_sync()?;
+        let directory = path.parent();
+        let names = state.names.clone();
+        state.durable_names.retain(|name, _| name.parent() != directory);
+        state
+            .durable_names
+            .extend(names.into_iter().filter(|(name, _)| name.parent() == directory));
+        Ok(())
+    }
+
+    fn lock(&self, path: &Path) -> io::Result<u64> {
+        let mut state = self.state();
+        state.step()?;
+        if state.locks.contains_key(path) {
+            return Err(io::Error::new(io::ErrorKind::WouldBlock, "Database file is locked"));
+        }
+        let token = new_lock_token();
+        state.locks.insert(path.to_path_buf(), token);
+        Ok(token)
+    }
+
+    fn unlock(&self, path: &Path, token: u64) -> io::Result<()> {
+        // Not a point to crash at: a crash releases every lock anyway
+        let mut state = self.state();
+        if state.locks.get(path) != Some(&token) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Database file is not locked"));
+        }
+        state.locks.remove(path);
+        Ok(())
+    }
+}
@@ -0,0 +1,288 @@
+use crate::database::Database;
+use crate::error::{DbError, Result};
+use crate::parser::Parser;
+use crate::query::Expr;
+use crate::table::Table;
+use crate::types::{encode_hex, Column, ColumnType, Key, Value};
+use std::io::Write;
+
+/// A statement of a SQL dump
+#[derive(Debug)]
+pub(crate) enum Statement {
+    /// `CREATE SEQUENCE name START n INCREMENT m`
+    CreateSequence {
+        name: String,
+        start: i64,
+        increment: i64,
+    },
+    /// `CREATE TABLE name (column definitions)`
+    CreateTable {
+        name: String,
+        columns: Vec<Column>,
+        primary_key: Option<String>,
+    },
+    /// `CREATE INDEX name ON table (expr)`
+    CreateIndex { name: String, table: String, expr: Expr },
+    /// `INSERT INTO table [KEY key] VALUES (values)`
+    Insert {
+        table: String,
+        key: Option<Key>,
+        values: Vec<Value>,
+    },
+    /// `ALTER TABLE table AUTO_INCREMENT = next`
+    SetAutoIncrement { table: String, next: i64 },
+}
+
+/// Write the schema and rows of a database as SQL statements
+pub(crate) fn write_dump(db: &Database, mut writer: impl Write) -> Result<()> {
+    writeln!(writer, "-- oxi-db {} dump", crate::VERSION)?;
+
+    for name in db.list_sequences() {
+        let sequence = db.get_sequence(&name)?;
+        writeln!(
+            writer,
+            "CREATE SEQUENCE {} START {} INCREMENT {};",
+            quote_ident(&name),
+            sequence.peek(),
+            sequence.increment
+        )?;
+    }
+
+    for name in db.list_tables() {
+        let table = db.get_table(&name)?;
+        writeln!(writer)?;
+        writeln!(writer, "CREATE TABLE {} ({});", quote_ident(&name), column_defs(table))?;
+
+        let pk = table
+            .primary_key
+            .as_ref()
+            .and_then(|pk| table.column_index(pk).ok());
+        for (key, row) in table.get_all() {
+            let values: Vec<String> = row.values.iter().map(sql_literal).collect();
+            // The key is only written when it cannot be derived from the row
+            let derived = pk.and_then(|idx| Key::from_value(&row.values[idx]));
+            let key_clause = if derived.as_ref() == Some(&key) {
+                String::new()
+            } else {
+                format!(" KEY {}", key_literal(&key))
+            };
+            writeln!(
+                writer,
+                "INSERT INTO {}{} VALUES ({});",
+                quote_ident(&name),
+                key_clause,
+                values.join(", ")
+            )?;
+        }
+
+        if let Some(next) = table.next_auto_increment() {
+            writeln!(writer, "ALTER TABLE {} AUTO_INCREMENT = {};", quote_ident(&name), next)?;
+        }
+
+        for index in table.indexes() {
+            writeln!(
+                writer,
+                "CREATE INDEX {} ON {} ({});",
+                quote_ident(&index.name),
+                quote_ident(&name),
+                index.expr
+            )?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the column definitions of a table for `CREATE TABLE`
+fn column_defs(table: &Table) -> String {
+    table
+        .columns
+        .iter()
+        .map(|column| {
+            let mut def = format!("{} {}", quote_ident(&column.name), sql_type(&column.column_type));
+            if table.primary_key.as_ref() == Some(&column.name) {
+                def.push_str(" PRIMARY KEY");
+            }
+            if column.auto_increment {
+                def.push_str(" AUTO_INCREMENT");
+            }
+            def
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Write a column type, quoting struct field names where needed
+fn sql_type(column_type: &ColumnType) -> String {
+    match column_type {
+        ColumnType::Array(element) => format!("ARRAY({})", sql_type(element)),
+        ColumnType::Struct(fields) => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|f| format!("{} {}", quote_ident(&f.name), sql_type(&f.column_type)))
+                .collect();
+            format!("STRUCT({})", fields.join(", "))
+        }
+        other => other.to_string(),
+    }
+}
+
+/// Write a value as a literal that parses back to the same value
+///
+/// Floats get an exponent so they are not read as decimals, and values
+/// without a literal syntax of their own (UUIDs, JSON documents and
+/// non-finite floats) are written as text that the column type converts.
+fn sql_literal(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) if f.is_finite() => format!("{:e}", f),
+        Value::Float(f) => quote_text(&f.to_string()),
+        Value::Decimal(d) => d.to_string(),
+        Value::Text(s) => quote_text(s),
+        Value::Boolean(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+        Value::Blob(bytes) => format!("x'{}'", encode_hex(bytes)),
+        Value::Uuid(u) => quote_text(&u.to_string()),
+        Value::Json(json) => quote_text(&json.to_string()),
+        Value::Array(values) => {
+            format!("[{}]", values.iter().map(sql_literal).collect::<Vec<_>>().join(", "))
+        }
+        Value::Struct(values) => {
+            format!("{{{}}}", values.iter().map(sql_literal).collect::<Vec<_>>().join(", "))
+        }
+    }
+}
+
+/// Write a key as a literal that parses back to a key of the same kind
+fn key_literal(key: &Key) -> String {
+    match key {
+        Key::Integer(i) => i.to_string(),
+        Key::Text(s) => quote_text(s),
+        Key::Bytes(bytes) => format!("x'{}'", encode_hex(bytes)),
+        Key::Uuid(u) => format!("UUID {}", quote_text(&u.to_string())),
+        Key::Composite(parts) => {
+            format!("({})", parts.iter().map(key_literal).collect::<Vec<_>>().join(", "))
+        }
+    }
+}
+
+fn quote_text(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// Write a name bare when the parser reads it back as a word, quoted otherwise
+pub(crate) fn quote_ident(name: &str) -> String {
+    let bare = name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if bare {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+}
+
+/// Parse a script of statements separated by semicolons
+///
+/// `--` outside quotes starts a comment that runs to the end of the line.
+pub(crate) fn parse_script(script: &str) -> Result<Vec<Statement>> {
+    let mut parser = Parser::new(script)?;
+    let mut statements = Vec::new();
+
+    while !parser.is_at_end() {
+        if parser.eat_symbol(";") {
+            continue;
+        }
+        let statement = parse_statement(&mut parser).map_err(|e| match e {
+            DbError::ParseError(message) => {
+                DbError::ParseError(format!("statement {}: {}", statements.len() + 1, message))
+            }
+            other => other,
+        })?;
+        statements.push(statement);
+        if !parser.is_at_end() {
+            parser.expect_symbol(";")?;
+        }
+    }
+
+    Ok(statements)
+}
+
+fn parse_statement(parser: &mut Parser) -> Result<Statement> {
+    if parser.eat_keyword("create") {
+        if parser.eat_keyword("table") {
+            let name = parser.parse_ident()?;
+            parser.expect_symbol("(")?;
+            let (columns, primary_key) = parser.parse_column_defs()?;
+            parser.expect_symbol(")")?;
+            return Ok(Statement::CreateTable {
+                name,
+                columns,
+                primary_key,
+            });
+        }
+        if parser.eat_keyword("index") {
+            let name = parser.parse_ident()?;
+            parser.expect_keyword("on")?;
+            let table = parser.parse_ident()?;
+            parser.expect_symbol("(")?;
+            let expr = parser.parse_expr()?;
+            parser.expect_symbol(")")?;
+            return Ok(Statement::CreateIndex { name, table, expr });
+        }
+        if parser.eat_keyword("sequence") {
+            let name = parser.parse_ident()?;
+            let start = if parser.eat_keyword("start") { parse_integer(parser)? } else { 1 };
+            let increment = if parser.eat_keyword("increment") { parse_integer(parser)? } else { 1 };
+            return Ok(Statement::CreateSequence {
+                name,
+                start,
+                increment,
+            });
+        }
+        return Err(DbError::ParseError(
+            "Expected TABLE, INDEX or SEQUENCE after CREATE".to_string(),
+        ));
+    }
+
+    if parser.eat_keyword("insert") {
+        parser.expect_keyword("into")?;
+        let table = parser.parse_ident()?;
+        let key = if parser.eat_keyword("key") {
+            Some(parser.parse_key()?)
+        } else {
+            None
+        };
+        parser.expect_keyword("values")?;
+        parser.expect_symbol("(")?;
+        let mut values = Vec::new();
+        if !parser.eat_symbol(")") {
+            loop {
+                values.push(parser.parse_literal()?);
+                if parser.eat_symbol(")") {
+                    break;
+                }
+                parser.expect_symbol(",")?;
+            }
+        }
+        return Ok(Statement::Insert { table, key, values });
+    }
+
+    if parser.eat_keyword("alter") {
+        parser.expect_keyword("table")?;
+        let table = parser.parse_ident()?;
+        parser.expect_keyword("auto_increment")?;
+        parser.expect_symbol("=")?;
+        let next = parse_integer(parser)?;
+        return Ok(Statement::SetAutoIncrement { table, next });
+    }
+
+    Err(DbError::ParseError("Expected CREATE, INSERT or ALTER".to_string()))
+}
+
+fn parse_integer(parser: &mut Parser) -> Result<i64> {
+    match parser.parse_literal()? {
+        Value::Integer(i) => Ok(i),
+        other => Err(DbError::ParseError(format!("Expected an integer, found {}", other))),
+    }
+}
@@ -0,0 +1,208 @@
+use crate::compression::{Compression, CompressionStats};
+use crate::file_format::PAGE_HEADER_SIZE;
+use crate::index::IndexValue;
+use crate::query::Expr;
+use crate::types::{Column, ColumnType, Value};
+use std::collections::BTreeSet;
+
+/// Number of hashes kept to estimate the distinct values of a column
+const SKETCH_SIZE: usize = 1024;
+
+/// Size and contents of a database
+///
+/// Returned by `Database::stats`. Byte counts include the eight-byte frame
+/// of every page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatabaseStats {
+    /// Statistics of every table, ordered by name
+    pub tables: Vec<TableStats>,
+    /// Number of named sequences
+    pub sequences: u64,
+    /// Size of the database file, 0 for an in-memory database or a file
+    /// that was not saved yet
+    pub file_bytes: u64,
+    /// Size of the file the next save writes
+    pub used_bytes: u64,
+    /// Bytes of the file the next save gives back, for example after a file
+    /// written with an older format or with a different compression
+    pub free_bytes: u64,
+}
+
+impl DatabaseStats {
+    /// Get the statistics of a table
+    pub fn table(&self, name: &str) -> Option<&TableStats> {
+        self.tables.iter().find(|table| table.name == name)
+    }
+
+    /// Get the number of rows in all tables
+    pub fn rows(&self) -> u64 {
+        self.tables.iter().map(|table| table.rows).sum()
+    }
+}
+
+/// Size and contents of a table
+///
+/// Returned by `Table::stats`. Byte counts are those of the table's pages in
+/// the database file, after compression and including the eight-byte frame
+/// of every page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableStats {
+    /// The name of the table
+    pub name: String,
+    /// Number of rows
+    pub rows: u64,
+    /// Estimated number of levels of the B-tree holding the rows, see
+    /// `BTree::estimated_depth`
+    pub estimated_depth: usize,
+    /// Size of all pages of the table: schema, rows, overflow and indexes
+    pub bytes: u64,
+    /// Size of the index pages
+    pub index_bytes: u64,
+    /// Number of overflow pages holding large values
+    pub overflow_pages: u64,
+    /// Compression of the table's pages
+    pub compression: Compression,
+    /// Statistics of every column, in schema order
+    pub columns: Vec<ColumnStats>,
+    /// Statistics of every index, in creation order
+    pub indexes: Vec<IndexStats>,
+}
+
+impl TableStats {
+    /// Get the statistics of a column
+    pub fn column(&self, name: &str) -> Option<&ColumnStats> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+}
+
+/// Distribution of the values of a column
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnStats {
+    /// The name of the column
+    pub name: String,
+    /// The type of the column
+    pub column_type: ColumnType,
+    /// Number of NULL values
+    pub nulls: u64,
+    /// Estimated number of distinct non-NULL values
+    ///
+    /// Exact below 1024 distinct values; from there on it is estimated from
+    /// the smallest hashes of the values and is typically within 5%.
+    pub distinct: u64,
+    /// Smallest non-NULL value, in index order
+    pub min: Option<Value>,
+    /// Largest non-NULL value, in index order
+    pub max: Option<Value>,
+}
+
+/// Size and shape of an index
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexStats {
+    /// The name of the index
+    pub name: String,
+    /// The indexed expression
+    pub expr: Expr,
+    /// Number of distinct indexed values
+    pub values: u64,
+    /// Number of indexed rows
+    pub entries: u64,
+    /// Estimated number of levels of the index's B-tree
+    pub estimated_depth: usize,
+    /// Size of the index page
+    pub bytes: u64,
+}
+
+/// Size of pages in the file, frames included
+pub(crate) fn file_bytes(stats: &CompressionStats) -> u64 {
+    stats.stored_bytes + stats.pages * PAGE_HEADER_SIZE as u64
+}
+
+/// Gathers the statistics of a column one value at a time
+pub(crate) struct ColumnCollector {
+    name: String,
+    column_type: ColumnType,
+    nulls: u64,
+    min: Option<IndexValue>,
+    max: Option<IndexValue>,
+    /// The smallest hashes of the values seen
+    hashes: BTreeSet<u32>,
+}
+
+impl ColumnCollector {
+    pub(crate) fn new(column: &Column) -> Self {
+        Self {
+            name: column.name.clone(),
+            column_type: column.column_type.clone(),
+            nulls: 0,
+            min: None,
+            max: None,
+            hashes: BTreeSet::new(),
+        }
+    }
+
+    pub(crate) fn add(&mut self, value: &Value) {
+        if value.is_null() {
+            self.nulls += 1;
+            return;
+        }
+
+        let value = IndexValue(value.clone());
+        if self.min.as_ref().is_none_or(|min| value < *min) {
+            self.min = Some(value.clone());
+        }
+        if self.max.as_ref().is_none_or(|max| value > *max) {
+            self.max = Some(value.clone());
+        }
+
+        let hash = hash_value(&value.0);
+        if self.hashes.len() < SKETCH_SIZE {
+            self.hashes.insert(hash);
+        } else if self.hashes.last().is_some_and(|&largest| hash < largest) && self.hashes.insert(hash) {
+            self.hashes.pop_last();
+        }
+    }
+
+    pub(crate) fn finish(self) -> ColumnStats {
+        ColumnStats {
+            distinct: self.distinct(),
+            name: self.name,
+            column_type: self.column_type,
+            nulls: self.nulls,
+            min: self.min.map(|value| value.0),
+            max: self.max.map(|value| value.0),
+        }
+    }
+
+    /// Estimate the distinct values from the k smallest of their hashes
+    ///
+    /// With fewer than k hashes every value was counted. Otherwise the k
+    /// smallest hashes spread evenly over a share of the hash space, and
+    /// the number of values is k - 1 divided by that share.
+    fn distinct(&self) -> u64 {
+        match self.hashes.last() {
+            Some(&largest) if self.hashes.len() == SKETCH_SIZE => {
+                let share = largest as f64 / u32::MAX as f64;
+                ((SKETCH_SIZE - 1) as f64 / share).round() as u64
+            }
+            _ => self.hashes.len() as u64,
+        }
+    }
+}
+
+/// Hash a value for the distinct estimate
+///
+/// CRC-32 is fully specified, so the estimate of the same data does not
+/// change between runs, platforms or Rust releases. Being linear, it maps
+/// similar values to related checksums, so MurmurHash3's finalizer mixes
+/// them into evenly spread hashes.
+fn hash_value(value: &Value) -> u32 {
+    let crc = match bincode::serialize(value) {
+        Ok(encoded) => crc32fast::hash(&encoded),
+        Err(_) => crc32fast::hash(value.to_string().as_bytes()),
+    };
+    let mut hash = crc ^ (crc >> 16);
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^ (hash >> 16)
+}
@@ -0,0 +1,384 @@
+use std::collections::hash_map::Entry;
+use std::collections::{btree_map, BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Where database files are read from and written to
+///
+/// `Database` performs all of its file access through this trait, so a
+/// backend that injects faults, encrypts or stores files elsewhere can be
+/// plugged in with `Database::with_storage` and `Database::open_in`.
+/// Files are addressed by path. A save writes a temporary file next to the
+/// database with `truncate` and `write`, makes it durable with `sync`,
+/// moves it over the database file with `rename` and makes the rename
+/// durable with `sync_directory`; a failed save `remove`s the temporary
+/// file. A save holds the `lock` of its path while it writes.
+///
+/// # Examples
+///
+/// ```
+/// use oxi_db::{Database, MemoryStorage, Storage};
+/// use std::path::Path;
+/// use std::sync::Arc;
+///
+/// let storage = MemoryStorage::new();
+/// let db = Database::new("app.db").with_storage(Arc::new(storage.clone()));
+/// db.save().unwrap();
+///
+/// assert!(storage.read(Path::new("app.db")).is_ok());
+/// assert!(!Path::new("app.db").exists());
+/// ```
+pub trait Storage: Debug + Send + Sync {
+    /// Read the whole file at `path`
+    ///
+    /// Fails with `io::ErrorKind::NotFound` if there is no such file.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Get the length of the file at `path` in bytes
+    ///
+    /// The default reads the whole file; backends that can tell the length
+    /// without reading should override it.
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        self.read(path).map(|data| data.len() as u64)
+    }
+
+    /// Write `data` at `offset` in the file, creating it if it is missing
+    fn write(&self, path: &Path, offset: u64, data: &[u8]) -> io::Result<()>;
+
+    /// Cut or extend the file to `len` bytes, creating it if it is missing
+    fn truncate(&self, path: &Path, len: u64) -> io::Result<()>;
+
+    /// Make everything written to the file durable
+    fn sync(&self, path: &Path) -> io::Result<()>;
+
+    /// Replace the file at `to` with the file at `from` in one step
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    /// Delete the file at `path`
+    fn remove(&self, path: &Path) -> io::Result<()>;
+
+    /// Make the renames into the directory holding `path` durable
+    ///
+    /// The default does nothing, for backends whose renames are durable as
+    /// soon as they are done.
+    fn sync_directory(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Take the exclusive lock of `path`
+    ///
+    /// Returns a token that identifies this holding of the lock, as made
+    /// by `new_lock_token`. Fails with `io::ErrorKind::WouldBlock` if the
+    /// lock is held, by this process or another one.
+    fn lock(&self, path: &Path) -> io::Result<u64>;
+
+    /// Release the lock of `path` taken under `token`
+    ///
+    /// Fails with `io::ErrorKind::InvalidInput`, leaving the lock alone, if
+    /// `path` is not locked under `token`: a holder whose lock was lost, as
+    /// in a crash, must not release the lock someone took after it.
+    fn unlock(&self, path: &Path, token: u64) -> io::Result<()>;
+}
+
+/// Make a lock token that no other lock of this process has
+pub fn new_lock_token() -> u64 {
+    static NEXT_TOKEN: AtomicU64 = AtomicU64::new(1);
+    NEXT_TOKEN.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Files on the local file system, through `std::fs`
+///
+/// Missing parent directories are created when a file is written, and a
+/// file stays open from its first write until it is synced. The lock of
+/// `path` is an OS file lock on `path` with `.lock` appended, a file that
+/// `unlock` removes again. A process that locks a lock file just as it is
+/// removed notices that the path no longer leads to it and reports the
+/// path as locked, so two processes never hold the same path.
+#[derive(Debug, Default)]
+pub struct FileStorage {
+    /// Lock files held by this process and their tokens, by database path
+    locks: Mutex<HashMap<PathBuf, (u64, File)>>,
+    /// Files being written, by path
+    open_files: Mutex<HashMap<PathBuf, File>>,
+}
+
+impl FileStorage {
+    /// Create a storage for local files
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn open_for_write(path: &Path) -> io::Result<File> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        OpenOptions::new().write(true).create(true).truncate(false).open(path)
+    }
+
+    fn locks(&self) -> MutexGuard<'_, HashMap<PathBuf, (u64, File)>> {
+        self.locks.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn open_files(&self) -> MutexGuard<'_, HashMap<PathBuf, File>> {
+        self.open_files.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Run `f` on the file at `path`, opening it unless it is open already
+    fn with_file<T>(&self, path: &Path, f: impl FnOnce(&mut File) -> io::Result<T>) -> io::Result<T> {
+        let mut open_files = self.open_files();
+        let file = match open_files.entry(path.to_path_buf()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(Self::open_for_write(path)?),
+        };
+        f(file)
+    }
+}
+
+impl Storage for FileStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        fs::metadata(path).map(|metadata| metadata.len())
+    }
+
+    fn write(&self, path: &Path, offset: u64, data: &[u8]) -> io::Result<()> {
+        self.with_file(path, |file| {
+            file.seek(SeekFrom::Start(offset))?;
+            file.write_all(data)
+        })
+    }
+
+    fn truncate(&self, path: &Path, len: u64) -> io::Result<()> {
+        self.with_file(path, |file| file.set_len(len))
+    }
+
+    fn sync(&self, path: &Path) -> io::Result<()> {
+        match self.open_files().remove(path) {
+            Some(file) => file.sync_all(),
+            None => OpenOptions::new().write(true).open(path)?.sync_all(),
+        }
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.open_files().remove(from);
+        fs::rename(from, to)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.open_files().remove(path);
+        fs::remove_file(path)
+    }
+
+    fn sync_directory(&self, path: &Path) -> io::Result<()> {
+        // Directories cannot be opened for syncing on Windows
+        if cfg!(windows) {
+            return Ok(());
+        }
+        let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty());
+        File::open(parent.unwrap_or(Path::new(".")))?.sync_all()
+    }
+
+    fn lock(&self, path: &Path) -> io::Result<u64> {
+        let mut locks = self.locks();
+        if locks.contains_key(path) {
+            return Err(io::Error::new(io::ErrorKind::WouldBlock, "Database file is locked"));
+        }
+        let locked_elsewhere = || io::Error::new(io::ErrorKind::WouldBlock, "Database file is locked by another process");
+        let lock_path = lock_path(path);
+        let lock_file = Self::open_for_write(&lock_path)?;
+        lock_file.try_lock().map_err(|e| match e {
+            fs::TryLockError::WouldBlock => locked_elsewhere(),
+            fs::TryLockError::Error(e) => e,
+        })?;
+        // The holder before may have removed the file between our open and lock
+        if !is_same_file(&lock_file, &lock_path)? {
+            return Err(locked_elsewhere());
+        }
+        let token = new_lock_token();
+        locks.insert(path.to_path_buf(), (token, lock_file));
+        Ok(token)
+    }
+
+    fn unlock(&self, path: &Path, token: u64) -> io::Result<()> {
+        let mut locks = self.locks();
+        let lock_file = match locks.entry(path.to_path_buf()) {
+            Entry::Occupied(entry) if entry.get().0 == token => entry.remove().1,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "Database file is not locked")),
+        };
+        // Removed while still locked, so nobody takes the lock of a file on its way out unnoticed
+        let removed = match fs::remove_file(lock_path(path)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+        lock_file.unlock()?;
+        removed
+    }
+}
+
+/// Check whether `path` still leads to `file`
+#[cfg(unix)]
+fn is_same_file(file: &File, path: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    match fs::metadata(path) {
+        Ok(current) => {
+            let held = file.metadata()?;
+            Ok(held.dev() == current.dev() && held.ino() == current.ino())
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Check whether `path` still leads to `file`
+///
+/// Without file identities in `std`, a lock file that still exists is taken
+/// to be the one locked; Windows does not let a file that is open be
+/// replaced anyway.
+#[cfg(not(unix))]
+fn is_same_file(_file: &File, path: &Path) -> io::Result<bool> {
+    Ok(path.exists())
+}
+
+/// The lock of a database path, released when dropped
+///
+/// Only the holding it was taken under is released, so a lock lost in a
+/// crash and taken again by another writer stays with that writer.
+#[derive(Debug)]
+pub(crate) struct PathLock {
+    storage: Arc<dyn Storage>,
+    path: PathBuf,
+    token: u64,
+}
+
+impl PathLock {
+    /// Take the lock of `path` in `storage`
+    pub(crate) fn acquire(storage: Arc<dyn Storage>, path: &Path) -> io::Result<Self> {
+        let token = storage.lock(path)?;
+        Ok(Self {
+            storage,
+            path: path.to_path_buf(),
+            token,
+        })
+    }
+}
+
+impl Drop for PathLock {
+    fn drop(&mut self) {
+        // A lock that cannot be released is gone with the process at the latest
+        let _ = self.storage.unlock(&self.path, self.token);
+    }
+}
+
+/// Path of the file `FileStorage` locks for `path`
+fn lock_path(path: &Path) -> PathBuf {
+    let mut lock_path = path.to_path_buf().into_os_string();
+    lock_path.push(".lock");
+    PathBuf::from(lock_path)
+}
+
+/// Files kept in memory, for tests and for databases that must not touch
+/// the disk
+///
+/// Clones share the same files, so a clone handed to a database can be
+/// inspected afterwards.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage {
+    files: Arc<Mutex<BTreeMap<PathBuf, Vec<u8>>>>,
+    /// Tokens of the locked paths
+    locks: Arc<Mutex<BTreeMap<PathBuf, u64>>>,
+}
+
+impl MemoryStorage {
+    /// Create a storage without files
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// List the paths of all files, in order
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.contents().keys().cloned().collect()
+    }
+
+    fn contents(&self) -> MutexGuard<'_, BTreeMap<PathBuf, Vec<u8>>> {
+        self.files.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.contents()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File not found"))
+    }
+
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        self.contents()
+            .get(path)
+            .map(|file| file.len() as u64)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File not found"))
+    }
+
+    fn write(&self, path: &Path, offset: u64, data: &[u8]) -> io::Result<()> {
+        let mut files = self.contents();
+        let file = files.entry(path.to_path_buf()).or_default();
+        let start = offset as usize;
+        if file.len() < start + data.len() {
+            file.resize(start + data.len(), 0);
+        }
+        file[start..start + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+
+    fn truncate(&self, path: &Path, len: u64) -> io::Result<()> {
+        self.contents().entry(path.to_path_buf()).or_default().resize(len as usize, 0);
+        Ok(())
+    }
+
+    fn sync(&self, path: &Path) -> io::Result<()> {
+        if self.contents().contains_key(path) {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::NotFound, "File not found"))
+        }
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut files = self.contents();
+        let data = files
+            .remove(from)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File not found"))?;
+        files.insert(to.to_path_buf(), data);
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.contents()
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File not found"))
+    }
+
+    fn lock(&self, path: &Path) -> io::Result<u64> {
+        let mut locks = self.locks.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match locks.entry(path.to_path_buf()) {
+            btree_map::Entry::Occupied(_) => Err(io::Error::new(io::ErrorKind::WouldBlock, "Database file is locked")),
+            btree_map::Entry::Vacant(entry) => Ok(*entry.insert(new_lock_token())),
+        }
+    }
+
+    fn unlock(&self, path: &Path, token: u64) -> io::Result<()> {
+        let mut locks = self.locks.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if locks.get(path) != Some(&token) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Database file is not locked"));
+        }
+        locks.remove(path);
+        Ok(())
+    }
+}
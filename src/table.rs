@@ -1,8 +1,19 @@
 use crate::btree::BTree;
+use crate::compression::Compression;
+use crate::csv::{CsvImportReport, CsvOptions};
 use crate::error::{DbError, Result};
-use crate::types::{Column, Key, Row, Value};
+use crate::file_format::{self, PageRef, PageWriter};
+use crate::index::Index;
+use crate::json::{read_objects, JsonFormat, ObjectWriter};
+use crate::parser::decode_hex;
+use crate::planner::{self, Operator};
+use crate::query::{Expr, Filter};
+use crate::sequence::Sequence;
+use crate::stats::{self, ColumnCollector, IndexStats, TableStats};
+use crate::types::{Column, ColumnType, Key, Row, Value};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io::{Read, Write};
 
 /// A table in the database that stores rows of data
 ///
@@ -35,16 +46,285 @@ pub struct Table {
     pub primary_key: Option<String>,
     /// The data stored in the table, organized as a B-tree
     data: BTree<Key, Row>,
+    /// Secondary indexes over the table's rows
+    indexes: Vec<Index>,
+    /// Counter for the auto-increment primary key column
+    auto_increment: Sequence,
+    /// Compression of the table's pages in the database file
+    #[serde(skip)]
+    compression: Compression,
+    /// Statistics gathered by the last `analyze`, used to estimate query costs
+    #[serde(skip)]
+    statistics: Option<TableStats>,
 }
 
 impl Table {
     /// Create a new table with the given name and columns
     pub fn new(name: impl Into<String>, columns: Vec<Column>, primary_key: Option<String>) -> Self {
+        let name = name.into();
+        let auto_increment =
+            Sequence::new(format!("{}_auto_increment", name), 1, 1).expect("1 is a valid increment");
+
         Self {
-            name: name.into(),
+            name,
             columns,
             primary_key,
             data: BTree::new(),
+            indexes: Vec::new(),
+            auto_increment,
+            compression: Compression::None,
+            statistics: None,
+        }
+    }
+
+    /// Replace the auto-increment counter, as stored in a database file
+    pub(crate) fn with_auto_increment(mut self, auto_increment: Sequence) -> Self {
+        self.auto_increment = auto_increment;
+        self
+    }
+
+    /// Get the compression of the table's pages in the database file
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// Set the compression of the table's pages, used from the next save
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    /// Write the table as pages of a database file
+    ///
+    /// The schema page comes first, then the compression page unless the
+    /// table is uncompressed, a page per row (with overflow pages for large
+    /// values) and a page per index. The schema page counts the others.
+    pub(crate) fn write_pages(&self, writer: &mut PageWriter) -> Result<()> {
+        let mut pages = (self.compression != Compression::None) as u64 + self.indexes.len() as u64;
+        let mut result = Ok(());
+        self.data.traverse(|_, row| {
+            if result.is_ok() {
+                result = PageWriter::row_pages(row).map(|count| pages += count);
+            }
+        });
+        result?;
+        writer.write(&PageRef::Table {
+            name: &self.name,
+            columns: &self.columns,
+            primary_key: &self.primary_key,
+            auto_increment: &self.auto_increment,
+            pages,
+        })?;
+        if self.compression != Compression::None {
+            writer.write(&PageRef::Compression(self.compression))?;
+        }
+        let mut result = Ok(());
+        self.data.traverse(|key, row| {
+            if result.is_ok() {
+                result = writer.write_row(key, row, self.compression);
+            }
+        });
+        result?;
+        for index in &self.indexes {
+            writer.write_compressed(&PageRef::Index(index), self.compression)?;
+        }
+        Ok(())
+    }
+
+    /// Add a row read from a database file, without checks or index updates
+    pub(crate) fn load_row(&mut self, key: Key, row: Row) {
+        self.data.insert(key, row);
+    }
+
+    /// Add an index read from a database file, entries included
+    pub(crate) fn load_index(&mut self, index: Index) {
+        self.indexes.push(index);
+    }
+
+    /// Take the rows at `keys` and the auto-increment counter, for `restore_rows`
+    pub(crate) fn snapshot_rows(&self, keys: Vec<Key>) -> RowSnapshot {
+        let rows = keys
+            .into_iter()
+            .map(|key| {
+                let row = self.data.search(&key).cloned();
+                (key, row)
+            })
+            .collect();
+        RowSnapshot {
+            rows,
+            auto_increment: self.auto_increment.clone(),
+        }
+    }
+
+    /// Put back the rows and the counter of a snapshot, with their index entries
+    pub(crate) fn restore_rows(&mut self, snapshot: RowSnapshot) {
+        for (key, row) in snapshot.rows {
+            let current = self.data.remove(&key);
+            for index in &mut self.indexes {
+                // Both rows were indexed before, so they evaluate again
+                if let Some(current) = &current {
+                    let _ = index.remove(&self.columns, &key, current);
+                }
+                if let Some(row) = &row {
+                    let _ = index.insert(&self.columns, &key, row);
+                }
+            }
+            if let Some(row) = row {
+                self.data.insert(key, row);
+            }
+        }
+        self.auto_increment = snapshot.auto_increment;
+    }
+
+    /// Check that a stored row has a value of the right type for every column
+    pub(crate) fn check_row(&self, row: &Row) -> Result<()> {
+        if row.values.len() != self.columns.len() {
+            return Err(DbError::Other(format!(
+                "Expected {} values, got {}",
+                self.columns.len(),
+                row.values.len()
+            )));
+        }
+        for i in 0..row.values.len() {
+            self.validate_value_type(i, &row.values[i])?;
+        }
+        Ok(())
+    }
+
+    /// Check that the schema is consistent
+    ///
+    /// Column types must be well-formed, column names must be unique, the
+    /// primary key must name a column, and an auto-increment column must be
+    /// the `Integer` primary key.
+    pub fn validate_schema(&self) -> Result<()> {
+        for (i, column) in self.columns.iter().enumerate() {
+            column.column_type.validate().map_err(DbError::Other)?;
+            if self.columns[..i].iter().any(|c| c.name == column.name) {
+                return Err(DbError::Other(format!("Duplicate column: {}", column.name)));
+            }
+        }
+
+        if let Some(pk) = &self.primary_key {
+            if !self.columns.iter().any(|c| &c.name == pk) {
+                return Err(DbError::ColumnNotFound(pk.clone()));
+            }
+        }
+
+        for column in self.columns.iter().filter(|c| c.auto_increment) {
+            if self.primary_key.as_ref() != Some(&column.name)
+                || column.column_type != ColumnType::Integer
+            {
+                return Err(DbError::Other(format!(
+                    "Auto-increment column {} must be the integer primary key",
+                    column.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the position of the auto-increment primary key column, if any
+    fn auto_increment_column(&self) -> Option<usize> {
+        let pk = self.primary_key.as_ref()?;
+        self.columns
+            .iter()
+            .position(|c| &c.name == pk && c.auto_increment && c.column_type == ColumnType::Integer)
+    }
+
+    /// Insert a row, assigning the next auto-increment key
+    ///
+    /// If the auto-increment primary key value is NULL, the next value of the
+    /// table's counter is stored in that column and used as the row key;
+    /// otherwise the supplied integer is used. The key of the new row is
+    /// returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the table has no auto-increment primary key, or
+    /// for any reason `insert` would fail. A counter value consumed by a
+    /// failed insert is not reused.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::{Column, ColumnType, Key, Table, Value};
+    ///
+    /// let columns = vec![
+    ///     Column::new("id", ColumnType::Integer).auto_increment(),
+    ///     Column::new("name", ColumnType::Text),
+    /// ];
+    /// let mut table = Table::new("users", columns, Some("id".to_string()));
+    ///
+    /// let key = table.insert_auto(vec![Value::Null, Value::from("Alice")]).unwrap();
+    /// assert_eq!(key, Key::from(1));
+    /// assert_eq!(table.get(&key).unwrap().values[0], Value::Integer(1));
+    /// ```
+    pub fn insert_auto(&mut self, mut values: Vec<Value>) -> Result<Key> {
+        let idx = self.auto_increment_column().ok_or_else(|| {
+            DbError::Other(format!("Table {} has no auto-increment primary key", self.name))
+        })?;
+
+        let id = match values.get(idx) {
+            Some(Value::Null) => {
+                let id = self.auto_increment.next_value()?;
+                values[idx] = Value::Integer(id);
+                id
+            }
+            Some(Value::Integer(id)) => *id,
+            Some(_) => return Err(DbError::TypeConversionError),
+            None => {
+                return Err(DbError::Other(format!(
+                    "Expected {} values, got {}",
+                    self.columns.len(),
+                    values.len()
+                )))
+            }
+        };
+
+        let key = Key::Integer(id);
+        self.insert(key.clone(), values)?;
+        Ok(key)
+    }
+
+    /// Get the value the next auto-increment insert will assign
+    pub fn next_auto_increment(&self) -> Option<i64> {
+        self.auto_increment_column()
+            .map(|_| self.auto_increment.peek())
+    }
+
+    /// Get the key for a row from its primary key value
+    ///
+    /// Returns `None` when the table has no primary key or the value is
+    /// NULL.
+    pub(crate) fn key_from_values(&self, values: &[Value]) -> Option<Key> {
+        let idx = self.column_index(self.primary_key.as_ref()?).ok()?;
+        values.get(idx).and_then(Key::from_value)
+    }
+
+    /// Make the auto-increment counter hand out `next` or later ids
+    ///
+    /// This restores the counter of a dumped table, whose deleted rows may
+    /// have used ids beyond those of its rows.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::Other` if the table has no auto-increment column.
+    pub(crate) fn advance_auto_increment(&mut self, next: i64) -> Result<()> {
+        if self.auto_increment_column().is_none() {
+            return Err(DbError::Other(format!("Table {} has no auto-increment column", self.name)));
+        }
+        if let Some(last) = next.checked_sub(1) {
+            self.auto_increment.advance_past(last);
+        }
+        Ok(())
+    }
+
+    /// Keep the auto-increment counter ahead of explicitly supplied ids
+    fn track_auto_increment(&mut self, values: &[Value]) {
+        if let Some(idx) = self.auto_increment_column() {
+            if let Some(Value::Integer(id)) = values.get(idx) {
+                self.auto_increment.advance_past(*id);
+            }
         }
     }
 
@@ -57,6 +337,30 @@ impl Table {
             return Err(DbError::KeyExists);
         }
 
+        let row = Row::new(self.prepare_values(values)?);
+        let index_values = self.index_values(&row)?;
+        for (index, value) in self.indexes.iter_mut().zip(index_values) {
+            index.insert_value(&key, value);
+        }
+        self.track_auto_increment(&row.values);
+        self.data.insert(key, row);
+
+        Ok(())
+    }
+
+    /// Evaluate the value of a row in every index
+    ///
+    /// Writes evaluate all of them before changing any index, so a failed
+    /// evaluation leaves the indexes as they were.
+    fn index_values(&self, row: &Row) -> Result<Vec<Value>> {
+        self.indexes
+            .iter()
+            .map(|index| index.expr.evaluate(&self.columns, row))
+            .collect()
+    }
+
+    /// Check the column count, convert and validate values for a new row
+    fn prepare_values(&self, values: Vec<Value>) -> Result<Vec<Value>> {
         // Check if values match column count
         if values.len() != self.columns.len() {
             return Err(DbError::Other(format!(
@@ -66,33 +370,31 @@ impl Table {
             )));
         }
 
-        // Validate value types
-        for (i, value) in values.iter().enumerate() {
-            self.validate_value_type(i, value)?;
-        }
+        let mut prepared = Vec::with_capacity(values.len());
+        for (i, value) in values.into_iter().enumerate() {
+            // JSON and UUID columns accept values written as text
+            let value = match (&self.columns[i].column_type, value) {
+                (ColumnType::Json, Value::Text(text)) => Value::json_from_str(&text)?,
+                (ColumnType::Uuid, Value::Text(text)) => Value::Uuid(
+                    text.parse()
+                        .map_err(|_| DbError::ParseError(format!("Invalid UUID: {}", text)))?,
+                ),
+                (_, value) => value,
+            };
 
-        let row = Row::new(values);
-        self.data.insert(key, row);
+            // Validate value types
+            self.validate_value_type(i, &value)?;
+            prepared.push(value);
+        }
 
-        Ok(())
+        Ok(prepared)
     }
 
     /// Validate that a value matches the expected column type
     fn validate_value_type(&self, column_idx: usize, value: &Value) -> Result<()> {
-        use crate::types::ColumnType;
-
         let column = &self.columns[column_idx];
-        let valid = match (&column.column_type, value) {
-            (ColumnType::Integer, Value::Integer(_)) => true,
-            (ColumnType::Float, Value::Float(_)) => true,
-            (ColumnType::Text, Value::Text(_)) => true,
-            (ColumnType::Boolean, Value::Boolean(_)) => true,
-            (ColumnType::Blob, Value::Blob(_)) => true,
-            (_, Value::Null) => true, // Allow NULL for any type
-            _ => false,
-        };
 
-        if valid {
+        if column.column_type.accepts(value) {
             Ok(())
         } else {
             Err(DbError::TypeConversionError)
@@ -109,25 +411,16 @@ impl Table {
     /// Update a row by key
     pub fn update(&mut self, key: &Key, values: Vec<Value>) -> Result<()> {
         // Check if key exists
-        if self.data.search(key).is_none() {
-            return Err(DbError::KeyNotFound);
-        }
-
-        // Check if values match column count
-        if values.len() != self.columns.len() {
-            return Err(DbError::Other(format!(
-                "Expected {} values, got {}",
-                self.columns.len(),
-                values.len()
-            )));
-        }
+        let old_row = self.data.search(key).cloned().ok_or(DbError::KeyNotFound)?;
 
-        // Validate value types
-        for (i, value) in values.iter().enumerate() {
-            self.validate_value_type(i, value)?;
+        let row = Row::new(self.prepare_values(values)?);
+        let old_values = self.index_values(&old_row)?;
+        let new_values = self.index_values(&row)?;
+        for ((index, old_value), new_value) in self.indexes.iter_mut().zip(old_values).zip(new_values) {
+            index.remove_value(key, old_value);
+            index.insert_value(key, new_value);
         }
-
-        let row = Row::new(values);
+        self.track_auto_increment(&row.values);
 
         // Get mutable reference and update
         if let Some(existing_row) = self.data.get_mut(key) {
@@ -138,12 +431,94 @@ impl Table {
         }
     }
 
+    /// Insert a row, or replace it if the key already exists
+    ///
+    /// Returns `true` if a new row was inserted and `false` if an existing
+    /// row was replaced.
+    pub fn upsert(&mut self, key: impl Into<Key>, values: Vec<Value>) -> Result<bool> {
+        let key = key.into();
+
+        if self.data.search(&key).is_some() {
+            self.update(&key, values)?;
+            Ok(false)
+        } else {
+            self.insert(key, values)?;
+            Ok(true)
+        }
+    }
+
+    /// Update some columns of a row by name, keeping the other values
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::KeyNotFound` if the row does not exist,
+    /// `DbError::ColumnNotFound` if a column name is not in the schema, or a
+    /// type error if a new value does not match its column.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::{Column, ColumnType, Key, Table, Value};
+    ///
+    /// let columns = vec![
+    ///     Column::new("name", ColumnType::Text),
+    ///     Column::new("active", ColumnType::Boolean),
+    /// ];
+    /// let mut table = Table::new("users", columns, None);
+    /// table.insert("1", vec![Value::from("Alice"), Value::from(true)]).unwrap();
+    ///
+    /// table.update_columns(&Key::from("1"), &[("active", Value::from(false))]).unwrap();
+    /// assert_eq!(table.get(&Key::from("1")).unwrap().values[1], Value::from(false));
+    /// ```
+    pub fn update_columns(&mut self, key: &Key, updates: &[(&str, Value)]) -> Result<()> {
+        let mut values = self.get(key)?.values.clone();
+
+        for (name, value) in updates {
+            let idx = self.column_index(name)?;
+            values[idx] = value.clone();
+        }
+
+        self.update(key, values)
+    }
+
+    /// Replace a row only if it currently equals `expected`
+    ///
+    /// This makes read-modify-write cycles safe: read a row, compute the new
+    /// values, and swap them in only if nobody changed the row in between.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::KeyNotFound` if the row does not exist,
+    /// `DbError::CompareFailed` if the row differs from `expected` in any
+    /// value or its type (a NaN matches itself), or a type error if the new
+    /// values do not match the schema.
+    pub fn compare_and_swap(&mut self, key: &Key, expected: &Row, values: Vec<Value>) -> Result<()> {
+        let current = &self.get(key)?.values;
+        let same = current.len() == expected.values.len()
+            && current.iter().zip(&expected.values).all(|(a, b)| a.is_identical(b));
+        if !same {
+            return Err(DbError::CompareFailed);
+        }
+
+        self.update(key, values)
+    }
+
+    /// Get the position of a column by name
+    pub fn column_index(&self, name: &str) -> Result<usize> {
+        self.columns
+            .iter()
+            .position(|c| c.name == name)
+            .ok_or_else(|| DbError::ColumnNotFound(name.to_string()))
+    }
+
     /// Delete a row by key
     pub fn delete(&mut self, key: &Key) -> Result<()> {
-        self.data
-            .remove(key)
-            .ok_or(DbError::KeyNotFound)
-            .map(|_| ())
+        let index_values = self.index_values(self.get(key)?)?;
+        for (index, value) in self.indexes.iter_mut().zip(index_values) {
+            index.remove_value(key, value);
+        }
+        self.data.remove(key);
+        Ok(())
     }
 
     /// Get all rows in the table
@@ -161,6 +536,99 @@ impl Table {
         self.data.is_empty()
     }
 
+    /// Compute a CRC-32 checksum of every key and row, in key order
+    ///
+    /// Two tables with the same rows have the same checksum, so it can be
+    /// used to check that a copy of a table is complete.
+    pub fn checksum(&self) -> Result<u32> {
+        let mut hasher = crc32fast::Hasher::new();
+        let mut result = Ok(());
+        self.data.traverse(|key, row| {
+            if result.is_ok() {
+                result = bincode::serialize(&(key, row)).map(|bytes| hasher.update(&bytes));
+            }
+        });
+        result?;
+        Ok(hasher.finalize())
+    }
+
+    /// Gather the size of the table and the distribution of its values
+    ///
+    /// Reads every row once; byte sizes are measured by encoding the pages
+    /// as a save would, without keeping them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::{Column, ColumnType, Table, Value};
+    ///
+    /// let mut table = Table::new("users", vec![Column::new("name", ColumnType::Text)], None);
+    /// table.insert(1, vec![Value::from("Alice")]).unwrap();
+    /// table.insert(2, vec![Value::Null]).unwrap();
+    ///
+    /// let stats = table.stats().unwrap();
+    /// assert_eq!(stats.rows, 2);
+    /// let name = stats.column("name").unwrap();
+    /// assert_eq!((name.nulls, name.distinct), (1, 1));
+    /// assert_eq!(name.min, Some(Value::from("Alice")));
+    /// ```
+    pub fn stats(&self) -> Result<TableStats> {
+        let mut columns: Vec<ColumnCollector> = self.columns.iter().map(ColumnCollector::new).collect();
+        self.data.traverse(|_, row| {
+            for (column, value) in columns.iter_mut().zip(&row.values) {
+                column.add(value);
+            }
+        });
+
+        let indexes = self
+            .indexes
+            .iter()
+            .map(|index| {
+                Ok(IndexStats {
+                    name: index.name.clone(),
+                    expr: index.expr.clone(),
+                    values: index.len() as u64,
+                    entries: index.key_count() as u64,
+                    estimated_depth: index.estimated_depth(),
+                    bytes: stats::file_bytes(&file_format::index_stats(index, self.compression)?),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let pages = file_format::table_stats(self)?;
+
+        Ok(TableStats {
+            name: self.name.clone(),
+            rows: self.len() as u64,
+            estimated_depth: self.data.estimated_depth(),
+            bytes: stats::file_bytes(&pages),
+            index_bytes: indexes.iter().map(|index| index.bytes).sum(),
+            overflow_pages: pages.overflow_pages,
+            compression: self.compression,
+            columns: columns.into_iter().map(ColumnCollector::finish).collect(),
+            indexes,
+        })
+    }
+
+    /// Gather statistics for the planner to estimate query costs with
+    ///
+    /// The planner uses the number of NULLs, distinct values and the
+    /// smallest and largest value of each column to estimate how many rows
+    /// a filter matches. The statistics are kept in memory until the next
+    /// `analyze` and are not saved; they are not updated as rows change.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if measuring the table's pages fails.
+    pub fn analyze(&mut self) -> Result<()> {
+        self.statistics = Some(self.stats()?);
+        Ok(())
+    }
+
+    /// Get the statistics gathered by the last `analyze`
+    pub fn statistics(&self) -> Option<&TableStats> {
+        self.statistics.as_ref()
+    }
+
     /// Get a row as a map of column names to values
     pub fn get_as_map(&self, key: &Key) -> Result<HashMap<String, Value>> {
         let row = self.get(key)?;
@@ -175,6 +643,264 @@ impl Table {
         Ok(map)
     }
 
+    /// Convert a row into a JSON object keyed by column name
+    ///
+    /// The row key comes first under `"_key"`, followed by the columns in
+    /// schema order. Values are converted with `Value::to_json`. Integer and
+    /// text keys are written as numbers and strings, bytes and UUID keys as
+    /// `{"bytes": "00ff"}` and `{"uuid": "..."}` so that their kind is kept,
+    /// and composite keys as arrays of their parts.
+    pub fn row_to_json(&self, key: &Key, row: &Row) -> serde_json::Value {
+        let mut object = serde_json::Map::new();
+        object.insert("_key".to_string(), key_to_json(key));
+        for (column, value) in self.columns.iter().zip(&row.values) {
+            object.insert(column.name.clone(), value.to_json(&column.column_type));
+        }
+        serde_json::Value::Object(object)
+    }
+
+    /// Convert a JSON object keyed by column name into a row
+    ///
+    /// This is the inverse of `row_to_json`: each field is converted with
+    /// `Value::from_json_as` and missing columns are NULL. The `"_key"`
+    /// field is ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ColumnNotFound` for fields that are not columns,
+    /// and `DbError::ParseError` if the input is not an object or a field
+    /// does not fit its column type.
+    pub fn row_from_json(&self, json: &serde_json::Value) -> Result<Row> {
+        let object = json
+            .as_object()
+            .ok_or_else(|| DbError::ParseError(format!("Expected a JSON object, found {}", json)))?;
+
+        let mut values = vec![Value::Null; self.columns.len()];
+        for (name, field) in object {
+            if name == "_key" {
+                continue;
+            }
+            let idx = self.column_index(name)?;
+            values[idx] = Value::from_json_as(field, &self.columns[idx].column_type)?;
+        }
+        Ok(Row::new(values))
+    }
+
+    /// Describe the schema of the table as a JSON object
+    ///
+    /// The object holds the table name, the primary key, the columns with
+    /// their types written as in `ColumnType`'s `Display`, the indexes and,
+    /// for an auto-increment table, the state of its counter.
+    /// `Table::from_schema_json` reads it back.
+    pub fn schema_to_json(&self) -> serde_json::Value {
+        let columns: Vec<serde_json::Value> = self
+            .columns
+            .iter()
+            .map(|column| {
+                serde_json::json!({
+                    "name": column.name,
+                    "type": column.column_type.to_string(),
+                    "primary_key": self.primary_key.as_ref() == Some(&column.name),
+                    "auto_increment": column.auto_increment,
+                })
+            })
+            .collect();
+        let indexes: Vec<serde_json::Value> = self
+            .indexes
+            .iter()
+            .map(|index| serde_json::json!({"name": index.name, "expr": index.expr.to_string()}))
+            .collect();
+
+        let mut schema = serde_json::json!({
+            "name": self.name,
+            "primary_key": self.primary_key,
+            "columns": columns,
+            "indexes": indexes,
+        });
+        if self.auto_increment_column().is_some() {
+            schema["auto_increment"] = serde_json::json!(self.auto_increment);
+        }
+        schema
+    }
+
+    /// Create an empty table from a schema written by `schema_to_json`
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ParseError` if the schema is malformed, and the
+    /// errors of `validate_schema` and `create_index`.
+    pub fn from_schema_json(json: &serde_json::Value) -> Result<Self> {
+        let invalid = |what: &str| DbError::ParseError(format!("Invalid table schema: {}", what));
+        let text = |value: &serde_json::Value, what: &str| {
+            value.as_str().map(String::from).ok_or_else(|| invalid(what))
+        };
+
+        let name = text(&json["name"], "missing name")?;
+        let primary_key = match &json["primary_key"] {
+            serde_json::Value::Null => None,
+            value => Some(text(value, "primary_key must be a string")?),
+        };
+        let columns = json["columns"]
+            .as_array()
+            .ok_or_else(|| invalid("missing columns"))?
+            .iter()
+            .map(|column| {
+                let mut parsed = Column::new(
+                    text(&column["name"], "column without a name")?,
+                    ColumnType::parse(&text(&column["type"], "column without a type")?)?,
+                );
+                parsed.auto_increment = column["auto_increment"].as_bool().unwrap_or(false);
+                Ok(parsed)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut table = Table::new(name, columns, primary_key);
+        table.validate_schema()?;
+        if let Some(counter) = json.get("auto_increment") {
+            table.auto_increment = serde_json::from_value(counter.clone())?;
+        }
+        for index in json["indexes"].as_array().into_iter().flatten() {
+            let expr = Expr::parse(&text(&index["expr"], "index without an expression")?)?;
+            table.create_index(text(&index["name"], "index without a name")?, expr)?;
+        }
+        Ok(table)
+    }
+
+    /// Insert a row given as a JSON object keyed by column name
+    ///
+    /// The key is the `"_key"` field written by `row_to_json`, if there is
+    /// one, and otherwise the primary key column's value. When that is NULL,
+    /// an auto-increment table assigns the next key.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `row_from_json` and `insert`, and
+    /// `DbError::ParseError` if no key can be found.
+    pub fn insert_json(&mut self, json: &serde_json::Value) -> Result<Key> {
+        let row = self.row_from_json(json)?;
+
+        if let Some(key) = json.get("_key").filter(|key| !key.is_null()) {
+            let key = key_from_json(key).ok_or_else(|| DbError::ParseError(format!("Invalid key: {}", key)))?;
+            self.insert(key.clone(), row.values)?;
+            return Ok(key);
+        }
+
+        let pk_value = self
+            .primary_key
+            .as_ref()
+            .and_then(|pk| self.column_index(pk).ok())
+            .map(|idx| &row.values[idx]);
+        let key = match pk_value {
+            Some(value) if !value.is_null() => Key::from_value(value),
+            Some(_) if self.next_auto_increment().is_some() => return self.insert_auto(row.values),
+            _ => None,
+        };
+        let key = key.ok_or_else(|| DbError::ParseError(format!("Missing key in {}", json)))?;
+
+        self.insert(key.clone(), row.values)?;
+        Ok(key)
+    }
+
+    /// Write the table as CSV, with a header row of column names
+    ///
+    /// NULL is written as `options.null`, blobs as hexadecimal, and JSON
+    /// documents, arrays and structs as JSON text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::{Column, ColumnType, CsvOptions, Table, Value};
+    ///
+    /// let mut table = Table::new("users", vec![
+    ///     Column::new("id", ColumnType::Integer),
+    ///     Column::new("name", ColumnType::Text),
+    /// ], Some("id".to_string()));
+    /// table.insert(1, vec![Value::Integer(1), Value::from("Alice, Jr.")]).unwrap();
+    ///
+    /// let mut csv = Vec::new();
+    /// table.export_csv(&mut csv, &CsvOptions::new()).unwrap();
+    /// assert_eq!(String::from_utf8(csv).unwrap(), "id,name\n1,\"Alice, Jr.\"\n");
+    /// ```
+    pub fn export_csv(&self, writer: impl Write, options: &CsvOptions) -> Result<()> {
+        crate::csv::export(self, writer, options)
+    }
+
+    /// Insert rows read from CSV
+    ///
+    /// Each field is parsed into its column's type with `Value::parse_as`.
+    /// Fields equal to `options.null` are NULL, and so are empty fields in
+    /// columns that cannot hold an empty value.
+    /// The row key is taken from the primary key column, or from the
+    /// auto-increment counter when that column is NULL. Rows that cannot be
+    /// parsed or inserted are skipped and listed in the returned report.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ColumnNotFound` if the header names an unknown
+    /// column, `DbError::Other` if the table has no primary key, and
+    /// `DbError::IoError` if the input cannot be read.
+    pub fn import_csv(&mut self, reader: impl Read, options: &CsvOptions) -> Result<CsvImportReport> {
+        crate::csv::import(self, reader, options)
+    }
+
+    /// Write the rows of the table as JSON objects keyed by column name
+    ///
+    /// Rows are written one at a time in key order, in the form produced by
+    /// `row_to_json`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::{Column, ColumnType, JsonFormat, Table, Value};
+    ///
+    /// let mut table = Table::new("users", vec![
+    ///     Column::new("id", ColumnType::Integer),
+    ///     Column::new("name", ColumnType::Text),
+    /// ], Some("id".to_string()));
+    /// table.insert(1, vec![Value::Integer(1), Value::from("Alice")]).unwrap();
+    ///
+    /// let mut out = Vec::new();
+    /// table.export_json(&mut out, JsonFormat::Ndjson).unwrap();
+    /// assert_eq!(String::from_utf8(out).unwrap(), "{\"_key\":1,\"id\":1,\"name\":\"Alice\"}\n");
+    /// ```
+    pub fn export_json(&self, writer: impl Write, format: JsonFormat) -> Result<()> {
+        let mut writer = ObjectWriter::new(writer, format);
+        self.write_json_rows(&mut writer)?;
+        writer.finish()
+    }
+
+    /// Write every row to an object stream without collecting them first
+    pub(crate) fn write_json_rows<W: Write>(&self, writer: &mut ObjectWriter<W>) -> Result<()> {
+        let mut result = Ok(());
+        self.data.traverse(|key, row| {
+            if result.is_ok() {
+                result = writer.write(&self.row_to_json(key, row));
+            }
+        });
+        result
+    }
+
+    /// Insert rows read as JSON objects keyed by column name
+    ///
+    /// Each object is inserted with `insert_json`. Import stops at the first
+    /// row that fails, leaving the rows before it inserted; use
+    /// `Database::import_table_json` for an all-or-nothing import. Returns
+    /// the number of rows inserted.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error, with the line or item number of the row
+    /// added to parse errors.
+    pub fn import_json(&mut self, reader: impl Read, format: JsonFormat) -> Result<usize> {
+        let mut count = 0;
+        read_objects(reader, format, |object| {
+            self.insert_json(object)?;
+            count += 1;
+            Ok(())
+        })?;
+        Ok(count)
+    }
+
     /// Find rows that match a predicate
     pub fn find<F>(&self, predicate: F) -> Vec<(Key, Row)>
     where
@@ -190,4 +916,136 @@ impl Table {
 
         results
     }
+
+    /// Find rows that match a filter
+    ///
+    /// The planner chooses how to read the rows: through an index on an
+    /// expression the filter compares with a value, either for equality or
+    /// for a range, or by scanning every row. Results are in key order
+    /// either way.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ColumnNotFound` if the filter references a column
+    /// that is not in the schema.
+    pub fn find_where(&self, filter: &Filter) -> Result<Vec<(Key, Row)>> {
+        let mut plan = planner::plan_access(self, Some(filter));
+        let sorted = matches!(plan.operator, Operator::Scan { .. });
+        let mut results = plan.run(&|_| Ok(self))?;
+        if !sorted {
+            results.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+        Ok(results)
+    }
+
+    /// Read the rows matching a filter by scanning every row, in key order
+    pub(crate) fn scan(&self, filter: Option<&Filter>) -> Result<Vec<(Key, Row)>> {
+        let mut results = Vec::new();
+        for (key, row) in self.data.range(..) {
+            if filter.map_or(Ok(true), |filter| filter.matches(&self.columns, row))? {
+                results.push((key.clone(), row.clone()));
+            }
+        }
+        Ok(results)
+    }
+
+    /// Read the rows with the given keys that match a filter
+    pub(crate) fn fetch(&self, keys: Vec<Key>, filter: Option<&Filter>) -> Result<Vec<(Key, Row)>> {
+        let mut results = Vec::new();
+        for key in keys {
+            if let Some(row) = self.data.search(&key) {
+                if filter.map_or(Ok(true), |filter| filter.matches(&self.columns, row))? {
+                    results.push((key, row.clone()));
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    /// Create a secondary index over a column or a path inside a JSON column
+    ///
+    /// Existing rows are indexed immediately.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an index with the same name exists, if the column
+    /// does not exist, or if a JSON path is used on a non-JSON column.
+    pub fn create_index(&mut self, name: impl Into<String>, expr: Expr) -> Result<()> {
+        let name = name.into();
+
+        if self.indexes.iter().any(|index| index.name == name) {
+            return Err(DbError::IndexExists(name));
+        }
+
+        let column = self
+            .columns
+            .iter()
+            .find(|c| c.name == expr.column_name())
+            .ok_or_else(|| DbError::ColumnNotFound(expr.column_name().to_string()))?;
+        if matches!(expr, Expr::JsonPath { .. }) && column.column_type != ColumnType::Json {
+            return Err(DbError::TypeConversionError);
+        }
+
+        let mut index = Index::new(name, expr);
+        for (key, row) in self.data.to_vec() {
+            index.insert(&self.columns, &key, &row)?;
+        }
+        self.indexes.push(index);
+
+        Ok(())
+    }
+
+    /// Drop a secondary index by name
+    pub fn drop_index(&mut self, name: &str) -> Result<()> {
+        let position = self
+            .indexes
+            .iter()
+            .position(|index| index.name == name)
+            .ok_or_else(|| DbError::IndexNotFound(name.to_string()))?;
+        self.indexes.remove(position);
+        Ok(())
+    }
+
+    /// Get the secondary indexes of the table
+    pub fn indexes(&self) -> &[Index] {
+        &self.indexes
+    }
+}
+
+/// Rows of a table and its auto-increment counter as they were before a change
+///
+/// A row that did not exist is `None`.
+#[derive(Debug)]
+pub(crate) struct RowSnapshot {
+    rows: Vec<(Key, Option<Row>)>,
+    auto_increment: Sequence,
+}
+
+/// Write a key the way `row_to_json` does
+fn key_to_json(key: &Key) -> serde_json::Value {
+    match key {
+        Key::Bytes(_) => serde_json::json!({ "bytes": key.to_json() }),
+        Key::Uuid(_) => serde_json::json!({ "uuid": key.to_json() }),
+        Key::Composite(parts) => parts.iter().map(key_to_json).collect(),
+        _ => key.to_json(),
+    }
+}
+
+/// Read a key written by `key_to_json`
+fn key_from_json(json: &serde_json::Value) -> Option<Key> {
+    match json {
+        serde_json::Value::Number(n) => n.as_i64().map(Key::Integer),
+        serde_json::Value::String(s) => Some(Key::Text(s.clone())),
+        serde_json::Value::Array(parts) => parts
+            .iter()
+            .map(key_from_json)
+            .collect::<Option<Vec<_>>>()
+            .map(Key::Composite),
+        serde_json::Value::Object(object) if object.len() == 1 => match object.iter().next()? {
+            (kind, serde_json::Value::String(s)) if kind == "bytes" => decode_hex(s).ok().map(Key::Bytes),
+            (kind, serde_json::Value::String(s)) if kind == "uuid" => s.parse().ok().map(Key::Uuid),
+            _ => None,
+        },
+        _ => None,
+    }
 }
@@ -1,25 +1,37 @@
+use crate::decimal::{Decimal, MAX_PRECISION};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::fmt;
+use uuid::Uuid;
 
 /// Supported data types in the database
 ///
 /// The `Value` enum represents all possible data types that can be stored in
 /// the database. It includes support for NULL values, integers, floating-point
-/// numbers, text strings, booleans, and binary data (blobs).
+/// numbers, exact decimals, text strings, booleans, binary data (blobs),
+/// UUIDs, JSON documents, and the composite array and struct values.
+///
+/// Database files identify a variant by its position, so new variants are
+/// only ever added at the end.
 ///
 /// # Examples
 ///
 /// Creating values of different types:
 ///
 /// ```
-/// use oxi_db::Value;
+/// use oxi_db::{Decimal, Value};
 ///
 /// let null_value = Value::Null;
 /// let integer_value = Value::Integer(42);
 /// let float_value = Value::Float(3.14);
+/// let decimal_value = Value::Decimal("19.99".parse::<Decimal>().unwrap());
 /// let text_value = Value::Text("Hello, world!".to_string());
 /// let boolean_value = Value::Boolean(true);
 /// let blob_value = Value::Blob(vec![0, 1, 2, 3, 4]);
+/// let uuid_value = Value::generate_uuid();
+/// let json_value = Value::Json(serde_json::json!({"city": "Paris"}));
+/// let array_value = Value::Array(vec![Value::from("rust"), Value::from("db")]);
+/// let struct_value = Value::Struct(vec![Value::from("Paris"), Value::Integer(75001)]);
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum Value {
@@ -35,6 +47,643 @@ pub enum Value {
     Boolean(bool),
     /// Represents binary data as a byte array
     Blob(Vec<u8>),
+    /// Represents an exact fixed-point decimal number
+    Decimal(Decimal),
+    /// Represents a semi-structured JSON document
+    Json(#[serde(with = "json_document")] serde_json::Value),
+    /// Represents a list of values of the same type
+    Array(#[serde(deserialize_with = "nested::deserialize")] Vec<Value>),
+    /// Represents a record whose values correspond to the fields of a
+    /// `ColumnType::Struct`
+    Struct(#[serde(deserialize_with = "nested::deserialize")] Vec<Value>),
+    /// Represents a UUID, stored as 16 bytes
+    Uuid(Uuid),
+}
+
+impl Value {
+    /// Parse a JSON document into a `Value::Json`
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ParseError` if the text is not valid JSON.
+    pub fn json_from_str(s: &str) -> crate::error::Result<Self> {
+        serde_json::from_str(s)
+            .map(Value::Json)
+            .map_err(|e| crate::error::DbError::ParseError(format!("Invalid JSON: {}", e)))
+    }
+
+    /// Generate a new random (version 4) UUID value
+    pub fn generate_uuid() -> Self {
+        Value::Uuid(Uuid::new_v4())
+    }
+
+    /// Parse a literal written in the query language
+    ///
+    /// Accepts `NULL`, `TRUE`, `FALSE`, integers, decimals such as `19.99`,
+    /// floats with an exponent such as `1e-3`, quoted strings, blobs written
+    /// as `x'00ff'`, arrays such as `[1, 2, 3]` and structs such as
+    /// `{'Paris', 75001}`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::Value;
+    ///
+    /// assert_eq!(Value::parse_literal("42").unwrap(), Value::Integer(42));
+    /// assert_eq!(Value::parse_literal("'it''s'").unwrap(), Value::from("it's"));
+    /// assert_eq!(Value::parse_literal("x'00ff'").unwrap(), Value::Blob(vec![0, 255]));
+    /// ```
+    pub fn parse_literal(input: &str) -> crate::error::Result<Self> {
+        let mut parser = crate::parser::Parser::new(input)?;
+        let value = parser.parse_literal()?;
+        parser.expect_end()?;
+        Ok(value)
+    }
+
+    /// Convert the value to the representation used by a column type
+    ///
+    /// This is how literals of the query language and the SQL dump are given
+    /// the type of their column; `Table` itself only converts text into
+    /// UUIDs and JSON documents. Integers widen to floats and decimals,
+    /// decimals are rescaled to the column scale when no digits are lost, and
+    /// text is parsed into UUIDs and JSON documents, and into floats when it
+    /// is `NaN` or an infinity. Array elements and struct fields are
+    /// converted recursively. Values that need no conversion are returned unchanged;
+    /// whether they fit the column is checked separately by
+    /// `ColumnType::accepts`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ParseError` if text cannot be parsed into the column
+    /// type.
+    pub fn coerce(self, column_type: &ColumnType) -> crate::error::Result<Self> {
+        Ok(match (column_type, self) {
+            (ColumnType::Float, Value::Integer(i)) => Value::Float(i as f64),
+            (ColumnType::Float, Value::Decimal(d)) => Value::Float(d.to_f64()),
+            (ColumnType::Decimal(_, scale), Value::Integer(i)) => {
+                Value::Decimal(Decimal::from(i).rescale(*scale, crate::decimal::RoundingMode::Down)?)
+            }
+            (ColumnType::Decimal(_, scale), Value::Decimal(d)) if d.scale() < *scale => {
+                Value::Decimal(d.rescale(*scale, crate::decimal::RoundingMode::Down)?)
+            }
+            (ColumnType::Float, Value::Text(text)) => match text.parse::<f64>() {
+                Ok(f) if !f.is_finite() => Value::Float(f),
+                _ => Value::Text(text),
+            },
+            (ColumnType::Json, Value::Text(text)) => Value::json_from_str(&text)?,
+            (ColumnType::Uuid, Value::Text(text)) => Value::Uuid(text.parse().map_err(|_| {
+                crate::error::DbError::ParseError(format!("Invalid UUID: {}", text))
+            })?),
+            (ColumnType::Array(element), Value::Array(values)) => Value::Array(
+                values
+                    .into_iter()
+                    .map(|v| v.coerce(element))
+                    .collect::<crate::error::Result<_>>()?,
+            ),
+            (ColumnType::Struct(fields), Value::Struct(values)) if fields.len() == values.len() => {
+                Value::Struct(
+                    fields
+                        .iter()
+                        .zip(values)
+                        .map(|(field, v)| v.coerce(&field.column_type))
+                        .collect::<crate::error::Result<_>>()?,
+                )
+            }
+            (_, value) => value,
+        })
+    }
+
+    /// Check if the value is NULL
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// Get the elements of an array, including arrays inside JSON documents
+    pub fn elements(&self) -> Option<Vec<Value>> {
+        match self {
+            Value::Array(values) => Some(values.clone()),
+            Value::Json(serde_json::Value::Array(values)) => {
+                Some(values.iter().map(Value::from_json).collect())
+            }
+            _ => None,
+        }
+    }
+
+    /// Convert a JSON value into the closest scalar `Value`
+    ///
+    /// Strings, numbers, booleans and null map to the matching scalar
+    /// variants; objects and arrays stay JSON documents.
+    pub fn from_json(json: &serde_json::Value) -> Self {
+        match json {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(b) => Value::Boolean(*b),
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(i) => Value::Integer(i),
+                None => Value::Float(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            serde_json::Value::String(s) => Value::Text(s.clone()),
+            other => Value::Json(other.clone()),
+        }
+    }
+
+    /// Parse text into a value of a column type
+    ///
+    /// This is how values are read from text formats such as CSV: numbers,
+    /// UUIDs and JSON documents are parsed, booleans accept `true`/`false`,
+    /// `t`/`f`, `yes`/`no` and `1`/`0`, blobs are hexadecimal with an
+    /// optional `0x` prefix, and arrays and structs are written as JSON.
+    /// Text and enum labels are taken as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::{ColumnType, Value};
+    ///
+    /// assert_eq!(Value::parse_as("42", &ColumnType::Integer).unwrap(), Value::Integer(42));
+    /// assert_eq!(Value::parse_as("yes", &ColumnType::Boolean).unwrap(), Value::Boolean(true));
+    /// assert!(Value::parse_as("4x", &ColumnType::Integer).is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ParseError` if the text is not a valid value of the
+    /// column type.
+    pub fn parse_as(text: &str, column_type: &ColumnType) -> crate::error::Result<Self> {
+        let invalid = || {
+            crate::error::DbError::ParseError(format!("Invalid {} value: '{}'", column_type, text))
+        };
+        let trimmed = text.trim();
+
+        let value = match column_type {
+            ColumnType::Integer => Value::Integer(trimmed.parse().map_err(|_| invalid())?),
+            ColumnType::Float => Value::Float(trimmed.parse().map_err(|_| invalid())?),
+            ColumnType::Decimal(_, _) => Value::Decimal(trimmed.parse().map_err(|_| invalid())?),
+            ColumnType::Text | ColumnType::Enum(_) => Value::Text(text.to_string()),
+            ColumnType::Boolean => match trimmed.to_lowercase().as_str() {
+                "true" | "t" | "yes" | "y" | "1" => Value::Boolean(true),
+                "false" | "f" | "no" | "n" | "0" => Value::Boolean(false),
+                _ => return Err(invalid()),
+            },
+            ColumnType::Blob => {
+                let hex = trimmed.strip_prefix("0x").unwrap_or(trimmed);
+                Value::Blob(crate::parser::decode_hex(hex).map_err(|_| invalid())?)
+            }
+            ColumnType::Uuid => Value::Uuid(trimmed.parse().map_err(|_| invalid())?),
+            ColumnType::Json => Value::json_from_str(text)?,
+            ColumnType::Array(_) | ColumnType::Struct(_) => {
+                let json: serde_json::Value = serde_json::from_str(text).map_err(|_| invalid())?;
+                return Value::from_json_as(&json, column_type);
+            }
+        };
+        value.coerce(column_type)
+    }
+
+    /// Convert JSON into a value of a column type
+    ///
+    /// This is the inverse of `Value::to_json`: structs are read from objects
+    /// keyed by field name (missing fields are NULL) or from arrays in field
+    /// order, and strings are parsed with `Value::parse_as`, so decimals,
+    /// UUIDs and hexadecimal blobs round-trip.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ParseError` if the JSON does not fit the column type.
+    pub fn from_json_as(json: &serde_json::Value, column_type: &ColumnType) -> crate::error::Result<Self> {
+        use serde_json::Value as Json;
+
+        let mismatch = || {
+            crate::error::DbError::ParseError(format!("Expected {} value, found {}", column_type, json))
+        };
+
+        let value = match (column_type, json) {
+            (_, Json::Null) => Value::Null,
+            (ColumnType::Json, json) => Value::Json(json.clone()),
+            (ColumnType::Integer, Json::Number(n)) => Value::Integer(n.as_i64().ok_or_else(mismatch)?),
+            (ColumnType::Float, Json::Number(n)) => Value::Float(n.as_f64().ok_or_else(mismatch)?),
+            (ColumnType::Decimal(_, _), Json::Number(n)) => {
+                Value::Decimal(n.to_string().parse().map_err(|_| mismatch())?)
+            }
+            (ColumnType::Boolean, Json::Bool(b)) => Value::Boolean(*b),
+            (ColumnType::Array(element), Json::Array(items)) => Value::Array(
+                items
+                    .iter()
+                    .map(|item| Value::from_json_as(item, element))
+                    .collect::<crate::error::Result<_>>()?,
+            ),
+            (ColumnType::Struct(fields), Json::Object(object)) => Value::Struct(
+                fields
+                    .iter()
+                    .map(|field| {
+                        let item = object.get(&field.name).unwrap_or(&Json::Null);
+                        Value::from_json_as(item, &field.column_type)
+                    })
+                    .collect::<crate::error::Result<_>>()?,
+            ),
+            (ColumnType::Struct(fields), Json::Array(items)) if items.len() == fields.len() => Value::Struct(
+                fields
+                    .iter()
+                    .zip(items)
+                    .map(|(field, item)| Value::from_json_as(item, &field.column_type))
+                    .collect::<crate::error::Result<_>>()?,
+            ),
+            (_, Json::String(text)) => return Value::parse_as(text, column_type),
+            _ => return Err(mismatch()),
+        };
+        value.coerce(column_type)
+    }
+
+    /// Convert the value into JSON, using the column type for field names
+    ///
+    /// Numbers and booleans map to JSON numbers and booleans, decimals to
+    /// strings so that no digits are lost, blobs to hexadecimal strings and
+    /// UUIDs to their hyphenated form. Structs become objects keyed by field
+    /// name; non-finite floats become null.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::{ColumnType, Value};
+    ///
+    /// let value = Value::Blob(vec![0, 255]);
+    /// assert_eq!(value.to_json(&ColumnType::Blob), serde_json::json!("00ff"));
+    /// ```
+    pub fn to_json(&self, column_type: &ColumnType) -> serde_json::Value {
+        match (self, column_type) {
+            (Value::Null, _) => serde_json::Value::Null,
+            (Value::Integer(i), _) => serde_json::Value::from(*i),
+            (Value::Float(f), _) => serde_json::Number::from_f64(*f)
+                .map(serde_json::Value::Number)
+                .unwrap_or(serde_json::Value::Null),
+            (Value::Decimal(d), _) => serde_json::Value::String(d.to_string()),
+            (Value::Text(s), _) => serde_json::Value::String(s.clone()),
+            (Value::Boolean(b), _) => serde_json::Value::Bool(*b),
+            (Value::Blob(bytes), _) => serde_json::Value::String(encode_hex(bytes)),
+            (Value::Uuid(u), _) => serde_json::Value::String(u.to_string()),
+            (Value::Json(json), _) => json.clone(),
+            (Value::Array(values), ColumnType::Array(element)) => {
+                values.iter().map(|v| v.to_json(element)).collect()
+            }
+            (Value::Struct(values), ColumnType::Struct(fields)) if fields.len() == values.len() => {
+                serde_json::Value::Object(
+                    fields
+                        .iter()
+                        .zip(values)
+                        .map(|(field, v)| (field.name.clone(), v.to_json(&field.column_type)))
+                        .collect(),
+                )
+            }
+            (Value::Array(values), _) | (Value::Struct(values), _) => {
+                values.iter().map(|v| v.to_json(&ColumnType::Json)).collect()
+            }
+        }
+    }
+
+    /// Compare two values
+    ///
+    /// Integers, floats and decimals compare by numeric value, exactly; other
+    /// values only compare with values of the same type. JSON documents
+    /// compare by their text with object keys sorted, and arrays and structs
+    /// element by element in the order of `total_cmp`, where NULL elements
+    /// come first. Returns `None` for values that cannot be compared,
+    /// including any comparison with NULL.
+    pub fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+            (Value::Float(a), Value::Float(b)) => Some(compare_floats(*a, *b)),
+            (Value::Decimal(a), Value::Decimal(b)) => Some(a.cmp(b)),
+            (Value::Integer(a), Value::Float(b)) => Some(compare_int_float(*a, *b)),
+            (Value::Float(a), Value::Integer(b)) => Some(compare_int_float(*b, *a).reverse()),
+            (Value::Integer(a), Value::Decimal(b)) => Some(Decimal::from(*a).cmp(b)),
+            (Value::Decimal(a), Value::Integer(b)) => Some(a.cmp(&Decimal::from(*b))),
+            (Value::Float(a), Value::Decimal(b)) => Some(compare_decimal_float(b, *a).reverse()),
+            (Value::Decimal(a), Value::Float(b)) => Some(compare_decimal_float(a, *b)),
+            (Value::Text(a), Value::Text(b)) => Some(a.cmp(b)),
+            (Value::Boolean(a), Value::Boolean(b)) => Some(a.cmp(b)),
+            (Value::Blob(a), Value::Blob(b)) => Some(a.cmp(b)),
+            (Value::Uuid(a), Value::Uuid(b)) => Some(a.cmp(b)),
+            (Value::Json(a), Value::Json(b)) if a == b => Some(Ordering::Equal),
+            (Value::Json(a), Value::Json(b)) => Some(canonical_json(a).cmp(&canonical_json(b))),
+            (Value::Array(a), Value::Array(b)) | (Value::Struct(a), Value::Struct(b)) => {
+                for (x, y) in a.iter().zip(b) {
+                    match x.total_cmp(y) {
+                        Ordering::Equal => continue,
+                        ordering => return Some(ordering),
+                    }
+                }
+                Some(a.len().cmp(&b.len()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Compare two values in a total order
+    ///
+    /// NULL comes first, then values of different types in a fixed order:
+    /// booleans, numbers, text, blobs, JSON documents, arrays, structs and
+    /// UUIDs. Values of the same kind are ordered by `compare`. Indexes and
+    /// sorts use this order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::Value;
+    /// use std::cmp::Ordering;
+    ///
+    /// let a = Value::Json(serde_json::json!({"a": 1, "b": 2}));
+    /// let b = Value::Json(serde_json::json!({"b": 2, "a": 1}));
+    /// assert_eq!(a.total_cmp(&b), Ordering::Equal);
+    /// assert_eq!(Value::Null.total_cmp(&Value::Integer(1)), Ordering::Less);
+    /// ```
+    pub fn total_cmp(&self, other: &Value) -> Ordering {
+        self.rank()
+            .cmp(&other.rank())
+            .then_with(|| self.compare(other).unwrap_or(Ordering::Equal))
+    }
+
+    /// Check whether two values are the same value of the same type
+    ///
+    /// Unlike `==`, a NaN is identical to itself, and unlike `total_cmp`,
+    /// `Integer(1)` differs from `Float(1.0)` and the decimal `1.0` from
+    /// `1.00`.
+    pub(crate) fn is_identical(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Float(a), Value::Float(b)) => a.to_bits() == b.to_bits(),
+            (Value::Decimal(a), Value::Decimal(b)) => a.mantissa() == b.mantissa() && a.scale() == b.scale(),
+            (Value::Array(a), Value::Array(b)) | (Value::Struct(a), Value::Struct(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.is_identical(b))
+            }
+            (a, b) => a == b,
+        }
+    }
+
+    /// Position of the value's kind in the order of `total_cmp`
+    fn rank(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::Boolean(_) => 1,
+            Value::Integer(_) | Value::Float(_) | Value::Decimal(_) => 2,
+            Value::Text(_) => 3,
+            Value::Blob(_) => 4,
+            Value::Json(_) => 5,
+            Value::Array(_) => 6,
+            Value::Struct(_) => 7,
+            Value::Uuid(_) => 8,
+        }
+    }
+}
+
+/// Format values as a comma-separated list
+fn join_values(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Encode bytes as lowercase hexadecimal
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Order a NaN after every number, or before them if its sign bit is set
+fn nan_order(f: f64) -> Ordering {
+    if f.is_sign_negative() {
+        Ordering::Greater
+    } else {
+        Ordering::Less
+    }
+}
+
+/// Compare two floats with both zeros equal and NaN placed as in `nan_order`
+fn compare_floats(a: f64, b: f64) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (false, false) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        (true, true) => b.is_sign_negative().cmp(&a.is_sign_negative()),
+        (false, true) => nan_order(b),
+        (true, false) => nan_order(a).reverse(),
+    }
+}
+
+/// Compare an integer and a float exactly, without rounding the integer
+fn compare_int_float(i: i64, f: f64) -> Ordering {
+    if f.is_nan() {
+        return nan_order(f);
+    }
+    if f >= i64::MAX as f64 {
+        return Ordering::Less;
+    }
+    if f < i64::MIN as f64 {
+        return Ordering::Greater;
+    }
+
+    let whole = f.trunc();
+    match i.cmp(&(whole as i64)) {
+        Ordering::Equal => 0f64.total_cmp(&(f - whole)),
+        ordering => ordering,
+    }
+}
+
+/// Compare a decimal and a float exactly, without rounding either
+///
+/// The integer parts are compared first; when they are equal, the digits of
+/// the fractional parts are, using the exact decimal expansion of the float.
+fn compare_decimal_float(d: &Decimal, f: f64) -> Ordering {
+    if f.is_nan() {
+        return nan_order(f);
+    }
+    let sign = |negative: bool, zero: bool| if zero { 0 } else if negative { -1 } else { 1 };
+    let (d_sign, f_sign) = (sign(d.is_negative(), d.is_zero()), sign(f < 0.0, f == 0.0));
+    if d_sign != f_sign || d_sign == 0 {
+        return d_sign.cmp(&f_sign);
+    }
+
+    // Compare magnitudes, then flip the result for negative values
+    let magnitude = f.abs();
+    let ordering = if magnitude >= 2f64.powi(127) {
+        // Beyond every decimal, which has at most 38 digits
+        Ordering::Less
+    } else {
+        let unit = 10u128.pow(d.scale());
+        let mantissa = d.mantissa().unsigned_abs();
+        let whole = magnitude.trunc();
+        (mantissa / unit).cmp(&(whole as u128)).then_with(|| {
+            let d_fraction = format!("{:0width$}", mantissa % unit, width = d.scale() as usize);
+            let f_fraction = format!("{:.1074}", magnitude - whole);
+            d_fraction
+                .trim_end_matches('0')
+                .cmp(f_fraction[2..].trim_end_matches('0'))
+        })
+    };
+    if d_sign < 0 {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+/// Write a JSON document as text with the keys of every object sorted
+///
+/// Documents that are equal, whatever the order of their keys, produce the
+/// same text.
+fn canonical_json(value: &serde_json::Value) -> String {
+    fn write(value: &serde_json::Value, out: &mut String) {
+        match value {
+            serde_json::Value::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    write(item, out);
+                }
+                out.push(']');
+            }
+            serde_json::Value::Object(map) => {
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                out.push('{');
+                for (i, (key, item)) in entries.into_iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    out.push_str(&serde_json::Value::from(key.as_str()).to_string());
+                    out.push(':');
+                    write(item, out);
+                }
+                out.push('}');
+            }
+            scalar => out.push_str(&scalar.to_string()),
+        }
+    }
+    let mut out = String::new();
+    write(value, &mut out);
+    out
+}
+
+/// Serialization for JSON documents
+///
+/// Binary formats such as bincode cannot deserialize a self-describing
+/// `serde_json::Value`, so documents are stored as JSON text there and as
+/// native JSON in human-readable formats.
+mod json_document {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        value: &serde_json::Value,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            value.serialize(serializer)
+        } else {
+            serializer.serialize_str(&value.to_string())
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<serde_json::Value, D::Error> {
+        if deserializer.is_human_readable() {
+            serde_json::Value::deserialize(deserializer)
+        } else {
+            let text = String::deserialize(deserializer)?;
+            serde_json::from_str(&text).map_err(serde::de::Error::custom)
+        }
+    }
+}
+
+/// Deepest nesting of arrays, structs and composite keys that is read back
+pub(crate) const MAX_NESTING: usize = 128;
+
+/// Deserialization of the nested parts of values, keys and column types
+///
+/// Every level counts against `MAX_NESTING`, so a crafted file or document
+/// fails to load instead of overflowing the stack.
+mod nested {
+    use super::MAX_NESTING;
+    use serde::{de, Deserialize, Deserializer};
+    use std::cell::Cell;
+
+    thread_local! {
+        static DEPTH: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Leaves a level when dropped, whether or not deserialization succeeded
+    struct Level;
+
+    impl Drop for Level {
+        fn drop(&mut self) {
+            DEPTH.with(|depth| depth.set(depth.get() - 1));
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<T, D::Error> {
+        let depth = DEPTH.with(|depth| {
+            depth.set(depth.get() + 1);
+            depth.get()
+        });
+        let _level = Level;
+        if depth > MAX_NESTING {
+            return Err(de::Error::custom(format!("nested deeper than {} levels", MAX_NESTING)));
+        }
+        T::deserialize(deserializer)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<Decimal> for Value {
+    fn from(value: Decimal) -> Self {
+        Value::Decimal(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Text(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Text(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Boolean(value)
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(value: Vec<u8>) -> Self {
+        Value::Blob(value)
+    }
+}
+
+impl From<Uuid> for Value {
+    fn from(value: Uuid) -> Self {
+        Value::Uuid(value)
+    }
+}
+
+impl From<serde_json::Value> for Value {
+    fn from(value: serde_json::Value) -> Self {
+        Value::Json(value)
+    }
 }
 
 impl fmt::Display for Value {
@@ -43,17 +692,29 @@ impl fmt::Display for Value {
             Value::Null => write!(f, "NULL"),
             Value::Integer(i) => write!(f, "{}", i),
             Value::Float(fl) => write!(f, "{}", fl),
-            Value::Text(s) => write!(f, "\"{}\"", s),
+            Value::Decimal(d) => write!(f, "{}", d),
+            Value::Text(s) => write!(f, "\"{}\"", s.replace('"', "\"\"")),
             Value::Boolean(b) => write!(f, "{}", b),
             Value::Blob(b) => write!(f, "<BLOB: {} bytes>", b.len()),
+            Value::Uuid(u) => write!(f, "{}", u),
+            Value::Json(j) => write!(f, "{}", j),
+            Value::Array(values) => write!(f, "[{}]", join_values(values)),
+            Value::Struct(values) => write!(f, "{{{}}}", join_values(values)),
         }
     }
 }
 
 /// Type for database keys
 ///
-/// A `Key` is a wrapper around a string that uniquely identifies a row in a table.
-/// Keys are used to insert, retrieve, update, and delete rows.
+/// A `Key` uniquely identifies a row in a table. Keys are used to insert,
+/// retrieve, update, and delete rows. Keys can be integers, text, raw bytes,
+/// UUIDs, or composite tuples of other keys, and are ordered by the natural
+/// order of their type, so integer keys sort numerically (`2` before `10`).
+/// Keys of different kinds sort in the order the variants are declared.
+///
+/// Every key also has an order-preserving binary encoding (see
+/// `Key::encode`): comparing two encodings byte by byte gives the same
+/// result as comparing the keys.
 ///
 /// # Examples
 ///
@@ -67,28 +728,299 @@ impl fmt::Display for Value {
 ///
 /// // From a String
 /// let key2 = Key::from(String::from("user_2"));
+///
+/// // A freshly generated UUID
+/// let key3 = Key::generate_uuid();
+///
+/// // Integers sort numerically
+/// assert!(Key::from(2) < Key::from(10));
+///
+/// // Composite keys sort element by element
+/// let key4 = Key::Composite(vec![Key::from("orders"), Key::from(42)]);
 /// ```
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Key(pub String);
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Key {
+    /// A signed 64-bit integer key
+    Integer(i64),
+    /// A UTF-8 text key
+    Text(String),
+    /// A raw byte string key
+    Bytes(Vec<u8>),
+    /// A UUID key
+    Uuid(Uuid),
+    /// A tuple of keys, ordered element by element
+    Composite(#[serde(deserialize_with = "nested::deserialize")] Vec<Key>),
+}
+
+/// Tags written before each key in the binary encoding, in variant order
+const KEY_TAG_END: u8 = 0x00;
+const KEY_TAG_INTEGER: u8 = 0x01;
+const KEY_TAG_TEXT: u8 = 0x02;
+const KEY_TAG_BYTES: u8 = 0x03;
+const KEY_TAG_UUID: u8 = 0x04;
+const KEY_TAG_COMPOSITE: u8 = 0x05;
+
+impl Key {
+    /// Create a key from a column value
+    ///
+    /// Integers, text, blobs and UUIDs map to the matching key kinds. Other
+    /// values, including NULL, cannot be used as keys.
+    pub fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Integer(i) => Some(Key::Integer(*i)),
+            Value::Text(s) => Some(Key::Text(s.clone())),
+            Value::Blob(b) => Some(Key::Bytes(b.clone())),
+            Value::Uuid(u) => Some(Key::Uuid(*u)),
+            _ => None,
+        }
+    }
+
+    /// Parse a key written in the query language
+    ///
+    /// Integers become integer keys; quoted strings and bare words become
+    /// text keys, blobs (`x'00ff'`) become byte keys, strings after `UUID`
+    /// become UUID keys, and parenthesized lists become composite keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::Key;
+    ///
+    /// assert_eq!(Key::parse("42").unwrap(), Key::from(42));
+    /// assert_eq!(Key::parse("'42'").unwrap(), Key::from("42"));
+    /// assert!(matches!(Key::parse("UUID '6f9619ff-8b86-d011-b42d-00cf4fc964ff'").unwrap(), Key::Uuid(_)));
+    /// assert_eq!(
+    ///     Key::parse("('orders', 7)").unwrap(),
+    ///     Key::Composite(vec![Key::from("orders"), Key::from(7)])
+    /// );
+    /// ```
+    pub fn parse(input: &str) -> crate::error::Result<Self> {
+        let mut parser = crate::parser::Parser::new(input)?;
+        let key = parser.parse_key()?;
+        parser.expect_end()?;
+        Ok(key)
+    }
+
+    /// Convert the key into JSON
+    ///
+    /// Integers become numbers, text and UUIDs strings, bytes hexadecimal
+    /// strings and composite keys arrays of their parts.
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Key::Integer(i) => serde_json::Value::from(*i),
+            Key::Text(s) => serde_json::Value::String(s.clone()),
+            Key::Bytes(bytes) => serde_json::Value::String(encode_hex(bytes)),
+            Key::Uuid(u) => serde_json::Value::String(u.to_string()),
+            Key::Composite(parts) => parts.iter().map(Key::to_json).collect(),
+        }
+    }
+
+    /// Generate a key from a new random (version 4) UUID
+    pub fn generate_uuid() -> Self {
+        Key::Uuid(Uuid::new_v4())
+    }
+
+    /// Encode the key into bytes that sort in the same order as the key
+    ///
+    /// Integers are written big-endian with the sign bit flipped. Text and
+    /// bytes escape `0x00` as `0x00 0xFF` and end with `0x00 0x01`, so that a
+    /// prefix sorts before any longer string. Composite keys write their
+    /// elements one after another, followed by a single `0x00` terminator.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_into(&mut out);
+        out
+    }
+
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        match self {
+            Key::Integer(i) => {
+                out.push(KEY_TAG_INTEGER);
+                out.extend_from_slice(&((*i as u64) ^ (1 << 63)).to_be_bytes());
+            }
+            Key::Text(s) => {
+                out.push(KEY_TAG_TEXT);
+                encode_escaped(s.as_bytes(), out);
+            }
+            Key::Bytes(b) => {
+                out.push(KEY_TAG_BYTES);
+                encode_escaped(b, out);
+            }
+            Key::Uuid(u) => {
+                out.push(KEY_TAG_UUID);
+                out.extend_from_slice(u.as_bytes());
+            }
+            Key::Composite(parts) => {
+                out.push(KEY_TAG_COMPOSITE);
+                for part in parts {
+                    part.encode_into(out);
+                }
+                out.push(KEY_TAG_END);
+            }
+        }
+    }
+
+    /// Decode a key produced by `Key::encode`
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ParseError` if the bytes are not a valid encoding.
+    pub fn decode(bytes: &[u8]) -> crate::error::Result<Self> {
+        let (key, rest) = Self::decode_from(bytes, 0)?;
+        if !rest.is_empty() {
+            return Err(invalid_key_encoding());
+        }
+        Ok(key)
+    }
+
+    /// Decode one key, `depth` levels inside composite keys
+    fn decode_from(bytes: &[u8], depth: usize) -> crate::error::Result<(Self, &[u8])> {
+        let (&tag, rest) = bytes.split_first().ok_or_else(invalid_key_encoding)?;
+        match tag {
+            KEY_TAG_INTEGER => {
+                if rest.len() < 8 {
+                    return Err(invalid_key_encoding());
+                }
+                let (raw, rest) = rest.split_at(8);
+                let mut buf = [0u8; 8];
+                buf.copy_from_slice(raw);
+                let i = (u64::from_be_bytes(buf) ^ (1 << 63)) as i64;
+                Ok((Key::Integer(i), rest))
+            }
+            KEY_TAG_TEXT => {
+                let (raw, rest) = decode_escaped(rest)?;
+                let s = String::from_utf8(raw).map_err(|_| invalid_key_encoding())?;
+                Ok((Key::Text(s), rest))
+            }
+            KEY_TAG_BYTES => {
+                let (raw, rest) = decode_escaped(rest)?;
+                Ok((Key::Bytes(raw), rest))
+            }
+            KEY_TAG_UUID => {
+                if rest.len() < 16 {
+                    return Err(invalid_key_encoding());
+                }
+                let (raw, rest) = rest.split_at(16);
+                let u = Uuid::from_slice(raw).map_err(|_| invalid_key_encoding())?;
+                Ok((Key::Uuid(u), rest))
+            }
+            KEY_TAG_COMPOSITE if depth >= MAX_NESTING => Err(invalid_key_encoding()),
+            KEY_TAG_COMPOSITE => {
+                let mut parts = Vec::new();
+                let mut rest = rest;
+                loop {
+                    match rest.first() {
+                        Some(&KEY_TAG_END) => return Ok((Key::Composite(parts), &rest[1..])),
+                        Some(_) => {
+                            let (part, remaining) = Self::decode_from(rest, depth + 1)?;
+                            parts.push(part);
+                            rest = remaining;
+                        }
+                        None => return Err(invalid_key_encoding()),
+                    }
+                }
+            }
+            _ => Err(invalid_key_encoding()),
+        }
+    }
+}
+
+fn invalid_key_encoding() -> crate::error::DbError {
+    crate::error::DbError::ParseError("Invalid key encoding".to_string())
+}
+
+/// Write bytes with `0x00` escaped, followed by the `0x00 0x01` terminator
+fn encode_escaped(bytes: &[u8], out: &mut Vec<u8>) {
+    for &b in bytes {
+        out.push(b);
+        if b == 0x00 {
+            out.push(0xFF);
+        }
+    }
+    out.extend_from_slice(&[0x00, 0x01]);
+}
+
+/// Read bytes written by `encode_escaped`, returning them and the remainder
+fn decode_escaped(bytes: &[u8]) -> crate::error::Result<(Vec<u8>, &[u8])> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == 0x00 {
+            match bytes.get(i + 1) {
+                Some(0xFF) => out.push(0x00),
+                Some(0x01) => return Ok((out, &bytes[i + 2..])),
+                _ => return Err(invalid_key_encoding()),
+            }
+            i += 2;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Err(invalid_key_encoding())
+}
+
+impl From<Uuid> for Key {
+    /// Create a Key from a UUID
+    fn from(u: Uuid) -> Self {
+        Key::Uuid(u)
+    }
+}
 
 impl From<&str> for Key {
     /// Create a Key from a string slice
     fn from(s: &str) -> Self {
-        Key(s.to_string())
+        Key::Text(s.to_string())
     }
 }
 
 impl From<String> for Key {
     /// Create a Key from a String
     fn from(s: String) -> Self {
-        Key(s)
+        Key::Text(s)
+    }
+}
+
+impl From<i64> for Key {
+    /// Create a Key from an integer
+    fn from(i: i64) -> Self {
+        Key::Integer(i)
+    }
+}
+
+impl From<Vec<u8>> for Key {
+    /// Create a Key from raw bytes
+    fn from(b: Vec<u8>) -> Self {
+        Key::Bytes(b)
+    }
+}
+
+impl From<Vec<Key>> for Key {
+    /// Create a composite Key from its parts
+    fn from(parts: Vec<Key>) -> Self {
+        Key::Composite(parts)
     }
 }
 
 impl fmt::Display for Key {
-    /// Format the Key as a string
+    /// Format the Key for display
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
+        match self {
+            Key::Integer(i) => write!(f, "{}", i),
+            Key::Text(s) => write!(f, "{}", s),
+            Key::Bytes(b) => {
+                write!(f, "0x")?;
+                for byte in b {
+                    write!(f, "{:02x}", byte)?;
+                }
+                Ok(())
+            }
+            Key::Uuid(u) => write!(f, "{}", u),
+            Key::Composite(parts) => {
+                let parts: Vec<String> = parts.iter().map(|p| p.to_string()).collect();
+                write!(f, "({})", parts.join(", "))
+            }
+        }
     }
 }
 
@@ -153,6 +1085,9 @@ pub struct Column {
     pub name: String,
     /// The data type of the column
     pub column_type: ColumnType,
+    /// Whether the column is filled from an increasing counter when no
+    /// value is supplied
+    pub auto_increment: bool,
 }
 
 impl Column {
@@ -170,15 +1105,53 @@ impl Column {
         Self {
             name: name.into(),
             column_type,
+            auto_increment: false,
         }
     }
+
+    /// Mark the column as auto-incrementing
+    ///
+    /// Only an `Integer` primary key column can be auto-incrementing. When a
+    /// row is inserted with `Table::insert_auto` and a NULL value for this
+    /// column, the next value of the table's counter is used as both the
+    /// column value and the row key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::{Column, ColumnType};
+    ///
+    /// let id = Column::new("id", ColumnType::Integer).auto_increment();
+    /// assert!(id.auto_increment);
+    /// ```
+    pub fn auto_increment(mut self) -> Self {
+        self.auto_increment = true;
+        self
+    }
+
+    /// Create a new enum column that only accepts the given labels
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::Column;
+    ///
+    /// let status = Column::new_enum("status", &["active", "suspended", "closed"]);
+    /// ```
+    pub fn new_enum(name: impl Into<String>, labels: &[&str]) -> Self {
+        Self::new(
+            name,
+            ColumnType::Enum(labels.iter().map(|l| l.to_string()).collect()),
+        )
+    }
 }
 
 /// Supported column types in the database
 ///
 /// The `ColumnType` enum defines the possible data types that can be used
 /// for columns in a table. Each column must have a defined type, which is
-/// used to validate data when inserting or updating rows.
+/// used to validate data when inserting or updating rows. As with `Value`,
+/// new variants are only ever added at the end.
 ///
 /// # Examples
 ///
@@ -191,8 +1164,20 @@ impl Column {
 ///     Column::new("id", ColumnType::Integer),
 ///     Column::new("name", ColumnType::Text),
 ///     Column::new("salary", ColumnType::Float),
+///     Column::new("balance", ColumnType::Decimal(12, 2)),
 ///     Column::new("active", ColumnType::Boolean),
 ///     Column::new("photo", ColumnType::Blob),
+///     Column::new("external_id", ColumnType::Uuid),
+///     Column::new("status", ColumnType::Enum(vec!["active".into(), "closed".into()])),
+///     Column::new("profile", ColumnType::Json),
+///     Column::new("tags", ColumnType::Array(Box::new(ColumnType::Text))),
+///     Column::new(
+///         "address",
+///         ColumnType::Struct(vec![
+///             Column::new("city", ColumnType::Text),
+///             Column::new("zip", ColumnType::Integer),
+///         ]),
+///     ),
 /// ];
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -207,4 +1192,124 @@ pub enum ColumnType {
     Boolean,
     /// Binary data type (`Vec<u8>`)
     Blob,
+    /// Exact decimal type with a total number of digits (precision) and a
+    /// number of fractional digits (scale)
+    Decimal(u32, u32),
+    /// JSON document type (`serde_json::Value`)
+    Json,
+    /// List of values that all have the element type
+    Array(#[serde(deserialize_with = "nested::deserialize")] Box<ColumnType>),
+    /// Record with named, typed fields
+    Struct(#[serde(deserialize_with = "nested::deserialize")] Vec<Column>),
+    /// UUID type (`uuid::Uuid`)
+    Uuid,
+    /// Text restricted to a fixed set of labels
+    Enum(Vec<String>),
+}
+
+impl fmt::Display for ColumnType {
+    /// Format the type the way it is written in a schema, e.g. `DECIMAL(12, 2)`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnType::Integer => write!(f, "INTEGER"),
+            ColumnType::Float => write!(f, "FLOAT"),
+            ColumnType::Decimal(precision, scale) => write!(f, "DECIMAL({}, {})", precision, scale),
+            ColumnType::Text => write!(f, "TEXT"),
+            ColumnType::Boolean => write!(f, "BOOLEAN"),
+            ColumnType::Blob => write!(f, "BLOB"),
+            ColumnType::Uuid => write!(f, "UUID"),
+            ColumnType::Enum(labels) => {
+                let labels: Vec<String> = labels
+                    .iter()
+                    .map(|l| format!("'{}'", l.replace('\'', "''")))
+                    .collect();
+                write!(f, "ENUM({})", labels.join(", "))
+            }
+            ColumnType::Json => write!(f, "JSON"),
+            ColumnType::Array(element) => write!(f, "ARRAY({})", element),
+            ColumnType::Struct(fields) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|c| format!("{} {}", c.name, c.column_type))
+                    .collect();
+                write!(f, "STRUCT({})", fields.join(", "))
+            }
+        }
+    }
+}
+
+impl ColumnType {
+    /// Parse a column type written the way it is displayed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use oxi_db::ColumnType;
+    ///
+    /// assert_eq!(ColumnType::parse("DECIMAL(10, 2)").unwrap(), ColumnType::Decimal(10, 2));
+    /// assert_eq!(
+    ///     ColumnType::parse("array(text)").unwrap(),
+    ///     ColumnType::Array(Box::new(ColumnType::Text))
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `DbError::ParseError` if the text is not a column type.
+    pub fn parse(input: &str) -> crate::error::Result<Self> {
+        let mut parser = crate::parser::Parser::new(input)?;
+        let column_type = parser.parse_column_type()?;
+        parser.expect_end()?;
+        Ok(column_type)
+    }
+
+    /// Check that the type is well-formed, describing the first problem
+    ///
+    /// A `DECIMAL` needs a precision of 1 to 38 digits and a scale no larger
+    /// than its precision. Array elements and struct fields are checked too.
+    pub(crate) fn validate(&self) -> std::result::Result<(), String> {
+        match self {
+            ColumnType::Decimal(precision, scale)
+                if *precision == 0 || *precision > MAX_PRECISION || scale > precision =>
+            {
+                Err(format!(
+                    "Invalid type {}: the precision must be 1 to {} and the scale at most the precision",
+                    self, MAX_PRECISION
+                ))
+            }
+            ColumnType::Array(element) => element.validate(),
+            ColumnType::Struct(fields) => fields.iter().try_for_each(|field| field.column_type.validate()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check if a value can be stored in a column of this type
+    ///
+    /// NULL is accepted for every type, including inside arrays and structs.
+    /// Array elements and struct fields are checked recursively.
+    pub fn accepts(&self, value: &Value) -> bool {
+        match (self, value) {
+            (_, Value::Null) => true,
+            (ColumnType::Integer, Value::Integer(_)) => true,
+            (ColumnType::Float, Value::Float(_)) => true,
+            (ColumnType::Decimal(precision, scale), Value::Decimal(d)) => d.fits(*precision, *scale),
+            (ColumnType::Text, Value::Text(_)) => true,
+            (ColumnType::Boolean, Value::Boolean(_)) => true,
+            (ColumnType::Blob, Value::Blob(_)) => true,
+            (ColumnType::Uuid, Value::Uuid(_)) => true,
+            (ColumnType::Enum(labels), Value::Text(label)) => labels.contains(label),
+            (ColumnType::Json, Value::Json(_)) => true,
+            (ColumnType::Array(element), Value::Array(values)) => {
+                values.iter().all(|v| element.accepts(v))
+            }
+            (ColumnType::Struct(fields), Value::Struct(values)) => {
+                fields.len() == values.len()
+                    && fields
+                        .iter()
+                        .zip(values)
+                        .all(|(field, v)| field.column_type.accepts(v))
+            }
+            _ => false,
+        }
+    }
 }
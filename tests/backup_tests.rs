@@ -0,0 +1,115 @@
+use oxi_db::{BackupProgress, Column, ColumnType, Database, DbError, Key, Table, Value};
+use std::fs;
+
+fn create_test_db(db_name: &str, rows: i64) -> Database {
+    let db_path = format!("{}.db", db_name);
+    fs::remove_file(&db_path).unwrap_or(());
+
+    let mut db = Database::new(&db_path);
+    let columns = vec![
+        Column::new("id", ColumnType::Integer).auto_increment(),
+        Column::new("note", ColumnType::Text),
+    ];
+    db.create_table("notes", columns, Some("id".to_string())).unwrap();
+    for i in 0..rows {
+        db.get_table_mut("notes")
+            .unwrap()
+            .insert_auto(vec![Value::Null, Value::Text(format!("note {} {}", i, "x".repeat(100)))])
+            .unwrap();
+    }
+    db.create_sequence("tickets", 1, 1).unwrap();
+    db
+}
+
+#[test]
+fn test_backup_is_a_working_copy() {
+    let mut db = create_test_db("test_backup_copy", 10);
+    let backup_path = "test_backup_copy_backup/notes.db";
+    fs::remove_dir_all("test_backup_copy_backup").unwrap_or(());
+
+    db.backup_to(backup_path).unwrap();
+    db.verify_backup(backup_path).unwrap();
+
+    // Writes to the backup go to the backup, not to the original
+    let mut backup = Database::open(backup_path).unwrap();
+    assert_eq!(backup.get_table("notes").unwrap().len(), 10);
+    backup.delete("notes", &Key::from(1)).unwrap();
+    assert!(Database::load_into_memory("test_backup_copy.db").unwrap().get("notes", &Key::from(1)).is_ok());
+    assert_eq!(Database::load_into_memory(backup_path).unwrap().get_table("notes").unwrap().len(), 9);
+
+    // The original stays open and usable after the backup
+    db.next_value("tickets").unwrap();
+    assert!(db.verify_backup(backup_path).is_err());
+
+    // Clean up
+    fs::remove_file("test_backup_copy.db").unwrap_or(());
+    fs::remove_dir_all("test_backup_copy_backup").unwrap_or(());
+}
+
+#[test]
+fn test_backup_reports_progress() {
+    let db = create_test_db("test_backup_progress", 2000);
+    let backup_path = "test_backup_progress_copy.db";
+
+    let mut reports: Vec<BackupProgress> = Vec::new();
+    db.backup_with_progress(backup_path, |progress| reports.push(progress)).unwrap();
+
+    // A large database is written in several chunks
+    assert!(reports.len() > 1, "{:?}", reports);
+    assert!(reports.windows(2).all(|pair| pair[0].bytes_written < pair[1].bytes_written));
+    let last = reports.last().unwrap();
+    assert_eq!(last.bytes_written, last.total_bytes);
+    assert_eq!(last.fraction(), 1.0);
+    assert_eq!(last.total_bytes, fs::metadata(backup_path).unwrap().len());
+    db.verify_backup(backup_path).unwrap();
+
+    // Clean up
+    fs::remove_file("test_backup_progress.db").unwrap_or(());
+    fs::remove_file(backup_path).unwrap_or(());
+}
+
+#[test]
+fn test_verify_backup_detects_differences() {
+    let mut db = create_test_db("test_backup_verify", 3);
+    let backup_path = "test_backup_verify_copy.db";
+    db.backup_to(backup_path).unwrap();
+
+    let new_row = vec![Value::Integer(1), Value::from("changed")];
+    db.update("notes", &Key::from(1), new_row).unwrap();
+    match db.verify_backup(backup_path) {
+        Err(DbError::Other(message)) => assert!(message.contains("different rows"), "{}", message),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    db.insert_auto("notes", vec![Value::Null, Value::from("more")]).unwrap();
+    match db.verify_backup(backup_path) {
+        Err(DbError::Other(message)) => assert!(message.contains("has 3 rows, expected 4"), "{}", message),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    db.drop_table("notes").unwrap();
+    assert!(matches!(db.verify_backup(backup_path), Err(DbError::Other(_))));
+    assert!(matches!(db.verify_backup("test_backup_missing.db"), Err(DbError::IoError(_))));
+
+    // Clean up
+    fs::remove_file("test_backup_verify.db").unwrap_or(());
+    fs::remove_file(backup_path).unwrap_or(());
+}
+
+#[test]
+fn test_table_checksum() {
+    let columns = vec![Column::new("name", ColumnType::Text)];
+    let mut first = Table::new("t", columns.clone(), None);
+    let mut second = Table::new("t", columns, None);
+    assert_eq!(first.checksum().unwrap(), second.checksum().unwrap());
+
+    // Insertion order does not matter, only the rows
+    first.insert(1, vec![Value::from("a")]).unwrap();
+    first.insert(2, vec![Value::from("b")]).unwrap();
+    second.insert(2, vec![Value::from("b")]).unwrap();
+    second.insert(1, vec![Value::from("a")]).unwrap();
+    assert_eq!(first.checksum().unwrap(), second.checksum().unwrap());
+
+    second.update(&Key::from(2), vec![Value::from("c")]).unwrap();
+    assert_ne!(first.checksum().unwrap(), second.checksum().unwrap());
+}
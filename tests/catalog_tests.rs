@@ -0,0 +1,153 @@
+use oxi_db::{
+    Column, ColumnType, Compression, CsvOptions, Database, DbError, Expr, Filter, Key, Value, CATALOG_TABLES,
+};
+use std::fs;
+
+fn create_test_db(db_name: &str) -> Database {
+    let db_path = format!("{}.db", db_name);
+    fs::remove_file(&db_path).unwrap_or(());
+
+    let mut db = Database::new(&db_path);
+    let columns = vec![
+        Column::new("id", ColumnType::Integer).auto_increment(),
+        Column::new("name", ColumnType::Text),
+        Column::new("tags", ColumnType::Array(Box::new(ColumnType::Text))),
+    ];
+    db.create_table("users", columns, Some("id".to_string())).unwrap();
+    db.create_table("logs", vec![Column::new("line", ColumnType::Text)], None).unwrap();
+    db.insert_auto("users", vec![Value::Null, Value::from("Alice"), Value::Array(vec![])]).unwrap();
+    db.insert_auto("users", vec![Value::Null, Value::from("Bob"), Value::Array(vec![])]).unwrap();
+    db.create_index("users", "users_name", Expr::column("name")).unwrap();
+    db.create_sequence("tickets", 100, 10).unwrap();
+    db
+}
+
+fn text(value: &Value) -> &str {
+    match value {
+        Value::Text(text) => text,
+        other => panic!("expected text, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_catalog_describes_the_schema() {
+    let db = create_test_db("test_catalog_schema");
+
+    let tables = db.get_table("oxi_tables").unwrap();
+    let users = tables.get(&Key::from("users")).unwrap();
+    assert_eq!(
+        users.values,
+        vec![
+            Value::from("users"),
+            Value::Integer(3),
+            Value::Integer(2),
+            Value::from("id"),
+            Value::Integer(1),
+            Value::from("none"),
+        ]
+    );
+    assert_eq!(tables.get(&Key::from("logs")).unwrap().values[3], Value::Null);
+
+    let filter = Filter::parse("table = 'users' AND primary_key = true").unwrap();
+    let columns = db.find_where("oxi_columns", &filter).unwrap();
+    assert_eq!(columns.len(), 1);
+    assert_eq!(columns[0].0, Key::Composite(vec![Key::from("users"), Key::Integer(0)]));
+    assert_eq!(columns[0].1.values[5], Value::Boolean(true));
+    let tags = db
+        .get("oxi_columns", &Key::Composite(vec![Key::from("users"), Key::Integer(2)]))
+        .unwrap();
+    assert_eq!(text(&tags.values[3]), "ARRAY(TEXT)");
+
+    let indexes = db.get_table("oxi_indexes").unwrap().get_all();
+    assert_eq!(indexes.len(), 1);
+    assert_eq!(indexes[0].1.values[..3], [Value::from("users"), Value::from("users_name"), Value::from("name")]);
+
+    let tickets = db.get("oxi_sequences", &Key::from("tickets")).unwrap();
+    assert_eq!(tickets.values[1..], [Value::Integer(100), Value::Integer(10), Value::Null]);
+
+    // Clean up
+    fs::remove_file("test_catalog_schema.db").unwrap_or(());
+}
+
+#[test]
+fn test_catalog_follows_changes() {
+    let mut db = create_test_db("test_catalog_changes");
+    let rows = |db: &Database, table: &str| db.get("oxi_tables", &Key::from(table)).unwrap().values[2].clone();
+    assert_eq!(rows(&db, "users"), Value::Integer(2));
+
+    db.insert_auto("users", vec![Value::Null, Value::from("Carol"), Value::Array(vec![])]).unwrap();
+    db.set_compression("users", Compression::Lz4).unwrap();
+    assert_eq!(rows(&db, "users"), Value::Integer(3));
+    assert_eq!(db.get("oxi_tables", &Key::from("users")).unwrap().values[5], Value::from("lz4"));
+
+    db.drop_index("users", "users_name").unwrap();
+    db.drop_table("logs").unwrap();
+    assert!(db.get_table("oxi_indexes").unwrap().is_empty());
+    assert_eq!(db.get_table("oxi_tables").unwrap().len(), 1);
+    assert_eq!(db.get_table("oxi_columns").unwrap().len(), 3);
+
+    db.next_value("tickets").unwrap();
+    let tickets = db.get("oxi_sequences", &Key::from("tickets")).unwrap();
+    assert_eq!(tickets.values[1..], [Value::Integer(110), Value::Integer(10), Value::Integer(100)]);
+
+    // A reopened database builds its catalog from the file
+    drop(db);
+    let db = Database::open("test_catalog_changes.db").unwrap();
+    assert_eq!(rows(&db, "users"), Value::Integer(3));
+
+    // Clean up
+    fs::remove_file("test_catalog_changes.db").unwrap_or(());
+}
+
+#[test]
+fn test_catalog_tables_are_read_only() {
+    let mut db = create_test_db("test_catalog_read_only");
+
+    let read_only = |result: Result<(), DbError>| {
+        assert!(matches!(result, Err(DbError::ReadOnlyTable(name)) if name == "oxi_tables"));
+    };
+    read_only(db.insert("oxi_tables", "x", vec![Value::from("x")]));
+    read_only(db.delete("oxi_tables", &Key::from("users")));
+    read_only(db.drop_table("oxi_tables"));
+    read_only(db.import_csv("oxi_tables", "name\nx\n".as_bytes(), &CsvOptions::new()).map(|_| ()));
+    read_only(db.create_index("oxi_tables", "by_rows", Expr::column("rows")));
+    assert!(matches!(
+        db.create_table("oxi_columns", vec![Column::new("x", ColumnType::Text)], None),
+        Err(DbError::TableExists)
+    ));
+    assert!(matches!(
+        db.restore("CREATE TABLE oxi_indexes (x TEXT);".as_bytes()),
+        Err(DbError::TableExists)
+    ));
+
+    // Catalog tables are neither listed nor stored
+    assert_eq!(db.list_tables(), vec!["logs", "users"]);
+    let mut dump = Vec::new();
+    db.dump(&mut dump).unwrap();
+    assert!(!String::from_utf8(dump).unwrap().contains("oxi_"));
+    for name in CATALOG_TABLES {
+        assert!(db.get_table(name).is_ok());
+    }
+    assert!(matches!(db.get_table("oxi_other"), Err(DbError::TableNotFound)));
+
+    // Clean up
+    fs::remove_file("test_catalog_read_only.db").unwrap_or(());
+}
+
+#[test]
+fn test_catalog_exports_like_any_table() {
+    let db = create_test_db("test_catalog_export");
+
+    let mut csv = Vec::new();
+    db.export_csv("oxi_columns", &mut csv, &CsvOptions::new()).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert_eq!(csv.lines().next(), Some("table,position,name,type,primary_key,auto_increment"));
+    assert!(csv.contains("users,0,id,INTEGER,true,true"), "{}", csv);
+
+    let stats = db.get_table("oxi_columns").unwrap().stats().unwrap();
+    assert_eq!(stats.rows, 4);
+    assert_eq!(stats.column("table").unwrap().distinct, 2);
+
+    // Clean up
+    fs::remove_file("test_catalog_export.db").unwrap_or(());
+}
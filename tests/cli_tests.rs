@@ -0,0 +1,435 @@
+#![cfg(feature = "cli")]
+
+use oxi_db::cli::{self, exit_code, Shell};
+use oxi_db::{Column, ColumnType, Database, DbError, Key, Value};
+use std::fs;
+use std::io::Cursor;
+
+fn create_test_shell(db_name: &str) -> Shell {
+    let db_path = format!("{}.db", db_name);
+    fs::remove_file(&db_path).unwrap_or(());
+
+    let mut db = Database::new(&db_path);
+    let columns = vec![
+        Column::new("id", ColumnType::Integer).auto_increment(),
+        Column::new("name", ColumnType::Text),
+        Column::new("balance", ColumnType::Decimal(10, 2)),
+    ];
+    db.create_table("users", columns, Some("id".to_string())).unwrap();
+
+    Shell::new(db)
+}
+
+/// Run a subcommand with the given arguments and stdin, returning its output
+fn run_command(args: &[&str], stdin: &str) -> oxi_db::Result<String> {
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    let mut output = Vec::new();
+    cli::run(&args, &mut Cursor::new(stdin), &mut output)?;
+    Ok(String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn test_shell_tables_and_schema() {
+    let mut shell = create_test_shell("test_shell_schema");
+
+    let output = shell.execute("tables").unwrap();
+    assert!(output.contains("| users |"));
+
+    let output = shell.execute(".schema users").unwrap();
+    assert!(output.contains("DECIMAL(10, 2)"));
+    assert!(output.contains("PRIMARY KEY AUTO_INCREMENT"));
+
+    assert!(matches!(shell.execute("schema missing"), Err(DbError::TableNotFound)));
+    assert!(matches!(shell.execute("frobnicate"), Err(DbError::ParseError(_))));
+
+    // Clean up
+    fs::remove_file("test_shell_schema.db").unwrap_or(());
+}
+
+#[test]
+fn test_shell_crud() {
+    let mut shell = create_test_shell("test_shell_crud");
+
+    assert_eq!(shell.execute("insert users NULL 'Alice' 10.5").unwrap(), "Inserted row 1\n");
+    assert_eq!(shell.execute("insert users 7 7 'Bob' 3").unwrap(), "Inserted row 7\n");
+
+    let output = shell.execute("get users 1").unwrap();
+    assert!(output.contains("| 1   | 1  | Alice | 10.50   |"), "{}", output);
+
+    shell.execute("update users 7 set name = 'Robert', balance = 4.25").unwrap();
+    let row = shell.database().get("users", &Key::from(7)).unwrap();
+    assert_eq!(row.values[1], Value::from("Robert"));
+    assert_eq!(row.values[2], Value::Decimal("4.25".parse().unwrap()));
+
+    shell.execute("delete users 1").unwrap();
+    assert!(shell.execute("get users 1").is_err());
+
+    // Literals take the type of their column, but text is not parsed
+    shell.execute("update users 7 set balance = 5").unwrap();
+    let row = shell.database().get("users", &Key::from(7)).unwrap();
+    assert_eq!(row.values[2], Value::Decimal("5.00".parse().unwrap()));
+
+    // Type errors come from the schema
+    assert!(shell.execute("insert users NULL 'Carol' 'lots'").is_err());
+    assert!(shell.execute("insert users NULL 'Carol' '5'").is_err());
+    assert!(matches!(shell.execute("insert 42 NULL 'Carol' 5"), Err(DbError::ParseError(_))));
+
+    // Clean up
+    fs::remove_file("test_shell_crud.db").unwrap_or(());
+}
+
+#[test]
+fn test_shell_query() {
+    let mut shell = create_test_shell("test_shell_query");
+
+    shell.execute("insert users NULL 'Alice' 10").unwrap();
+    shell.execute("insert users NULL 'Bob' 99.99").unwrap();
+    shell.execute("insert users NULL 'Carol' NULL").unwrap();
+
+    let output = shell.execute("query users").unwrap();
+    assert!(output.ends_with("(3 rows)\n"));
+
+    let output = shell.execute("query users where balance > 50 or name = 'Alice'").unwrap();
+    assert!(output.contains("Alice"));
+    assert!(output.contains("Bob"));
+    assert!(!output.contains("Carol"));
+    assert!(output.ends_with("(2 rows)\n"));
+
+    let output = shell.execute("query users where balance IS NULL").unwrap();
+    assert!(output.contains("Carol"));
+
+    assert!(shell.execute("query users where").is_err());
+
+    // Clean up
+    fs::remove_file("test_shell_query.db").unwrap_or(());
+}
+
+#[test]
+fn test_commands_create_put_query() {
+    let db = "test_commands_crud.db";
+    fs::remove_file(db).unwrap_or(());
+
+    let columns = "id INTEGER PRIMARY KEY AUTO_INCREMENT, name TEXT";
+    let output = run_command(&[db, "create-table", "users"], columns).unwrap();
+    assert_eq!(output, "Created table users\n");
+
+    // Rows from stdin, one per line
+    let output = run_command(&[db, "put", "users"], "NULL 'Alice'\nNULL 'Bob'\n").unwrap();
+    assert_eq!(output, "Inserted row 1\nInserted row 2\n");
+
+    // An explicit key replaces the row
+    let output = run_command(&[db, "put", "users", "2", "'Robert'", "--json"], "").unwrap();
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json, serde_json::json!({"inserted": [], "updated": [2]}));
+
+    let args = [db, "query", "users", "where", "name", "=", "'Robert'", "--format", "json"];
+    let output = run_command(&args, "").unwrap();
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json, serde_json::json!([{"_key": 2, "id": 2, "name": "Robert"}]));
+
+    assert_eq!(run_command(&[db, "count", "users"], "").unwrap(), "2\n");
+    assert_eq!(run_command(&[db, "count", "users", "-"], "id > 1").unwrap(), "1\n");
+
+    let output = run_command(&[db, "get", "users", "1"], "").unwrap();
+    assert!(output.contains("| 1   | 1  | Alice |"), "{}", output);
+
+    // Clean up
+    fs::remove_file(db).unwrap_or(());
+}
+
+#[test]
+fn test_commands_schema_delete_drop() {
+    let db = "test_commands_schema.db";
+    fs::remove_file(db).unwrap_or(());
+
+    let columns = "id TEXT PRIMARY KEY, tags ARRAY(TEXT), at DECIMAL(10, 2)";
+    run_command(&[db, "create-table", "events", columns], "").unwrap();
+    let output = run_command(&[db, "schema", "events", "--json"], "").unwrap();
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["primary_key"], "id");
+    assert_eq!(json["columns"][1]["type"], "ARRAY(TEXT)");
+
+    run_command(&[db, "put", "events"], "'a' ['x'] 1.5\n'b' [] NULL\n'c' NULL NULL").unwrap();
+    let output = run_command(&[db, "delete", "events"], "'a' 'b'").unwrap();
+    assert_eq!(output, "Deleted row a\nDeleted row b\n");
+
+    // A batch with a bad row or a missing key changes nothing
+    assert!(run_command(&[db, "put", "events"], "'d' [] NULL\n'e' 'x' NULL").is_err());
+    assert!(run_command(&[db, "delete", "events"], "'c' 'zzz'").is_err());
+
+    let output = run_command(&[db, "tables", "--json"], "").unwrap();
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json, serde_json::json!([{"name": "events", "columns": 3, "rows": 1}]));
+
+    run_command(&[db, "drop-table", "events"], "").unwrap();
+    assert_eq!(run_command(&[db, "tables"], "").unwrap().lines().count(), 3);
+
+    // Clean up
+    fs::remove_file(db).unwrap_or(());
+}
+
+#[test]
+fn test_commands_exit_codes() {
+    let db = "test_commands_errors.db";
+    fs::remove_file(db).unwrap_or(());
+
+    run_command(&[db, "create-table", "users", "id INTEGER PRIMARY KEY, name TEXT"], "").unwrap();
+    run_command(&[db, "put", "users", "1", "'Alice'"], "").unwrap();
+
+    let code = |args: &[&str]| exit_code(&run_command(args, "").unwrap_err());
+    assert_eq!(code(&[db, "frobnicate"]), 2);
+    assert_eq!(code(&[db, "count", "users", "--format", "xml"]), 2);
+    assert_eq!(code(&[db, "create-table", "t", "id WHATEVER"]), 2);
+    assert_eq!(code(&[db, "get", "users", "2"]), 3);
+    assert_eq!(code(&[db, "schema", "missing"]), 3);
+    assert_eq!(code(&[db, "create-table", "users", "id INTEGER"]), 4);
+    assert_eq!(code(&["missing_commands.db", "tables"]), 7);
+
+    // Flags alone are a usage error rather than a shell on a file named after them
+    assert_eq!(code(&["--json"]), 2);
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_oxi-db")).arg("--json").output().unwrap();
+    assert_eq!(status.status.code(), Some(2));
+    assert!(!std::path::Path::new("--json").exists());
+
+    // Clean up
+    fs::remove_file(db).unwrap_or(());
+}
+
+#[test]
+fn test_commands_dump_restore() {
+    let db = "test_commands_dump.db";
+    let copy = "test_commands_dump_copy.db";
+    fs::remove_file(db).unwrap_or(());
+    fs::remove_file(copy).unwrap_or(());
+
+    run_command(&[db, "create-table", "users", "id INTEGER PRIMARY KEY, name TEXT"], "").unwrap();
+    run_command(&[db, "put", "users"], "1 'Alice'\n2 'O''Brien'\n").unwrap();
+
+    let dump = run_command(&[db, "dump"], "").unwrap();
+    assert!(dump.contains("INSERT INTO users VALUES (2, 'O''Brien');"), "{}", dump);
+    assert_eq!(run_command(&[copy, "restore"], &dump).unwrap(), "Restored 2 rows\n");
+    assert_eq!(run_command(&[copy, "count", "users"], "").unwrap(), "2\n");
+
+    // Restoring again collides with the restored table
+    assert_eq!(exit_code(&run_command(&[copy, "restore"], &dump).unwrap_err()), 4);
+
+    // Clean up
+    fs::remove_file(db).unwrap_or(());
+    fs::remove_file(copy).unwrap_or(());
+}
+
+#[test]
+fn test_commands_backup() {
+    let db = "test_commands_backup.db";
+    let copy = "test_commands_backup_copy.db";
+    fs::remove_file(db).unwrap_or(());
+
+    run_command(&[db, "create-table", "users", "id INTEGER PRIMARY KEY, name TEXT"], "").unwrap();
+    run_command(&[db, "put", "users", "1", "'Alice'"], "").unwrap();
+
+    let output = run_command(&[db, "backup", copy, "--json"], "").unwrap();
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["backup"], copy);
+    assert_eq!(json["bytes"], fs::metadata(copy).unwrap().len());
+    assert_eq!(run_command(&[copy, "count", "users"], "").unwrap(), "1\n");
+
+    assert_eq!(exit_code(&run_command(&[db, "backup"], "").unwrap_err()), 2);
+
+    // Clean up
+    fs::remove_file(db).unwrap_or(());
+    fs::remove_file(copy).unwrap_or(());
+}
+
+#[test]
+fn test_commands_check_repair() {
+    let db = "test_commands_check.db";
+    let repaired = "test_commands_check_repaired.db";
+    fs::remove_file(db).unwrap_or(());
+
+    run_command(&[db, "create-table", "users", "id INTEGER PRIMARY KEY, name TEXT"], "").unwrap();
+    run_command(&[db, "put", "users"], "1 'Alice'\n2 'Bob'\n").unwrap();
+    let output = run_command(&[db, "check"], "").unwrap();
+    assert_eq!(output, "Checked 1 tables and 2 rows: 0 problems\n");
+
+    // Damage the page of the second row
+    let mut data = fs::read(db).unwrap();
+    let at = data.windows(3).position(|w| w == b"Bob").unwrap();
+    data[at] = b'R';
+    fs::write(db, data).unwrap();
+    assert_eq!(exit_code(&run_command(&[db, "check"], "").unwrap_err()), 1);
+
+    let output = run_command(&[db, "repair", repaired, "--json"], "").unwrap();
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["rows"], 1);
+    assert_eq!(json["problems"].as_array().unwrap().len(), 1);
+    assert_eq!(run_command(&[repaired, "count", "users"], "").unwrap(), "1\n");
+
+    // Clean up
+    fs::remove_file(db).unwrap_or(());
+    fs::remove_file(repaired).unwrap_or(());
+}
+
+#[test]
+fn test_commands_encryption() {
+    let db = "test_commands_encryption.db";
+    let passphrase = "test_commands_encryption.passphrase";
+    fs::remove_file(db).unwrap_or(());
+    fs::write(passphrase, "open sesame\n").unwrap();
+
+    let schema = "id INTEGER PRIMARY KEY, name TEXT";
+    run_command(&[db, "create-table", "users", schema, "--passphrase-file", passphrase], "").unwrap();
+    run_command(&[db, "put", "users", "1", "'Alice'", "--passphrase-file", passphrase], "").unwrap();
+    assert!(matches!(Database::open(db), Err(DbError::KeyRequired)));
+    assert_eq!(exit_code(&run_command(&[db, "count", "users"], "").unwrap_err()), 9);
+    let output = run_command(&[db, "count", "users", "--passphrase-file", passphrase], "").unwrap();
+    assert_eq!(output, "1\n");
+    let output = run_command(&[db, "check", "--passphrase-file", passphrase], "").unwrap();
+    assert_eq!(output, "Checked 1 tables and 1 rows: 0 problems\n");
+
+    run_command(&[db, "decrypt", "--passphrase-file", passphrase], "").unwrap();
+    assert_eq!(run_command(&[db, "count", "users"], "").unwrap(), "1\n");
+    run_command(&[db, "encrypt", passphrase], "").unwrap();
+    assert_eq!(exit_code(&run_command(&[db, "count", "users"], "").unwrap_err()), 9);
+
+    // Clean up
+    fs::remove_file(db).unwrap_or(());
+    fs::remove_file(passphrase).unwrap_or(());
+}
+
+#[test]
+fn test_commands_compress() {
+    let db = "test_commands_compress.db";
+    fs::remove_file(db).unwrap_or(());
+
+    run_command(&[db, "create-table", "notes", "id INTEGER PRIMARY KEY, text TEXT"], "").unwrap();
+    let text = "la ".repeat(500);
+    run_command(&[db, "put", "notes"], &format!("1 '{}'\n2 '{}'\n", text, text)).unwrap();
+    let output = run_command(&[db, "compress", "notes"], "").unwrap();
+    assert!(output.starts_with("notes: none, 3 pages (0 compressed, 0 overflow)"), "{}", output);
+
+    let output = run_command(&[db, "compress", "notes", "lz4", "--json"], "").unwrap();
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["compression"], "lz4");
+    assert_eq!(json["compressed_pages"], 2);
+    assert!(json["stored_bytes"].as_u64() < json["raw_bytes"].as_u64());
+    assert_eq!(run_command(&[db, "count", "notes"], "").unwrap(), "2\n");
+    assert_eq!(exit_code(&run_command(&[db, "compress", "notes", "zip"], "").unwrap_err()), 2);
+
+    // Clean up
+    fs::remove_file(db).unwrap_or(());
+}
+
+#[test]
+fn test_commands_stats() {
+    let db = "test_commands_stats.db";
+    fs::remove_file(db).unwrap_or(());
+
+    run_command(&[db, "create-table", "users", "id INTEGER PRIMARY KEY, name TEXT"], "").unwrap();
+    run_command(&[db, "put", "users"], "1 'Alice'\n2 'Bob'\n3 NULL\n").unwrap();
+    let output = run_command(&[db, "stats"], "").unwrap();
+    let size = fs::metadata(db).unwrap().len();
+    assert!(output.starts_with(&format!("{} bytes in file, {} used, 0 free; 1 tables, 3 rows", size, size)));
+    assert!(output.contains("| users | 3    | 1          |"), "{}", output);
+
+    let output = run_command(&[db, "stats", "users", "--json"], "").unwrap();
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["rows"], 3);
+    assert_eq!(json["columns"][1]["nulls"], 1);
+    assert_eq!(json["columns"][1]["distinct"], 2);
+    assert_eq!(json["columns"][1]["min"], "Alice");
+    assert_eq!(exit_code(&run_command(&[db, "stats", "missing"], "").unwrap_err()), 3);
+
+    let mut shell = Shell::new(Database::open(db).unwrap());
+    let output = shell.execute("stats users").unwrap();
+    assert!(output.starts_with("users: 3 rows, estimated depth 1"), "{}", output);
+    assert!(output.contains("| name   | TEXT    | 1     | 2        | Alice | Bob |"), "{}", output);
+
+    // Clean up
+    fs::remove_file(db).unwrap_or(());
+}
+
+#[test]
+fn test_commands_catalog() {
+    let db = "test_commands_catalog.db";
+    fs::remove_file(db).unwrap_or(());
+
+    run_command(&[db, "create-table", "users", "id INTEGER PRIMARY KEY, name TEXT"], "").unwrap();
+    let output = run_command(&[db, "query", "oxi_columns", "where", "table = 'users'", "--json"], "").unwrap();
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json[1]["name"], "name");
+    assert_eq!(json[1]["type"], "TEXT");
+    assert_eq!(run_command(&[db, "count", "oxi_tables"], "").unwrap(), "1\n");
+    assert!(run_command(&[db, "schema", "oxi_indexes"], "").unwrap().contains("| expression |"));
+    assert_eq!(run_command(&[db, "tables"], "").unwrap().matches("oxi_").count(), 0);
+    assert_eq!(exit_code(&run_command(&[db, "delete", "oxi_tables", "'users'"], "").unwrap_err()), 1);
+
+    let mut shell = Shell::new(Database::open(db).unwrap());
+    let output = shell.execute("query oxi_tables where rows = 0").unwrap();
+    assert!(output.contains("(1 rows)"), "{}", output);
+
+    // Clean up
+    fs::remove_file(db).unwrap_or(());
+}
+
+#[test]
+fn test_commands_explain() {
+    let db = "test_commands_explain.db";
+    fs::remove_file(db).unwrap_or(());
+
+    run_command(&[db, "create-table", "users", "id INTEGER PRIMARY KEY, name TEXT"], "").unwrap();
+    let orders = "id INTEGER PRIMARY KEY, user INTEGER, total INTEGER";
+    run_command(&[db, "create-table", "orders", orders], "").unwrap();
+    run_command(&[db, "put", "users"], "1 'Alice'\n2 'Bob'\n").unwrap();
+    run_command(&[db, "put", "orders"], "1 1 30\n2 1 70\n3 2 50\n4 NULL 90\n").unwrap();
+
+    let clauses = "join users on user = users.id where total > 40 order by total desc limit 5";
+    let output = run_command(&[db, "query", "orders", clauses], "").unwrap();
+    let header = "| key    | orders.id | orders.user | orders.total | users.id | users.name |";
+    assert!(output.contains(header), "{}", output);
+    let row = "| (2, 1) | 2         | 1           | 70           | 1        | Alice      |";
+    assert!(output.contains(row), "{}", output);
+    assert!(output.ends_with("(2 rows)\n"), "{}", output);
+    assert_eq!(run_command(&[db, "count", "orders", "user = 1"], "").unwrap(), "2\n");
+
+    let output = run_command(&[db, "explain", "orders", "where user = 1 order by total"], "").unwrap();
+    assert!(output.starts_with("Sort by total (estimated 2 rows, actual 2"), "{}", output);
+    // Four rows are cheaper to scan than to look up in an index
+    let scan = "  Scan orders where user = 1 (estimated 2 rows, actual 2, cost 4.0)";
+    assert!(output.contains(scan), "{}", output);
+    let output = run_command(&[db, "explain", "users", "-", "--json"], "order by name desc").unwrap();
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["operator"], "Sort by name DESC");
+    assert_eq!(json["inputs"][0]["actual_rows"], 2);
+
+    let mut shell = Shell::new(Database::open(db).unwrap());
+    let output = shell.execute("explain orders where total >= 50").unwrap();
+    assert!(output.starts_with("Scan orders where total >= 50"), "{}", output);
+    shell.execute("analyze").unwrap();
+    let query = "select orders join users on user = users.id order by users.name, total limit 1";
+    let output = shell.execute(query).unwrap();
+    assert!(output.contains("| (1, 1) | 1 "), "{}", output);
+
+    // Clean up
+    fs::remove_file(db).unwrap_or(());
+}
+
+#[test]
+fn test_shell_in_memory() {
+    let path = "test_shell_in_memory.db";
+    fs::remove_file(path).unwrap_or(());
+
+    let mut db = Database::in_memory();
+    db.create_table("tags", vec![Column::new("name", ColumnType::Text)], Some("name".to_string()))
+        .unwrap();
+    let mut shell = Shell::new(db);
+    shell.execute("insert tags 'rust'").unwrap();
+    assert!(shell.database().is_in_memory());
+    assert!(matches!(shell.execute("save 42"), Err(DbError::ParseError(_))));
+
+    assert_eq!(shell.execute(&format!("save '{}'", path)).unwrap(), format!("Saved to {}\n", path));
+    shell.execute("insert tags 'sql'").unwrap();
+    assert_eq!(Database::load_into_memory(path).unwrap().get_table("tags").unwrap().len(), 2);
+
+    // Clean up
+    fs::remove_file(path).unwrap_or(());
+}
@@ -0,0 +1,186 @@
+use oxi_db::{Column, ColumnType, CompareOp, Database, Expr, Key, Table, Value};
+use serde_json::json;
+use std::fs;
+
+fn tags(values: &[&str]) -> Value {
+    Value::Array(values.iter().map(|&v| Value::from(v)).collect())
+}
+
+fn create_articles_table() -> Table {
+    let columns = vec![
+        Column::new("title", ColumnType::Text),
+        Column::new("tags", ColumnType::Array(Box::new(ColumnType::Text))),
+        Column::new("scores", ColumnType::Array(Box::new(ColumnType::Integer))),
+        Column::new(
+            "author",
+            ColumnType::Struct(vec![
+                Column::new("name", ColumnType::Text),
+                Column::new("age", ColumnType::Integer),
+            ]),
+        ),
+    ];
+
+    Table::new("articles", columns, None)
+}
+
+#[test]
+fn test_array_and_struct_validation() {
+    let mut table = create_articles_table();
+
+    let result = table.insert(
+        "1",
+        vec![
+            Value::from("Rust"),
+            tags(&["rust", "db"]),
+            Value::Array(vec![Value::Integer(3), Value::Null]),
+            Value::Struct(vec![Value::from("Alice"), Value::Integer(31)]),
+        ],
+    );
+    assert!(result.is_ok());
+
+    // Element of the wrong type
+    let result = table.insert(
+        "2",
+        vec![
+            Value::from("Bad"),
+            Value::Array(vec![Value::from("ok"), Value::Integer(1)]),
+            Value::Array(vec![]),
+            Value::Null,
+        ],
+    );
+    assert!(result.is_err());
+
+    // Struct with the wrong number of fields
+    let result = table.insert(
+        "3",
+        vec![
+            Value::from("Bad"),
+            tags(&[]),
+            Value::Array(vec![]),
+            Value::Struct(vec![Value::from("Bob")]),
+        ],
+    );
+    assert!(result.is_err());
+
+    // Struct field of the wrong type
+    let result = table.insert(
+        "4",
+        vec![
+            Value::from("Bad"),
+            tags(&[]),
+            Value::Array(vec![]),
+            Value::Struct(vec![Value::from("Bob"), Value::from("old")]),
+        ],
+    );
+    assert!(result.is_err());
+
+    // Nested arrays are checked recursively
+    let matrix = ColumnType::Array(Box::new(ColumnType::Array(Box::new(ColumnType::Float))));
+    assert!(matrix.accepts(&Value::Array(vec![Value::Array(vec![Value::Float(1.0)])])));
+    assert!(!matrix.accepts(&Value::Array(vec![Value::Float(1.0)])));
+}
+
+#[test]
+fn test_contains_and_any_filters() {
+    let mut table = create_articles_table();
+
+    table.insert(
+        "1",
+        vec![
+            Value::from("Rust"),
+            tags(&["rust", "db"]),
+            Value::Array(vec![Value::Integer(3), Value::Integer(9)]),
+            Value::Null,
+        ],
+    ).unwrap();
+
+    table.insert(
+        "2",
+        vec![
+            Value::from("Go"),
+            tags(&["go"]),
+            Value::Array(vec![Value::Integer(1)]),
+            Value::Null,
+        ],
+    ).unwrap();
+
+    let rows = table.find_where(&Expr::column("tags").contains("db")).unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].0, Key::from("1"));
+
+    let rows = table.find_where(&Expr::column("scores").any(CompareOp::Gt, 5)).unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].0, Key::from("1"));
+
+    let rows = table.find_where(&Expr::column("scores").any(CompareOp::Le, 3)).unwrap();
+    assert_eq!(rows.len(), 2);
+
+    // Containment is false for non-array values
+    let rows = table.find_where(&Expr::column("title").contains("Rust")).unwrap();
+    assert!(rows.is_empty());
+}
+
+#[test]
+fn test_contains_on_json_arrays() {
+    let mut table = Table::new("docs", vec![Column::new("doc", ColumnType::Json)], None);
+    table.insert("1", vec![Value::Json(json!({"tags": ["a", "b"]}))]).unwrap();
+    table.insert("2", vec![Value::Json(json!({"tags": ["c"]}))]).unwrap();
+
+    let filter = Expr::json_path("doc", "$.tags").unwrap().contains("b");
+    let rows = table.find_where(&filter).unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].0, Key::from("1"));
+}
+
+#[test]
+fn test_arrays_with_null_elements_are_indexed_apart() {
+    let mut table = Table::new(
+        "results",
+        vec![Column::new("scores", ColumnType::Array(Box::new(ColumnType::Integer)))],
+        None,
+    );
+    let scores = |last: i64| Value::Array(vec![Value::Null, Value::Integer(last)]);
+    table.insert("1", vec![scores(1)]).unwrap();
+    table.insert("2", vec![scores(2)]).unwrap();
+    table.insert("3", vec![scores(1)]).unwrap();
+    table.create_index("results_scores", Expr::column("scores")).unwrap();
+
+    // NULL elements sort first and equal each other, so the rest decides
+    assert_eq!(scores(1).compare(&scores(2)), Some(std::cmp::Ordering::Less));
+    assert_eq!(scores(1).compare(&Value::Array(vec![Value::Integer(0)])), Some(std::cmp::Ordering::Less));
+    let index = &table.indexes()[0];
+    assert_eq!(index.lookup(&scores(1)), vec![Key::from("1"), Key::from("3")]);
+    assert_eq!(index.lookup(&scores(2)), vec![Key::from("2")]);
+    let rows = table.find_where(&Expr::column("scores").eq(scores(2))).unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].0, Key::from("2"));
+}
+
+#[test]
+fn test_composite_persistence() {
+    let db_path = "test_composite_persist.db";
+    let mut db = Database::new(db_path);
+
+    let table = create_articles_table();
+    db.create_table("articles", table.columns.clone(), None).unwrap();
+
+    let values = vec![
+        Value::from("Rust"),
+        tags(&["rust", "db"]),
+        Value::Array(vec![Value::Integer(3)]),
+        Value::Struct(vec![Value::from("Alice"), Value::Integer(31)]),
+    ];
+    db.insert("articles", "1", values.clone()).unwrap();
+
+    drop(db);
+    let db = Database::open(db_path).unwrap();
+    let table = db.get_table("articles").unwrap();
+    assert_eq!(table.columns[3].column_type, create_articles_table().columns[3].column_type);
+    assert_eq!(table.get(&Key::from("1")).unwrap().values, values);
+
+    let json = serde_json::to_string(&values[1]).unwrap();
+    assert_eq!(json, r#"{"Array":[{"Text":"rust"},{"Text":"db"}]}"#);
+
+    // Clean up
+    fs::remove_file(db_path).unwrap_or(());
+}
@@ -0,0 +1,194 @@
+use oxi_db::{Column, ColumnType, Compression, CompressionStats, Database, DbError, Expr, Key, Value};
+use std::fs;
+
+fn create_test_db(db_name: &str) -> Database {
+    let db_path = format!("{}.db", db_name);
+    fs::remove_file(&db_path).unwrap_or(());
+
+    let mut db = Database::new(&db_path);
+    let columns = vec![
+        Column::new("id", ColumnType::Integer).auto_increment(),
+        Column::new("title", ColumnType::Text),
+        Column::new("body", ColumnType::Text),
+    ];
+    db.create_table("articles", columns, Some("id".to_string())).unwrap();
+    for i in 0..50 {
+        let body = format!("Article {} is about databases. ", i).repeat(20);
+        db.insert_auto("articles", vec![Value::Null, Value::from(format!("Title {}", i)), Value::from(body)])
+            .unwrap();
+    }
+    db.create_index("articles", "articles_title", Expr::column("title")).unwrap();
+    db
+}
+
+/// A blob that does not compress, so its bytes can be found in the file
+fn noise(len: usize, seed: u32) -> Vec<u8> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 24) as u8
+        })
+        .collect()
+}
+
+#[test]
+fn test_compressed_tables_shrink_the_file() {
+    let mut db = create_test_db("test_compression_shrink");
+    let path = "test_compression_shrink.db";
+    let plain_size = fs::metadata(path).unwrap().len();
+    let plain = db.compression_stats("articles").unwrap();
+    assert_eq!(plain.compressed_pages, 0);
+    assert_eq!(plain.stored_bytes, plain.raw_bytes);
+
+    db.set_compression("articles", Compression::Lz4).unwrap();
+    let stats = db.compression_stats("articles").unwrap();
+    // One more page records the compression of the table
+    assert_eq!(stats.pages, plain.pages + 1);
+    assert_eq!(stats.compressed_pages, 51);
+    assert!(stats.ratio() < 0.5, "{:?}", stats);
+    assert!(fs::metadata(path).unwrap().len() < plain_size / 2);
+
+    let reopened = Database::load_into_memory(path).unwrap();
+    let table = reopened.get_table("articles").unwrap();
+    assert_eq!(table.compression(), Compression::Lz4);
+    assert_eq!(table.get_all(), db.get_table("articles").unwrap().get_all());
+    assert_eq!(table.checksum().unwrap(), db.get_table("articles").unwrap().checksum().unwrap());
+    assert!(Database::integrity_check(path).unwrap().is_ok());
+
+    db.set_compression("articles", Compression::None).unwrap();
+    assert_eq!(fs::metadata(path).unwrap().len(), plain_size);
+
+    // Clean up
+    fs::remove_file(path).unwrap_or(());
+}
+
+#[test]
+fn test_large_values_use_overflow_pages() {
+    let mut db = create_test_db("test_compression_overflow");
+    let path = "test_compression_overflow.db";
+    db.create_table(
+        "files",
+        vec![
+            Column::new("name", ColumnType::Text),
+            Column::new("data", ColumnType::Blob),
+            Column::new("notes", ColumnType::Text),
+        ],
+        Some("name".to_string()),
+    )
+    .unwrap();
+    let large = vec![Value::from("large"), Value::from(noise(20_000, 1)), Value::from("x".repeat(10_000))];
+    db.insert("files", "large", large.clone()).unwrap();
+    db.insert("files", "small", vec![Value::from("small"), Value::from(vec![1, 2, 3]), Value::Null])
+        .unwrap();
+
+    // 20 000 + 10 000 bytes of values, plus their encoding, in 4 KiB pages
+    let stats = db.compression_stats("files").unwrap();
+    assert_eq!(stats.overflow_pages, 8);
+    assert_eq!(stats.pages, 1 + 8 + 2);
+
+    for compression in [Compression::None, Compression::Lz4] {
+        db.set_compression("files", compression).unwrap();
+        let reopened = Database::load_into_memory(path).unwrap();
+        assert_eq!(reopened.get("files", &Key::from("large")).unwrap().values, large);
+        assert_eq!(reopened.get_table("files").unwrap().len(), 2);
+        assert!(Database::integrity_check(path).unwrap().is_ok());
+    }
+    // The noise does not compress, the text does
+    let stats = db.compression_stats("files").unwrap();
+    assert!(stats.compressed_pages > 0 && stats.compressed_pages < stats.pages);
+
+    // Clean up
+    fs::remove_file(path).unwrap_or(());
+}
+
+#[test]
+fn test_damaged_overflow_pages() {
+    let path = "test_compression_damaged.db";
+    let repaired = "test_compression_damaged_repaired.db";
+    fs::remove_file(path).unwrap_or(());
+    let mut db = Database::new(path);
+    db.create_table(
+        "files",
+        vec![Column::new("id", ColumnType::Integer), Column::new("data", ColumnType::Blob)],
+        Some("id".to_string()),
+    )
+    .unwrap();
+    db.insert("files", 1, vec![Value::from(1), Value::from(noise(10_000, 1))]).unwrap();
+    db.insert("files", 2, vec![Value::from(2), Value::from(noise(10_000, 2))]).unwrap();
+    drop(db);
+
+    // Flip a byte in the middle of the second blob
+    let mut data = fs::read(path).unwrap();
+    let blob = noise(10_000, 2);
+    let at = data.windows(64).position(|w| w == &blob[5_000..5_064]).unwrap();
+    data[at] ^= 0xff;
+    fs::write(path, data).unwrap();
+
+    match Database::open(path) {
+        Err(DbError::Corruption { table, message, .. }) => {
+            assert_eq!(table.as_deref(), Some("files"));
+            assert_eq!(message, "checksum mismatch");
+        }
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+    let report = Database::repair(path, repaired).unwrap();
+    assert_eq!(report.rows, 1);
+    let messages: Vec<String> = report.problems.iter().map(|p| p.message.clone()).collect();
+    assert_eq!(messages, ["checksum mismatch", "row 2: overflow pages are missing"]);
+    let recovered = Database::open(repaired).unwrap();
+    assert_eq!(recovered.get("files", &Key::from(1)).unwrap().values[1], Value::from(noise(10_000, 1)));
+
+    // Clean up
+    fs::remove_file(path).unwrap_or(());
+    fs::remove_file(repaired).unwrap_or(());
+}
+
+#[test]
+fn test_overflow_pages_of_a_damaged_row_are_dropped() {
+    let path = "test_compression_leftover.db";
+    let repaired = "test_compression_leftover_repaired.db";
+    fs::remove_file(path).unwrap_or(());
+    let mut db = Database::new(path);
+    db.create_table(
+        "files",
+        vec![Column::new("id", ColumnType::Integer), Column::new("data", ColumnType::Blob)],
+        Some("id".to_string()),
+    )
+    .unwrap();
+    db.insert("files", 1, vec![Value::from(1), Value::from(noise(10_000, 1))]).unwrap();
+    db.insert("files", 2, vec![Value::from(2), Value::from(noise(10_000, 2))]).unwrap();
+
+    // Damage the row page that follows the overflow pages of the first blob
+    let mut data = fs::read(path).unwrap();
+    let blob = noise(10_000, 1);
+    let end = data.windows(64).position(|w| w == &blob[10_000 - 64..]).unwrap() + 64;
+    data[end + 12] ^= 0xff;
+    fs::write(path, data).unwrap();
+
+    // The second row must not pick up the overflow pages of the first
+    let report = Database::repair(path, repaired).unwrap();
+    assert_eq!(report.rows, 1);
+    let messages: Vec<String> = report.problems.iter().map(|p| p.message.clone()).collect();
+    assert_eq!(messages, ["checksum mismatch"]);
+    let recovered = Database::open(repaired).unwrap();
+    assert!(recovered.get("files", &Key::from(1)).is_err());
+    assert_eq!(recovered.get("files", &Key::from(2)).unwrap().values[1], Value::from(noise(10_000, 2)));
+
+    // Clean up
+    fs::remove_file(path).unwrap_or(());
+    fs::remove_file(repaired).unwrap_or(());
+}
+
+#[test]
+fn test_compression_names_and_stats() {
+    assert_eq!("LZ4".parse::<Compression>().unwrap(), Compression::Lz4);
+    assert_eq!("none".parse::<Compression>().unwrap(), Compression::None);
+    assert!(matches!("zstd".parse::<Compression>(), Err(DbError::ParseError(_))));
+    assert_eq!(Compression::default(), Compression::None);
+    assert_eq!(CompressionStats::default().ratio(), 1.0);
+
+    let mut db = Database::new("test_compression_missing.db");
+    assert!(matches!(db.set_compression("missing", Compression::Lz4), Err(DbError::TableNotFound)));
+    assert!(matches!(db.compression_stats("missing"), Err(DbError::TableNotFound)));
+}
@@ -0,0 +1,260 @@
+use oxi_db::{Column, ColumnType, Database, Key, SimStorage, Storage, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+const PATH: &str = "crash.db";
+
+/// Rows of the test table, by id
+type Model = BTreeMap<i64, String>;
+
+/// A small deterministic generator for the operations of a run
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, bound: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % bound
+    }
+}
+
+fn create_test_db(storage: &SimStorage) -> Database {
+    let mut db = Database::new(PATH).with_storage(Arc::new(storage.clone()));
+    let columns = vec![
+        Column::new("id", ColumnType::Integer).auto_increment(),
+        Column::new("text", ColumnType::Text),
+    ];
+    db.create_table("items", columns, Some("id".to_string())).unwrap();
+    db
+}
+
+/// Reopen the database from the simulated disk and read back its rows
+fn reopen(storage: &SimStorage) -> (Database, Model) {
+    let db = Database::open_in(Arc::new(storage.clone()), PATH).unwrap();
+    let rows = rows(&db);
+    (db, rows)
+}
+
+fn rows(db: &Database) -> Model {
+    db.get_table("items")
+        .unwrap()
+        .get_all()
+        .into_iter()
+        .map(|(_, row)| match &row.values[..] {
+            [Value::Integer(id), Value::Text(text)] => (*id, text.clone()),
+            other => panic!("unexpected row {:?}", other),
+        })
+        .collect()
+}
+
+/// Run random operations with crashes and failed syncs in between
+///
+/// After every failed operation the disk restarts, and the database must
+/// hold exactly the committed rows, or those and the failed operation: an
+/// operation whose save fails after its file was renamed into place is
+/// durable even though it reports an error. After a successful operation a
+/// crash must lose nothing.
+fn run_workload(seed: u64, operations: usize) {
+    let storage = SimStorage::new(seed);
+    let mut rng = Rng(seed.wrapping_mul(2_654_435_761) | 1);
+    let mut db = create_test_db(&storage);
+    let mut committed = Model::new();
+
+    for step in 0..operations {
+        let mut expected = committed.clone();
+        let result = match rng.below(4) {
+            0 | 1 => {
+                let id = db.get_table("items").unwrap().next_auto_increment().unwrap();
+                let text = format!("item {} of step {}", id, step).repeat(1 + rng.below(3) as usize);
+                expected.insert(id, text.clone());
+                db.insert_auto("items", vec![Value::Null, Value::from(text)]).map(|_| ())
+            }
+            2 if !committed.is_empty() => {
+                let id = *committed.keys().nth(rng.below(committed.len() as u64) as usize).unwrap();
+                let text = format!("updated at step {}", step);
+                expected.insert(id, text.clone());
+                db.update("items", &Key::from(id), vec![Value::from(id), Value::from(text)])
+            }
+            _ if !committed.is_empty() => {
+                let id = *committed.keys().nth(rng.below(committed.len() as u64) as usize).unwrap();
+                expected.remove(&id);
+                db.delete("items", &Key::from(id))
+            }
+            _ => continue,
+        };
+        // Plan faults for the next operation
+        match rng.below(10) {
+            0..=2 => storage.crash_after(rng.below(8)),
+            3 => storage.fail_syncs(1),
+            _ => {}
+        }
+
+        match result {
+            Ok(()) => {
+                assert!(!storage.has_crashed(), "seed {} step {}", seed, step);
+                committed = expected;
+            }
+            Err(e) => {
+                storage.restart();
+                let (reopened, rows) = reopen(&storage);
+                assert!(
+                    rows == committed || rows == expected,
+                    "seed {} step {}: {} left {:?}, committed {:?}",
+                    seed,
+                    step,
+                    e,
+                    rows,
+                    committed
+                );
+                db = reopened;
+                committed = rows;
+            }
+        }
+
+        if rng.below(5) == 0 {
+            storage.restart();
+            let (reopened, rows) = reopen(&storage);
+            assert_eq!(rows, committed, "seed {} step {}", seed, step);
+            db = reopened;
+        }
+    }
+}
+
+#[test]
+fn test_random_crashes_keep_committed_writes() {
+    for seed in 0..100 {
+        run_workload(seed, 60);
+    }
+}
+
+#[test]
+fn test_failed_writes_are_never_visible() {
+    for seed in 0..50 {
+        let storage = SimStorage::new(seed);
+        let mut rng = Rng(seed.wrapping_mul(2_654_435_761) | 1);
+        let mut db = create_test_db(&storage);
+        let mut committed = Model::new();
+
+        for step in 0..60 {
+            if rng.below(3) == 0 {
+                storage.fail_syncs(1);
+            }
+            let mut expected = committed.clone();
+            let result = match rng.below(3) {
+                0 => {
+                    let id = db.get_table("items").unwrap().next_auto_increment().unwrap();
+                    expected.insert(id, format!("step {}", step));
+                    db.insert_auto("items", vec![Value::Null, Value::from(format!("step {}", step))])
+                        .map(|_| ())
+                }
+                1 => {
+                    let id = rng.below(10) as i64 + 1;
+                    expected.insert(id, format!("step {}", step));
+                    let values = vec![Value::from(id), Value::from(format!("step {}", step))];
+                    db.upsert("items", id, values).map(|_| ())
+                }
+                _ => {
+                    let id = rng.below(10) as i64 + 1;
+                    expected.remove(&id);
+                    db.delete("items", &Key::from(id))
+                }
+            };
+            if result.is_ok() {
+                committed = expected;
+            }
+            // Neither the database nor its next save shows a failed write
+            assert_eq!(rows(&db), committed, "seed {} step {}", seed, step);
+        }
+
+        storage.fail_syncs(0);
+        db.save().unwrap();
+        drop(db);
+        storage.restart();
+        assert_eq!(reopen(&storage).1, committed, "seed {}", seed);
+    }
+}
+
+#[test]
+fn test_crash_at_every_point_of_a_save() {
+    for crash_point in 0..8 {
+        for seed in 0..10 {
+            let storage = SimStorage::new(seed);
+            let mut db = create_test_db(&storage);
+            db.insert_auto("items", vec![Value::Null, Value::from("first")]).unwrap();
+
+            storage.crash_after(crash_point);
+            let saved = db.insert_auto("items", vec![Value::Null, Value::from("second")]);
+            storage.restart();
+            let (_, rows) = reopen(&storage);
+            let texts: Vec<&str> = rows.values().map(String::as_str).collect();
+            match saved {
+                Ok(_) => assert_eq!(texts, ["first", "second"]),
+                Err(_) => assert!(texts == ["first"] || texts == ["first", "second"], "{:?}", texts),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_unsynced_writes_and_failed_syncs() {
+    let storage = SimStorage::new(1);
+    let mut db = create_test_db(&storage);
+    db.insert_auto("items", vec![Value::Null, Value::from("kept")]).unwrap();
+
+    // A failed sync leaves the database file as it was
+    storage.fail_syncs(1);
+    assert!(db.insert_auto("items", vec![Value::Null, Value::from("lost")]).is_err());
+    assert!(!storage.has_crashed());
+    storage.restart();
+    assert_eq!(reopen(&storage).1.values().collect::<Vec<_>>(), ["kept"]);
+
+    // Writes that were never synced do not survive every restart
+    let path = Path::new("scratch");
+    let mut lengths = Vec::new();
+    for seed in 0..20 {
+        let storage = SimStorage::new(seed);
+        storage.write(path, 0, b"synced").unwrap();
+        storage.sync(path).unwrap();
+        storage.sync_directory(path).unwrap();
+        storage.write(path, 6, b" and unsynced").unwrap();
+        storage.restart();
+        let data = storage.read(path).unwrap();
+        assert!(b"synced and unsynced".starts_with(&data) && data.len() >= 6);
+        lengths.push(data.len());
+    }
+    assert!(lengths.contains(&6) && lengths.contains(&19));
+    assert!(lengths.iter().any(|&len| len > 6 && len < 19));
+
+    // A rename is lost on some restarts until its directory is synced
+    let mut kept = Vec::new();
+    for seed in 0..20 {
+        let storage = SimStorage::new(seed);
+        storage.write(Path::new("old"), 0, b"data").unwrap();
+        storage.sync(Path::new("old")).unwrap();
+        storage.sync_directory(Path::new("old")).unwrap();
+        storage.rename(Path::new("old"), Path::new("new")).unwrap();
+        storage.restart();
+        let renamed = storage.read(Path::new("new")).is_ok();
+        assert_ne!(renamed, storage.read(Path::new("old")).is_ok());
+        kept.push(renamed);
+    }
+    assert!(kept.contains(&true) && kept.contains(&false));
+}
+
+#[test]
+fn test_crashes_are_deterministic() {
+    let run = |seed| {
+        let storage = SimStorage::new(seed);
+        let mut db = create_test_db(&storage);
+        db.insert_auto("items", vec![Value::Null, Value::from("a")]).unwrap();
+        storage.crash_after(3);
+        assert!(db.insert_auto("items", vec![Value::Null, Value::from("b")]).is_err());
+        storage.restart();
+        let temp = storage.read(Path::new("crash.db.tmp")).unwrap_or_default();
+        (storage.read(Path::new(PATH)).unwrap(), temp)
+    };
+    assert_eq!(run(5), run(5));
+    run_workload(42, 40);
+}
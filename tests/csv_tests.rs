@@ -0,0 +1,221 @@
+use oxi_db::{Column, ColumnType, CsvOptions, Database, DbError, Decimal, Key, Value};
+use std::fs;
+use std::io::{self, Read};
+
+fn create_test_db(db_name: &str) -> Database {
+    let db_path = format!("{}.db", db_name);
+    fs::remove_file(&db_path).unwrap_or(());
+
+    let mut db = Database::new(&db_path);
+    let columns = vec![
+        Column::new("id", ColumnType::Integer).auto_increment(),
+        Column::new("name", ColumnType::Text),
+        Column::new("balance", ColumnType::Decimal(10, 2)),
+        Column::new("tags", ColumnType::Array(Box::new(ColumnType::Text))),
+        Column::new("avatar", ColumnType::Blob),
+    ];
+    db.create_table("users", columns, Some("id".to_string())).unwrap();
+    db
+}
+
+#[test]
+fn test_csv_export_import_round_trip() {
+    let mut db = create_test_db("test_csv_round_trip");
+    db.insert_auto(
+        "users",
+        vec![
+            Value::Null,
+            Value::from("Alice \"Al\", Jr."),
+            Value::Decimal("10.50".parse().unwrap()),
+            Value::Array(vec![Value::from("admin"), Value::from("staff")]),
+            Value::Blob(vec![0xca, 0xfe]),
+        ],
+    )
+    .unwrap();
+    db.insert_auto("users", vec![Value::Null, Value::from("Bob"), Value::Null, Value::Null, Value::Null])
+        .unwrap();
+
+    let mut csv = Vec::new();
+    db.export_csv("users", &mut csv, &CsvOptions::new()).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert_eq!(
+        csv,
+        "id,name,balance,tags,avatar\n\
+         1,\"Alice \"\"Al\"\", Jr.\",10.50,\"[\"\"admin\"\",\"\"staff\"\"]\",cafe\n\
+         2,Bob,\\N,\\N,\\N\n"
+    );
+
+    // Import into an empty copy of the table
+    db.drop_table("users").unwrap();
+    let mut copy = create_test_db("test_csv_round_trip_copy");
+    let report = copy.import_csv("users", csv.as_bytes(), &CsvOptions::new()).unwrap();
+    assert_eq!(report.imported, 2);
+    assert!(report.errors.is_empty());
+
+    let row = copy.get("users", &Key::from(1)).unwrap();
+    assert_eq!(row.values[1], Value::from("Alice \"Al\", Jr."));
+    assert_eq!(row.values[2], Value::Decimal("10.50".parse().unwrap()));
+    assert_eq!(row.values[3], Value::Array(vec![Value::from("admin"), Value::from("staff")]));
+    assert_eq!(row.values[4], Value::Blob(vec![0xca, 0xfe]));
+    assert_eq!(copy.get("users", &Key::from(2)).unwrap().values[2], Value::Null);
+
+    // Clean up
+    fs::remove_file("test_csv_round_trip.db").unwrap_or(());
+    fs::remove_file("test_csv_round_trip_copy.db").unwrap_or(());
+}
+
+#[test]
+fn test_csv_import_reports_bad_rows() {
+    let mut db = create_test_db("test_csv_bad_rows");
+
+    // Columns are matched by header name; missing ones are NULL and the id
+    // comes from the auto-increment counter
+    let csv = "name,balance\n\
+               Alice,12\n\
+               Bob,lots\n\
+               Carol,1.999\n\
+               Dan\n\
+               Erin,3.5\n";
+    let report = db.import_csv("users", csv.as_bytes(), &CsvOptions::new()).unwrap();
+    assert_eq!(report.imported, 2);
+
+    let lines: Vec<u64> = report.errors.iter().map(|e| e.line).collect();
+    assert_eq!(lines, vec![3, 4, 5]);
+    assert!(matches!(report.errors[0].error, DbError::ParseError(_)));
+    assert!(report.errors[0].to_string().starts_with("line 3: "));
+
+    let table = db.get_table("users").unwrap();
+    assert_eq!(table.len(), 2);
+    let names: Vec<Value> = table.get_all().into_iter().map(|(_, row)| row.values[1].clone()).collect();
+    assert_eq!(names, vec![Value::from("Alice"), Value::from("Erin")]);
+
+    // Unknown columns in the header fail the whole import
+    let result = db.import_csv("users", "name,nickname\nA,B\n".as_bytes(), &CsvOptions::new());
+    assert!(matches!(result, Err(DbError::ColumnNotFound(_))));
+
+    // Clean up
+    fs::remove_file("test_csv_bad_rows.db").unwrap_or(());
+}
+
+#[test]
+fn test_csv_import_infers_new_table() {
+    let db_path = "test_csv_infer.db";
+    fs::remove_file(db_path).unwrap_or(());
+    let mut db = Database::new(db_path);
+
+    let csv = "sku,price,weight,active,external_id,note\n\
+               A-1,9.99,1e3,true,6f9619ff-8b86-d011-b42d-00cf4fc964ff,\n\
+               B-2,120,0.5,FALSE,,fragile\n";
+    let report = db.import_csv("products", csv.as_bytes(), &CsvOptions::new()).unwrap();
+    assert_eq!(report.imported, 2);
+
+    let table = db.get_table("products").unwrap();
+    let types: Vec<ColumnType> = table.columns.iter().map(|c| c.column_type.clone()).collect();
+    assert_eq!(
+        types,
+        vec![
+            ColumnType::Text,
+            ColumnType::Decimal(5, 2),
+            ColumnType::Float,
+            ColumnType::Boolean,
+            ColumnType::Uuid,
+            ColumnType::Text,
+        ]
+    );
+    assert_eq!(table.primary_key, Some("sku".to_string()));
+
+    let row = table.get(&Key::from("B-2")).unwrap();
+    assert_eq!(row.values[1], Value::Decimal(Decimal::new(12000, 2).unwrap()));
+    assert_eq!(row.values[3], Value::Boolean(false));
+    assert_eq!(row.values[4], Value::Null);
+
+    // Clean up
+    fs::remove_file(db_path).unwrap_or(());
+}
+
+#[test]
+fn test_csv_options() {
+    let db_path = "test_csv_options.db";
+    fs::remove_file(db_path).unwrap_or(());
+    let mut db = Database::new(db_path);
+
+    let options = CsvOptions::new()
+        .delimiter(b'\t')
+        .quote(b'\'')
+        .has_header(false)
+        .null("NA")
+        .primary_key("column2");
+    let report = db
+        .import_csv("codes", "'a\tb'\t1\tNA\nc\t2\tx\n".as_bytes(), &options)
+        .unwrap();
+    assert_eq!(report.imported, 2);
+
+    let table = db.get_table("codes").unwrap();
+    assert_eq!(table.columns[0].name, "column1");
+    assert_eq!(table.primary_key, Some("column2".to_string()));
+    assert_eq!(table.get(&Key::from(1)).unwrap().values[0], Value::from("a\tb"));
+    assert_eq!(table.get(&Key::from(1)).unwrap().values[2], Value::Null);
+
+    let mut csv = Vec::new();
+    table.export_csv(&mut csv, &options.clone().quote_all(true)).unwrap();
+    assert_eq!(String::from_utf8(csv).unwrap(), "'a\tb'\t'1'\t'NA'\n'c'\t'2'\t'x'\n");
+
+    // A primary key that is not a column is rejected
+    let result = db.import_csv("other", "a,b\n1,2\n".as_bytes(), &CsvOptions::new().primary_key("c"));
+    assert!(matches!(result, Err(DbError::ColumnNotFound(_))));
+
+    // Clean up
+    fs::remove_file(db_path).unwrap_or(());
+}
+
+/// A reader whose input breaks off after the first chunk
+struct BrokenReader(Option<&'static [u8]>);
+
+impl Read for BrokenReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.0.take() {
+            Some(data) => {
+                buf[..data.len()].copy_from_slice(data);
+                Ok(data.len())
+            }
+            None => Err(io::Error::new(io::ErrorKind::ConnectionReset, "connection lost")),
+        }
+    }
+}
+
+#[test]
+fn test_csv_empty_text_malformed_records_and_read_errors() {
+    let mut db = create_test_db("test_csv_empty_text");
+    db.insert_auto("users", vec![Value::Null, Value::from(""), Value::Null, Value::Null, Value::Null])
+        .unwrap();
+    db.insert_auto("users", vec![Value::Null, Value::Null, Value::Null, Value::Null, Value::Blob(vec![])])
+        .unwrap();
+
+    // Empty text and NULL stay apart, and so do an empty blob and NULL
+    let mut csv = Vec::new();
+    db.export_csv("users", &mut csv, &CsvOptions::new()).unwrap();
+    db.drop_table("users").unwrap();
+    let mut copy = create_test_db("test_csv_empty_text_copy");
+    copy.import_csv("users", csv.as_slice(), &CsvOptions::new()).unwrap();
+    assert_eq!(copy.get("users", &Key::from(1)).unwrap().values[1], Value::from(""));
+    assert_eq!(copy.get("users", &Key::from(2)).unwrap().values[1], Value::Null);
+    assert_eq!(copy.get("users", &Key::from(2)).unwrap().values[4], Value::Blob(vec![]));
+
+    // A new table reports malformed records, and empty numbers are NULL
+    let csv: &[u8] = b"code,count\na,\nb,\xff\nc,5\n";
+    let report = copy.import_csv("counts", csv, &CsvOptions::new()).unwrap();
+    assert_eq!(report.imported, 2);
+    assert_eq!(report.errors.iter().map(|e| e.line).collect::<Vec<_>>(), vec![3]);
+    let table = copy.get_table("counts").unwrap();
+    assert_eq!(table.columns[1].column_type, ColumnType::Integer);
+    assert_eq!(table.get(&Key::from("a")).unwrap().values[1], Value::Null);
+
+    // Input that cannot be read is an I/O error, not malformed CSV
+    let result = copy.import_csv("users", BrokenReader(Some(b"id,name\n")), &CsvOptions::new());
+    assert!(matches!(result, Err(DbError::IoError(_))), "{:?}", result);
+    assert_eq!(copy.get_table("users").unwrap().len(), 2);
+
+    // Clean up
+    fs::remove_file("test_csv_empty_text.db").unwrap_or(());
+    fs::remove_file("test_csv_empty_text_copy.db").unwrap_or(());
+}
@@ -212,3 +212,51 @@ fn test_database_save_and_open() {
     // Clean up
     fs::remove_file("test_save_open.db").unwrap_or(());
 }
+
+#[test]
+fn test_database_upsert_update_columns_and_compare_and_swap() {
+    let mut db = create_test_db("test_upsert_cas");
+
+    // Upsert a row
+    let inserted = db
+        .upsert(
+            "users",
+            "1",
+            vec![
+                Value::Integer(1),
+                Value::Text("Alice".to_string()),
+                Value::Boolean(true),
+            ],
+        )
+        .unwrap();
+    assert!(inserted);
+
+    // Update a single column
+    let key = Key::from("1");
+    db.update_columns("users", &key, &[("active", Value::Boolean(false))])
+        .unwrap();
+
+    // Swap based on the current row
+    let current = db.get("users", &key).unwrap().clone();
+    db.compare_and_swap(
+        "users",
+        &key,
+        &current,
+        vec![
+            Value::Integer(1),
+            Value::Text("Alice Smith".to_string()),
+            Value::Boolean(true),
+        ],
+    )
+    .unwrap();
+
+    // Changes are saved
+    drop(db);
+    let db = Database::open("test_upsert_cas.db").unwrap();
+    let row = db.get("users", &key).unwrap();
+    assert_eq!(row.values[1], Value::Text("Alice Smith".to_string()));
+    assert_eq!(row.values[2], Value::Boolean(true));
+
+    // Clean up
+    fs::remove_file("test_upsert_cas.db").unwrap_or(());
+}
@@ -0,0 +1,167 @@
+use oxi_db::{Column, ColumnType, Database, DbError, Decimal, Key, RoundingMode, Table, Value};
+use std::cmp::Ordering;
+use std::fs;
+
+fn dec(s: &str) -> Decimal {
+    s.parse().unwrap()
+}
+
+#[test]
+fn test_decimal_parse_and_display() {
+    assert_eq!(dec("19.99").to_string(), "19.99");
+    assert_eq!(dec("-0.05").to_string(), "-0.05");
+    assert_eq!(dec("100").to_string(), "100");
+    assert_eq!(dec("1.50").scale(), 2);
+    assert_eq!(dec(".5").to_string(), "0.5");
+
+    assert!("abc".parse::<Decimal>().is_err());
+    assert!("1.2.3".parse::<Decimal>().is_err());
+    assert!("".parse::<Decimal>().is_err());
+}
+
+#[test]
+fn test_decimal_exact_arithmetic() {
+    // 0.1 + 0.2 is exactly 0.3, unlike f64
+    let sum = dec("0.1").checked_add(&dec("0.2")).unwrap();
+    assert_eq!(sum, dec("0.3"));
+
+    let difference = dec("10.00").checked_sub(&dec("0.01")).unwrap();
+    assert_eq!(difference.to_string(), "9.99");
+
+    let product = dec("19.99").checked_mul(&Decimal::from(3)).unwrap();
+    assert_eq!(product.to_string(), "59.97");
+
+    let quotient = dec("10").checked_div(&dec("4"), 2, RoundingMode::HalfEven).unwrap();
+    assert_eq!(quotient.to_string(), "2.50");
+
+    // Equality is numeric, independent of scale
+    assert_eq!(dec("1.0"), dec("1.000"));
+    assert!(dec("-2.5") < dec("1"));
+    assert_eq!((-dec("2.50")).to_string(), "-2.50");
+}
+
+#[test]
+fn test_decimal_rounding_modes() {
+    let cases = [
+        (RoundingMode::Down, "2.5", "2"),
+        (RoundingMode::Up, "2.1", "3"),
+        (RoundingMode::Floor, "-2.1", "-3"),
+        (RoundingMode::Ceiling, "-2.9", "-2"),
+        (RoundingMode::HalfUp, "2.5", "3"),
+        (RoundingMode::HalfUp, "-2.5", "-3"),
+        (RoundingMode::HalfDown, "2.5", "2"),
+        (RoundingMode::HalfEven, "2.5", "2"),
+        (RoundingMode::HalfEven, "3.5", "4"),
+        (RoundingMode::HalfEven, "2.51", "3"),
+    ];
+
+    for (mode, input, expected) in cases {
+        let rounded = dec(input).rescale(0, mode).unwrap();
+        assert_eq!(rounded.to_string(), expected, "{:?} of {}", mode, input);
+    }
+}
+
+#[test]
+fn test_decimal_overflow_and_division_by_zero() {
+    let huge = dec("99999999999999999999999999999999999999");
+    assert!(matches!(huge.checked_add(&dec("1")), Err(DbError::NumericOverflow)));
+    assert!(matches!(huge.checked_mul(&dec("10")), Err(DbError::NumericOverflow)));
+    assert!(matches!(
+        dec("1").checked_div(&Decimal::zero(), 2, RoundingMode::HalfEven),
+        Err(DbError::DivisionByZero)
+    ));
+    assert!(matches!(
+        "999999999999999999999999999999999999999".parse::<Decimal>(),
+        Err(DbError::NumericOverflow)
+    ));
+}
+
+#[test]
+fn test_decimal_column_validation() {
+    let columns = vec![
+        Column::new("id", ColumnType::Integer),
+        Column::new("amount", ColumnType::Decimal(6, 2)),
+    ];
+
+    let mut table = Table::new("invoices", columns, Some("id".to_string()));
+
+    assert!(table.insert("1", vec![Value::Integer(1), Value::Decimal(dec("1234.56"))]).is_ok());
+    assert!(table.insert("2", vec![Value::Integer(2), Value::Decimal(dec("7.5"))]).is_ok());
+
+    // Too many fractional digits
+    assert!(table.insert("3", vec![Value::Integer(3), Value::Decimal(dec("1.234"))]).is_err());
+    // Too many integer digits
+    assert!(table.insert("4", vec![Value::Integer(4), Value::Decimal(dec("12345.6"))]).is_err());
+    // Floats are not accepted in decimal columns
+    assert!(table.insert("5", vec![Value::Integer(5), Value::Float(1.5)]).is_err());
+
+    // Precision is 1 to 38 digits, of which the scale is a part
+    let mut db = Database::in_memory();
+    for (precision, scale) in [(0, 0), (39, 2), (5, 6)] {
+        let column_type = ColumnType::Decimal(precision, scale);
+        let parsed = ColumnType::parse(&column_type.to_string());
+        assert!(matches!(parsed, Err(DbError::ParseError(_))), "{}", column_type);
+        let columns = vec![Column::new("amount", column_type)];
+        assert!(db.create_table("invoices", columns, None).is_err());
+        let nested = ColumnType::Array(Box::new(ColumnType::Decimal(precision, scale)));
+        assert!(db.create_table("invoices", vec![Column::new("amounts", nested)], None).is_err());
+    }
+    assert_eq!(ColumnType::parse("DECIMAL(38, 38)").unwrap(), ColumnType::Decimal(38, 38));
+}
+
+#[test]
+fn test_decimal_serialization_is_lossless() {
+    let value = Value::Decimal(dec("12345678901234567890.123456789012345678"));
+
+    let json = serde_json::to_string(&value).unwrap();
+    assert_eq!(json, r#"{"Decimal":"12345678901234567890.123456789012345678"}"#);
+    let from_json: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(from_json, value);
+
+    let db_path = "test_decimal_persist.db";
+    let mut db = Database::new(db_path);
+    db.create_table(
+        "ledger",
+        vec![Column::new("amount", ColumnType::Decimal(38, 18))],
+        None,
+    )
+    .unwrap();
+    db.insert("ledger", "1", vec![value.clone()]).unwrap();
+
+    drop(db);
+    let db = Database::open(db_path).unwrap();
+    let row = db.get("ledger", &Key::from("1")).unwrap();
+    assert_eq!(row.values[0], value);
+    if let Value::Decimal(d) = &row.values[0] {
+        assert_eq!(d.scale(), 18);
+    }
+
+    // Clean up
+    fs::remove_file(db_path).unwrap_or(());
+}
+
+#[test]
+fn test_decimal_compares_exactly_with_floats() {
+    let compare = |a: &Value, b: &Value| a.compare(b).unwrap();
+
+    // 2^53 + 1 has no float of its own, so no value may round onto another
+    let integer = Value::Integer((1 << 53) + 1);
+    let decimal = Value::Decimal(dec("9007199254740993"));
+    let float = Value::Float(9007199254740992.0);
+    assert_eq!(compare(&integer, &decimal), Ordering::Equal);
+    assert_eq!(compare(&integer, &float), Ordering::Greater);
+    assert_eq!(compare(&decimal, &float), Ordering::Greater);
+    assert_eq!(compare(&float, &decimal), Ordering::Less);
+
+    // The float nearest to 0.1 is slightly above it
+    assert_eq!(compare(&Value::Decimal(dec("0.1")), &Value::Float(0.1)), Ordering::Less);
+    assert_eq!(compare(&Value::Decimal(dec("-0.1")), &Value::Float(-0.1)), Ordering::Greater);
+    assert_eq!(compare(&Value::Decimal(dec("0.50")), &Value::Float(0.5)), Ordering::Equal);
+    assert_eq!(compare(&Value::Decimal(dec("0")), &Value::Float(-0.0)), Ordering::Equal);
+    assert_eq!(compare(&Value::Decimal(dec("-1.5")), &Value::Float(0.25)), Ordering::Less);
+    let largest = Value::Decimal(dec(&"9".repeat(38)));
+    assert_eq!(compare(&largest, &Value::Float(1e38)), Ordering::Greater);
+    assert_eq!(compare(&largest, &Value::Float(1e300)), Ordering::Less);
+    assert_eq!(compare(&Value::Decimal(dec("12.5")), &Value::Float(f64::NAN)), Ordering::Less);
+    assert_eq!(compare(&Value::Decimal(dec("12.5")), &Value::Float(f64::NEG_INFINITY)), Ordering::Greater);
+}
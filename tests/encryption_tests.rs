@@ -0,0 +1,158 @@
+use oxi_db::{Column, ColumnType, Database, DbError, EncryptionKey, Key, Value};
+use std::fs;
+
+fn create_test_db(db_name: &str, key: &EncryptionKey) -> Database {
+    let db_path = format!("{}.db", db_name);
+    fs::remove_file(&db_path).unwrap_or(());
+
+    let mut db = Database::new_encrypted(&db_path, key).unwrap();
+    let columns = vec![
+        Column::new("id", ColumnType::Integer).auto_increment(),
+        Column::new("email", ColumnType::Text),
+    ];
+    db.create_table("customers", columns, Some("id".to_string())).unwrap();
+    db.insert_auto("customers", vec![Value::Null, Value::from("alice@example.com")]).unwrap();
+    db
+}
+
+fn contains(path: &str, needle: &[u8]) -> bool {
+    fs::read(path).unwrap().windows(needle.len()).any(|w| w == needle)
+}
+
+#[test]
+fn test_raw_key_round_trip() {
+    let key = EncryptionKey::Raw([1; 32]);
+    create_test_db("test_encryption_raw", &key);
+    let path = "test_encryption_raw.db";
+
+    assert!(!contains(path, b"alice@example.com"));
+    assert!(!contains(path, b"customers"));
+
+    assert!(matches!(Database::open(path), Err(DbError::KeyRequired)));
+    let wrong = EncryptionKey::Raw([2; 32]);
+    assert!(matches!(Database::open_encrypted(path, &wrong), Err(DbError::WrongKey)));
+    assert_eq!(DbError::WrongKey.to_string(), "Wrong encryption key");
+
+    let db = Database::open_encrypted(path, &key).unwrap();
+    assert!(db.is_encrypted());
+    assert_eq!(db.get("customers", &Key::from(1)).unwrap().values[1], Value::from("alice@example.com"));
+
+    // Clean up
+    fs::remove_file(path).unwrap_or(());
+}
+
+#[test]
+fn test_passphrase_round_trip() {
+    let key = EncryptionKey::passphrase("correct horse battery staple");
+    create_test_db("test_encryption_passphrase", &key);
+    let path = "test_encryption_passphrase.db";
+
+    // Saves reuse the derived key, so reopening gives the same data
+    let mut db = Database::open_encrypted(path, &key).unwrap();
+    db.insert_auto("customers", vec![Value::Null, Value::from("bob@example.com")]).unwrap();
+    drop(db);
+    let db = Database::open_encrypted(path, &key).unwrap();
+    assert_eq!(db.get_table("customers").unwrap().len(), 2);
+    drop(db);
+
+    let wrong = EncryptionKey::passphrase("correct horse battery stapler");
+    assert!(matches!(Database::open_encrypted(path, &wrong), Err(DbError::WrongKey)));
+    let raw = EncryptionKey::Raw([0; 32]);
+    assert!(matches!(Database::open_encrypted(path, &raw), Err(DbError::WrongKey)));
+
+    // Clean up
+    fs::remove_file(path).unwrap_or(());
+}
+
+#[test]
+fn test_tampering_is_detected() {
+    let key = EncryptionKey::Raw([3; 32]);
+    create_test_db("test_encryption_tamper", &key);
+    let path = "test_encryption_tamper.db";
+
+    let mut data = fs::read(path).unwrap();
+    let last = data.len() - 1;
+    data[last] ^= 1;
+    fs::write(path, data).unwrap();
+
+    match Database::open_encrypted(path, &key) {
+        Err(DbError::Corruption { message, .. }) => {
+            assert_eq!(message, "encrypted data failed authentication")
+        }
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+    assert!(matches!(Database::integrity_check(path), Err(DbError::KeyRequired)));
+
+    // A plain file is not opened as an encrypted one
+    Database::new("test_encryption_plain.db").save().unwrap();
+    assert!(matches!(Database::open_encrypted("test_encryption_plain.db", &key), Err(DbError::Other(_))));
+
+    // Clean up
+    fs::remove_file(path).unwrap_or(());
+    fs::remove_file("test_encryption_plain.db").unwrap_or(());
+}
+
+#[test]
+fn test_encrypted_files_are_checked_and_repaired() {
+    let key = EncryptionKey::passphrase("check me");
+    drop(create_test_db("test_encryption_check", &key));
+    let path = "test_encryption_check.db";
+    let repaired = "test_encryption_check_repaired.db";
+
+    let report = Database::integrity_check_encrypted(path, &key).unwrap();
+    assert!(report.is_ok());
+    assert_eq!((report.tables, report.rows), (1, 1));
+    let wrong = EncryptionKey::passphrase("not me");
+    assert!(matches!(Database::integrity_check_encrypted(path, &wrong), Err(DbError::WrongKey)));
+
+    // The repaired copy is encrypted with the same key
+    assert!(Database::repair_encrypted(path, repaired, &key).unwrap().is_ok());
+    assert!(!contains(repaired, b"alice@example.com"));
+    let copy = Database::open_encrypted(repaired, &key).unwrap();
+    assert_eq!(copy.get_table("customers").unwrap().len(), 1);
+    drop(copy);
+
+    // Damage to an encrypted file is one problem, and nothing can be recovered
+    let mut data = fs::read(path).unwrap();
+    let last = data.len() - 1;
+    data[last] ^= 1;
+    fs::write(path, data).unwrap();
+    let report = Database::integrity_check_encrypted(path, &key).unwrap();
+    assert_eq!(report.problems.len(), 1);
+    assert_eq!(report.problems[0].message, "encrypted data failed authentication");
+    assert!(matches!(Database::repair_encrypted(path, repaired, &key), Err(DbError::Corruption { .. })));
+
+    // Clean up
+    fs::remove_file(path).unwrap_or(());
+    fs::remove_file(repaired).unwrap_or(());
+}
+
+#[test]
+fn test_key_rotation_and_backups() {
+    let old_key = EncryptionKey::Raw([4; 32]);
+    let mut db = create_test_db("test_encryption_rotate", &old_key);
+    let path = "test_encryption_rotate.db";
+    let backup_path = "test_encryption_rotate_backup.db";
+
+    // Backups are encrypted with the key of the database
+    db.backup_to(backup_path).unwrap();
+    db.verify_backup(backup_path).unwrap();
+    assert!(!contains(backup_path, b"alice@example.com"));
+    assert!(Database::open_encrypted(backup_path, &old_key).is_ok());
+
+    let new_key = EncryptionKey::Raw([5; 32]);
+    db.set_encryption(Some(&new_key)).unwrap();
+    drop(db);
+    assert!(matches!(Database::open_encrypted(path, &old_key), Err(DbError::WrongKey)));
+    let mut db = Database::open_encrypted(path, &new_key).unwrap();
+    assert!(matches!(db.verify_backup(backup_path), Err(DbError::WrongKey)));
+
+    db.set_encryption(None).unwrap();
+    drop(db);
+    assert!(!Database::open(path).unwrap().is_encrypted());
+    assert!(contains(path, b"alice@example.com"));
+
+    // Clean up
+    fs::remove_file(path).unwrap_or(());
+    fs::remove_file(backup_path).unwrap_or(());
+}
@@ -0,0 +1,97 @@
+use oxi_db::{Column, ColumnType, Database, DbError, EncryptionKey, Expr, Key, Value};
+use std::fs;
+use std::path::Path;
+
+fn create_test_db() -> Database {
+    let mut db = Database::in_memory();
+    let columns = vec![
+        Column::new("id", ColumnType::Integer).auto_increment(),
+        Column::new("name", ColumnType::Text),
+    ];
+    db.create_table("users", columns, Some("id".to_string())).unwrap();
+    for name in ["Alice", "Bob", "Carol"] {
+        db.insert_auto("users", vec![Value::Null, Value::from(name)]).unwrap();
+    }
+    db.create_index("users", "users_name", Expr::column("name")).unwrap();
+    db.create_sequence("tickets", 100, 1).unwrap();
+    db
+}
+
+#[test]
+fn test_in_memory_database() {
+    let mut db = create_test_db();
+    assert!(db.is_in_memory());
+    assert_eq!(db.path(), None);
+
+    db.update("users", &Key::from(2), vec![Value::from(2), Value::from("Robert")]).unwrap();
+    db.delete("users", &Key::from(3)).unwrap();
+    let found = db.find_where("users", &Expr::column("name").eq("Robert")).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(db.next_value("tickets").unwrap(), 100);
+    assert!(matches!(db.get("users", &Key::from(3)), Err(DbError::KeyNotFound)));
+    assert!(matches!(db.insert("missing", 1, vec![]), Err(DbError::TableNotFound)));
+
+    // Saving writes nothing
+    db.save().unwrap();
+    assert!(!Path::new("").exists());
+
+    // Only `in_memory` makes a database that lives in memory
+    let db = Database::new("");
+    assert!(!db.is_in_memory());
+    assert_eq!(db.path(), Some(Path::new("")));
+    assert!(matches!(db.save(), Err(DbError::IoError(_))));
+    assert!(!Path::new(".lock").exists());
+}
+
+#[test]
+fn test_persist_to() {
+    let path = "test_in_memory_persist.db";
+    let moved = "test_in_memory_persist_moved.db";
+    fs::remove_file(path).unwrap_or(());
+    fs::remove_file(moved).unwrap_or(());
+
+    let mut db = create_test_db();
+    assert!(matches!(db.persist_to(""), Err(DbError::Other(_))));
+    db.persist_to(path).unwrap();
+    assert_eq!(db.path(), Some(Path::new(path)));
+    db.insert_auto("users", vec![Value::Null, Value::from("Dave")]).unwrap();
+    assert_eq!(Database::load_into_memory(path).unwrap().get_table("users").unwrap().len(), 4);
+
+    // Moving to another file leaves the first one as it was
+    db.persist_to(moved).unwrap();
+    db.delete("users", &Key::from(1)).unwrap();
+    assert_eq!(Database::open(path).unwrap().get_table("users").unwrap().len(), 4);
+    assert_eq!(Database::load_into_memory(moved).unwrap().get_table("users").unwrap().len(), 3);
+
+    // Clean up
+    fs::remove_file(path).unwrap_or(());
+    fs::remove_file(moved).unwrap_or(());
+}
+
+#[test]
+fn test_load_into_memory() {
+    let path = "test_in_memory_load.db";
+    fs::remove_file(path).unwrap_or(());
+    create_test_db().persist_to(path).unwrap();
+
+    let mut db = Database::load_into_memory(path).unwrap();
+    assert!(db.is_in_memory());
+    db.drop_table("users").unwrap();
+    assert_eq!(Database::open(path).unwrap().get_table("users").unwrap().len(), 3);
+
+    // Encrypted files keep their key through memory and back
+    let key = EncryptionKey::Raw([9; 32]);
+    let mut db = Database::open(path).unwrap();
+    db.set_encryption(Some(&key)).unwrap();
+    drop(db);
+    assert!(matches!(Database::load_into_memory(path), Err(DbError::KeyRequired)));
+    let mut db = Database::open_encrypted(path, &key).unwrap().into_memory();
+    db.insert_auto("users", vec![Value::Null, Value::from("Dave")]).unwrap();
+    assert_eq!(Database::open_encrypted(path, &key).unwrap().get_table("users").unwrap().len(), 3);
+    db.persist_to(path).unwrap();
+    drop(db);
+    assert_eq!(Database::open_encrypted(path, &key).unwrap().get_table("users").unwrap().len(), 4);
+
+    // Clean up
+    fs::remove_file(path).unwrap_or(());
+}
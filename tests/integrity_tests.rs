@@ -0,0 +1,289 @@
+use oxi_db::{Column, ColumnType, Database, DbError, Expr, Key, Value};
+use std::fs;
+
+fn create_test_db(db_name: &str) -> Database {
+    let db_path = format!("{}.db", db_name);
+    fs::remove_file(&db_path).unwrap_or(());
+
+    let mut db = Database::new(&db_path);
+    let columns = vec![
+        Column::new("id", ColumnType::Integer).auto_increment(),
+        Column::new("name", ColumnType::Text),
+    ];
+    db.create_table("users", columns, Some("id".to_string())).unwrap();
+    for name in ["alpha", "bravo", "gamma"] {
+        db.insert_auto("users", vec![Value::Null, Value::from(name)]).unwrap();
+    }
+    db.create_index("users", "users_name", Expr::column("name")).unwrap();
+    db.create_table("tags", vec![Column::new("tag", ColumnType::Text)], Some("tag".to_string()))
+        .unwrap();
+    db.insert("tags", "rust", vec![Value::from("rust")]).unwrap();
+    db.create_sequence("tickets", 1, 1).unwrap();
+    db
+}
+
+/// Replace the first occurrence of `from` in a file with `to`
+fn patch_file(path: &str, from: &[u8], to: &[u8]) {
+    let mut data = fs::read(path).unwrap();
+    let at = data.windows(from.len()).position(|w| w == from).unwrap();
+    data[at..at + to.len()].copy_from_slice(to);
+    fs::write(path, data).unwrap();
+}
+
+/// Replace the first occurrence of `from` in a file with `to`, keeping the
+/// checksum of the page it is in valid
+fn patch_page(path: &str, from: &[u8], to: &[u8]) {
+    let mut data = fs::read(path).unwrap();
+    let at = data.windows(from.len()).position(|w| w == from).unwrap();
+    data[at..at + to.len()].copy_from_slice(to);
+    let mut offset = 8;
+    loop {
+        let length = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        let payload = offset + 8..offset + 8 + length;
+        if payload.contains(&at) {
+            let checksum = crc32fast::hash(&data[payload]);
+            data[offset + 4..offset + 8].copy_from_slice(&checksum.to_le_bytes());
+            break;
+        }
+        offset = payload.end;
+    }
+    fs::write(path, data).unwrap();
+}
+
+/// Bytes of a `Value::Text` in the bincode encoding, preceded by its variant tag
+fn encoded_text(tag: u32, text: &str) -> Vec<u8> {
+    let mut bytes = tag.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&(text.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(text.as_bytes());
+    bytes
+}
+
+#[test]
+fn test_integrity_check_of_sound_files() {
+    let db = create_test_db("test_integrity_sound");
+    let data = fs::read("test_integrity_sound.db").unwrap();
+    assert!(data.starts_with(b"OXIDB\0"));
+
+    let report = Database::integrity_check("test_integrity_sound.db").unwrap();
+    assert!(report.is_ok(), "{:?}", report);
+    assert_eq!((report.tables, report.rows), (2, 4));
+
+    // Files written before the paged format still open and check clean
+    fs::copy("tests/data/baseline.db", "test_integrity_legacy.db").unwrap();
+    let report = Database::integrity_check("test_integrity_legacy.db").unwrap();
+    assert!(report.is_ok(), "{:?}", report);
+    assert_eq!((report.tables, report.rows), (1, 3));
+    let legacy = Database::open("test_integrity_legacy.db").unwrap();
+    let users = legacy.get_table("users").unwrap();
+    let names: Vec<&str> = users.columns.iter().map(|column| column.name.as_str()).collect();
+    assert_eq!(names, ["id", "name", "email", "active"]);
+    assert_eq!(users.primary_key.as_deref(), Some("id"));
+    let row = users.get(&Key::from("2")).unwrap();
+    assert_eq!(row.values[1..], [Value::from("Jane Smith"), Value::from("jane@example.com"), Value::from(true)]);
+    drop(db);
+
+    // Saving converts a legacy file to the paged format
+    legacy.save().unwrap();
+    assert!(fs::read("test_integrity_legacy.db").unwrap().starts_with(b"OXIDB\0"));
+
+    // Clean up
+    fs::remove_file("test_integrity_sound.db").unwrap_or(());
+    fs::remove_file("test_integrity_legacy.db").unwrap_or(());
+}
+
+#[test]
+fn test_damaged_page_is_detected_and_repaired() {
+    create_test_db("test_integrity_damaged");
+    let path = "test_integrity_damaged.db";
+    patch_file(path, b"bravo", b"brave");
+
+    match Database::open(path) {
+        Err(DbError::Corruption { table, offset, message }) => {
+            assert_eq!(table.as_deref(), Some("users"));
+            assert!(offset > 0);
+            assert_eq!(message, "checksum mismatch");
+        }
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+    let report = Database::integrity_check(path).unwrap();
+    assert_eq!(report.problems.len(), 1, "{:?}", report);
+    let problem = &report.problems[0];
+    assert_eq!(problem.table.as_deref(), Some("users"));
+    assert!(problem.offset.is_some());
+    assert!(problem.to_string().ends_with("checksum mismatch"), "{}", problem);
+
+    let repaired_path = "test_integrity_damaged_repaired.db";
+    let report = Database::repair(path, repaired_path).unwrap();
+    assert_eq!((report.tables, report.rows), (2, 3));
+
+    // The other rows, the index and the sequence survive
+    let mut repaired = Database::open(repaired_path).unwrap();
+    assert!(Database::integrity_check(repaired_path).unwrap().is_ok());
+    assert!(matches!(repaired.get("users", &Key::from(2)), Err(DbError::KeyNotFound)));
+    let found = repaired.find_where("users", &Expr::column("name").eq("gamma")).unwrap();
+    assert_eq!(found[0].0, Key::from(3));
+    assert_eq!(repaired.next_value("tickets").unwrap(), 1);
+    assert_eq!(repaired.insert_auto("users", vec![Value::Null, Value::from("delta")]).unwrap(), Key::from(4));
+
+    // Clean up
+    fs::remove_file(path).unwrap_or(());
+    fs::remove_file(repaired_path).unwrap_or(());
+}
+
+#[test]
+fn test_truncated_file_keeps_earlier_pages() {
+    create_test_db("test_integrity_truncated");
+    let path = "test_integrity_truncated.db";
+    let data = fs::read(path).unwrap();
+    let cut = data.windows(5).position(|w| w == b"gamma").unwrap();
+    fs::write(path, &data[..cut]).unwrap();
+
+    let report = Database::integrity_check(path).unwrap();
+    assert_eq!(report.problems.len(), 1, "{:?}", report);
+    assert!(report.problems[0].message.starts_with("truncated page"));
+
+    let repaired_path = "test_integrity_truncated_repaired.db";
+    let report = Database::repair(path, repaired_path).unwrap();
+    assert_eq!((report.tables, report.rows), (2, 3));
+    let repaired = Database::open(repaired_path).unwrap();
+    assert_eq!(repaired.get_table("users").unwrap().len(), 2);
+    assert!(repaired.get_table("users").unwrap().indexes().is_empty());
+
+    // Clean up
+    fs::remove_file(path).unwrap_or(());
+    fs::remove_file(repaired_path).unwrap_or(());
+}
+
+#[test]
+fn test_rows_and_indexes_are_checked() {
+    create_test_db("test_integrity_rows");
+    let path = "test_integrity_rows_patched.db";
+    fs::copy("test_integrity_rows.db", path).unwrap();
+
+    // A row whose text changed no longer matches the index
+    patch_page(path, b"alpha", b"alpho");
+    let report = Database::integrity_check(path).unwrap();
+    assert_eq!(report.problems.len(), 1, "{:?}", report);
+    assert_eq!(report.problems[0].to_string(), "table users: index users_name does not match the rows");
+
+    // A blob in a text column is a row of the wrong type
+    patch_page(path, &encoded_text(3, "gamma"), &encoded_text(5, "gamma"));
+    let report = Database::integrity_check(path).unwrap();
+    assert_eq!(report.problems.len(), 1, "{:?}", report);
+    assert_eq!(report.problems[0].message, "row 3: Type conversion error");
+
+    let repaired_path = "test_integrity_rows_repaired.db";
+    Database::repair(path, repaired_path).unwrap();
+    let repaired = Database::open(repaired_path).unwrap();
+    assert_eq!(repaired.get_table("users").unwrap().len(), 2);
+    let found = repaired.find_where("users", &Expr::column("name").eq("alpho")).unwrap();
+    assert_eq!(found.len(), 1);
+
+    // Clean up
+    fs::remove_file("test_integrity_rows.db").unwrap_or(());
+    fs::remove_file(path).unwrap_or(());
+    fs::remove_file(repaired_path).unwrap_or(());
+}
+
+#[test]
+fn test_invalid_schemas_are_reported() {
+    create_test_db("test_integrity_schema");
+    let path = "test_integrity_schema.db";
+
+    // The primary key of tags, after the tag of its `Some`, names no column
+    let mut primary_key = vec![1u8];
+    primary_key.extend_from_slice(&encoded_text(3, "tag")[4..]);
+    let mut patched = primary_key.clone();
+    patched[primary_key.len() - 1] = b'x';
+    patch_page(path, &primary_key, &patched);
+    match Database::open(path) {
+        Err(DbError::Corruption { table, message, .. }) => {
+            assert_eq!(table.as_deref(), Some("tags"));
+            assert_eq!(message, "invalid schema: Column not found: tax");
+        }
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+    let report = Database::integrity_check(path).unwrap();
+    assert_eq!(report.problems.len(), 1, "{:?}", report);
+    assert_eq!((report.tables, report.rows), (1, 3));
+
+    // Tables are created with valid schemas only
+    let mut db = Database::in_memory();
+    let columns = vec![Column::new("id", ColumnType::Integer), Column::new("id", ColumnType::Text)];
+    assert!(matches!(db.create_table("pairs", columns, None), Err(DbError::Other(_))));
+    let columns = vec![Column::new("id", ColumnType::Integer)];
+    let result = db.create_table("pairs", columns, Some("key".to_string()));
+    assert!(matches!(result, Err(DbError::ColumnNotFound(_))));
+    assert!(db.list_tables().is_empty());
+
+    // Clean up
+    fs::remove_file(path).unwrap_or(());
+}
+
+#[test]
+fn test_pages_of_a_damaged_table_are_dropped() {
+    let path = "test_integrity_damaged_table.db";
+    fs::remove_file(path).unwrap_or(());
+    let mut db = Database::new(path);
+    for (table, keys) in [("drafts", [1, 2]), ("notes", [3, 4])] {
+        db.create_table(table, vec![Column::new("text", ColumnType::Text)], None).unwrap();
+        for key in keys {
+            db.insert(table, key, vec![Value::from(format!("text {}", key))]).unwrap();
+        }
+    }
+    drop(db);
+    patch_file(path, b"notes", b"nodes");
+
+    // The rows of notes fit drafts, but are not taken for its rows
+    let report = Database::integrity_check(path).unwrap();
+    let messages: Vec<_> = report.problems.iter().map(|problem| problem.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        ["checksum mismatch", "row 3 does not belong to a table", "row 4 does not belong to a table"]
+    );
+    assert!(report.problems.iter().all(|problem| problem.table.is_none()));
+    assert_eq!((report.tables, report.rows), (1, 2));
+    let repaired_path = "test_integrity_damaged_table_repaired.db";
+    Database::repair(path, repaired_path).unwrap();
+    let repaired = Database::open(repaired_path).unwrap();
+    assert_eq!(repaired.get_table("drafts").unwrap().len(), 2);
+
+    // Clean up
+    fs::remove_file(path).unwrap_or(());
+    fs::remove_file(repaired_path).unwrap_or(());
+}
+
+#[test]
+fn test_truncation_between_pages_is_detected() {
+    create_test_db("test_integrity_end_page");
+    let path = "test_integrity_end_page.db";
+    let data = fs::read(path).unwrap();
+
+    // Drop the end page: 8 bytes of frame and a 12 byte payload
+    fs::write(path, &data[..data.len() - 20]).unwrap();
+    match Database::open(path) {
+        Err(error @ DbError::Corruption { .. }) => assert_eq!(
+            error.to_string(),
+            format!(
+                "Corrupt database file at offset {} in table users: file is truncated: the end page is missing",
+                data.len() - 20
+            )
+        ),
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+    let report = Database::integrity_check(path).unwrap();
+    assert_eq!(report.problems.len(), 1, "{:?}", report);
+    assert_eq!(report.rows, 4);
+
+    // Other versions are unknown
+    for version in [0u16, 2, 99] {
+        let mut other = data.clone();
+        other[6..8].copy_from_slice(&version.to_le_bytes());
+        fs::write(path, other).unwrap();
+        assert!(matches!(Database::open(path), Err(DbError::Other(_))));
+        assert!(!Database::integrity_check(path).unwrap().is_ok());
+    }
+
+    // Clean up
+    fs::remove_file(path).unwrap_or(());
+}
@@ -0,0 +1,219 @@
+use oxi_db::{Column, ColumnType, Database, DbError, Expr, JsonFormat, Key, Table, Uuid, Value};
+use serde_json::json;
+use std::fs;
+
+fn create_test_db(db_name: &str) -> Database {
+    let db_path = format!("{}.db", db_name);
+    fs::remove_file(&db_path).unwrap_or(());
+
+    let mut db = Database::new(&db_path);
+    let columns = vec![
+        Column::new("id", ColumnType::Integer).auto_increment(),
+        Column::new("name", ColumnType::Text),
+        Column::new("balance", ColumnType::Decimal(10, 2)),
+        Column::new("external_id", ColumnType::Uuid),
+        Column::new("avatar", ColumnType::Blob),
+        Column::new("profile", ColumnType::Json),
+        Column::new(
+            "address",
+            ColumnType::Struct(vec![
+                Column::new("city", ColumnType::Text),
+                Column::new("zip", ColumnType::Integer),
+            ]),
+        ),
+        Column::new("scores", ColumnType::Array(Box::new(ColumnType::Float))),
+    ];
+    db.create_table("users", columns, Some("id".to_string())).unwrap();
+    db
+}
+
+fn sample_row() -> Vec<Value> {
+    vec![
+        Value::Null,
+        Value::from("Alice"),
+        Value::Decimal("1234.50".parse().unwrap()),
+        Value::Uuid(Uuid::parse_str("6f9619ff-8b86-d011-b42d-00cf4fc964ff").unwrap()),
+        Value::Blob(vec![0, 255]),
+        Value::Json(json!({"theme": "dark"})),
+        Value::Struct(vec![Value::from("Paris"), Value::Integer(75001)]),
+        Value::Array(vec![Value::Float(1.5), Value::Null]),
+    ]
+}
+
+#[test]
+fn test_table_json_round_trip() {
+    let mut db = create_test_db("test_json_export_table");
+    db.insert_auto("users", sample_row()).unwrap();
+
+    let mut ndjson = Vec::new();
+    db.export_table_json("users", &mut ndjson, JsonFormat::Ndjson).unwrap();
+    let line: serde_json::Value = serde_json::from_slice(&ndjson).unwrap();
+    assert_eq!(
+        line,
+        json!({
+            "_key": 1,
+            "id": 1,
+            "name": "Alice",
+            "balance": "1234.50",
+            "external_id": "6f9619ff-8b86-d011-b42d-00cf4fc964ff",
+            "avatar": "00ff",
+            "profile": {"theme": "dark"},
+            "address": {"city": "Paris", "zip": 75001},
+            "scores": [1.5, null]
+        })
+    );
+
+    let mut array = Vec::new();
+    db.export_table_json("users", &mut array, JsonFormat::Array).unwrap();
+    let items: Vec<serde_json::Value> = serde_json::from_slice(&array).unwrap();
+    assert_eq!(items, vec![line]);
+
+    // Both layouts import back to the same row
+    for (data, format) in [(ndjson, JsonFormat::Ndjson), (array, JsonFormat::Array)] {
+        let mut copy = create_test_db("test_json_export_table_copy");
+        assert_eq!(copy.import_table_json("users", data.as_slice(), format).unwrap(), 1);
+        assert_eq!(copy.get("users", &Key::from(1)).unwrap(), db.get("users", &Key::from(1)).unwrap());
+    }
+
+    // Clean up
+    fs::remove_file("test_json_export_table.db").unwrap_or(());
+    fs::remove_file("test_json_export_table_copy.db").unwrap_or(());
+}
+
+#[test]
+fn test_database_json_round_trip() {
+    let mut db = create_test_db("test_json_export_db");
+    db.insert_auto("users", sample_row()).unwrap();
+    db.create_index("users", "users_theme", Expr::json_path("profile", "$.theme").unwrap())
+        .unwrap();
+    db.create_table("tags", vec![Column::new("name", ColumnType::Text)], None).unwrap();
+    db.insert("tags", Key::Composite(vec![Key::from("a"), Key::from(1)]), vec![Value::from("rust")])
+        .unwrap();
+    db.create_sequence("invoices", 1000, 10).unwrap();
+    db.next_value("invoices").unwrap();
+
+    let mut dump = Vec::new();
+    db.export_json(&mut dump, JsonFormat::Ndjson).unwrap();
+    let dump = String::from_utf8(dump).unwrap();
+    assert!(dump.starts_with("{\"_sequence\":"));
+
+    let copy_path = "test_json_export_db_copy.db";
+    fs::remove_file(copy_path).unwrap_or(());
+    let mut copy = Database::new(copy_path);
+    assert_eq!(copy.import_json(dump.as_bytes(), JsonFormat::Ndjson).unwrap(), 2);
+
+    // The import was saved, including schema, indexes and sequence state
+    drop(copy);
+    let mut copy = Database::open(copy_path).unwrap();
+    assert_eq!(copy.list_tables(), vec!["tags".to_string(), "users".to_string()]);
+    assert_eq!(copy.get_table("users").unwrap().columns, db.get_table("users").unwrap().columns);
+    assert_eq!(copy.get_table("users").unwrap().indexes()[0].name, "users_theme");
+    let key = Key::Composite(vec![Key::from("a"), Key::from(1)]);
+    assert_eq!(copy.get("tags", &key).unwrap().values[0], Value::from("rust"));
+    assert_eq!(copy.next_value("invoices").unwrap(), 1010);
+
+    // A second import collides with the existing tables
+    let result = copy.import_json(dump.as_bytes(), JsonFormat::Ndjson);
+    assert!(matches!(result, Err(DbError::SequenceExists(_))));
+
+    // Clean up
+    fs::remove_file("test_json_export_db.db").unwrap_or(());
+    fs::remove_file(copy_path).unwrap_or(());
+}
+
+#[test]
+fn test_json_import_is_all_or_nothing() {
+    let mut db = create_test_db("test_json_import_errors");
+
+    let input = "{\"name\": \"Alice\"}\n\n{\"name\": \"Bob\", \"balance\": \"lots\"}\n";
+    match db.import_table_json("users", input.as_bytes(), JsonFormat::Ndjson) {
+        Err(DbError::ParseError(message)) => assert!(message.starts_with("line 3: "), "{}", message),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(db.get_table("users").unwrap().is_empty());
+
+    let result = db.import_table_json("users", "[{\"nickname\": 1}]".as_bytes(), JsonFormat::Array);
+    assert!(matches!(result, Err(DbError::ColumnNotFound(_))));
+    let result = db.import_table_json("users", "{\"name\": ".as_bytes(), JsonFormat::Ndjson);
+    assert!(matches!(result, Err(DbError::ParseError(_))));
+    let result = db.import_json("{\"name\": \"Alice\"}".as_bytes(), JsonFormat::Ndjson);
+    assert!(matches!(result, Err(DbError::ParseError(_))));
+
+    // Clean up
+    fs::remove_file("test_json_import_errors.db").unwrap_or(());
+}
+
+#[test]
+fn test_values_from_json_use_the_schema() {
+    let table = Table::new(
+        "t",
+        vec![
+            Column::new("amount", ColumnType::Decimal(6, 2)),
+            Column::new("flags", ColumnType::Array(Box::new(ColumnType::Boolean))),
+            Column::new("point", ColumnType::Struct(vec![Column::new("x", ColumnType::Integer)])),
+        ],
+        None,
+    );
+
+    let row = table
+        .row_from_json(&json!({"amount": 12.5, "flags": [true, null], "point": [3]}))
+        .unwrap();
+    assert_eq!(row.values[0], Value::Decimal("12.50".parse().unwrap()));
+    assert_eq!(row.values[1], Value::Array(vec![Value::Boolean(true), Value::Null]));
+    assert_eq!(row.values[2], Value::Struct(vec![Value::Integer(3)]));
+
+    assert!(table.row_from_json(&json!({"amount": true})).is_err());
+    assert!(table.row_from_json(&json!({"point": {"x": "three"}})).is_err());
+    assert!(table.row_from_json(&json!([1, 2])).is_err());
+    assert_eq!(
+        Value::from_json_as(&json!("00ff"), &ColumnType::Blob).unwrap(),
+        Value::Blob(vec![0, 255])
+    );
+}
+
+#[test]
+fn test_json_keeps_keys_and_the_auto_increment_counter() {
+    let mut db = create_test_db("test_json_export_keys");
+    for _ in 0..3 {
+        db.insert_auto("users", sample_row()).unwrap();
+    }
+    // The key of a row need not be its primary key value
+    let mut row = sample_row();
+    row[0] = Value::Integer(10);
+    db.insert("users", 20, row).unwrap();
+    let last = db.insert_auto("users", sample_row()).unwrap();
+    assert_eq!(last, Key::from(11));
+    db.delete("users", &last).unwrap();
+
+    let uuid = Uuid::parse_str("6f9619ff-8b86-d011-b42d-00cf4fc964ff").unwrap();
+    let keys = [
+        Key::Bytes(vec![0, 255]),
+        Key::Uuid(uuid),
+        Key::Composite(vec![Key::Uuid(uuid), Key::Bytes(vec![1])]),
+        Key::from("6f9619ff-8b86-d011-b42d-00cf4fc964ff"),
+    ];
+    db.create_table("tags", vec![Column::new("name", ColumnType::Text)], None).unwrap();
+    for key in &keys {
+        db.insert("tags", key.clone(), vec![Value::from("rust")]).unwrap();
+    }
+
+    let mut dump = Vec::new();
+    db.export_json(&mut dump, JsonFormat::Ndjson).unwrap();
+    assert!(String::from_utf8_lossy(&dump).contains("\"_key\":{\"bytes\":\"00ff\"}"));
+
+    let mut copy = Database::in_memory();
+    copy.import_json(dump.as_slice(), JsonFormat::Ndjson).unwrap();
+    for key in &keys {
+        assert!(copy.get("tags", key).is_ok(), "{:?}", key);
+    }
+    assert_eq!(copy.get_table("tags").unwrap().len(), keys.len());
+
+    assert_eq!(copy.get("users", &Key::from(20)).unwrap().values[0], Value::Integer(10));
+
+    // Ids of deleted rows are not handed out again
+    assert_eq!(copy.get_table("users").unwrap().next_auto_increment(), Some(12));
+    assert_eq!(copy.insert_auto("users", sample_row()).unwrap(), Key::from(12));
+
+    // Clean up
+    fs::remove_file("test_json_export_keys.db").unwrap_or(());
+}
@@ -0,0 +1,199 @@
+use oxi_db::{Column, ColumnType, Database, DbError, Expr, JsonPath, Key, Table, Value};
+use serde_json::json;
+use std::fs;
+
+fn create_people_table() -> Table {
+    let columns = vec![
+        Column::new("name", ColumnType::Text),
+        Column::new("profile", ColumnType::Json),
+    ];
+
+    let mut table = Table::new("people", columns, None);
+
+    table.insert(
+        "1",
+        vec![
+            Value::from("Alice"),
+            Value::Json(json!({"address": {"city": "Paris"}, "tags": ["admin", "staff"], "age": 31})),
+        ],
+    ).unwrap();
+
+    table.insert(
+        "2",
+        vec![
+            Value::from("Bob"),
+            Value::Json(json!({"address": {"city": "Berlin"}, "tags": ["staff"], "age": 45})),
+        ],
+    ).unwrap();
+
+    table.insert(
+        "3",
+        vec![
+            Value::from("Carol"),
+            Value::Json(json!({"tags": []})),
+        ],
+    ).unwrap();
+
+    table
+}
+
+#[test]
+fn test_json_path_parse_and_evaluate() {
+    let document = json!({"address": {"city": "Paris"}, "tags": ["a", "b"], "first name": "Al"});
+
+    let path = JsonPath::parse("$.address.city").unwrap();
+    assert_eq!(path.evaluate(&document), Some(&json!("Paris")));
+
+    let path = JsonPath::parse("$.tags[1]").unwrap();
+    assert_eq!(path.evaluate(&document), Some(&json!("b")));
+
+    let path = JsonPath::parse("$[\"first name\"]").unwrap();
+    assert_eq!(path.evaluate(&document), Some(&json!("Al")));
+    assert_eq!(path.to_string(), "$[\"first name\"]");
+
+    let path = JsonPath::parse("$.missing.field").unwrap();
+    assert_eq!(path.evaluate(&document), None);
+
+    assert!(JsonPath::parse("address.city").is_err());
+    assert!(JsonPath::parse("$.tags[x]").is_err());
+    assert!(JsonPath::parse("$..city").is_err());
+}
+
+#[test]
+fn test_json_column_validation() {
+    let mut table = create_people_table();
+
+    // Text is parsed into a document when it is valid JSON
+    table.insert("4", vec![Value::from("Dan"), Value::from(r#"{"age": 20}"#)]).unwrap();
+    let row = table.get(&Key::from("4")).unwrap();
+    assert_eq!(row.values[1], Value::Json(json!({"age": 20})));
+
+    // Invalid JSON is rejected
+    let result = table.insert("5", vec![Value::from("Eve"), Value::from("{not json")]);
+    assert!(matches!(result, Err(DbError::ParseError(_))));
+
+    // Other value types are rejected
+    let result = table.insert("6", vec![Value::from("Fay"), Value::Integer(1)]);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_json_path_filters() {
+    let table = create_people_table();
+
+    let filter = Expr::json_path("profile", "$.address.city").unwrap().eq("Paris");
+    let rows = table.find_where(&filter).unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].1.values[0], Value::from("Alice"));
+
+    let filter = Expr::json_path("profile", "$.age").unwrap().gt(40);
+    let rows = table.find_where(&filter).unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].1.values[0], Value::from("Bob"));
+
+    let filter = Expr::json_path("profile", "$.address").unwrap().is_null();
+    let rows = table.find_where(&filter).unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].1.values[0], Value::from("Carol"));
+
+    let filter = Expr::json_path("profile", "$.tags[0]").unwrap().eq("staff")
+        .or(Expr::column("name").eq("Alice"));
+    assert_eq!(table.find_where(&filter).unwrap().len(), 2);
+
+    let filter = Expr::column("nickname").eq("Al");
+    assert!(matches!(table.find_where(&filter), Err(DbError::ColumnNotFound(_))));
+}
+
+#[test]
+fn test_json_path_index() {
+    let mut table = create_people_table();
+
+    let city = Expr::json_path("profile", "$.address.city").unwrap();
+    table.create_index("people_city", city.clone()).unwrap();
+    assert!(matches!(
+        table.create_index("people_city", city.clone()),
+        Err(DbError::IndexExists(_))
+    ));
+    assert!(table.create_index("bad", Expr::json_path("name", "$.x").unwrap()).is_err());
+
+    let index = &table.indexes()[0];
+    assert_eq!(index.lookup(&Value::from("Paris")), vec![Key::from("1")]);
+
+    // The index follows updates and deletes
+    table.update(
+        &Key::from("2"),
+        vec![Value::from("Bob"), Value::Json(json!({"address": {"city": "Paris"}}))],
+    ).unwrap();
+    let rows = table.find_where(&city.clone().eq("Paris")).unwrap();
+    assert_eq!(rows.len(), 2);
+
+    table.delete(&Key::from("1")).unwrap();
+    let rows = table.find_where(&city.clone().eq("Paris")).unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].0, Key::from("2"));
+    assert!(table.find_where(&city.eq("Berlin")).unwrap().is_empty());
+
+    table.drop_index("people_city").unwrap();
+    assert!(table.indexes().is_empty());
+}
+
+#[test]
+fn test_json_index_order_ignores_key_order() {
+    let mut table = Table::new("docs", vec![Column::new("doc", ColumnType::Json)], None);
+    let docs = [
+        json!({"a": 1, "b": 2}),
+        json!({"b": 2, "a": 1}),
+        json!({"a": 1, "b": [2, {"d": 4, "c": 3}]}),
+        json!({"b": [2, {"c": 3, "d": 4}], "a": 1}),
+        json!({"a": 2}),
+        json!([1, 2]),
+        json!("text"),
+    ];
+    for (i, doc) in docs.iter().enumerate() {
+        table.insert(Key::from(i as i64), vec![Value::Json(doc.clone())]).unwrap();
+    }
+    table.insert(Key::from(99), vec![Value::Null]).unwrap();
+    table.create_index("docs_doc", Expr::column("doc")).unwrap();
+
+    // Index lookups find the same rows as a scan, whatever the key order
+    for doc in &docs {
+        let filter = Expr::column("doc").eq(Value::Json(doc.clone()));
+        let expected = table.find(|row| filter.matches(&table.columns, row).unwrap());
+        let keys: Vec<Key> = expected.iter().map(|(key, _)| key.clone()).collect();
+        assert_eq!(table.indexes()[0].lookup(&Value::Json(doc.clone())), keys, "{}", doc);
+        assert_eq!(table.find_where(&filter).unwrap(), expected, "{}", doc);
+    }
+    assert_eq!(table.indexes()[0].lookup(&Value::Json(json!({"b": 2, "a": 1}))).len(), 2);
+
+    // Comparisons with NULL are unknown, and so are their negations
+    let filter = Expr::column("doc").eq(Value::Json(json!({"a": 2}))).not();
+    assert_eq!(table.find_where(&filter).unwrap().len(), docs.len() - 1);
+    let filter = filter.or(Expr::column("doc").is_null());
+    assert_eq!(table.find_where(&filter).unwrap().len(), docs.len());
+}
+
+#[test]
+fn test_json_persistence() {
+    let db_path = "test_json_persist.db";
+    let mut db = Database::new(db_path);
+
+    db.create_table(
+        "events",
+        vec![Column::new("payload", ColumnType::Json)],
+        None,
+    ).unwrap();
+    db.insert("events", "1", vec![Value::Json(json!({"kind": "login", "ok": true}))]).unwrap();
+    db.create_index("events", "events_kind", Expr::json_path("payload", "$.kind").unwrap()).unwrap();
+
+    drop(db);
+    let db = Database::open(db_path).unwrap();
+    let row = db.get("events", &Key::from("1")).unwrap();
+    assert_eq!(row.values[0], Value::Json(json!({"kind": "login", "ok": true})));
+
+    let filter = Expr::json_path("payload", "$.kind").unwrap().eq("login");
+    assert_eq!(db.find_where("events", &filter).unwrap().len(), 1);
+    assert_eq!(db.get_table("events").unwrap().indexes().len(), 1);
+
+    // Clean up
+    fs::remove_file(db_path).unwrap_or(());
+}
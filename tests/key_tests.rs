@@ -0,0 +1,118 @@
+use oxi_db::{Column, ColumnType, Database, Key, Table, Uuid, Value};
+use std::fs;
+
+fn sample_keys() -> Vec<Key> {
+    vec![
+        Key::from(i64::MIN),
+        Key::from(-10),
+        Key::from(-1),
+        Key::from(0),
+        Key::from(2),
+        Key::from(10),
+        Key::from(i64::MAX),
+        Key::from(""),
+        Key::from("a"),
+        Key::from("a\0"),
+        Key::from("a\0b"),
+        Key::from("ab"),
+        Key::from("b"),
+        Key::from(vec![0u8]),
+        Key::from(vec![0u8, 0]),
+        Key::from(vec![1u8, 255]),
+        Key::from(Uuid::nil()),
+        Key::from(Uuid::max()),
+        Key::Composite(vec![]),
+        Key::Composite(vec![Key::from(1)]),
+        Key::Composite(vec![Key::from(1), Key::from("x")]),
+        Key::Composite(vec![Key::from(2)]),
+        Key::Composite(vec![Key::from("a"), Key::Composite(vec![Key::from(3)])]),
+    ]
+}
+
+#[test]
+fn test_integer_keys_sort_numerically() {
+    assert!(Key::from(2) < Key::from(10));
+    assert!(Key::from(-5) < Key::from(3));
+    assert!(Key::from("10") < Key::from("2"));
+}
+
+#[test]
+fn test_key_encoding_round_trip() {
+    for key in sample_keys() {
+        let encoded = key.encode();
+        assert_eq!(Key::decode(&encoded).unwrap(), key);
+    }
+
+    assert!(Key::decode(&[]).is_err());
+    assert!(Key::decode(&[0x01, 0x00]).is_err());
+    assert!(Key::decode(&[0x02, b'a']).is_err());
+    assert!(Key::decode(&[0x09]).is_err());
+}
+
+#[test]
+fn test_key_encoding_preserves_order() {
+    let keys = sample_keys();
+
+    for a in &keys {
+        for b in &keys {
+            assert_eq!(
+                a.encode().cmp(&b.encode()),
+                a.cmp(b),
+                "encoding order differs for {:?} and {:?}",
+                a,
+                b
+            );
+        }
+    }
+
+    // The sample list is written in ascending order
+    let mut sorted = keys.clone();
+    sorted.sort();
+    assert_eq!(sorted, keys);
+}
+
+#[test]
+fn test_key_display() {
+    assert_eq!(Key::from(42).to_string(), "42");
+    assert_eq!(Key::from("user_1").to_string(), "user_1");
+    assert_eq!(Key::from(vec![0xde, 0xad]).to_string(), "0xdead");
+    assert_eq!(
+        Key::Composite(vec![Key::from("orders"), Key::from(7)]).to_string(),
+        "(orders, 7)"
+    );
+}
+
+#[test]
+fn test_table_scans_follow_key_order() {
+    let mut table = Table::new("numbers", vec![Column::new("n", ColumnType::Integer)], None);
+
+    for n in [10, 2, 33, 1, -4] {
+        table.insert(n, vec![Value::Integer(n)]).unwrap();
+    }
+
+    let keys: Vec<Key> = table.get_all().into_iter().map(|(k, _)| k).collect();
+    assert_eq!(
+        keys,
+        vec![Key::from(-4), Key::from(1), Key::from(2), Key::from(10), Key::from(33)]
+    );
+}
+
+#[test]
+fn test_typed_keys_persistence() {
+    let db_path = "test_typed_keys_persist.db";
+    let mut db = Database::new(db_path);
+
+    db.create_table("items", vec![Column::new("n", ColumnType::Integer)], None).unwrap();
+    db.insert("items", 7, vec![Value::Integer(7)]).unwrap();
+    db.insert("items", Key::Composite(vec![Key::from("a"), Key::from(1)]), vec![Value::Integer(1)]).unwrap();
+
+    drop(db);
+    let db = Database::open(db_path).unwrap();
+    assert_eq!(db.get("items", &Key::from(7)).unwrap().values[0], Value::Integer(7));
+    assert!(db.get("items", &Key::from("7")).is_err());
+    let composite = Key::Composite(vec![Key::from("a"), Key::from(1)]);
+    assert_eq!(db.get("items", &composite).unwrap().values[0], Value::Integer(1));
+
+    // Clean up
+    fs::remove_file(db_path).unwrap_or(());
+}
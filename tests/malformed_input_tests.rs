@@ -0,0 +1,195 @@
+use oxi_db::{
+    Column, ColumnType, Compression, Database, DbError, EncryptionKey, Filter, Key, MemoryStorage, Storage, Value,
+};
+use std::path::Path;
+use std::sync::Arc;
+
+const PATH: &str = "malformed.db";
+
+/// A deterministic generator of mutations
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, bound: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % bound as u64) as usize
+    }
+}
+
+/// The bytes of a database with every kind of page
+fn valid_file() -> Vec<u8> {
+    let storage = MemoryStorage::new();
+    let mut db = Database::new(PATH).with_storage(Arc::new(storage.clone()));
+    let columns = vec![
+        Column::new("id", ColumnType::Integer).auto_increment(),
+        Column::new("tags", ColumnType::Array(Box::new(ColumnType::Text))),
+        Column::new("body", ColumnType::Text),
+    ];
+    db.create_table("posts", columns, Some("id".to_string())).unwrap();
+    let tags = Value::Array(vec![Value::from("rust"), Value::from("db")]);
+    db.insert_auto("posts", vec![Value::Null, tags.clone(), Value::from("short")]).unwrap();
+    db.insert_auto("posts", vec![Value::Null, tags, Value::from("long ".repeat(1000))]).unwrap();
+    db.create_index("posts", "posts_body", oxi_db::Expr::column("body")).unwrap();
+    db.create_sequence("tickets", 1, 1).unwrap();
+    db.set_compression("posts", Compression::Lz4).unwrap();
+    storage.read(Path::new(PATH)).unwrap()
+}
+
+/// Offset and length of every page payload in a file
+fn payloads(data: &[u8]) -> Vec<(usize, usize)> {
+    let mut pages = Vec::new();
+    let mut offset = 8;
+    while offset + 8 <= data.len() {
+        let length = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        pages.push((offset + 8, length));
+        offset += 8 + length;
+    }
+    pages
+}
+
+/// Store a new checksum for the page whose payload starts at `start`
+fn fix_checksum(data: &mut [u8], start: usize, length: usize) {
+    let checksum = crc32fast::hash(&data[start..start + length]);
+    data[start - 4..start].copy_from_slice(&checksum.to_le_bytes());
+}
+
+/// Open the bytes as a database file; it must fail or succeed, not panic
+fn open(data: &[u8]) -> oxi_db::Result<Database> {
+    let storage = MemoryStorage::new();
+    storage.write(Path::new(PATH), 0, data).unwrap();
+    Database::open_in(Arc::new(storage), PATH)
+}
+
+fn corruption_message(result: oxi_db::Result<Database>) -> String {
+    match result {
+        Err(DbError::Corruption { message, .. }) => message,
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_mutated_files_fail_cleanly() {
+    let valid = valid_file();
+    assert!(open(&valid).is_ok());
+    let pages = payloads(&valid);
+
+    let mut rng = Rng(0x5eed);
+    for _ in 0..3000 {
+        let mut data = valid.clone();
+        // Mutate one page and give it a matching checksum, so the damage
+        // reaches the decoder instead of stopping at the checksum
+        let (start, length) = pages[rng.below(pages.len())];
+        for _ in 0..1 + rng.below(4) {
+            let at = start + rng.below(length);
+            data[at] = match rng.below(3) {
+                0 => rng.below(256) as u8,
+                1 => 0xff,
+                _ => data[at] ^ (1 << rng.below(8)),
+            };
+        }
+        fix_checksum(&mut data, start, length);
+        if let Ok(db) = open(&data) {
+            for name in db.list_tables() {
+                db.get_table(&name).unwrap().checksum().unwrap();
+            }
+        }
+        open(&data[..rng.below(data.len())]).ok();
+    }
+}
+
+#[test]
+fn test_deeply_nested_values_are_rejected() {
+    // A value nested inside 100 000 arrays, as bincode writes it
+    let mut encoded = Vec::new();
+    for _ in 0..100_000 {
+        encoded.extend_from_slice(&8u32.to_le_bytes());
+        encoded.extend_from_slice(&1u64.to_le_bytes());
+    }
+    encoded.extend_from_slice(&0u32.to_le_bytes());
+    let error = bincode::deserialize::<Value>(&encoded).unwrap_err();
+    assert_eq!(error.to_string(), "nested deeper than 128 levels");
+
+    let mut nested = Value::Null;
+    for _ in 0..100 {
+        nested = Value::Array(vec![nested]);
+    }
+    let encoded = bincode::serialize(&nested).unwrap();
+    assert_eq!(bincode::deserialize::<Value>(&encoded).unwrap(), nested);
+
+    // JSON goes through the same check
+    let json = format!("{}null{}", "{\"Array\":[".repeat(200), "]}".repeat(200));
+    assert!(serde_json::from_str::<Value>(&json).is_err());
+
+    // So do filters, literals, column types, keys and key encodings
+    let deep = |open: &str, inner: &str, close: &str| {
+        format!("{}{}{}", open.repeat(10_000), inner, close.repeat(10_000))
+    };
+    let parse_error = |result: oxi_db::Result<()>| matches!(result, Err(DbError::ParseError(_)));
+    assert!(parse_error(Filter::parse(&deep("(", "a = 1", ")")).map(drop)));
+    assert!(parse_error(Filter::parse(&deep("NOT ", "a = 1", "")).map(drop)));
+    assert!(parse_error(Value::parse_literal(&deep("[", "1", "]")).map(drop)));
+    assert!(parse_error(Value::parse_literal(&deep("{", "1", "}")).map(drop)));
+    assert!(parse_error(ColumnType::parse(&deep("ARRAY(", "INTEGER", ")")).map(drop)));
+    assert!(parse_error(Key::parse(&deep("(", "1", ")")).map(drop)));
+    assert!(parse_error(Key::decode(&[5u8; 10_000]).map(drop)));
+    let sql = format!("CREATE TABLE t (v JSON); INSERT INTO t VALUES ({});", deep("[", "1", "]"));
+    assert!(Database::in_memory().restore(sql.as_bytes()).is_err());
+    let literal = format!("{}1{}", "[".repeat(100), "]".repeat(100));
+    assert_eq!(Value::parse_literal(&literal).unwrap().to_string(), literal);
+}
+
+#[test]
+fn test_crafted_sizes_and_rows() {
+    let valid = valid_file();
+
+    // A compressed page claiming to expand to 4 GiB
+    let mut data = valid.clone();
+    let (start, length) = payloads(&valid)
+        .into_iter()
+        .find(|&(start, _)| valid[start..start + 4] == 6u32.to_le_bytes())
+        .unwrap();
+    // Variant, codec and byte count come before the size of the LZ4 block
+    data[start + 16..start + 20].copy_from_slice(&u32::MAX.to_le_bytes());
+    fix_checksum(&mut data, start, length);
+    assert_eq!(corruption_message(open(&data)), "compressed page claims an impossible size");
+
+    // A row whose text became an integer
+    let storage = MemoryStorage::new();
+    let mut db = Database::new(PATH).with_storage(Arc::new(storage.clone()));
+    db.create_table("notes", vec![Column::new("text", ColumnType::Text)], None).unwrap();
+    db.insert("notes", 1, vec![Value::from("hello")]).unwrap();
+    let mut data = storage.read(Path::new(PATH)).unwrap();
+    let (start, length) = payloads(&data)[1];
+    let at = start + length - 4 - 5 - 8;
+    assert_eq!(data[at..at + 4], 3u32.to_le_bytes());
+    data[at] = 1;
+    fix_checksum(&mut data, start, length);
+    assert_eq!(corruption_message(open(&data)), "row 1: Type conversion error");
+
+    // Argon2 costs that would take gigabytes and hours
+    let key = EncryptionKey::passphrase("secret");
+    let storage = MemoryStorage::new();
+    Database::new_encrypted(PATH, &key).unwrap().with_storage(Arc::new(storage.clone())).save().unwrap();
+    let mut data = storage.read(Path::new(PATH)).unwrap();
+    data[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+    storage.write(Path::new(PATH), 0, &data).unwrap();
+    let result = Database::open_encrypted_in(Arc::new(storage), PATH, &key);
+    assert_eq!(corruption_message(result), "key derivation costs out of range");
+}
+
+#[test]
+fn test_malformed_text_input() {
+    let mut rng = Rng(42);
+    let alphabet: Vec<char> = "()[]{}'\"$.,;=<>!-+*/ \\\n0123456789abcxyzNULLANDORWHERE CREATE TABLE INSERT INTO VALUES é"
+        .chars()
+        .collect();
+    for _ in 0..3000 {
+        let text: String = (0..rng.below(40)).map(|_| alphabet[rng.below(alphabet.len())]).collect();
+        let _ = Filter::parse(&text);
+        let _ = Value::parse_literal(&text);
+        let _ = Database::in_memory().restore(text.as_bytes());
+        let _ = Database::in_memory().import_json(text.as_bytes(), oxi_db::JsonFormat::Ndjson);
+    }
+}
@@ -0,0 +1,118 @@
+use oxi_db::{Column, ColumnType, CompareOp, Decimal, Expr, Filter, Value};
+
+#[test]
+fn test_parse_literals() {
+    assert_eq!(Value::parse_literal("NULL").unwrap(), Value::Null);
+    assert_eq!(Value::parse_literal("true").unwrap(), Value::Boolean(true));
+    assert_eq!(Value::parse_literal("-42").unwrap(), Value::Integer(-42));
+    assert_eq!(
+        Value::parse_literal("19.990").unwrap(),
+        Value::Decimal("19.990".parse::<Decimal>().unwrap())
+    );
+    assert_eq!(Value::parse_literal("1.5e3").unwrap(), Value::Float(1500.0));
+    assert_eq!(Value::parse_literal("\"hi\"").unwrap(), Value::from("hi"));
+    assert_eq!(Value::parse_literal("'it''s'").unwrap(), Value::from("it's"));
+    assert_eq!(Value::parse_literal("x'DEad'").unwrap(), Value::Blob(vec![0xde, 0xad]));
+    assert_eq!(
+        Value::parse_literal("[1, 'a', []]").unwrap(),
+        Value::Array(vec![Value::Integer(1), Value::from("a"), Value::Array(vec![])])
+    );
+
+    assert!(Value::parse_literal("'unterminated").is_err());
+    assert!(Value::parse_literal("x'abc'").is_err());
+    assert!(Value::parse_literal("1 2").is_err());
+    assert!(Value::parse_literal("bare").is_err());
+}
+
+#[test]
+fn test_parse_filters() {
+    let filter = Filter::parse("age >= 18 AND name != 'Bob'").unwrap();
+    assert_eq!(filter, Expr::column("age").ge(18).and(Expr::column("name").ne("Bob")));
+
+    // AND binds tighter than OR
+    let filter = Filter::parse("a = 1 OR b = 2 AND c = 3").unwrap();
+    let expected = Expr::column("a")
+        .eq(1)
+        .or(Expr::column("b").eq(2).and(Expr::column("c").eq(3)));
+    assert_eq!(filter, expected);
+
+    let filter = Filter::parse("NOT (a = 1 OR b <> 2)").unwrap();
+    let expected = Expr::column("a").eq(1).or(Expr::column("b").ne(2)).not();
+    assert_eq!(filter, expected);
+
+    let filter = Filter::parse("profile$.address[\"zip code\"] = '75001'").unwrap();
+    let expected = Expr::json_path("profile", "$.address[\"zip code\"]").unwrap().eq("75001");
+    assert_eq!(filter, expected);
+
+    let filter = Filter::parse("email IS NOT NULL AND tags CONTAINS 'rust'").unwrap();
+    let expected = Expr::column("email")
+        .is_null()
+        .not()
+        .and(Expr::column("tags").contains("rust"));
+    assert_eq!(filter, expected);
+
+    let filter = Filter::parse("ANY(scores) < 10").unwrap();
+    assert_eq!(filter, Expr::column("scores").any(CompareOp::Lt, 10));
+}
+
+#[test]
+fn test_parse_filter_errors() {
+    assert!(Filter::parse("").is_err());
+    assert!(Filter::parse("age >=").is_err());
+    assert!(Filter::parse("age 18").is_err());
+    assert!(Filter::parse("(age = 1").is_err());
+    assert!(Filter::parse("age = 1 extra").is_err());
+    assert!(Filter::parse("name = 'a' # comment").is_err());
+    assert!(Filter::parse("profile$.[0] = 1").is_err());
+    assert!(Filter::parse("first-name = 'a'").is_err());
+}
+
+#[test]
+fn test_filter_display_round_trip() {
+    let inputs = [
+        "age >= 18",
+        "(a = 1 OR NOT b IS NULL)",
+        "profile$.address.city = \"Paris\"",
+        "ANY(scores) > 90",
+        "tags CONTAINS \"rust\"",
+        // Quotes inside text and member names
+        "name = 'it''s'",
+        "quote = \"say \"\"hi\"\"\"",
+        "profile$[\"a \"\"b\"\" ]c\"] = 'x'",
+        "profile$['it''s'][0] = 1",
+    ];
+
+    for input in inputs {
+        let filter = Filter::parse(input).unwrap();
+        assert_eq!(Filter::parse(&filter.to_string()).unwrap(), filter, "{}", filter);
+    }
+
+    // Quotes are doubled the way the parser reads them
+    let filter = Filter::parse("name = 'say \"hi\"'").unwrap();
+    assert_eq!(filter.to_string(), "name = \"say \"\"hi\"\"\"");
+    let filter = Filter::parse("profile$['a\"]b'] IS NULL").unwrap();
+    assert_eq!(filter.to_string(), "profile$[\"a\"\"]b\"] IS NULL");
+}
+
+#[test]
+fn test_parse_column_types() {
+    let types = [
+        ColumnType::Integer,
+        ColumnType::Decimal(12, 2),
+        ColumnType::Enum(vec!["open".into(), "it's done".into()]),
+        ColumnType::Array(Box::new(ColumnType::Array(Box::new(ColumnType::Uuid)))),
+        ColumnType::Struct(vec![
+            Column::new("city", ColumnType::Text),
+            Column::new("tags", ColumnType::Array(Box::new(ColumnType::Json))),
+        ]),
+    ];
+    for column_type in types {
+        assert_eq!(ColumnType::parse(&column_type.to_string()).unwrap(), column_type);
+    }
+
+    assert_eq!(ColumnType::parse("varchar").unwrap(), ColumnType::Text);
+    assert_eq!(ColumnType::parse("NUMERIC(5)").unwrap(), ColumnType::Decimal(5, 0));
+    assert!(ColumnType::parse("ENUM()").is_err());
+    assert!(ColumnType::parse("ARRAY(TEXT").is_err());
+    assert!(ColumnType::parse("STRING TEXT").is_err());
+}
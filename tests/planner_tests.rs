@@ -0,0 +1,288 @@
+use oxi_db::{Column, ColumnType, Database, DbError, Expr, Filter, Key, Operator, Query, Value};
+use serde_json::json;
+use std::cmp::Ordering;
+
+fn create_people() -> Database {
+    let mut db = Database::in_memory();
+    let columns = vec![
+        Column::new("id", ColumnType::Integer).auto_increment(),
+        Column::new("city", ColumnType::Text),
+        Column::new("age", ColumnType::Integer),
+    ];
+    db.create_table("people", columns, Some("id".to_string()))
+        .unwrap();
+    let cities = ["Paris", "Berlin", "Oslo"];
+    for i in 0..1000 {
+        let values = vec![
+            Value::Null,
+            Value::from(cities[i % 3]),
+            Value::Integer(i as i64 % 100),
+        ];
+        db.insert_auto("people", values).unwrap();
+    }
+    db.create_index("people", "people_age", Expr::column("age"))
+        .unwrap();
+    db.create_index("people", "people_city", Expr::column("city"))
+        .unwrap();
+    db
+}
+
+fn create_orders(db: &mut Database) {
+    let users = vec![
+        Column::new("id", ColumnType::Integer),
+        Column::new("name", ColumnType::Text),
+    ];
+    db.create_table("users", users, Some("id".to_string())).unwrap();
+    let orders = vec![
+        Column::new("id", ColumnType::Integer),
+        Column::new("user_id", ColumnType::Integer),
+        Column::new("total", ColumnType::Integer),
+    ];
+    db.create_table("orders", orders, Some("id".to_string())).unwrap();
+    for i in 0..100 {
+        db.insert(
+            "users",
+            i,
+            vec![Value::Integer(i), Value::from(format!("user{}", i))],
+        )
+        .unwrap();
+    }
+    for i in 0..1000 {
+        let user = if i % 50 == 49 {
+            Value::Null
+        } else {
+            Value::Integer(i % 100)
+        };
+        db.insert(
+            "orders",
+            i,
+            vec![Value::Integer(i), user, Value::Integer(i * 3 % 500)],
+        )
+        .unwrap();
+    }
+}
+
+fn operator_name(operator: &Operator) -> &'static str {
+    match operator {
+        Operator::Scan { .. } => "Scan",
+        Operator::IndexSeek { .. } => "IndexSeek",
+        Operator::RangeScan { .. } => "RangeScan",
+        Operator::NestedLoopJoin { .. } => "NestedLoopJoin",
+        Operator::IndexJoin { .. } => "IndexJoin",
+        Operator::MergeJoin { .. } => "MergeJoin",
+        Operator::Filter { .. } => "Filter",
+        Operator::Sort { .. } => "Sort",
+        Operator::Limit { .. } => "Limit",
+    }
+}
+
+#[test]
+fn test_access_paths_follow_estimated_cost() {
+    let db = create_people();
+    let people = db.get_table("people").unwrap();
+    let cases = [
+        ("age = 42", "IndexSeek", 10),
+        ("age >= 90 AND city = 'Oslo'", "RangeScan", 33),
+        ("age >= 10", "Scan", 900),
+        ("id < 100", "Scan", 99),
+    ];
+
+    for (filter, operator, rows) in cases {
+        let query = Query::parse(&format!("people WHERE {}", filter)).unwrap();
+        let plan = db.explain(&query).unwrap();
+        assert_eq!(operator_name(&plan.operator), operator, "{}", plan);
+        assert_eq!(plan.actual_rows, Some(rows), "{}", plan);
+
+        // Every access path returns the rows a scan finds, in key order
+        let filter = Filter::parse(filter).unwrap();
+        let expected = people.find(|row| filter.matches(&people.columns, row).unwrap());
+        assert_eq!(db.find_where("people", &filter).unwrap(), expected);
+        assert_eq!(db.query(&query).unwrap().rows.len(), expected.len());
+    }
+
+    // Equality estimates are exact from the index, ranges interpolate between its bounds
+    let plan = db.plan(&Query::parse("people WHERE age = 42").unwrap()).unwrap();
+    assert_eq!((plan.estimated_rows, plan.actual_rows), (10.0, None));
+    let plan = db.plan(&Query::parse("people WHERE age > 49").unwrap()).unwrap();
+    assert!((plan.estimated_rows - 500.0).abs() < 10.0, "{}", plan);
+}
+
+#[test]
+fn test_order_by_and_limit() {
+    let db = create_people();
+
+    let query = Query::new("people")
+        .filter(Expr::column("city").eq("Oslo"))
+        .order_by(Expr::column("age"), true)
+        .order_by(Expr::column("id"), false)
+        .limit(3);
+    let plan = db.explain(&query).unwrap();
+    assert_eq!(operator_name(&plan.operator), "Limit");
+    assert_eq!(operator_name(&plan.inputs[0].operator), "Sort");
+    let result = db.query(&query).unwrap();
+    let ids: Vec<&Key> = result.rows.iter().map(|(key, _)| key).collect();
+    assert_eq!(ids, [&Key::Integer(300), &Key::Integer(600), &Key::Integer(900)]);
+    assert!(result
+        .rows
+        .iter()
+        .all(|(_, row)| row.values[2] == Value::Integer(99)));
+
+    // A range scan already returns rows in index order, so no sort is needed
+    let query = Query::parse("people WHERE age >= 95 ORDER BY people.age LIMIT 12").unwrap();
+    let plan = db.explain(&query).unwrap();
+    assert_eq!(operator_name(&plan.inputs[0].operator), "RangeScan", "{}", plan);
+    let ages: Vec<Value> = db
+        .query(&query)
+        .unwrap()
+        .rows
+        .into_iter()
+        .map(|(_, row)| row.values[2].clone())
+        .collect();
+    assert_eq!(ages.len(), 12);
+    assert_eq!(
+        (&ages[0], &ages[9], &ages[10]),
+        (&Value::Integer(95), &Value::Integer(95), &Value::Integer(96))
+    );
+}
+
+#[test]
+fn test_join_strategies_return_the_same_rows() {
+    let mut db = Database::in_memory();
+    create_orders(&mut db);
+    let all = Query::parse("users JOIN orders ON users.id = user_id").unwrap();
+    let one =
+        Query::parse("users JOIN orders ON users.id = user_id WHERE name = 'user7' ORDER BY total").unwrap();
+
+    // Without indexes, sorting both sides is cheaper than comparing every pair
+    assert_eq!(operator_name(&db.plan(&all).unwrap().operator), "MergeJoin");
+    let result = db.query(&all).unwrap();
+    assert_eq!(result.rows.len(), 980);
+    assert_eq!(result.columns[0].name, "users.id");
+    assert_eq!(result.columns[2].name, "orders.id");
+    let (key, row) = &result.rows[0];
+    assert_eq!(key, &Key::Composite(vec![Key::Integer(0), Key::Integer(0)]));
+    assert_eq!(row.values[0], row.values[3]);
+
+    // Statistics show the filter leaves one user, making a nested loop cheapest
+    db.analyze().unwrap();
+    let plan = db.explain(&one).unwrap();
+    let join = &plan.inputs[0];
+    assert_eq!(operator_name(&join.operator), "NestedLoopJoin", "{}", plan);
+    assert_eq!(join.inputs[0].estimated_rows, 1.0);
+    assert_eq!(join.inputs[0].actual_rows, Some(1));
+    let expected = db.query(&one).unwrap();
+    assert_eq!(expected.rows.len(), 10);
+
+    db.create_index("orders", "orders_user", Expr::column("user_id"))
+        .unwrap();
+    db.create_index("users", "users_name", Expr::column("name"))
+        .unwrap();
+    let plan = db.explain(&one).unwrap();
+    let join = &plan.inputs[0];
+    assert_eq!(operator_name(&join.operator), "IndexJoin", "{}", plan);
+    assert_eq!(operator_name(&join.inputs[0].operator), "IndexSeek", "{}", plan);
+    assert_eq!(db.query(&one).unwrap(), expected);
+    assert_eq!(db.query(&all).unwrap(), result);
+}
+
+#[test]
+fn test_explain_output_and_query_errors() {
+    let mut db = Database::in_memory();
+    create_orders(&mut db);
+    db.create_index("orders", "orders_user", Expr::column("user_id"))
+        .unwrap();
+
+    let query =
+        Query::parse("orders JOIN users ON user_id = users.id WHERE orders.total >= 490 OR name = 'user1'")
+            .unwrap();
+    let text = db.explain(&query).unwrap().to_string();
+    let lines: Vec<&str> = text.lines().collect();
+    assert!(lines[0].starts_with("Filter "), "{}", text);
+    // A condition spanning both tables gets the default selectivity
+    assert!(lines[0].contains("estimated 100 rows, actual 28"), "{}", text);
+    assert!(
+        lines[1].starts_with("  MergeJoin on orders.user_id = users.id (estimated 1000 rows, actual 980"),
+        "{}",
+        text
+    );
+
+    // Only queries qualify column names with their table
+    let query = Query::parse("users WHERE users.id = 1 ORDER BY users.name").unwrap();
+    assert_eq!(query.filter, Some(Expr::column("users.id").eq(1)));
+    assert!(matches!(Filter::parse("users.id = 1"), Err(DbError::ParseError(_))));
+
+    // A bare filter after the table name is still accepted
+    assert_eq!(
+        Query::parse("orders total > 5").unwrap(),
+        Query::parse("orders WHERE total > 5").unwrap()
+    );
+
+    let err = |query: &str| db.query(&Query::parse(query).unwrap()).unwrap_err();
+    assert!(matches!(
+        err("users JOIN orders ON id = user_id"),
+        DbError::ParseError(_)
+    ));
+    assert!(matches!(
+        err("users JOIN orders ON users.id = total WHERE missing = 1"),
+        DbError::ColumnNotFound(_)
+    ));
+    assert!(matches!(
+        err("users JOIN orders ON users.id = users.name"),
+        DbError::ParseError(_)
+    ));
+    assert!(matches!(
+        err("users JOIN users ON id = id"),
+        DbError::ParseError(_)
+    ));
+    assert!(matches!(
+        err("users ORDER BY orders.total"),
+        DbError::ColumnNotFound(_)
+    ));
+    assert!(matches!(err("missing WHERE a = 1"), DbError::TableNotFound));
+}
+
+#[test]
+fn test_json_values_in_seeks_sorts_and_merge_joins() {
+    let mut db = Database::in_memory();
+    let columns = vec![Column::new("id", ColumnType::Integer), Column::new("doc", ColumnType::Json)];
+    db.create_table("docs", columns.clone(), Some("id".to_string())).unwrap();
+    db.create_table("copies", columns, Some("id".to_string())).unwrap();
+    for i in 0..400 {
+        // The same documents come back with their keys in another order
+        let doc = match i % 4 {
+            0 => Value::Json(json!({"a": i / 4 % 10, "b": "x"})),
+            1 => Value::Json(json!({"b": "x", "a": i / 4 % 10})),
+            2 => Value::Json(json!([i / 4 % 10, {"c": 1}])),
+            _ => Value::Null,
+        };
+        db.insert("docs", i, vec![Value::Integer(i), doc.clone()]).unwrap();
+        db.insert("copies", i, vec![Value::Integer(i), doc]).unwrap();
+    }
+    db.create_index("docs", "docs_doc", Expr::column("doc")).unwrap();
+    let docs = db.get_table("docs").unwrap();
+
+    // Seeks and ranges find exactly the rows a scan finds
+    let probes = [
+        Expr::column("doc").eq(Value::Json(json!({"b": "x", "a": 8}))),
+        Expr::column("doc").eq(Value::Json(json!([8, {"c": 1}]))),
+        Expr::column("doc").ge(Value::Json(json!({"b": "x", "a": 7}))),
+    ];
+    for filter in probes {
+        let plan = db.explain(&Query::new("docs").filter(filter.clone())).unwrap();
+        assert_ne!(operator_name(&plan.operator), "Scan", "{}", plan);
+        let expected = docs.find(|row| filter.matches(&docs.columns, row).unwrap());
+        assert!(!expected.is_empty());
+        assert_eq!(db.find_where("docs", &filter).unwrap(), expected, "{}", filter);
+    }
+
+    // Sorting puts equal documents next to each other
+    let sorted = db.query(&Query::new("docs").order_by(Expr::column("doc"), false)).unwrap();
+    assert!(sorted.rows.windows(2).all(|pair| {
+        pair[0].1.values[1].total_cmp(&pair[1].1.values[1]) != Ordering::Greater
+    }));
+
+    // A merge join matches documents with their keys in any order
+    let join = Query::parse("docs JOIN copies ON docs.doc = copies.doc").unwrap();
+    assert_eq!(operator_name(&db.plan(&join).unwrap().operator), "MergeJoin");
+    assert_eq!(db.query(&join).unwrap().rows.len(), 10 * (20 * 20 + 10 * 10));
+}
@@ -0,0 +1,212 @@
+use oxi_db::{
+    BTree, Column, ColumnType, Compression, Database, DbError, Expr, Key, MemoryStorage, Table, Value,
+};
+use proptest::prelude::*;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// An operation on a table, applied both to the table and to the model
+#[derive(Debug, Clone)]
+enum Op {
+    Insert(Key, Option<String>, i64),
+    Update(Key, Option<String>, i64),
+    Upsert(Key, Option<String>, i64),
+    SetScore(Key, i64),
+    Delete(Key),
+    Get(Key),
+    ScoreAbove(i64),
+    NameIs(String),
+}
+
+/// Rows of the model table: name and score by key
+type Model = BTreeMap<Key, (Option<String>, i64)>;
+
+fn key() -> impl Strategy<Value = Key> {
+    prop_oneof![(0i64..12).prop_map(Key::from), "[a-c]{0,2}".prop_map(Key::from)]
+}
+
+/// Mostly short names that repeat, sometimes one large enough to overflow a page
+fn name() -> impl Strategy<Value = Option<String>> {
+    prop_oneof![
+        4 => "[a-c]{0,3}".prop_map(Some),
+        1 => Just(None),
+        1 => (0u8..26, 4000usize..6000).prop_map(|(c, n)| Some(((b'a' + c) as char).to_string().repeat(n))),
+    ]
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        3 => (key(), name(), -50i64..50).prop_map(|(k, n, s)| Op::Insert(k, n, s)),
+        2 => (key(), name(), -50i64..50).prop_map(|(k, n, s)| Op::Update(k, n, s)),
+        2 => (key(), name(), -50i64..50).prop_map(|(k, n, s)| Op::Upsert(k, n, s)),
+        1 => (key(), -50i64..50).prop_map(|(k, s)| Op::SetScore(k, s)),
+        2 => key().prop_map(Op::Delete),
+        1 => key().prop_map(Op::Get),
+        1 => (-50i64..50).prop_map(Op::ScoreAbove),
+        1 => "[a-c]{0,3}".prop_map(Op::NameIs),
+    ]
+}
+
+fn create_test_table() -> Table {
+    let columns = vec![Column::new("name", ColumnType::Text), Column::new("score", ColumnType::Integer)];
+    let mut table = Table::new("scores", columns, None);
+    table.create_index("scores_name", Expr::column("name")).unwrap();
+    table
+}
+
+fn values(name: &Option<String>, score: i64) -> Vec<Value> {
+    vec![name.clone().map_or(Value::Null, Value::from), Value::from(score)]
+}
+
+fn rows(model: &Model) -> Vec<(Key, Vec<Value>)> {
+    model
+        .iter()
+        .map(|(key, (name, score))| (key.clone(), values(name, score.to_owned())))
+        .collect()
+}
+
+fn scan(table: &Table) -> Vec<(Key, Vec<Value>)> {
+    table.get_all().into_iter().map(|(key, row)| (key, row.values)).collect()
+}
+
+/// Apply `op` to the table and the model, checking that they agree
+fn apply(table: &mut Table, model: &mut Model, op: &Op) -> Result<(), TestCaseError> {
+    match op {
+        Op::Insert(key, name, score) => {
+            let result = table.insert(key.clone(), values(name, *score));
+            if model.contains_key(key) {
+                prop_assert!(matches!(result, Err(DbError::KeyExists)));
+            } else {
+                prop_assert!(result.is_ok());
+                model.insert(key.clone(), (name.clone(), *score));
+            }
+        }
+        Op::Update(key, name, score) => {
+            let result = table.update(key, values(name, *score));
+            match model.get_mut(key) {
+                Some(row) => {
+                    prop_assert!(result.is_ok());
+                    *row = (name.clone(), *score);
+                }
+                None => prop_assert!(matches!(result, Err(DbError::KeyNotFound))),
+            }
+        }
+        Op::Upsert(key, name, score) => {
+            let inserted = table.upsert(key.clone(), values(name, *score)).unwrap();
+            prop_assert_eq!(inserted, model.insert(key.clone(), (name.clone(), *score)).is_none());
+        }
+        Op::SetScore(key, score) => {
+            let result = table.update_columns(key, &[("score", Value::from(*score))]);
+            match model.get_mut(key) {
+                Some(row) => {
+                    prop_assert!(result.is_ok());
+                    row.1 = *score;
+                }
+                None => prop_assert!(matches!(result, Err(DbError::KeyNotFound))),
+            }
+        }
+        Op::Delete(key) => {
+            let result = table.delete(key);
+            prop_assert_eq!(result.is_ok(), model.remove(key).is_some());
+        }
+        Op::Get(key) => match model.get(key) {
+            Some((name, score)) => prop_assert_eq!(&table.get(key).unwrap().values, &values(name, *score)),
+            None => prop_assert!(matches!(table.get(key), Err(DbError::KeyNotFound))),
+        },
+        Op::ScoreAbove(threshold) => {
+            let found = table.find_where(&Expr::column("score").gt(*threshold)).unwrap();
+            let expected: Vec<Key> = model
+                .iter()
+                .filter(|(_, row)| row.1 > *threshold)
+                .map(|(k, _)| k.clone())
+                .collect();
+            prop_assert_eq!(found.into_iter().map(|(key, _)| key).collect::<Vec<_>>(), expected);
+        }
+        Op::NameIs(wanted) => {
+            // Answered from the index
+            let found = table.find_where(&Expr::column("name").eq(wanted.as_str())).unwrap();
+            let expected: Vec<Key> = model
+                .iter()
+                .filter(|(_, row)| row.0.as_deref() == Some(wanted.as_str()))
+                .map(|(k, _)| k.clone())
+                .collect();
+            prop_assert_eq!(found.into_iter().map(|(key, _)| key).collect::<Vec<_>>(), expected);
+        }
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn test_btree_matches_model(
+        ops in prop::collection::vec((any::<bool>(), 0i32..32, any::<i32>()), 0..200)
+    ) {
+        let mut btree = BTree::new();
+        let mut model = BTreeMap::new();
+        for (insert, key, value) in ops {
+            if insert {
+                btree.insert(key, value);
+                model.insert(key, value);
+            } else {
+                prop_assert_eq!(btree.remove(&key), model.remove(&key));
+            }
+            prop_assert_eq!(btree.search(&key), model.get(&key));
+            prop_assert_eq!(btree.len(), model.len());
+        }
+
+        let mut traversed = Vec::new();
+        btree.traverse(|key, value| traversed.push((*key, *value)));
+        prop_assert_eq!(&traversed, &model.into_iter().collect::<Vec<_>>());
+        prop_assert_eq!(btree.to_vec(), traversed);
+    }
+
+    #[test]
+    fn test_table_matches_model(ops in prop::collection::vec(op(), 0..60)) {
+        let mut table = create_test_table();
+        let mut model = Model::new();
+        for op in &ops {
+            apply(&mut table, &mut model, op)?;
+            prop_assert_eq!(table.len(), model.len());
+        }
+        prop_assert_eq!(scan(&table), rows(&model));
+    }
+
+    #[test]
+    fn test_database_round_trip(ops in prop::collection::vec(op(), 0..30), lz4 in any::<bool>()) {
+        let storage = Arc::new(MemoryStorage::new());
+        let mut db = Database::new("property.db").with_storage(storage.clone());
+        let columns = vec![Column::new("name", ColumnType::Text), Column::new("score", ColumnType::Integer)];
+        db.create_table("scores", columns, None).unwrap();
+        db.create_index("scores", "scores_name", Expr::column("name")).unwrap();
+        if lz4 {
+            db.set_compression("scores", Compression::Lz4).unwrap();
+        }
+
+        let mut model = Model::new();
+        for op in &ops {
+            let (key, name, score) = match op {
+                Op::Insert(key, name, score) | Op::Upsert(key, name, score) => (key, name, *score),
+                Op::Delete(key) => {
+                    prop_assert_eq!(db.delete("scores", key).is_ok(), model.remove(key).is_some());
+                    continue;
+                }
+                _ => continue,
+            };
+            db.upsert("scores", key.clone(), values(name, score)).unwrap();
+            model.insert(key.clone(), (name.clone(), score));
+        }
+
+        // Every mutation saved, so the file holds the same rows and index
+        let saved = db.get_table("scores").unwrap().clone();
+        drop(db);
+        let reopened = Database::open_in(storage, "property.db").unwrap();
+        let table = reopened.get_table("scores").unwrap();
+        prop_assert_eq!(scan(table), rows(&model));
+        prop_assert_eq!(table.compression(), saved.compression());
+        prop_assert_eq!(table.checksum().unwrap(), saved.checksum().unwrap());
+        let mut reopened_table = table.clone();
+        for wanted in ["", "a", "ab"] {
+            apply(&mut reopened_table, &mut model, &Op::NameIs(wanted.to_string()))?;
+        }
+    }
+}
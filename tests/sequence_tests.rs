@@ -0,0 +1,114 @@
+use oxi_db::{Column, ColumnType, Database, DbError, Key, Sequence, Table, Value};
+use std::fs;
+
+fn auto_columns() -> Vec<Column> {
+    vec![
+        Column::new("id", ColumnType::Integer).auto_increment(),
+        Column::new("name", ColumnType::Text),
+    ]
+}
+
+#[test]
+fn test_table_insert_auto() {
+    let mut table = Table::new("users", auto_columns(), Some("id".to_string()));
+    assert_eq!(table.next_auto_increment(), Some(1));
+
+    let first = table.insert_auto(vec![Value::Null, Value::from("Alice")]).unwrap();
+    let second = table.insert_auto(vec![Value::Null, Value::from("Bob")]).unwrap();
+    assert_eq!(first, Key::from(1));
+    assert_eq!(second, Key::from(2));
+    assert_eq!(table.get(&second).unwrap().values[0], Value::Integer(2));
+
+    // An explicit id moves the counter past it
+    let explicit = table.insert_auto(vec![Value::Integer(10), Value::from("Carol")]).unwrap();
+    assert_eq!(explicit, Key::from(10));
+    let next = table.insert_auto(vec![Value::Null, Value::from("Dan")]).unwrap();
+    assert_eq!(next, Key::from(11));
+
+    // So does a plain insert with an explicit id
+    table.insert(20, vec![Value::Integer(20), Value::from("Eve")]).unwrap();
+    assert_eq!(table.next_auto_increment(), Some(21));
+}
+
+#[test]
+fn test_insert_auto_does_not_reuse_ids() {
+    let mut table = Table::new("users", auto_columns(), Some("id".to_string()));
+
+    table.insert_auto(vec![Value::Null, Value::from("Alice")]).unwrap();
+
+    // A failed insert still consumes its id
+    assert!(table.insert_auto(vec![Value::Null, Value::Integer(5)]).is_err());
+
+    // Deleting the newest row does not free its id
+    let key = table.insert_auto(vec![Value::Null, Value::from("Bob")]).unwrap();
+    assert_eq!(key, Key::from(3));
+    table.delete(&key).unwrap();
+
+    let key = table.insert_auto(vec![Value::Null, Value::from("Carol")]).unwrap();
+    assert_eq!(key, Key::from(4));
+}
+
+#[test]
+fn test_insert_auto_requires_auto_increment_key() {
+    let columns = vec![Column::new("name", ColumnType::Text)];
+    let mut table = Table::new("plain", columns, None);
+    assert!(table.insert_auto(vec![Value::from("Alice")]).is_err());
+    assert_eq!(table.next_auto_increment(), None);
+
+    let mut db = Database::new("test_auto_increment_schema.db");
+    let columns = vec![Column::new("id", ColumnType::Text).auto_increment()];
+    assert!(db.create_table("bad", columns, Some("id".to_string())).is_err());
+    let columns = vec![Column::new("id", ColumnType::Integer).auto_increment()];
+    assert!(db.create_table("bad", columns, None).is_err());
+
+    // Clean up
+    fs::remove_file("test_auto_increment_schema.db").unwrap_or(());
+}
+
+#[test]
+fn test_sequence_values() {
+    let mut seq = Sequence::new("countdown", 3, -1).unwrap();
+    assert_eq!(seq.current_value(), None);
+    assert_eq!(seq.next_value().unwrap(), 3);
+    assert_eq!(seq.next_value().unwrap(), 2);
+    assert_eq!(seq.current_value(), Some(2));
+
+    let mut seq = Sequence::new("almost_full", i64::MAX - 1, 1).unwrap();
+    assert_eq!(seq.next_value().unwrap(), i64::MAX - 1);
+    assert_eq!(seq.next_value().unwrap(), i64::MAX);
+    assert!(matches!(seq.next_value(), Err(DbError::NumericOverflow)));
+
+    // A sequence has to move
+    assert!(matches!(Sequence::new("stuck", 1, 0), Err(DbError::Other(_))));
+}
+
+#[test]
+fn test_sequences_persist_across_reopen() {
+    let db_path = "test_sequences_persist.db";
+    let mut db = Database::new(db_path);
+
+    db.create_sequence("invoices", 100, 5).unwrap();
+    assert!(matches!(db.create_sequence("invoices", 1, 1), Err(DbError::SequenceExists(_))));
+    assert!(db.create_sequence("zero", 1, 0).is_err());
+
+    assert_eq!(db.next_value("invoices").unwrap(), 100);
+    assert_eq!(db.next_value("invoices").unwrap(), 105);
+
+    db.create_table("users", auto_columns(), Some("id".to_string())).unwrap();
+    db.insert_auto("users", vec![Value::Null, Value::from("Alice")]).unwrap();
+
+    // Values handed out before reopening are never returned again
+    drop(db);
+    let mut db = Database::open(db_path).unwrap();
+    assert_eq!(db.next_value("invoices").unwrap(), 110);
+    assert_eq!(db.get_sequence("invoices").unwrap().current_value(), Some(110));
+    let key = db.insert_auto("users", vec![Value::Null, Value::from("Bob")]).unwrap();
+    assert_eq!(key, Key::from(2));
+
+    assert_eq!(db.list_sequences(), vec!["invoices".to_string()]);
+    db.drop_sequence("invoices").unwrap();
+    assert!(matches!(db.next_value("invoices"), Err(DbError::SequenceNotFound(_))));
+
+    // Clean up
+    fs::remove_file(db_path).unwrap_or(());
+}
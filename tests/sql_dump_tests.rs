@@ -0,0 +1,193 @@
+use oxi_db::{Column, ColumnType, Database, DbError, Expr, Key, Uuid, Value};
+use serde_json::json;
+use std::fs;
+
+fn create_test_db(db_name: &str) -> Database {
+    let db_path = format!("{}.db", db_name);
+    fs::remove_file(&db_path).unwrap_or(());
+
+    let mut db = Database::new(&db_path);
+    let columns = vec![
+        Column::new("id", ColumnType::Integer).auto_increment(),
+        Column::new("name", ColumnType::Text),
+        Column::new("balance", ColumnType::Decimal(10, 2)),
+        Column::new("ratio", ColumnType::Float),
+        Column::new("active", ColumnType::Boolean),
+        Column::new("external_id", ColumnType::Uuid),
+        Column::new("avatar", ColumnType::Blob),
+        Column::new("profile", ColumnType::Json),
+        Column::new(
+            "address",
+            ColumnType::Struct(vec![
+                Column::new("city", ColumnType::Text),
+                Column::new("zip code", ColumnType::Integer),
+            ]),
+        ),
+        Column::new("scores", ColumnType::Array(Box::new(ColumnType::Float))),
+    ];
+    db.create_table("users", columns, Some("id".to_string())).unwrap();
+    db
+}
+
+fn restore_copy(db_name: &str, dump: &[u8]) -> Database {
+    let db_path = format!("{}.db", db_name);
+    fs::remove_file(&db_path).unwrap_or(());
+    let mut copy = Database::new(&db_path);
+    copy.restore(dump).unwrap();
+    drop(copy);
+    Database::open(&db_path).unwrap()
+}
+
+#[test]
+fn test_dump_round_trips_every_type() {
+    let mut db = create_test_db("test_sql_dump_types");
+    db.insert_auto(
+        "users",
+        vec![
+            Value::Null,
+            Value::from("Alice -- O'Neil\nsecond line"),
+            Value::Decimal("-1234.50".parse().unwrap()),
+            Value::Float(0.1),
+            Value::Boolean(true),
+            Value::Uuid(Uuid::parse_str("6f9619ff-8b86-d011-b42d-00cf4fc964ff").unwrap()),
+            Value::Blob(vec![0, 255]),
+            Value::Json(json!({"theme": "dark", "tags": ["a"]})),
+            Value::Struct(vec![Value::from("Paris"), Value::Integer(75001)]),
+            Value::Array(vec![Value::Float(f64::INFINITY), Value::Float(1e300), Value::Null]),
+        ],
+    )
+    .unwrap();
+    db.insert_auto("users", vec![Value::Null; 10]).unwrap();
+
+    let mut dump = Vec::new();
+    db.dump(&mut dump).unwrap();
+    let text = String::from_utf8(dump.clone()).unwrap();
+    assert!(text.starts_with("-- oxi-db "));
+    assert!(text.contains("address STRUCT(city TEXT, \"zip code\" INTEGER)"), "{}", text);
+    assert!(text.contains("INSERT INTO users VALUES (2, NULL, NULL"), "{}", text);
+
+    let mut copy = restore_copy("test_sql_dump_types_copy", &dump);
+    let original = db.get_table("users").unwrap();
+    let restored = copy.get_table("users").unwrap();
+    assert_eq!(restored.columns, original.columns);
+    assert_eq!(restored.primary_key, original.primary_key);
+    assert_eq!(restored.get_all(), original.get_all());
+
+    // The auto-increment counter carries on after the restored rows
+    assert_eq!(copy.insert_auto("users", vec![Value::Null; 10]).unwrap(), Key::from(3));
+
+    // Clean up
+    fs::remove_file("test_sql_dump_types.db").unwrap_or(());
+    fs::remove_file("test_sql_dump_types_copy.db").unwrap_or(());
+}
+
+#[test]
+fn test_dump_keeps_keys_indexes_and_sequences() {
+    let mut db = create_test_db("test_sql_dump_schema");
+    db.create_index("users", "users_theme", Expr::json_path("profile", "$.theme").unwrap())
+        .unwrap();
+    db.create_table("tags", vec![Column::new("name", ColumnType::Text)], None).unwrap();
+    let composite = Key::Composite(vec![Key::from("a"), Key::from(1)]);
+    db.insert("tags", composite.clone(), vec![Value::from("rust")]).unwrap();
+    db.insert("tags", Key::Bytes(vec![1, 2]), vec![Value::from("db")]).unwrap();
+    let uuid = Key::Uuid(Uuid::parse_str("6f9619ff-8b86-d011-b42d-00cf4fc964ff").unwrap());
+    db.insert("tags", uuid.clone(), vec![Value::from("id")]).unwrap();
+    db.create_sequence("invoices", 1000, 10).unwrap();
+    db.next_value("invoices").unwrap();
+
+    let mut dump = Vec::new();
+    db.dump(&mut dump).unwrap();
+    let text = String::from_utf8(dump.clone()).unwrap();
+    assert!(text.contains("CREATE SEQUENCE invoices START 1010 INCREMENT 10;"), "{}", text);
+    assert!(text.contains("INSERT INTO tags KEY ('a', 1) VALUES ('rust');"), "{}", text);
+    assert!(text.contains("KEY UUID '6f9619ff-8b86-d011-b42d-00cf4fc964ff' VALUES ('id');"), "{}", text);
+    assert!(text.contains("CREATE INDEX users_theme ON users (profile$.theme);"), "{}", text);
+
+    let mut copy = restore_copy("test_sql_dump_schema_copy", &dump);
+    assert_eq!(copy.list_tables(), vec!["tags".to_string(), "users".to_string()]);
+    assert_eq!(copy.get("tags", &composite).unwrap().values[0], Value::from("rust"));
+    assert_eq!(copy.get("tags", &Key::Bytes(vec![1, 2])).unwrap().values[0], Value::from("db"));
+    assert_eq!(copy.get("tags", &uuid).unwrap().values[0], Value::from("id"));
+    assert_eq!(copy.get_table("users").unwrap().indexes()[0].name, "users_theme");
+    assert_eq!(copy.next_value("invoices").unwrap(), 1010);
+
+    // Clean up
+    fs::remove_file("test_sql_dump_schema.db").unwrap_or(());
+    fs::remove_file("test_sql_dump_schema_copy.db").unwrap_or(());
+}
+
+#[test]
+fn test_dump_keeps_auto_increment_and_dashes_in_quotes() {
+    let mut db = Database::in_memory();
+    let columns = vec![
+        Column::new("id", ColumnType::Integer).auto_increment(),
+        Column::new("note -- not a comment", ColumnType::Text),
+    ];
+    db.create_table("events", columns, Some("id".to_string())).unwrap();
+    db.insert_auto("events", vec![Value::Null, Value::from("a -- b 'c' --")]).unwrap();
+    let last = db.insert_auto("events", vec![Value::Null, Value::from("gone")]).unwrap();
+    db.delete("events", &last).unwrap();
+
+    let mut dump = Vec::new();
+    db.dump(&mut dump).unwrap();
+    let text = String::from_utf8(dump.clone()).unwrap();
+    assert!(text.contains("ALTER TABLE events AUTO_INCREMENT = 3;"), "{}", text);
+
+    // `--` inside quotes does not start a comment
+    let mut copy = Database::in_memory();
+    assert_eq!(copy.restore(dump.as_slice()).unwrap(), 1);
+    let table = copy.get_table("events").unwrap();
+    assert_eq!(table.columns[1].name, "note -- not a comment");
+    assert_eq!(table.get(&Key::from(1)).unwrap().values[1], Value::from("a -- b 'c' --"));
+    assert_eq!(copy.insert_auto("events", vec![Value::Null, Value::from("c")]).unwrap(), Key::from(3));
+
+    let script = "CREATE TABLE t (id INTEGER PRIMARY KEY); ALTER TABLE t AUTO_INCREMENT = 5;";
+    assert!(matches!(Database::in_memory().restore(script.as_bytes()), Err(DbError::Other(_))));
+}
+
+#[test]
+fn test_restore_hand_written_script() {
+    let script = "
+        -- Accounts keyed by UUID
+        CREATE TABLE accounts (id UUID PRIMARY KEY, owner TEXT, tags ARRAY(TEXT));
+        INSERT INTO accounts VALUES ('6f9619ff-8b86-d011-b42d-00cf4fc964ff', 'Alice', ['a', 'b']);;
+        CREATE SEQUENCE ids;
+        CREATE INDEX accounts_owner ON accounts (owner)
+    ";
+    let mut db = create_test_db("test_sql_restore_script");
+    assert_eq!(db.restore(script.as_bytes()).unwrap(), 1);
+
+    let key = Key::Uuid(Uuid::parse_str("6f9619ff-8b86-d011-b42d-00cf4fc964ff").unwrap());
+    let row = db.get("accounts", &key).unwrap();
+    assert_eq!(row.values[2], Value::Array(vec![Value::from("a"), Value::from("b")]));
+    assert_eq!(db.next_value("ids").unwrap(), 1);
+
+    // Clean up
+    fs::remove_file("test_sql_restore_script.db").unwrap_or(());
+}
+
+#[test]
+fn test_restore_is_all_or_nothing() {
+    let mut db = create_test_db("test_sql_restore_errors");
+
+    let script = "CREATE TABLE t (id INTEGER PRIMARY KEY); INSERT INTO t VALUES (1); DROP TABLE t;";
+    match db.restore(script.as_bytes()) {
+        Err(DbError::ParseError(message)) => assert!(message.starts_with("statement 3: "), "{}", message),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(db.list_tables(), vec!["users".to_string()]);
+
+    let script = "CREATE TABLE t (id INTEGER PRIMARY KEY); INSERT INTO t VALUES (1); INSERT INTO t VALUES (1);";
+    assert!(matches!(db.restore(script.as_bytes()), Err(DbError::KeyExists)));
+    let script = "CREATE TABLE users (id INTEGER);";
+    assert!(matches!(db.restore(script.as_bytes()), Err(DbError::TableExists)));
+    let script = "INSERT INTO users VALUES (NULL, 'Alice');";
+    assert!(matches!(db.restore(script.as_bytes()), Err(DbError::TableNotFound)));
+    let script = "CREATE TABLE t (name TEXT); INSERT INTO t VALUES ('x');";
+    assert!(matches!(db.restore(script.as_bytes()), Err(DbError::ParseError(_))));
+    assert_eq!(db.list_tables(), vec!["users".to_string()]);
+    assert!(db.get_table("users").unwrap().is_empty());
+
+    // Clean up
+    fs::remove_file("test_sql_restore_errors.db").unwrap_or(());
+}
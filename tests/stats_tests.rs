@@ -0,0 +1,128 @@
+use oxi_db::{
+    Column, ColumnType, Compression, Database, EncryptionKey, Expr, MemoryStorage, Storage, Table, Value,
+};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+fn create_test_db(db_name: &str) -> Database {
+    let db_path = format!("{}.db", db_name);
+    fs::remove_file(&db_path).unwrap_or(());
+
+    let mut db = Database::new(&db_path);
+    let columns = vec![
+        Column::new("id", ColumnType::Integer).auto_increment(),
+        Column::new("city", ColumnType::Text),
+        Column::new("score", ColumnType::Float),
+    ];
+    db.create_table("people", columns, Some("id".to_string())).unwrap();
+    let cities = ["Paris", "Berlin", "Oslo"];
+    for i in 0..200 {
+        let score = if i % 4 == 0 { Value::Null } else { Value::Float(i as f64 / 2.0) };
+        db.insert_auto("people", vec![Value::Null, Value::from(cities[i % 3]), score]).unwrap();
+    }
+    db.create_index("people", "people_city", Expr::column("city")).unwrap();
+    db
+}
+
+#[test]
+fn test_table_stats_describe_columns_and_indexes() {
+    let db = create_test_db("test_stats_columns");
+    let stats = db.get_table("people").unwrap().stats().unwrap();
+
+    assert_eq!(stats.rows, 200);
+    assert_eq!(stats.estimated_depth, 3);
+    assert_eq!(stats.compression, Compression::None);
+
+    let id = stats.column("id").unwrap();
+    assert_eq!((id.nulls, id.distinct), (0, 200));
+    assert_eq!((id.min.clone(), id.max.clone()), (Some(Value::Integer(1)), Some(Value::Integer(200))));
+    let city = stats.column("city").unwrap();
+    assert_eq!(city.distinct, 3);
+    assert_eq!((city.min.clone(), city.max.clone()), (Some(Value::from("Berlin")), Some(Value::from("Paris"))));
+    let score = stats.column("score").unwrap();
+    assert_eq!((score.nulls, score.distinct), (50, 150));
+    assert_eq!(score.max, Some(Value::Float(99.5)));
+
+    let index = &stats.indexes[0];
+    assert_eq!(index.name, "people_city");
+    assert_eq!((index.values, index.entries, index.estimated_depth), (3, 200, 1));
+    assert_eq!(stats.index_bytes, index.bytes);
+    assert!(index.bytes > 0 && index.bytes < stats.bytes);
+
+    // Clean up
+    fs::remove_file("test_stats_columns.db").unwrap_or(());
+}
+
+#[test]
+fn test_distinct_estimate_and_depth_of_large_tables() {
+    let mut table = Table::new("numbers", vec![Column::new("n", ColumnType::Integer)], None);
+    for i in 0..20_000 {
+        table.insert(i, vec![Value::Integer(i % 10_000)]).unwrap();
+    }
+    let stats = table.stats().unwrap();
+
+    // 11 keys per node: three levels hold 1727 entries, four hold 20735
+    assert_eq!(stats.estimated_depth, 4);
+    let estimate = stats.column("n").unwrap().distinct;
+    assert!((9_000..=11_000).contains(&estimate), "estimated {}", estimate);
+
+    let empty = Table::new("empty", vec![Column::new("n", ColumnType::Integer)], None).stats().unwrap();
+    assert_eq!((empty.rows, empty.estimated_depth), (0, 0));
+    let column = &empty.columns[0];
+    assert_eq!((column.nulls, column.distinct, column.min.clone()), (0, 0, None));
+}
+
+#[test]
+fn test_database_stats_match_the_file() {
+    let mut db = create_test_db("test_stats_file");
+    db.create_sequence("tickets", 1, 1).unwrap();
+    let path = "test_stats_file.db";
+
+    let stats = db.stats().unwrap();
+    assert_eq!(stats.file_bytes, fs::metadata(path).unwrap().len());
+    assert_eq!((stats.used_bytes, stats.free_bytes), (stats.file_bytes, 0));
+    assert_eq!((stats.rows(), stats.sequences), (200, 1));
+    // Header and the end and sequence pages are not part of any table
+    assert!(stats.table("people").unwrap().bytes < stats.used_bytes);
+
+    db.set_compression("people", Compression::Lz4).unwrap();
+    let compressed = db.stats().unwrap();
+    assert!(compressed.used_bytes < stats.used_bytes);
+    assert_eq!(compressed.file_bytes, fs::metadata(path).unwrap().len());
+
+    // Encryption adds its header and tag around the same pages
+    db.set_encryption(Some(&EncryptionKey::Raw([7; 32]))).unwrap();
+    let encrypted = db.stats().unwrap();
+    assert_eq!(encrypted.file_bytes, fs::metadata(path).unwrap().len());
+    assert_eq!((encrypted.used_bytes, encrypted.free_bytes), (encrypted.file_bytes, 0));
+
+    // Clean up
+    fs::remove_file(path).unwrap_or(());
+}
+
+#[test]
+fn test_free_space_and_in_memory_stats() {
+    let storage = Arc::new(MemoryStorage::new());
+    let mut small = Database::new("free.db").with_storage(storage.clone());
+    small.create_table("notes", vec![Column::new("text", ColumnType::Text)], None).unwrap();
+
+    // Another writer leaves a larger file under the same name
+    let mut large = Database::new("large.db").with_storage(storage.clone());
+    large.create_table("notes", vec![Column::new("text", ColumnType::Text)], None).unwrap();
+    large.insert("notes", 1, vec![Value::from("x".repeat(10_000))]).unwrap();
+    let data = storage.read(Path::new("large.db")).unwrap();
+    storage.write(Path::new("free.db"), 0, &data).unwrap();
+
+    let stats = small.stats().unwrap();
+    let large_stats = large.stats().unwrap();
+    assert_eq!(stats.file_bytes, large_stats.used_bytes);
+    assert_eq!(stats.free_bytes, large_stats.used_bytes - stats.used_bytes);
+    assert_eq!(large_stats.table("notes").unwrap().overflow_pages, 3);
+
+    let mut memory = Database::in_memory();
+    memory.create_table("notes", vec![Column::new("text", ColumnType::Text)], None).unwrap();
+    let stats = memory.stats().unwrap();
+    assert_eq!((stats.file_bytes, stats.free_bytes), (0, 0));
+    assert!(stats.used_bytes > 0);
+}
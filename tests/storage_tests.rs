@@ -0,0 +1,234 @@
+use oxi_db::{
+    Column, ColumnType, Database, DbError, EncryptionKey, Expr, FileStorage, Key, MemoryStorage, Storage, Value,
+};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+fn create_test_db(path: &str, storage: Arc<dyn Storage>) -> Database {
+    let mut db = Database::new(path).with_storage(storage);
+    let columns = vec![
+        Column::new("id", ColumnType::Integer).auto_increment(),
+        Column::new("name", ColumnType::Text),
+    ];
+    db.create_table("users", columns, Some("id".to_string())).unwrap();
+    for name in ["Alice", "Bob"] {
+        db.insert_auto("users", vec![Value::Null, Value::from(name)]).unwrap();
+    }
+    db
+}
+
+/// A storage that records every call before passing it on
+#[derive(Debug, Default)]
+struct RecordingStorage {
+    inner: MemoryStorage,
+    calls: Mutex<Vec<String>>,
+}
+
+impl RecordingStorage {
+    fn record(&self, call: &str, path: &Path) {
+        self.calls.lock().unwrap().push(format!("{} {}", call, path.display()));
+    }
+}
+
+impl Storage for RecordingStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.record("read", path);
+        self.inner.read(path)
+    }
+
+    fn write(&self, path: &Path, offset: u64, data: &[u8]) -> io::Result<()> {
+        self.record("write", path);
+        self.inner.write(path, offset, data)
+    }
+
+    fn truncate(&self, path: &Path, len: u64) -> io::Result<()> {
+        self.record("truncate", path);
+        self.inner.truncate(path, len)
+    }
+
+    fn sync(&self, path: &Path) -> io::Result<()> {
+        self.record("sync", path);
+        self.inner.sync(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.record("rename", to);
+        self.inner.rename(from, to)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.record("remove", path);
+        self.inner.remove(path)
+    }
+
+    fn sync_directory(&self, path: &Path) -> io::Result<()> {
+        self.record("sync_directory", path);
+        self.inner.sync_directory(path)
+    }
+
+    fn lock(&self, path: &Path) -> io::Result<u64> {
+        self.record("lock", path);
+        self.inner.lock(path)
+    }
+
+    fn unlock(&self, path: &Path, token: u64) -> io::Result<()> {
+        self.record("unlock", path);
+        self.inner.unlock(path, token)
+    }
+}
+
+#[test]
+fn test_memory_storage() {
+    let storage = MemoryStorage::new();
+    let mut db = create_test_db("test_storage_memory.db", Arc::new(storage.clone()));
+    db.backup_to("backups/test_storage_memory.db").unwrap();
+    db.verify_backup("backups/test_storage_memory.db").unwrap();
+
+    assert!(!Path::new("test_storage_memory.db").exists());
+    assert!(!Path::new("backups/test_storage_memory.db").exists());
+    assert_eq!(
+        storage.files(),
+        [PathBuf::from("backups/test_storage_memory.db"), PathBuf::from("test_storage_memory.db")]
+    );
+
+    match Database::open_in(Arc::new(storage.clone()), "missing.db") {
+        Err(DbError::IoError(e)) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+
+    // An encrypted database stays encrypted in any storage
+    let key = EncryptionKey::Raw([6; 32]);
+    db.set_encryption(Some(&key)).unwrap();
+    let data = storage.read(Path::new("test_storage_memory.db")).unwrap();
+    assert!(!data.windows(5).any(|w| w == b"Alice"));
+    let reopened = Database::open_encrypted_in(Arc::new(storage), "test_storage_memory.db", &key).unwrap();
+    assert_eq!(reopened.get_table("users").unwrap().len(), 2);
+    assert_eq!(reopened.get("users", &Key::from(2)).unwrap().values[1], Value::from("Bob"));
+}
+
+#[test]
+fn test_saves_go_through_the_storage() {
+    let storage = Arc::new(RecordingStorage::default());
+    let db = create_test_db("app.db", storage.clone());
+    storage.calls.lock().unwrap().clear();
+
+    db.save().unwrap();
+    drop(db);
+    let calls = storage.calls.lock().unwrap().clone();
+    assert_eq!(
+        calls,
+        [
+            "lock app.db",
+            "truncate app.db.tmp",
+            "write app.db.tmp",
+            "sync app.db.tmp",
+            "rename app.db",
+            "sync_directory app.db",
+            "unlock app.db"
+        ]
+    );
+
+    // Opening a file does not lock it
+    storage.calls.lock().unwrap().clear();
+    let db = Database::open_in(storage.clone(), "app.db").unwrap();
+    assert_eq!(*storage.calls.lock().unwrap(), ["read app.db"]);
+    drop(db);
+    assert_eq!(storage.calls.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn test_locked_files_are_not_written() {
+    let storage = MemoryStorage::new();
+    let path = Path::new("test_storage_locked.db");
+
+    // A save holds the lock only while it writes
+    let mut db = create_test_db("test_storage_locked.db", Arc::new(storage.clone()));
+    let token = storage.lock(path).unwrap();
+
+    // Another process is saving the file
+    match db.insert_auto("users", vec![Value::Null, Value::from("Carol")]) {
+        Err(DbError::IoError(e)) => assert_eq!(e.kind(), io::ErrorKind::WouldBlock),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(db.get_table("users").unwrap().len(), 2);
+
+    // Only the holder of the lock releases it
+    assert_eq!(storage.unlock(path, token + 1).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(storage.lock(path).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+    storage.unlock(path, token).unwrap();
+    db.insert_auto("users", vec![Value::Null, Value::from("Carol")]).unwrap();
+    let reopened = Database::open_in(Arc::new(storage), path).unwrap();
+    assert_eq!(reopened.get_table("users").unwrap().len(), 3);
+}
+
+#[test]
+fn test_failed_saves_undo_only_their_change() {
+    let storage = MemoryStorage::new();
+    let path = Path::new("test_storage_undo.db");
+    let mut db = create_test_db("test_storage_undo.db", Arc::new(storage.clone()));
+    db.create_index("users", "by_name", Expr::column("name")).unwrap();
+    db.create_sequence("tickets", 1, 1).unwrap();
+
+    // Every save fails while another process holds the lock
+    let token = storage.lock(path).unwrap();
+    assert!(db.insert_auto("users", vec![Value::Null, Value::from("Carol")]).is_err());
+    assert!(db.update("users", &Key::from(1), vec![Value::from(1), Value::from("Ann")]).is_err());
+    assert!(db.delete("users", &Key::from(2)).is_err());
+    assert!(db.drop_index("users", "by_name").is_err());
+    assert!(db.next_value("tickets").is_err());
+    assert!(db.create_table("other", vec![Column::new("x", ColumnType::Integer)], None).is_err());
+    assert!(db.drop_table("users").is_err());
+
+    let users = db.get_table("users").unwrap();
+    assert_eq!(users.len(), 2);
+    assert_eq!(users.next_auto_increment(), Some(3));
+    let by_name = &users.indexes()[0];
+    assert_eq!(by_name.lookup(&Value::from("Alice")), [Key::from(1)]);
+    assert!(by_name.lookup(&Value::from("Ann")).is_empty());
+    assert!(by_name.lookup(&Value::from("Carol")).is_empty());
+    assert_eq!(db.get_sequence("tickets").unwrap().peek(), 1);
+    assert!(db.get_table("other").is_err());
+
+    storage.unlock(path, token).unwrap();
+    assert_eq!(db.insert_auto("users", vec![Value::Null, Value::from("Carol")]).unwrap(), Key::from(3));
+}
+
+#[test]
+fn test_file_storage_locks() {
+    let path = Path::new("test_storage_file.db");
+    let lock_file = Path::new("test_storage_file.db.lock");
+    let storage = FileStorage::new();
+
+    let token = storage.lock(path).unwrap();
+    assert!(lock_file.exists());
+    assert_eq!(storage.lock(path).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+    // A second handle stands for another process
+    let other = FileStorage::new();
+    assert_eq!(other.lock(path).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+    assert!(other.unlock(path, token).is_err());
+    // Unlocking removes the lock file
+    storage.unlock(path, token).unwrap();
+    assert!(!lock_file.exists());
+    assert!(storage.unlock(path, token).is_err());
+    let token = other.lock(path).unwrap();
+    other.unlock(path, token).unwrap();
+
+    // Databases use the file storage by default, and leave no lock file
+    create_test_db("test_storage_file.db", Arc::new(FileStorage::new())).save().unwrap();
+    assert_eq!(Database::open(path).unwrap().get_table("users").unwrap().len(), 2);
+    assert!(!lock_file.exists());
+
+    // A failed save removes its temporary file
+    let directory = Path::new("test_storage_file_directory.db");
+    fs::create_dir_all(directory).unwrap();
+    assert!(Database::new(directory).save().is_err());
+    assert!(!Path::new("test_storage_file_directory.db.tmp").exists());
+
+    assert!(!Path::new("test_storage_file_directory.db.lock").exists());
+
+    // Clean up
+    fs::remove_file(path).unwrap_or(());
+    fs::remove_dir(directory).unwrap_or(());
+}
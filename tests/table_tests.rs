@@ -1,4 +1,4 @@
-use oxi_db::{Column, ColumnType, Key, Table, Value};
+use oxi_db::{Column, ColumnType, DbError, Decimal, Expr, Key, Row, Table, Value};
 
 #[test]
 fn test_table_create() {
@@ -152,10 +152,10 @@ fn test_table_get_all() {
     
     // Sort by key to ensure consistent order
     let mut rows = rows;
-    rows.sort_by(|a, b| a.0.0.cmp(&b.0.0));
+    rows.sort_by(|a, b| a.0.cmp(&b.0));
     
-    assert_eq!(rows[0].0.0, "1");
-    assert_eq!(rows[1].0.0, "2");
+    assert_eq!(rows[0].0, Key::from("1"));
+    assert_eq!(rows[1].0, Key::from("2"));
     
     assert_eq!(rows[0].1.values[0], Value::Integer(1));
     assert_eq!(rows[0].1.values[1], Value::Text("Alice".to_string()));
@@ -235,6 +235,172 @@ fn test_table_find() {
     });
     
     assert_eq!(active_users.len(), 1);
-    assert_eq!(active_users[0].0.0, "1");
+    assert_eq!(active_users[0].0, Key::from("1"));
     assert_eq!(active_users[0].1.values[1], Value::Text("Alice".to_string()));
 }
+
+#[test]
+fn test_table_upsert() {
+    let columns = vec![
+        Column::new("id", ColumnType::Integer),
+        Column::new("name", ColumnType::Text),
+        Column::new("active", ColumnType::Boolean),
+    ];
+    
+    let mut table = Table::new("users", columns, Some("id".to_string()));
+    
+    // Upsert a new row
+    let inserted = table.upsert(
+        "1",
+        vec![
+            Value::Integer(1),
+            Value::Text("Alice".to_string()),
+            Value::Boolean(true),
+        ],
+    ).unwrap();
+    assert!(inserted);
+    
+    // Upsert over the existing row
+    let inserted = table.upsert(
+        "1",
+        vec![
+            Value::Integer(1),
+            Value::Text("Alice Smith".to_string()),
+            Value::Boolean(false),
+        ],
+    ).unwrap();
+    assert!(!inserted);
+    assert_eq!(table.len(), 1);
+    
+    let row = table.get(&Key::from("1")).unwrap();
+    assert_eq!(row.values[1], Value::Text("Alice Smith".to_string()));
+    
+    // Upserted values are validated
+    let result = table.upsert("2", vec![Value::Integer(2), Value::Integer(3), Value::Boolean(true)]);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_table_update_columns() {
+    let columns = vec![
+        Column::new("id", ColumnType::Integer),
+        Column::new("name", ColumnType::Text),
+        Column::new("active", ColumnType::Boolean),
+    ];
+    
+    let mut table = Table::new("users", columns, Some("id".to_string()));
+    
+    table.insert(
+        "1",
+        vec![
+            Value::Integer(1),
+            Value::Text("Alice".to_string()),
+            Value::Boolean(true),
+        ],
+    ).unwrap();
+    
+    // Update only the name
+    let key = Key::from("1");
+    table.update_columns(&key, &[("name", Value::Text("Alicia".to_string()))]).unwrap();
+    
+    let row = table.get(&key).unwrap();
+    assert_eq!(row.values[0], Value::Integer(1));
+    assert_eq!(row.values[1], Value::Text("Alicia".to_string()));
+    assert_eq!(row.values[2], Value::Boolean(true));
+    
+    // Unknown columns, wrong types and missing rows are rejected
+    let result = table.update_columns(&key, &[("email", Value::Null)]);
+    assert!(matches!(result, Err(DbError::ColumnNotFound(_))));
+    let result = table.update_columns(&key, &[("active", Value::Integer(0))]);
+    assert!(result.is_err());
+    let result = table.update_columns(&Key::from("2"), &[("active", Value::Boolean(false))]);
+    assert!(matches!(result, Err(DbError::KeyNotFound)));
+    
+    assert_eq!(table.get(&key).unwrap().values[2], Value::Boolean(true));
+}
+
+#[test]
+fn test_table_compare_and_swap() {
+    let columns = vec![
+        Column::new("id", ColumnType::Integer),
+        Column::new("balance", ColumnType::Integer),
+    ];
+    
+    let mut table = Table::new("accounts", columns, Some("id".to_string()));
+    table.insert("1", vec![Value::Integer(1), Value::Integer(100)]).unwrap();
+    
+    let key = Key::from("1");
+    let snapshot = table.get(&key).unwrap().clone();
+    
+    // The first swap succeeds
+    let result = table.compare_and_swap(&key, &snapshot, vec![Value::Integer(1), Value::Integer(150)]);
+    assert!(result.is_ok());
+    
+    // A second swap based on the stale snapshot fails
+    let result = table.compare_and_swap(&key, &snapshot, vec![Value::Integer(1), Value::Integer(200)]);
+    assert!(matches!(result, Err(DbError::CompareFailed)));
+    assert_eq!(table.get(&key).unwrap().values[1], Value::Integer(150));
+
+    // A row holding NaN still matches its own snapshot
+    let mut table = Table::new("readings", vec![Column::new("value", ColumnType::Float)], None);
+    table.insert("1", vec![Value::Float(f64::NAN)]).unwrap();
+    let snapshot = table.get(&key).unwrap().clone();
+    table.compare_and_swap(&key, &snapshot, vec![Value::Float(1.5)]).unwrap();
+    assert_eq!(table.get(&key).unwrap().values[0], Value::Float(1.5));
+
+    // Equal numbers of another type or scale do not match
+    let expected = Row::new(vec![Value::Integer(1)]);
+    table.update(&key, vec![Value::Float(1.0)]).unwrap();
+    let result = table.compare_and_swap(&key, &expected, vec![Value::Float(2.0)]);
+    assert!(matches!(result, Err(DbError::CompareFailed)));
+
+    let mut table = Table::new("prices", vec![Column::new("price", ColumnType::Decimal(10, 2))], None);
+    table.insert("1", vec![Value::Decimal(Decimal::new(100, 2).unwrap())]).unwrap();
+    let expected = Row::new(vec![Value::Decimal(Decimal::new(10, 1).unwrap())]);
+    let result = table.compare_and_swap(&key, &expected, vec![Value::Decimal(Decimal::zero())]);
+    assert!(matches!(result, Err(DbError::CompareFailed)));
+}
+
+#[test]
+fn test_failed_writes_leave_indexes_unchanged() {
+    let columns = vec![
+        Column::new("a", ColumnType::Integer),
+        Column::new("b", ColumnType::Integer),
+    ];
+    let mut table = Table::new("pairs", columns, None);
+    table.create_index("by_a", Expr::column("a")).unwrap();
+    table.create_index("by_b", Expr::column("b")).unwrap();
+    table.insert("1", vec![Value::Integer(1), Value::Integer(2)]).unwrap();
+
+    // The second index can no longer be evaluated
+    table.columns[1].name = "renamed".to_string();
+    let key = Key::from("1");
+    assert!(table.update(&key, vec![Value::Integer(10), Value::Integer(20)]).is_err());
+    assert!(table.insert("2", vec![Value::Integer(30), Value::Integer(40)]).is_err());
+    assert!(table.delete(&key).is_err());
+
+    let by_a = &table.indexes()[0];
+    assert_eq!(by_a.lookup(&Value::Integer(1)), [Key::from("1")]);
+    assert!(by_a.lookup(&Value::Integer(10)).is_empty());
+    assert!(by_a.lookup(&Value::Integer(30)).is_empty());
+    assert_eq!(table.get(&key).unwrap().values[0], Value::Integer(1));
+}
+
+#[test]
+fn test_table_rejects_values_of_other_types() {
+    let columns = vec![
+        Column::new("id", ColumnType::Integer),
+        Column::new("price", ColumnType::Float),
+    ];
+    let mut table = Table::new("prices", columns, Some("id".to_string()));
+
+    // A table converts nothing; literals are typed by the shell and restore
+    for value in [Value::Integer(3), Value::from("3.5"), Value::from("NaN")] {
+        let result = table.insert(1, vec![Value::Integer(1), value]);
+        assert!(matches!(result, Err(DbError::TypeConversionError)), "{:?}", result);
+    }
+    assert!(table.is_empty());
+
+    table.insert(1, vec![Value::Integer(1), Value::Float(3.5)]).unwrap();
+    assert_eq!(table.get(&Key::from(1)).unwrap().values[1], Value::Float(3.5));
+}
@@ -0,0 +1,96 @@
+use oxi_db::{Column, ColumnType, Database, DbError, Expr, Key, Table, Uuid, Value};
+use std::fs;
+
+fn create_accounts_table() -> Table {
+    let columns = vec![
+        Column::new("id", ColumnType::Uuid),
+        Column::new_enum("status", &["active", "suspended", "closed"]),
+    ];
+
+    Table::new("accounts", columns, Some("id".to_string()))
+}
+
+#[test]
+fn test_uuid_generation() {
+    let a = Value::generate_uuid();
+    let b = Value::generate_uuid();
+    assert_ne!(a, b);
+
+    if let Value::Uuid(u) = a {
+        assert_eq!(u.get_version_num(), 4);
+        assert_eq!(u.as_bytes().len(), 16);
+    } else {
+        panic!("expected a UUID value");
+    }
+
+    assert_ne!(Key::generate_uuid(), Key::generate_uuid());
+}
+
+#[test]
+fn test_uuid_column() {
+    let mut table = create_accounts_table();
+    let id = Uuid::new_v4();
+
+    table.insert(Key::from(id), vec![Value::Uuid(id), Value::from("active")]).unwrap();
+    assert_eq!(table.get(&Key::from(id)).unwrap().values[0], Value::Uuid(id));
+
+    // UUIDs written as text are parsed
+    let text_id = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+    table.insert(text_id, vec![Value::from(text_id), Value::from("closed")]).unwrap();
+    assert_eq!(
+        table.get(&Key::from(text_id)).unwrap().values[0],
+        Value::Uuid(text_id.parse().unwrap())
+    );
+
+    let result = table.insert("bad", vec![Value::from("not-a-uuid"), Value::from("active")]);
+    assert!(matches!(result, Err(DbError::ParseError(_))));
+
+    let result = table.insert("blob", vec![Value::Blob(vec![0; 16]), Value::from("active")]);
+    assert!(result.is_err());
+
+    let filter = Expr::column("id").eq(Value::Uuid(id));
+    assert_eq!(table.find_where(&filter).unwrap().len(), 1);
+}
+
+#[test]
+fn test_enum_column_enforces_labels() {
+    let mut table = create_accounts_table();
+
+    assert!(table.insert("1", vec![Value::generate_uuid(), Value::from("active")]).is_ok());
+    assert!(table.insert("2", vec![Value::generate_uuid(), Value::Null]).is_ok());
+
+    let result = table.insert("3", vec![Value::generate_uuid(), Value::from("deleted")]);
+    assert!(matches!(result, Err(DbError::TypeConversionError)));
+
+    let result = table.update(
+        &Key::from("1"),
+        vec![Value::generate_uuid(), Value::from("ACTIVE")],
+    );
+    assert!(result.is_err());
+    assert_eq!(table.get(&Key::from("1")).unwrap().values[1], Value::from("active"));
+}
+
+#[test]
+fn test_uuid_and_enum_persistence() {
+    let db_path = "test_uuid_enum_persist.db";
+    let mut db = Database::new(db_path);
+
+    let table = create_accounts_table();
+    db.create_table("accounts", table.columns.clone(), table.primary_key.clone()).unwrap();
+
+    let id = Uuid::new_v4();
+    db.insert("accounts", Key::from(id), vec![Value::Uuid(id), Value::from("suspended")]).unwrap();
+
+    drop(db);
+    let db = Database::open(db_path).unwrap();
+    let table = db.get_table("accounts").unwrap();
+    assert_eq!(
+        table.columns[1].column_type,
+        ColumnType::Enum(vec!["active".into(), "suspended".into(), "closed".into()])
+    );
+    let row = table.get(&Key::from(id)).unwrap();
+    assert_eq!(row.values, vec![Value::Uuid(id), Value::from("suspended")]);
+
+    // Clean up
+    fs::remove_file(db_path).unwrap_or(());
+}